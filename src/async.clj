@@ -0,0 +1,17 @@
+(ns clojure.core.async)
+
+(defmacro go
+  "Evaluates body in a go block, where <!, >! and alts! park instead of
+  blocking. Returns a channel that gets the result."
+  [& body]
+  `(go-call (fn [] ~@body)))
+
+(defmacro go-loop
+  "Same as (go (loop bindings body*))."
+  [bindings & body]
+  `(go (loop ~bindings ~@body)))
+
+(defmacro thread
+  "Evaluates body on a thread of its own. Returns a channel that gets the result."
+  [& body]
+  `(thread-call (fn [] ~@body)))
@@ -0,0 +1,136 @@
+(defmacro defn
+  "Same as (def name (fn name [params*] exprs*)), with the position of the
+  definition in the var's metadata."
+  [name & fdecl]
+  (let [name (vary-meta name (fn [m] (conj (or (meta &form) {}) m)))]
+    (if (string? (first fdecl))
+      `(def ~name ~(first fdecl) (fn ~name ~@(next fdecl)))
+      `(def ~name (fn ~name ~@fdecl)))))
+
+(defmacro when
+  "Evaluates body in an implicit do when test is truthy."
+  [test & body]
+  `(if ~test (do ~@body)))
+
+(defmacro when-not
+  "Evaluates body in an implicit do when test is falsey."
+  [test & body]
+  `(if ~test nil (do ~@body)))
+
+(defmacro if-not
+  "Evaluates then when test is falsey, otherwise else."
+  ([test then] `(if ~test nil ~then))
+  ([test then else] `(if ~test ~else ~then)))
+
+(defmacro cond
+  "Takes test/expr pairs and evaluates the expr of the first truthy test."
+  [& clauses]
+  (when clauses
+    `(if ~(first clauses)
+       ~(if (next clauses)
+          (second clauses)
+          (throw "cond requires an even number of forms"))
+       (cond ~@(next (next clauses))))))
+
+(defmacro ->
+  "Threads x through the forms as their second item."
+  [x & forms]
+  (loop [x x
+         forms forms]
+    (if forms
+      (let [form (first forms)]
+        (recur (if (seq? form)
+                 `(~(first form) ~x ~@(next form))
+                 `(~form ~x))
+               (next forms)))
+      x)))
+
+(defmacro ->>
+  "Threads x through the forms as their last item."
+  [x & forms]
+  (loop [x x
+         forms forms]
+    (if forms
+      (let [form (first forms)]
+        (recur (if (seq? form)
+                 `(~@form ~x)
+                 `(~form ~x))
+               (next forms)))
+      x)))
+
+(defmacro as->
+  "Binds name to expr, then to the result of each form in turn, returning the last."
+  [expr name & forms]
+  `(let [~name ~expr
+         ~@(loop [bindings []
+                  forms forms]
+             (if forms
+               (recur (conj bindings name (first forms)) (next forms))
+               bindings))]
+     ~name))
+
+(defmacro some->
+  "Threads x through the forms like -> until one of them returns nil."
+  [expr & forms]
+  (let [g (gensym)]
+    (loop [bindings [g expr]
+           forms forms]
+      (if forms
+        (recur (conj bindings g `(if (nil? ~g) nil (-> ~g ~(first forms))))
+               (next forms))
+        `(let ~bindings ~g)))))
+
+(defmacro some->>
+  "Threads x through the forms like ->> until one of them returns nil."
+  [expr & forms]
+  (let [g (gensym)]
+    (loop [bindings [g expr]
+           forms forms]
+      (if forms
+        (recur (conj bindings g `(if (nil? ~g) nil (->> ~g ~(first forms))))
+               (next forms))
+        `(let ~bindings ~g)))))
+
+(defmacro cond->
+  "Threads expr through each form like -> whose test is truthy."
+  [expr & clauses]
+  (let [g (gensym)]
+    (loop [bindings [g expr]
+           clauses clauses]
+      (if clauses
+        (recur (conj bindings g `(if ~(first clauses) (-> ~g ~(second clauses)) ~g))
+               (next (next clauses)))
+        `(let ~bindings ~g)))))
+
+(defmacro cond->>
+  "Threads expr through each form like ->> whose test is truthy."
+  [expr & clauses]
+  (let [g (gensym)]
+    (loop [bindings [g expr]
+           clauses clauses]
+      (if clauses
+        (recur (conj bindings g `(if ~(first clauses) (->> ~g ~(second clauses)) ~g))
+               (next (next clauses)))
+        `(let ~bindings ~g)))))
+
+(defmacro ..
+  "An alias of ->. Without host interop there are no members to access, so
+  (.. x (f a) g) is (-> x (f a) g)."
+  [x & forms]
+  `(-> ~x ~@forms))
+
+(defmacro doto
+  "Calls each form with x as its first argument and returns x."
+  [x & forms]
+  (let [g (gensym)]
+    `(let [~g ~x]
+       ~@(loop [calls []
+                forms forms]
+           (if forms
+             (let [form (first forms)]
+               (recur (conj calls (if (seq? form)
+                                    `(~(first form) ~g ~@(next form))
+                                    `(~form ~g)))
+                      (next forms)))
+             calls))
+       ~g)))
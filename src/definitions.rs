@@ -5,15 +5,33 @@ use std::{
     hash::Hash,
 };
 
+use im::HashMap as Hamt;
 use num_bigint::BigInt;
 use num_traits::{ToPrimitive, Zero};
 use ordered_float::OrderedFloat;
 
-use crate::{error::Error, funtions::eval_list};
+use crate::{
+    error::Error,
+    funtions::{
+        apply,
+        atoms::Atom,
+        channels::{Buffer, Chan, Mux},
+        concurrency::{Agent, Pending},
+        eval_list,
+        macros::expand_list,
+        protocols::{Object, Record},
+        refs::Ref,
+        special_forms::quoted,
+        strings::Pattern,
+        transducers::Transducer,
+        vars::print_length,
+        Func,
+    },
+};
 
 #[derive(Debug, Clone, Eq)]
 pub enum DefinitionTypes {
-    Symbol(String),
+    Symbol(String, Meta),
     Keyword(String),
     String(String),
     Char(char),
@@ -21,35 +39,180 @@ pub enum DefinitionTypes {
     Double(OrderedFloat<f64>),
     Int(BigInt),
     Rational(BigInt, BigInt),
-    HashSet(HashSet<DefinitionTypes>),
+    HashSet(HashSet<DefinitionTypes>, Meta),
     OrderedSet(BTreeSet<DefinitionTypes>),
-    HashMap(HashMap<DefinitionTypes, DefinitionTypes>),
+    HashMap(HashMap<DefinitionTypes, DefinitionTypes>, Meta),
     OrderedMap(BTreeMap<DefinitionTypes, DefinitionTypes>),
-    List(Vec<DefinitionTypes>),
-    Vector(Vec<DefinitionTypes>),
+    List(Vec<DefinitionTypes>, Meta),
+    Vector(Vec<DefinitionTypes>, Meta),
     Nil,
+    Seq(Vec<DefinitionTypes>, Meta),
+    Function(Function),
+    Xform(Vec<Transducer>),
+    Recur(Vec<DefinitionTypes>),
+    Throwable(Box<Error>),
+    Atom(Atom),
+    Ref(Ref),
+    Future(Pending),
+    Promise(Pending),
+    Agent(Agent),
+    Chan(Chan),
+    Buffer(Buffer),
+    Mux(Mux),
+    Record(Record),
+    Object(Object),
+    Regex(Pattern),
     // Issue 11
     // Issue 13
 }
 
+// Values cross threads in futures, agents and `pmap`, and live in the shared var tables.
+const _: fn() = || {
+    fn assert_send_sync<V: Send + Sync>() {}
+    assert_send_sync::<DefinitionTypes>();
+};
+
+/// The metadata of a symbol, collection or function. It never takes part in
+/// equality, hashing or ordering.
+#[derive(Clone, Default)]
+pub struct Meta(Option<Box<DefinitionTypes>>);
+
+impl Meta {
+    /// Metadata holding `map`, or none for nil.
+    pub fn new(map: DefinitionTypes) -> Meta {
+        match map {
+            DefinitionTypes::Nil => Meta(None),
+            map => Meta(Some(Box::new(map))),
+        }
+    }
+
+    /// The metadata map, or nil.
+    pub fn get(&self) -> DefinitionTypes {
+        self.0.as_deref().cloned().unwrap_or(DefinitionTypes::Nil)
+    }
+}
+
+impl PartialEq for Meta {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Meta {}
+
+// Values hash by their debug output, which must not depend on metadata.
+impl std::fmt::Debug for Meta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("_")
+    }
+}
+
+#[derive(Debug, Clone, Eq)]
+pub enum Function {
+    Native(String, Func),
+    Comp(Vec<DefinitionTypes>),
+    Lambda(Box<Lambda>),
+    Macro(Box<Lambda>),
+    /// A protocol method, by protocol var and method name, dispatching on the
+    /// type of its first argument.
+    Method(String, String),
+    /// A multimethod, by var, calling the method for the value of its dispatch function.
+    Multi(String),
+}
+
+/// A function created by `fn`, closing over the local scope it was created in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lambda {
+    pub name: Option<String>,
+    pub arities: Vec<Arity>,
+    pub scope: Hamt<String, DefinitionTypes>,
+    /// The namespace the body resolves symbols in.
+    pub ns: String,
+    pub meta: Meta,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arity {
+    /// The parameter vector as written, destructuring patterns included.
+    pub arglist: Vec<DefinitionTypes>,
+    pub params: Vec<String>,
+    pub rest: Option<String>,
+    pub body: Vec<DefinitionTypes>,
+}
+
+impl Function {
+    pub fn name(&self) -> String {
+        match self {
+            Function::Native(name, _) => name.to_owned(),
+            Function::Comp(_) => String::from("comp"),
+            Function::Method(_, name) => name.to_owned(),
+            Function::Multi(var) => var.rsplit('/').next().unwrap_or(var).to_owned(),
+            Function::Lambda(lambda) | Function::Macro(lambda) => {
+                lambda.name.clone().unwrap_or_else(|| String::from("fn"))
+            }
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Function::Native(l0, _), Function::Native(r0, _)) => l0 == r0,
+            (Function::Comp(l0), Function::Comp(r0)) => l0 == r0,
+            (Function::Lambda(l0), Function::Lambda(r0)) => l0 == r0,
+            (Function::Macro(l0), Function::Macro(r0)) => l0 == r0,
+            (Function::Method(l0, l1), Function::Method(r0, r1)) => l0 == r0 && l1 == r1,
+            (Function::Multi(l0), Function::Multi(r0)) => l0 == r0,
+            _ => false,
+        }
+    }
+}
+
+/// Prints `items` between `open` and `close`, eliding those past `*print-length*`.
+fn print_coll(
+    open: &str,
+    items: impl Iterator<Item = Result<String, Error>>,
+    close: char,
+) -> Result<String, Error> {
+    let limit = print_length();
+    let mut s = String::from(open);
+    for (i, item) in items.enumerate() {
+        if limit == Some(i) {
+            s.push_str("... ");
+            break;
+        }
+        s.push_str(&item?);
+        s.push(' ');
+    }
+    s.push(close);
+    Ok(s)
+}
+
 impl Hash for DefinitionTypes {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
-            DefinitionTypes::HashSet(set) => {
+            DefinitionTypes::HashSet(set, _) => {
                 let set = set.iter().collect::<Vec<&DefinitionTypes>>();
                 state.write(format!("HS={:?}", set).as_bytes())
             }
-            DefinitionTypes::HashMap(map) => {
+            DefinitionTypes::HashMap(map, _) => {
                 let mut map = map
                     .iter()
                     .collect::<Vec<(&DefinitionTypes, &DefinitionTypes)>>();
                 map.sort();
                 state.write(format!("HM={:?}", map).as_bytes())
             }
+            // Records are equal whatever order their keys were added in.
+            DefinitionTypes::Record(record) => {
+                let mut entries = record
+                    .entries()
+                    .iter()
+                    .collect::<Vec<&(DefinitionTypes, DefinitionTypes)>>();
+                entries.sort();
+                state.write(format!("R={}={:?}", record.name(), entries).as_bytes())
+            }
             _ => state.write(format!("{:?}", self).as_bytes()),
         }
-
-        state.finish();
     }
 }
 
@@ -58,11 +221,11 @@ impl Display for DefinitionTypes {
         match self {
             DefinitionTypes::Keyword(key) => write!(f, ":{}", key),
             DefinitionTypes::Rational(num, den) => write!(f, "{}/{}", num, den),
-            DefinitionTypes::HashSet(set) => {
+            DefinitionTypes::HashSet(set, _) => {
                 let set = set.iter().collect::<Vec<&DefinitionTypes>>();
                 write!(f, "#{{0HS}}={:?}", set)
             }
-            DefinitionTypes::HashMap(map) => {
+            DefinitionTypes::HashMap(map, _) => {
                 let mut map = map
                     .iter()
                     .collect::<Vec<(&DefinitionTypes, &DefinitionTypes)>>();
@@ -84,16 +247,14 @@ impl Ord for DefinitionTypes {
 
 impl PartialOrd for DefinitionTypes {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let s = self.to_string();
-        let o = other.to_string();
-        Some(s.cmp(&o))
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for DefinitionTypes {
     fn eq(&self, other: &Self) -> bool {
         match (&self.clone(), &other.clone()) {
-            (Self::Symbol(l0), Self::Symbol(r0)) => l0 == r0,
+            (Self::Symbol(l0, _), Self::Symbol(r0, _)) => l0 == r0,
             (Self::Keyword(l0), Self::Keyword(r0)) => l0 == r0,
             (Self::String(l0), Self::String(r0)) => l0 == r0,
             (Self::Char(l0), Self::Char(r0)) => l0 == r0,
@@ -112,26 +273,108 @@ impl PartialEq for DefinitionTypes {
                     == r0
             }
             (Self::Rational(l0, l1), Self::Int(r0)) => l0 / l1 == *r0 && l0 % l1 == BigInt::zero(),
-            (Self::HashSet(l0), Self::HashSet(r0)) => l0 == r0,
+            (Self::HashSet(l0, _), Self::HashSet(r0, _)) => l0 == r0,
             (Self::OrderedSet(l0), Self::OrderedSet(r0)) => l0 == r0,
-            (Self::HashMap(l0), Self::HashMap(r0)) => l0 == r0,
+            (Self::HashMap(l0, _), Self::HashMap(r0, _)) => l0 == r0,
             (Self::OrderedMap(l0), Self::OrderedMap(r0)) => l0 == r0,
-            (Self::List(_), Self::List(_)) => {
+            (Self::List(_, _), Self::List(_, _)) => {
                 self.clone().eval().unwrap_or(DefinitionTypes::Nil)
                     == other.clone().eval().unwrap_or(DefinitionTypes::Bool(true))
             }
-            (Self::Vector(l0), Self::Vector(r0)) => l0 == r0,
-            (v, Self::List(_)) => v == &other.clone().eval().unwrap_or(DefinitionTypes::Bool(true)),
-            (Self::List(_), v) => v == &self.clone().eval().unwrap_or(DefinitionTypes::Nil),
+            (Self::Vector(l0, _), Self::Vector(r0, _)) => l0 == r0,
+            (Self::Seq(l0, _), Self::Seq(r0, _)) => l0 == r0,
+            (Self::Seq(l0, _), Self::Vector(r0, _)) => l0 == r0,
+            (Self::Vector(l0, _), Self::Seq(r0, _)) => l0 == r0,
+            (Self::Function(l0), Self::Function(r0)) => l0 == r0,
+            (Self::Xform(l0), Self::Xform(r0)) => l0 == r0,
+            (Self::Recur(l0), Self::Recur(r0)) => l0 == r0,
+            (Self::Throwable(l0), Self::Throwable(r0)) => l0 == r0,
+            (Self::Atom(l0), Self::Atom(r0)) => l0 == r0,
+            (Self::Ref(l0), Self::Ref(r0)) => l0 == r0,
+            (Self::Future(l0), Self::Future(r0)) => l0 == r0,
+            (Self::Promise(l0), Self::Promise(r0)) => l0 == r0,
+            (Self::Agent(l0), Self::Agent(r0)) => l0 == r0,
+            (Self::Chan(l0), Self::Chan(r0)) => l0 == r0,
+            (Self::Buffer(l0), Self::Buffer(r0)) => l0 == r0,
+            (Self::Mux(l0), Self::Mux(r0)) => l0 == r0,
+            (Self::Record(l0), Self::Record(r0)) => l0 == r0,
+            (Self::Object(l0), Self::Object(r0)) => l0 == r0,
+            (Self::Regex(l0), Self::Regex(r0)) => l0 == r0,
+            (v, Self::List(_, _)) => {
+                v == &other.clone().eval().unwrap_or(DefinitionTypes::Bool(true))
+            }
+            (Self::List(_, _), v) => v == &self.clone().eval().unwrap_or(DefinitionTypes::Nil),
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
 }
 
 impl DefinitionTypes {
+    pub fn symbol(name: impl Into<String>) -> Self {
+        DefinitionTypes::Symbol(name.into(), Meta::default())
+    }
+
+    pub fn list(items: Vec<DefinitionTypes>) -> Self {
+        DefinitionTypes::List(items, Meta::default())
+    }
+
+    pub fn seq(items: Vec<DefinitionTypes>) -> Self {
+        DefinitionTypes::Seq(items, Meta::default())
+    }
+
+    pub fn vector(items: Vec<DefinitionTypes>) -> Self {
+        DefinitionTypes::Vector(items, Meta::default())
+    }
+
+    pub fn hash_map(map: HashMap<DefinitionTypes, DefinitionTypes>) -> Self {
+        DefinitionTypes::HashMap(map, Meta::default())
+    }
+
+    pub fn hash_set(set: HashSet<DefinitionTypes>) -> Self {
+        DefinitionTypes::HashSet(set, Meta::default())
+    }
+
+    /// The metadata map of a symbol, collection or function, or nil.
+    pub fn meta(&self) -> DefinitionTypes {
+        match self {
+            DefinitionTypes::Symbol(_, meta)
+            | DefinitionTypes::List(_, meta)
+            | DefinitionTypes::Seq(_, meta)
+            | DefinitionTypes::Vector(_, meta)
+            | DefinitionTypes::HashMap(_, meta)
+            | DefinitionTypes::HashSet(_, meta) => meta.get(),
+            DefinitionTypes::Function(Function::Lambda(lambda) | Function::Macro(lambda)) => {
+                lambda.meta.get()
+            }
+            _ => DefinitionTypes::Nil,
+        }
+    }
+
+    /// The same value with `meta` in place of its metadata, or the value back
+    /// if it can't have any.
+    pub fn with_meta(self, meta: Meta) -> Result<DefinitionTypes, DefinitionTypes> {
+        match self {
+            DefinitionTypes::Symbol(name, _) => Ok(DefinitionTypes::Symbol(name, meta)),
+            DefinitionTypes::List(list, _) => Ok(DefinitionTypes::List(list, meta)),
+            DefinitionTypes::Seq(list, _) => Ok(DefinitionTypes::Seq(list, meta)),
+            DefinitionTypes::Vector(vec, _) => Ok(DefinitionTypes::Vector(vec, meta)),
+            DefinitionTypes::HashMap(map, _) => Ok(DefinitionTypes::HashMap(map, meta)),
+            DefinitionTypes::HashSet(set, _) => Ok(DefinitionTypes::HashSet(set, meta)),
+            DefinitionTypes::Function(Function::Lambda(mut lambda)) => {
+                lambda.meta = meta;
+                Ok(DefinitionTypes::Function(Function::Lambda(lambda)))
+            }
+            DefinitionTypes::Function(Function::Macro(mut lambda)) => {
+                lambda.meta = meta;
+                Ok(DefinitionTypes::Function(Function::Macro(lambda)))
+            }
+            value => Err(value),
+        }
+    }
+
     pub fn print(&self) -> Result<String, Error> {
         let res = match self.clone() {
-            DefinitionTypes::Symbol(el) => el,
+            DefinitionTypes::Symbol(el, _) => el,
             DefinitionTypes::Keyword(el) => {
                 if el.starts_with(':') {
                     el
@@ -147,80 +390,166 @@ impl DefinitionTypes {
             DefinitionTypes::Rational(num, den) => format!("{}/{}", num, den),
             DefinitionTypes::Nil => "nil".to_owned(),
 
-            DefinitionTypes::HashSet(set) => {
-                let mut s = String::from("#{");
-                for el in set {
-                    s.push_str(&el.print()?);
-                    s.push(' ');
-                }
-                s.push('}');
-                s
+            DefinitionTypes::HashSet(set, _) => {
+                print_coll("#{", set.iter().map(DefinitionTypes::print), '}')?
             }
             DefinitionTypes::OrderedSet(set) => {
-                let mut s = String::from("#{");
-                for el in set {
-                    s.push_str(&el.print()?);
-                    s.push(' ');
-                }
-                s.push('}');
-                s
-            }
-            DefinitionTypes::Vector(vec) => {
-                let mut s = String::from('[');
-                for el in vec {
-                    s.push_str(&el.print()?);
-                    s.push(' ');
-                }
-                s.push(']');
-                s
-            }
-            DefinitionTypes::HashMap(map) => {
-                let mut s = String::from('{');
-                for (key, val) in map {
-                    s.push_str(&key.print()?);
-                    s.push(' ');
-                    s.push_str(&val.print()?);
-                    s.push(' ');
-                }
-                s.push('}');
-                s
-            }
-            DefinitionTypes::OrderedMap(map) => {
-                let mut s = String::from('{');
-                for (key, val) in map {
-                    s.push_str(&key.print()?);
-                    s.push(' ');
-                    s.push_str(&val.print()?);
-                    s.push(' ');
-                }
-                s.push('}');
-                s
+                print_coll("#{", set.iter().map(DefinitionTypes::print), '}')?
+            }
+            DefinitionTypes::Vector(vec, _) => {
+                print_coll("[", vec.iter().map(DefinitionTypes::print), ']')?
+            }
+            DefinitionTypes::HashMap(map, _) => print_coll(
+                "{",
+                map.iter()
+                    .map(|(key, val)| Ok(format!("{} {}", key.print()?, val.print()?))),
+                '}',
+            )?,
+            DefinitionTypes::OrderedMap(map) => print_coll(
+                "{",
+                map.iter()
+                    .map(|(key, val)| Ok(format!("{} {}", key.print()?, val.print()?))),
+                '}',
+            )?,
+            DefinitionTypes::List(mut list, _) => eval_list(&mut list)?,
+            DefinitionTypes::Seq(seq, _) => {
+                print_coll("(", seq.iter().map(DefinitionTypes::print), ')')?
             }
-            DefinitionTypes::List(mut list) => eval_list(&mut list)?,
+            DefinitionTypes::Function(f) => format!("#function[{}]", f.name()),
+            DefinitionTypes::Xform(xform) => {
+                let names = xform.iter().map(|x| x.name()).collect::<Vec<&str>>();
+                format!("#transducer[{}]", names.join(" "))
+            }
+            DefinitionTypes::Recur(_) => {
+                return Err(Error::cant_eval(Some(String::from(
+                    "Can only recur from tail position",
+                ))))
+            }
+            DefinitionTypes::Throwable(err) => {
+                format!("#error[{} {:?}]", err.kind.class_name(), err.message)
+            }
+            DefinitionTypes::Atom(atom) => format!("#atom[{}]", atom.deref().print()?),
+            DefinitionTypes::Ref(reference) => format!("#ref[{}]", reference.deref()?.print()?),
+            DefinitionTypes::Future(pending) => format!("#future[{}]", pending.print()?),
+            DefinitionTypes::Promise(pending) => format!("#promise[{}]", pending.print()?),
+            DefinitionTypes::Agent(agent) => format!("#agent[{}]", agent.deref().print()?),
+            DefinitionTypes::Chan(chan) => {
+                format!(
+                    "#chan[{}]",
+                    if chan.is_closed() { "closed" } else { "open" }
+                )
+            }
+            DefinitionTypes::Buffer(buffer) => buffer.print(),
+            DefinitionTypes::Mux(mux) => mux.print(),
+            DefinitionTypes::Record(record) => record.print()?,
+            DefinitionTypes::Object(object) => format!("#object[{}]", object.name()),
+            DefinitionTypes::Regex(pattern) => format!("{:?}", pattern),
         };
 
         Ok(res)
     }
 
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, DefinitionTypes::Nil | DefinitionTypes::Bool(false))
+    }
+
+    fn eval_call(list: &[Self], meta: &Meta) -> Result<Self, Error> {
+        use crate::funtions::{check_interrupt, is_bound, namespaces::resolve_native};
+        check_interrupt()?;
+        let (head, args) = match list.split_first() {
+            Some(split) => split,
+            None => return Ok(Self::list(Vec::new())),
+        };
+        if let Some(expanded) = expand_list(list, meta)? {
+            return expanded.eval_tail();
+        }
+
+        if let Self::Symbol(symbol, _) = head {
+            if !is_bound(symbol) {
+                let (_, func) =
+                    resolve_native(symbol).ok_or_else(|| Error::unknown_symbol(symbol))?;
+                return func(args);
+            }
+        }
+        let f = head.clone().eval()?;
+        let args = args
+            .iter()
+            .map(|arg| arg.clone().eval())
+            .collect::<Result<Vec<Self>, Error>>()?;
+        apply(&f, &args)
+    }
+
     pub fn eval(self) -> Result<Self, Error> {
-        use crate::STD;
-        if let Self::List(mut list) = self.clone() {
-            if list.is_empty() {
-                return Ok(Self::List(Vec::new()));
-            }
-
-            let mut list = list.iter_mut();
-            let next = list.next();
-            if let Some(Self::Symbol(symbol)) = next {
-                let rest: Vec<Self> = list.map(|e| e.clone()).collect();
-                STD.get(symbol)
-                    .ok_or_else(|| Error::UnknownSymbol(symbol.to_string()))?(&rest)
-            } else {
-                let next = next.map(|e| e.print().unwrap_or_default());
-                Err(Error::CantEval(next))
-            }
-        } else {
-            Ok(self)
+        match self.eval_tail()? {
+            Self::Recur(_) => Err(Error::cant_eval(Some(String::from(
+                "Can only recur from tail position",
+            )))),
+            value => Ok(value),
+        }
+    }
+
+    /// Evaluates a form in tail position, where a `recur` is returned for the
+    /// enclosing `loop` or `fn` to act on.
+    pub(crate) fn eval_tail(self) -> Result<Self, Error> {
+        use crate::{
+            funtions::{
+                namespaces::{current_ns, lookup_var, resolve_native},
+                nested,
+            },
+            SCOPE,
+        };
+        match self {
+            Self::List(list, meta) => nested(|| Self::eval_call(&list, &meta)).map_err(|err| {
+                let name = match list.first() {
+                    Some(Self::Symbol(name, _)) => name.to_owned(),
+                    _ => String::from("fn"),
+                };
+                err.push_frame(name, quoted(Self::list(list)).print().unwrap_or_default())
+            }),
+            Self::Symbol(symbol, _) => {
+                let local = SCOPE.with(|scope| scope.borrow().get(&symbol).cloned());
+                if let Some(local) = local {
+                    return Ok(local);
+                }
+                if symbol == "*ns*" {
+                    return Ok(Self::symbol(current_ns()));
+                }
+                if let Some(var) = lookup_var(&symbol) {
+                    return Ok(var);
+                }
+                resolve_native(&symbol)
+                    .map(|(name, f)| Self::Function(Function::Native(name.to_owned(), f)))
+                    .ok_or_else(|| Error::unknown_symbol(&symbol))
+            }
+            Self::Vector(vec, meta) => Ok(Self::Vector(
+                vec.into_iter()
+                    .map(Self::eval)
+                    .collect::<Result<_, Error>>()?,
+                meta,
+            )),
+            Self::HashSet(set, meta) => Ok(Self::HashSet(
+                set.into_iter()
+                    .map(Self::eval)
+                    .collect::<Result<_, Error>>()?,
+                meta,
+            )),
+            Self::OrderedSet(set) => Ok(Self::OrderedSet(
+                set.into_iter()
+                    .map(Self::eval)
+                    .collect::<Result<_, Error>>()?,
+            )),
+            Self::HashMap(map, meta) => Ok(Self::HashMap(
+                map.into_iter()
+                    .map(|(k, v)| Ok((k.eval()?, v.eval()?)))
+                    .collect::<Result<_, Error>>()?,
+                meta,
+            )),
+            Self::OrderedMap(map) => Ok(Self::OrderedMap(
+                map.into_iter()
+                    .map(|(k, v)| Ok((k.eval()?, v.eval()?)))
+                    .collect::<Result<_, Error>>()?,
+            )),
+            _ => Ok(self),
         }
     }
 }
@@ -232,64 +561,66 @@ impl ops::Add for DefinitionTypes {
 
     fn add(self, rhs: Self) -> Self::Output {
         let res = match self.clone() {
-            DefinitionTypes::Symbol(_) => todo!(),
-            DefinitionTypes::Keyword(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Symbol(_, _) => Err(Error::cant_eval(Some(String::from(
+                "Can't eval add of symbol",
+            )))),
+            DefinitionTypes::Keyword(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval add of keyword",
             )))),
             DefinitionTypes::String(s) => {
                 if let DefinitionTypes::String(rhs_s) = rhs {
                     let s = String::new() + &s + &rhs_s;
                     Ok(DefinitionTypes::String(s))
-                } else if let DefinitionTypes::List(_) = rhs {
+                } else if let DefinitionTypes::List(_, _) = rhs {
                     self + rhs.eval()?
                 } else {
-                    Err(Error::CantEval(Some(String::from(
+                    Err(Error::cant_eval(Some(String::from(
                         "Can't add non-string to string using `+`",
                     ))))
                 }
             }
-            DefinitionTypes::Char(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Char(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval add of char",
             )))),
-            DefinitionTypes::Bool(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Bool(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval add of bool",
             )))),
             DefinitionTypes::Double(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(num + rhs_num)),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.0 + rhs_num.to_f64().ok_or(Error::IntParseError)?).into(),
+                    (num.0 + rhs_num.to_f64().ok_or_else(not_a_double)?).into(),
                 )),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => Ok(DefinitionTypes::Double(
-                    (((num.0 * rhs_den.to_f64().ok_or(Error::IntParseError)?)
-                        + rhs_num.to_f64().ok_or(Error::IntParseError)?)
-                        / rhs_den.to_f64().ok_or(Error::IntParseError)?)
+                    (((num.0 * rhs_den.to_f64().ok_or_else(not_a_double)?)
+                        + rhs_num.to_f64().ok_or_else(not_a_double)?)
+                        / rhs_den.to_f64().ok_or_else(not_a_double)?)
                     .into(),
                 )),
-                DefinitionTypes::List(_) => self + rhs.eval()?,
+                DefinitionTypes::List(_, _) => self + rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't add non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Int(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.to_f64().ok_or(Error::IntParseError)? + rhs_num.0).into(),
+                    (num.to_f64().ok_or_else(not_a_double)? + rhs_num.0).into(),
                 )),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Int(num + rhs_num)),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => Ok(DefinitionTypes::Rational(
                     rhs_num + (num * &rhs_den),
                     rhs_den,
                 )),
-                DefinitionTypes::List(_) => self + rhs.eval()?,
+                DefinitionTypes::List(_, _) => self + rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't add non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Rational(num, den) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    ((num.to_f64().ok_or(Error::IntParseError)?
-                        / den.to_f64().ok_or(Error::IntParseError)?)
+                    ((num.to_f64().ok_or_else(not_a_double)?
+                        / den.to_f64().ok_or_else(not_a_double)?)
                         + rhs_num.0)
                         .into(),
                 )),
@@ -304,23 +635,23 @@ impl ops::Add for DefinitionTypes {
                     },
                     rhs_den * den,
                 )),
-                DefinitionTypes::List(_) => self + rhs.eval()?,
+                DefinitionTypes::List(_, _) => self + rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't add non-numeric to numeric using `+`",
                 )))),
             },
-            DefinitionTypes::HashSet(v) => {
-                if let DefinitionTypes::HashSet(rhs_v) = rhs {
+            DefinitionTypes::HashSet(v, _) => {
+                if let DefinitionTypes::HashSet(rhs_v, _) = rhs {
                     let mut v = v;
                     for k in rhs_v {
                         v.insert(k);
                     }
-                    Ok(DefinitionTypes::HashSet(v))
-                } else if let DefinitionTypes::List(_) = rhs {
+                    Ok(DefinitionTypes::hash_set(v))
+                } else if let DefinitionTypes::List(_, _) = rhs {
                     self + rhs.eval()?
                 } else {
-                    Err(Error::CantEval(Some(String::from(
+                    Err(Error::cant_eval(Some(String::from(
                         "Can't add non-hash-set to hash-set using `+`",
                     ))))
                 }
@@ -331,25 +662,25 @@ impl ops::Add for DefinitionTypes {
                     let mut rhs_v = rhs_v;
                     v.append(&mut rhs_v);
                     Ok(DefinitionTypes::OrderedSet(v))
-                } else if let DefinitionTypes::List(_) = rhs {
+                } else if let DefinitionTypes::List(_, _) = rhs {
                     self + rhs.eval()?
                 } else {
-                    Err(Error::CantEval(Some(String::from(
+                    Err(Error::cant_eval(Some(String::from(
                         "Can't add non-ordered-set to ordered-set using `+`",
                     ))))
                 }
             }
-            DefinitionTypes::HashMap(v) => {
-                if let DefinitionTypes::HashMap(rhs_v) = rhs {
+            DefinitionTypes::HashMap(v, _) => {
+                if let DefinitionTypes::HashMap(rhs_v, _) = rhs {
                     let mut v = v;
                     for (k, val) in rhs_v {
                         v.insert(k, val);
                     }
-                    Ok(DefinitionTypes::HashMap(v))
-                } else if let DefinitionTypes::List(_) = rhs {
+                    Ok(DefinitionTypes::hash_map(v))
+                } else if let DefinitionTypes::List(_, _) = rhs {
                     self + rhs.eval()?
                 } else {
-                    Err(Error::CantEval(Some(String::from(
+                    Err(Error::cant_eval(Some(String::from(
                         "Can't add non-hash-map to hash-map using `+`",
                     ))))
                 }
@@ -360,30 +691,48 @@ impl ops::Add for DefinitionTypes {
                     let mut rhs_v = rhs_v;
                     v.append(&mut rhs_v);
                     Ok(DefinitionTypes::OrderedMap(v))
-                } else if let DefinitionTypes::List(_) = rhs {
+                } else if let DefinitionTypes::List(_, _) = rhs {
                     self + rhs.eval()?
                 } else {
-                    Err(Error::CantEval(Some(String::from(
+                    Err(Error::cant_eval(Some(String::from(
                         "Can't add non-ordered-map to ordered-map using `+`",
                     ))))
                 }
             }
-            DefinitionTypes::List(_) => self.eval()? + rhs,
-            DefinitionTypes::Vector(v) => {
-                if let DefinitionTypes::Vector(rhs_v) = rhs {
+            DefinitionTypes::List(_, _) => self.eval()? + rhs,
+            DefinitionTypes::Vector(v, _) => {
+                if let DefinitionTypes::Vector(rhs_v, _) = rhs {
                     let mut v = v;
                     let mut rhs_v = rhs_v;
                     v.append(&mut rhs_v);
-                    Ok(DefinitionTypes::Vector(v))
-                } else if let DefinitionTypes::List(_) = rhs {
+                    Ok(DefinitionTypes::vector(v))
+                } else if let DefinitionTypes::List(_, _) = rhs {
                     self + rhs.eval()?
                 } else {
-                    Err(Error::CantEval(Some(String::from(
+                    Err(Error::cant_eval(Some(String::from(
                         "Can't add non-vector to vector using `+`",
                     ))))
                 }
             }
             DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
+            DefinitionTypes::Seq(_, _)
+            | DefinitionTypes::Function(_)
+            | DefinitionTypes::Xform(_)
+            | DefinitionTypes::Recur(_)
+            | DefinitionTypes::Throwable(_)
+            | DefinitionTypes::Atom(_)
+            | DefinitionTypes::Ref(_)
+            | DefinitionTypes::Future(_)
+            | DefinitionTypes::Promise(_)
+            | DefinitionTypes::Agent(_)
+            | DefinitionTypes::Chan(_)
+            | DefinitionTypes::Buffer(_)
+            | DefinitionTypes::Mux(_)
+            | DefinitionTypes::Record(_)
+            | DefinitionTypes::Object(_)
+            | DefinitionTypes::Regex(_) => {
+                Err(Error::cant_eval(format!("Can't add {}", self.print()?)))
+            }
         }?;
 
         Ok(res)
@@ -395,55 +744,57 @@ impl ops::Sub for DefinitionTypes {
 
     fn sub(self, rhs: Self) -> Self::Output {
         let res = match self.clone() {
-            DefinitionTypes::Symbol(_) => todo!(),
-            DefinitionTypes::Keyword(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Symbol(_, _) => Err(Error::cant_eval(Some(String::from(
+                "Can't eval sub of symbol",
+            )))),
+            DefinitionTypes::Keyword(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of keyword",
             )))),
-            DefinitionTypes::String(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::String(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of keyword",
             )))),
-            DefinitionTypes::Char(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Char(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of char",
             )))),
-            DefinitionTypes::Bool(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Bool(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of bool",
             )))),
             DefinitionTypes::Double(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(num - rhs_num)),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.0 - rhs_num.to_f64().ok_or(Error::IntParseError)?).into(),
+                    (num.0 - rhs_num.to_f64().ok_or_else(not_a_double)?).into(),
                 )),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => Ok(DefinitionTypes::Double(
-                    (((num.0 * rhs_den.to_f64().ok_or(Error::IntParseError)?)
-                        - rhs_num.to_f64().ok_or(Error::IntParseError)?)
-                        / rhs_den.to_f64().ok_or(Error::IntParseError)?)
+                    (((num.0 * rhs_den.to_f64().ok_or_else(not_a_double)?)
+                        - rhs_num.to_f64().ok_or_else(not_a_double)?)
+                        / rhs_den.to_f64().ok_or_else(not_a_double)?)
                     .into(),
                 )),
-                DefinitionTypes::List(_) => self - rhs.eval()?,
+                DefinitionTypes::List(_, _) => self - rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't sub non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Int(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.to_f64().ok_or(Error::IntParseError)? - rhs_num.0).into(),
+                    (num.to_f64().ok_or_else(not_a_double)? - rhs_num.0).into(),
                 )),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Int(num - rhs_num)),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => Ok(DefinitionTypes::Rational(
                     (num * &rhs_den) - rhs_num,
                     rhs_den,
                 )),
-                DefinitionTypes::List(_) => self - rhs.eval()?,
+                DefinitionTypes::List(_, _) => self - rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't sub non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Rational(num, den) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    ((num.to_f64().ok_or(Error::IntParseError)?
-                        / den.to_f64().ok_or(Error::IntParseError)?)
+                    ((num.to_f64().ok_or_else(not_a_double)?
+                        / den.to_f64().ok_or_else(not_a_double)?)
                         - rhs_num.0)
                         .into(),
                 )),
@@ -454,29 +805,48 @@ impl ops::Sub for DefinitionTypes {
                     (num * &rhs_den) - (rhs_num * &den),
                     rhs_den * den,
                 )),
-                DefinitionTypes::List(_) => self - rhs.eval()?,
+                DefinitionTypes::List(_, _) => self - rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't sub non-numeric to numeric using `+`",
                 )))),
             },
-            DefinitionTypes::HashSet(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::HashSet(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of hash-set using `-`",
             )))),
-            DefinitionTypes::OrderedSet(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::OrderedSet(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of ordered-set using `-`",
             )))),
-            DefinitionTypes::HashMap(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::HashMap(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of hash-map using `-`",
             )))),
-            DefinitionTypes::OrderedMap(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::OrderedMap(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of ordered-map using `-`",
             )))),
-            DefinitionTypes::List(_) => self.eval()? - rhs,
-            DefinitionTypes::Vector(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::List(_, _) => self.eval()? - rhs,
+            DefinitionTypes::Vector(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval sub of vector using `-`",
             )))),
             DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
+            DefinitionTypes::Seq(_, _)
+            | DefinitionTypes::Function(_)
+            | DefinitionTypes::Xform(_)
+            | DefinitionTypes::Recur(_)
+            | DefinitionTypes::Throwable(_)
+            | DefinitionTypes::Atom(_)
+            | DefinitionTypes::Ref(_)
+            | DefinitionTypes::Future(_)
+            | DefinitionTypes::Promise(_)
+            | DefinitionTypes::Agent(_)
+            | DefinitionTypes::Chan(_)
+            | DefinitionTypes::Buffer(_)
+            | DefinitionTypes::Mux(_)
+            | DefinitionTypes::Record(_)
+            | DefinitionTypes::Object(_)
+            | DefinitionTypes::Regex(_) => Err(Error::cant_eval(format!(
+                "Can't subtract {}",
+                self.print()?
+            ))),
         }?;
 
         Ok(res)
@@ -488,53 +858,55 @@ impl ops::Mul for DefinitionTypes {
 
     fn mul(self, rhs: Self) -> Self::Output {
         let res = match self.clone() {
-            DefinitionTypes::Symbol(_) => todo!(),
-            DefinitionTypes::Keyword(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Symbol(_, _) => Err(Error::cant_eval(Some(String::from(
+                "Can't eval mul of symbol",
+            )))),
+            DefinitionTypes::Keyword(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval mul of keyword",
             )))),
-            DefinitionTypes::String(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::String(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-string to string using `+`",
             )))),
-            DefinitionTypes::Char(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Char(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval mul of char",
             )))),
-            DefinitionTypes::Bool(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Bool(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval mul of bool",
             )))),
             DefinitionTypes::Double(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(num * rhs_num)),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.0 * rhs_num.to_f64().ok_or(Error::IntParseError)?).into(),
+                    (num.0 * rhs_num.to_f64().ok_or_else(not_a_double)?).into(),
                 )),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => Ok(DefinitionTypes::Double(
-                    (num.0 * (rhs_num.to_f64().ok_or(Error::IntParseError)?)
-                        / rhs_den.to_f64().ok_or(Error::IntParseError)?)
+                    (num.0 * (rhs_num.to_f64().ok_or_else(not_a_double)?)
+                        / rhs_den.to_f64().ok_or_else(not_a_double)?)
                     .into(),
                 )),
-                DefinitionTypes::List(_) => self * rhs.eval()?,
+                DefinitionTypes::List(_, _) => self * rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't mul non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Int(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.to_f64().ok_or(Error::IntParseError)? * rhs_num.0).into(),
+                    (num.to_f64().ok_or_else(not_a_double)? * rhs_num.0).into(),
                 )),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Int(num * rhs_num)),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => {
                     Ok(DefinitionTypes::Rational(rhs_num * num, rhs_den))
                 }
-                DefinitionTypes::List(_) => self * rhs.eval()?,
+                DefinitionTypes::List(_, _) => self * rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't mul non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Rational(num, den) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    ((num.to_f64().ok_or(Error::IntParseError)?
-                        / den.to_f64().ok_or(Error::IntParseError)?)
+                    ((num.to_f64().ok_or_else(not_a_double)?
+                        / den.to_f64().ok_or_else(not_a_double)?)
                         * rhs_num.0)
                         .into(),
                 )),
@@ -542,74 +914,104 @@ impl ops::Mul for DefinitionTypes {
                 DefinitionTypes::Rational(rhs_num, rhs_den) => {
                     Ok(DefinitionTypes::Rational(rhs_num * num, rhs_den * den))
                 }
-                DefinitionTypes::List(_) => self * rhs.eval()?,
+                DefinitionTypes::List(_, _) => self * rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't mul non-numeric to numeric using `+`",
                 )))),
             },
-            DefinitionTypes::HashSet(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::HashSet(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-hash-set to hash-set using `+`",
             )))),
-            DefinitionTypes::OrderedSet(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::OrderedSet(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-ordered-set to ordered-set using `+`",
             )))),
-            DefinitionTypes::HashMap(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::HashMap(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-hash-map to hash-map using `+`",
             )))),
-            DefinitionTypes::OrderedMap(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::OrderedMap(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-ordered-map to ordered-map using `+`",
             )))),
-            DefinitionTypes::List(_) => self.eval()? * rhs,
-            DefinitionTypes::Vector(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::List(_, _) => self.eval()? * rhs,
+            DefinitionTypes::Vector(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-vector to vector using `+`",
             )))),
             DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
+            DefinitionTypes::Seq(_, _)
+            | DefinitionTypes::Function(_)
+            | DefinitionTypes::Xform(_)
+            | DefinitionTypes::Recur(_)
+            | DefinitionTypes::Throwable(_)
+            | DefinitionTypes::Atom(_)
+            | DefinitionTypes::Ref(_)
+            | DefinitionTypes::Future(_)
+            | DefinitionTypes::Promise(_)
+            | DefinitionTypes::Agent(_)
+            | DefinitionTypes::Chan(_)
+            | DefinitionTypes::Buffer(_)
+            | DefinitionTypes::Mux(_)
+            | DefinitionTypes::Record(_)
+            | DefinitionTypes::Object(_)
+            | DefinitionTypes::Regex(_) => Err(Error::cant_eval(format!(
+                "Can't multiply {}",
+                self.print()?
+            ))),
         }?;
 
         Ok(res)
     }
 }
 
+fn not_a_double() -> Error {
+    Error::number_format("Number can't be represented as a double")
+}
+
+fn divide_by_zero() -> Error {
+    Error::arithmetic("Divide by zero")
+}
+
 impl ops::Div for DefinitionTypes {
     type Output = Result<Self, Error>;
 
     fn div(self, rhs: Self) -> Self::Output {
         let res = match self.clone() {
-            DefinitionTypes::Symbol(_) => todo!(),
-            DefinitionTypes::Keyword(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Symbol(_, _) => Err(Error::cant_eval(Some(String::from(
+                "Can't eval div of symbol",
+            )))),
+            DefinitionTypes::Keyword(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval mul of keyword",
             )))),
-            DefinitionTypes::String(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::String(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-string to string using `+`",
             )))),
-            DefinitionTypes::Char(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Char(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval mul of char",
             )))),
-            DefinitionTypes::Bool(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::Bool(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't eval mul of bool",
             )))),
             DefinitionTypes::Double(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(num / rhs_num)),
                 DefinitionTypes::Int(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.0 / rhs_num.to_f64().ok_or(Error::IntParseError)?).into(),
+                    (num.0 / rhs_num.to_f64().ok_or_else(not_a_double)?).into(),
                 )),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => Ok(DefinitionTypes::Double(
                     (num.0
-                        / (rhs_num.to_f64().ok_or(Error::IntParseError)?
-                            / rhs_den.to_f64().ok_or(Error::IntParseError)?))
+                        / (rhs_num.to_f64().ok_or_else(not_a_double)?
+                            / rhs_den.to_f64().ok_or_else(not_a_double)?))
                     .into(),
                 )),
-                DefinitionTypes::List(_) => self / rhs.eval()?,
+                DefinitionTypes::List(_, _) => self / rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't mul non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Int(num) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    (num.to_f64().ok_or(Error::IntParseError)? / rhs_num.0).into(),
+                    (num.to_f64().ok_or_else(not_a_double)? / rhs_num.0).into(),
                 )),
+                DefinitionTypes::Int(rhs_num) if rhs_num.is_zero() => Err(divide_by_zero()),
                 DefinitionTypes::Int(rhs_num) => {
                     if num.clone() % rhs_num.clone() == BigInt::zero() {
                         Ok(DefinitionTypes::Int(num / rhs_num))
@@ -617,22 +1019,24 @@ impl ops::Div for DefinitionTypes {
                         Ok(DefinitionTypes::Rational(num, rhs_num))
                     }
                 }
+                DefinitionTypes::Rational(rhs_num, _) if rhs_num.is_zero() => Err(divide_by_zero()),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => {
                     Ok(DefinitionTypes::Rational(rhs_den * num, rhs_num))
                 }
-                DefinitionTypes::List(_) => self / rhs.eval()?,
+                DefinitionTypes::List(_, _) => self / rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't mul non-numeric to numeric using `+`",
                 )))),
             },
             DefinitionTypes::Rational(num, den) => match rhs {
                 DefinitionTypes::Double(rhs_num) => Ok(DefinitionTypes::Double(
-                    ((num.to_f64().ok_or(Error::IntParseError)?
-                        / den.to_f64().ok_or(Error::IntParseError)?)
+                    ((num.to_f64().ok_or_else(not_a_double)?
+                        / den.to_f64().ok_or_else(not_a_double)?)
                         / rhs_num.0)
                         .into(),
                 )),
+                DefinitionTypes::Int(rhs_num) if rhs_num.is_zero() => Err(divide_by_zero()),
                 DefinitionTypes::Int(rhs_num) => {
                     if num.clone() % rhs_num.clone() == BigInt::zero() {
                         Ok(DefinitionTypes::Rational(num / rhs_num, den))
@@ -640,32 +1044,51 @@ impl ops::Div for DefinitionTypes {
                         Ok(DefinitionTypes::Rational(num, den * rhs_num))
                     }
                 }
+                DefinitionTypes::Rational(rhs_num, _) if rhs_num.is_zero() => Err(divide_by_zero()),
                 DefinitionTypes::Rational(rhs_num, rhs_den) => {
                     Ok(DefinitionTypes::Rational(rhs_den * num, rhs_num * den))
                 }
-                DefinitionTypes::List(_) => self / rhs.eval()?,
+                DefinitionTypes::List(_, _) => self / rhs.eval()?,
                 DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
-                _ => Err(Error::CantEval(Some(String::from(
+                _ => Err(Error::cant_eval(Some(String::from(
                     "Can't mul non-numeric to numeric using `+`",
                 )))),
             },
-            DefinitionTypes::HashSet(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::HashSet(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-hash-set to hash-set using `+`",
             )))),
-            DefinitionTypes::OrderedSet(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::OrderedSet(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-ordered-set to ordered-set using `+`",
             )))),
-            DefinitionTypes::HashMap(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::HashMap(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-hash-map to hash-map using `+`",
             )))),
-            DefinitionTypes::OrderedMap(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::OrderedMap(_) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-ordered-map to ordered-map using `+`",
             )))),
-            DefinitionTypes::List(_) => self.eval()? / rhs,
-            DefinitionTypes::Vector(_) => Err(Error::CantEval(Some(String::from(
+            DefinitionTypes::List(_, _) => self.eval()? / rhs,
+            DefinitionTypes::Vector(_, _) => Err(Error::cant_eval(Some(String::from(
                 "Can't mul non-vector to vector using `+`",
             )))),
             DefinitionTypes::Nil => Ok(DefinitionTypes::Nil),
+            DefinitionTypes::Seq(_, _)
+            | DefinitionTypes::Function(_)
+            | DefinitionTypes::Xform(_)
+            | DefinitionTypes::Recur(_)
+            | DefinitionTypes::Throwable(_)
+            | DefinitionTypes::Atom(_)
+            | DefinitionTypes::Ref(_)
+            | DefinitionTypes::Future(_)
+            | DefinitionTypes::Promise(_)
+            | DefinitionTypes::Agent(_)
+            | DefinitionTypes::Chan(_)
+            | DefinitionTypes::Buffer(_)
+            | DefinitionTypes::Mux(_)
+            | DefinitionTypes::Record(_)
+            | DefinitionTypes::Object(_)
+            | DefinitionTypes::Regex(_) => {
+                Err(Error::cant_eval(format!("Can't divide {}", self.print()?)))
+            }
         }?;
 
         Ok(res)
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashSet,
+    sync::{Once, RwLock},
+};
+
+use im::{hashmap, HashMap as Hamt};
+
+use crate::{
+    definitions::DefinitionTypes as T,
+    funtions::{
+        multimethods::{Hierarchy, MultiFn},
+        namespaces::{Namespace, CORE},
+        protocols::{Protocol, TypeDef},
+        transducers::Transducer,
+    },
+    ENV,
+};
+
+lazy_static! {
+    static ref ROOT: Env = Env::empty();
+}
+
+/// The vars, namespaces and other definitions code is evaluated against.
+///
+/// An environment is `Send + Sync` and shared by every thread evaluating in
+/// it: a thread starts in the root environment, and futures, agents, `pmap`,
+/// go blocks and REPL clients run in the one they were started from.
+pub struct Env {
+    /// The root value of every var, by `ns/name`.
+    pub(crate) data: RwLock<Hamt<String, T>>,
+    /// The metadata every var was defined with, by `ns/name`.
+    pub(crate) metadata: RwLock<Hamt<String, T>>,
+    pub(crate) namespaces: RwLock<Hamt<String, Namespace>>,
+    pub(crate) protocols: RwLock<Hamt<String, Protocol>>,
+    pub(crate) types: RwLock<Hamt<String, TypeDef>>,
+    pub(crate) multimethods: RwLock<Hamt<String, MultiFn>>,
+    pub(crate) hierarchy: RwLock<Hierarchy>,
+    /// The text of every var defined from source, for `source`.
+    pub(crate) sources: RwLock<Hamt<String, String>>,
+    /// The namespaces `require` has loaded.
+    pub(crate) loaded: RwLock<HashSet<String>>,
+    /// Every keyword read, for completion.
+    pub(crate) keywords: RwLock<HashSet<String>>,
+    /// Loads `core.clj` into the environment the first time it is used.
+    pub(crate) bootstrap: Once,
+}
+
+impl Env {
+    /// A new environment holding only the builtins, for an interpreter
+    /// separate from the root one. It lives as long as the process, since
+    /// threads started in it may outlive the caller.
+    pub fn new() -> &'static Env {
+        Box::leak(Box::new(Env::empty()))
+    }
+
+    /// The environment threads start in.
+    pub fn root() -> &'static Env {
+        &ROOT
+    }
+
+    fn empty() -> Env {
+        let dynamic = [
+            "*command-line-args*",
+            "*1",
+            "*2",
+            "*3",
+            "*e",
+            "*file*",
+            "*out*",
+            "*print-length*",
+        ];
+        Env {
+            data: RwLock::new(hashmap! {
+                String::from("clojure.core/cat") => T::Xform(vec![Transducer::Cat]),
+                String::from("clojure.core/*command-line-args*") => T::Nil,
+                String::from("clojure.core/*1") => T::Nil,
+                String::from("clojure.core/*2") => T::Nil,
+                String::from("clojure.core/*3") => T::Nil,
+                String::from("clojure.core/*e") => T::Nil,
+                String::from("clojure.core/*file*") => T::String(String::from("NO_SOURCE_PATH")),
+                String::from("clojure.core/*out*") => T::Nil,
+                String::from("clojure.core/*print-length*") => T::Nil,
+            }),
+            metadata: RwLock::new(
+                dynamic
+                    .iter()
+                    .map(|name| {
+                        let dynamic = (T::Keyword(String::from(":dynamic")), T::Bool(true));
+                        (format!("{}/{}", CORE, name), T::hash_map([dynamic].into()))
+                    })
+                    .collect(),
+            ),
+            namespaces: RwLock::new(hashmap! {
+                String::from(CORE) => Namespace::default(),
+                String::from("user") => Namespace::default(),
+            }),
+            protocols: RwLock::new(Hamt::new()),
+            types: RwLock::new(Hamt::new()),
+            multimethods: RwLock::new(Hamt::new()),
+            hierarchy: RwLock::new(Hierarchy::default()),
+            sources: RwLock::new(Hamt::new()),
+            loaded: RwLock::new(HashSet::new()),
+            keywords: RwLock::new(HashSet::new()),
+            bootstrap: Once::new(),
+        }
+    }
+}
+
+/// The environment this thread evaluates in.
+pub fn current_env() -> &'static Env {
+    ENV.with(|env| env.get())
+}
+
+/// Runs `f` in `env`, restoring this thread's environment afterwards.
+pub fn with_env<R>(env: &'static Env, f: impl FnOnce() -> R) -> R {
+    let previous = ENV.with(|current| current.replace(env));
+    let result = f();
+    ENV.with(|current| current.replace(previous));
+    result
+}
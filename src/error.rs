@@ -1,36 +1,225 @@
+use std::fmt::{self, Display};
+
 use num_bigint::ParseBigIntError;
 
-// Issue 12
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Error {
-    Reason(String),
-    Thrown(String),
-    ArityException(u16, String),
-    UnknownSymbol(String),
-    CantEval(Option<String>),
-    IntParseError,
+use crate::definitions::DefinitionTypes as T;
+
+/// What went wrong, named after the Clojure exception classes `catch` matches against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Reader and general runtime failures.
+    Reason,
+    /// `throw` of a string.
+    Thrown,
+    /// A call with the wrong number of arguments, carrying the expected arity.
+    Arity(u16),
+    UnknownSymbol,
+    CantEval,
+    /// A literal or conversion that doesn't fit the numeric type.
+    NumberFormat,
+    Arithmetic,
+    /// `throw` of an `ex-info`.
+    ExInfo,
+    /// An evaluation stopped from another thread, e.g. by an nREPL `interrupt`.
+    Interrupted,
+    /// An operation invalid in the current state, e.g. a value an atom's validator rejects.
+    IllegalState,
+    /// An argument a function can't accept, e.g. nil put on a channel.
+    IllegalArgument,
+    /// Unwinds a transaction that must start over. `catch` never sees it.
+    Retry,
+    /// Calls nested deeper than the interpreter's stack allows.
+    StackOverflow,
+}
+
+impl ErrorKind {
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            ErrorKind::Arity(_) => "ArityException",
+            ErrorKind::UnknownSymbol => "UnknownSymbolException",
+            ErrorKind::Arithmetic => "ArithmeticException",
+            ErrorKind::NumberFormat => "NumberFormatException",
+            ErrorKind::Thrown | ErrorKind::ExInfo => "ExceptionInfo",
+            ErrorKind::Reason | ErrorKind::CantEval => "RuntimeException",
+            ErrorKind::Interrupted => "InterruptedException",
+            ErrorKind::IllegalState => "IllegalStateException",
+            ErrorKind::IllegalArgument => "IllegalArgumentException",
+            ErrorKind::Retry => "RetryEx",
+            ErrorKind::StackOverflow => "StackOverflowError",
+        }
+    }
+}
+
+/// Char indices of the offending text in the source being read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One interpreter level call the error unwound through.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Frame {
+    pub name: String,
+    pub call_site: String,
+}
+
+/// Deep recursion keeps only the innermost calls.
+const MAX_FRAMES: usize = 64;
+
+/// A failure raised while reading or evaluating, with the `ex-info` data, cause
+/// and interpreter calls `catch`, `ex-data` and the REPL report.
+#[derive(Debug, Clone, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Option<Span>,
+    pub data: Option<Box<T>>,
+    pub cause: Option<Box<Error>>,
+    pub stack: Vec<Frame>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error {
+            kind,
+            message: message.into(),
+            span: None,
+            data: None,
+            cause: None,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn reason(message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::Reason, message)
+    }
+
+    pub fn thrown(message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::Thrown, message)
+    }
+
+    pub fn arity(expected: u16, message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::Arity(expected), message)
+    }
+
+    pub fn unknown_symbol(symbol: &str) -> Self {
+        Error::new(
+            ErrorKind::UnknownSymbol,
+            format!("Unable to resolve symbol: {}", symbol),
+        )
+    }
+
+    pub fn cant_eval(message: impl Into<Option<String>>) -> Self {
+        Error::new(
+            ErrorKind::CantEval,
+            message
+                .into()
+                .unwrap_or_else(|| String::from("Can't eval form")),
+        )
+    }
+
+    pub fn number_format(message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::NumberFormat, message)
+    }
+
+    pub fn arithmetic(message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::Arithmetic, message)
+    }
+
+    pub fn illegal_state(message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::IllegalState, message)
+    }
+
+    pub fn illegal_argument(message: impl Into<String>) -> Self {
+        Error::new(ErrorKind::IllegalArgument, message)
+    }
+
+    pub fn ex_info(message: impl Into<String>, data: T, cause: Option<Error>) -> Self {
+        Error {
+            data: Some(Box::new(data)),
+            cause: cause.map(Box::new),
+            ..Error::new(ErrorKind::ExInfo, message)
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub(crate) fn push_frame(mut self, name: String, call_site: String) -> Self {
+        if self.stack.len() < MAX_FRAMES {
+            self.stack.push(Frame { name, call_site });
+        }
+        self
+    }
+
+    /// The message, the calls it unwound through and its causes, as printed by the REPL.
+    pub fn trace(&self) -> String {
+        let mut trace = self.to_string();
+        for frame in &self.stack {
+            trace.push_str(&format!("\n  at {} {}", frame.name, frame.call_site));
+        }
+        if let Some(cause) = &self.cause {
+            trace.push_str("\nCaused by: ");
+            trace.push_str(&cause.trace());
+        }
+        trace
+    }
+}
+
+// The span and stack describe where an error was raised, not which error it is.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.message == other.message
+            && self.data == other.data
+            && self.cause == other.cause
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.class_name(), self.message)?;
+        if let Some(span) = self.span {
+            write!(f, " at {}..{}", span.start, span.end)?;
+        }
+        if let Some(data) = &self.data {
+            write!(f, " {}", data.print().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .as_deref()
+            .map(|cause| cause as &(dyn std::error::Error + 'static))
+    }
 }
 
 impl From<std::num::ParseIntError> for Error {
     fn from(s: std::num::ParseIntError) -> Self {
-        Error::Reason(s.to_string())
+        Error::number_format(s.to_string())
     }
 }
 
 impl From<ParseBigIntError> for Error {
     fn from(s: ParseBigIntError) -> Self {
-        Error::Reason(s.to_string())
+        Error::number_format(s.to_string())
     }
 }
 
 impl From<std::num::ParseFloatError> for Error {
     fn from(s: std::num::ParseFloatError) -> Self {
-        Error::Reason(s.to_string())
+        Error::number_format(s.to_string())
     }
 }
 
 impl From<std::str::ParseBoolError> for Error {
     fn from(s: std::str::ParseBoolError) -> Self {
-        Error::Reason(s.to_string())
+        Error::reason(s.to_string())
     }
 }
@@ -0,0 +1,287 @@
+use std::{fmt, sync::Arc};
+
+use arc_swap::{ArcSwap, ArcSwapOption};
+
+use crate::{definitions::DefinitionTypes as T, error::Error};
+
+use super::{apply, arity, metadata::expect_meta};
+
+/// A reference updated by compare-and-swap, so threads changing it never block
+/// each other.
+#[derive(Clone)]
+pub struct Atom(Arc<AtomState>);
+
+struct AtomState {
+    value: ArcSwap<T>,
+    validator: ArcSwapOption<T>,
+    /// Watch functions by key, called with every change.
+    watches: ArcSwap<Vec<(T, T)>>,
+    meta: ArcSwap<T>,
+}
+
+impl Atom {
+    pub fn new(value: T, validator: Option<T>, meta: T) -> Result<Atom, Error> {
+        let atom = Atom(Arc::new(AtomState {
+            value: ArcSwap::from_pointee(value),
+            validator: ArcSwapOption::from(validator.map(Arc::new)),
+            watches: ArcSwap::from_pointee(Vec::new()),
+            meta: ArcSwap::from_pointee(meta),
+        }));
+        atom.validate(&atom.deref())?;
+        Ok(atom)
+    }
+
+    pub fn deref(&self) -> T {
+        (**self.0.value.load()).clone()
+    }
+
+    fn validate(&self, value: &T) -> Result<(), Error> {
+        match self.0.validator.load_full() {
+            Some(validator) if !apply(&validator, std::slice::from_ref(value))?.is_truthy() => {
+                Err(Error::illegal_state("Invalid reference state"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn notify(&self, old: &T, new: &T) -> Result<(), Error> {
+        for (key, watch) in self.0.watches.load().iter() {
+            apply(
+                watch,
+                &[key.clone(), T::Atom(self.clone()), old.clone(), new.clone()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Sets the value to `f` of the current one, calling `f` again whenever
+    /// another thread changed the value first. Returns the old and new values.
+    pub fn swap(&self, f: impl Fn(&T) -> Result<T, Error>) -> Result<(T, T), Error> {
+        loop {
+            let current = self.0.value.load_full();
+            let new = f(&current)?;
+            self.validate(&new)?;
+            let new = Arc::new(new);
+            let previous = self.0.value.compare_and_swap(&current, new.clone());
+            if Arc::ptr_eq(&previous, &current) {
+                self.notify(&current, &new)?;
+                return Ok(((*current).clone(), (*new).clone()));
+            }
+        }
+    }
+
+    /// Sets the value regardless of the current one, returning the old value.
+    pub fn reset(&self, new: T) -> Result<T, Error> {
+        self.validate(&new)?;
+        let old = self.0.value.swap(Arc::new(new.clone()));
+        self.notify(&old, &new)?;
+        Ok((*old).clone())
+    }
+
+    /// Sets the value to `new` only if the current one equals `old`.
+    pub fn compare_and_set(&self, old: &T, new: T) -> Result<bool, Error> {
+        self.validate(&new)?;
+        let new = Arc::new(new);
+        loop {
+            let current = self.0.value.load_full();
+            if *current != *old {
+                return Ok(false);
+            }
+            let previous = self.0.value.compare_and_swap(&current, new.clone());
+            if Arc::ptr_eq(&previous, &current) {
+                self.notify(&current, &new)?;
+                return Ok(true);
+            }
+        }
+    }
+
+    pub fn meta(&self) -> T {
+        (**self.0.meta.load()).clone()
+    }
+
+    /// Sets the metadata to `f` of the current one, the way `swap` sets the value.
+    pub fn alter_meta(&self, f: impl Fn(&T) -> Result<T, Error>) -> Result<T, Error> {
+        loop {
+            let current = self.0.meta.load_full();
+            let new = Arc::new(f(&current)?);
+            let previous = self.0.meta.compare_and_swap(&current, new.clone());
+            if Arc::ptr_eq(&previous, &current) {
+                return Ok((*new).clone());
+            }
+        }
+    }
+
+    fn update_watches(&self, f: impl Fn(&mut Vec<(T, T)>)) {
+        self.0.watches.rcu(|watches| {
+            let mut watches = (**watches).clone();
+            f(&mut watches);
+            watches
+        });
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Atom {}
+
+// Atoms are identities, told apart by address rather than by value.
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Atom({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+fn expect_atom(name: &str, value: T) -> Result<Atom, Error> {
+    match value {
+        T::Atom(atom) => Ok(atom),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects an atom but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+/// `(atom x)`, with the options `:validator f` and `:meta map`.
+pub fn atom(list: &[T]) -> Result<T, Error> {
+    let (value, options) = match list.split_first() {
+        Some((value, options)) if options.len() % 2 == 0 => (value.clone().eval()?, options),
+        _ => return Err(arity("atom", 1, "1 plus option pairs", list)),
+    };
+    let mut validator = None;
+    let mut meta = T::Nil;
+    for option in options.chunks(2) {
+        match option[0].clone().eval()? {
+            T::Keyword(key) if key.trim_start_matches(':') == "validator" => {
+                validator = Some(option[1].clone().eval()?).filter(T::is_truthy)
+            }
+            T::Keyword(key) if key.trim_start_matches(':') == "meta" => {
+                meta = expect_meta("atom", option[1].clone().eval()?)?
+            }
+            key => {
+                return Err(Error::cant_eval(format!(
+                    "Unknown atom option {}",
+                    key.print()?
+                )))
+            }
+        }
+    }
+    Ok(T::Atom(Atom::new(value, validator, meta)?))
+}
+
+fn swap_with(name: &str, list: &[T]) -> Result<(T, T), Error> {
+    let mut args = eval_all(list)?;
+    if args.len() < 2 {
+        return Err(arity(name, 2, "at least 2", list));
+    }
+    let atom = expect_atom(name, args.remove(0))?;
+    let f = args.remove(0);
+    atom.swap(|current| {
+        let mut call = vec![current.clone()];
+        call.extend(args.iter().cloned());
+        apply(&f, &call)
+    })
+}
+
+/// `(swap! atom f & args)` sets the atom to `(apply f @atom args)`.
+pub fn swap(list: &[T]) -> Result<T, Error> {
+    Ok(swap_with("swap!", list)?.1)
+}
+
+/// Like `swap!`, returning `[old new]`.
+pub fn swap_vals(list: &[T]) -> Result<T, Error> {
+    let (old, new) = swap_with("swap-vals!", list)?;
+    Ok(T::vector(vec![old, new]))
+}
+
+fn reset_with(name: &str, list: &[T]) -> Result<(T, T), Error> {
+    match &eval_all(list)?[..] {
+        [atom, value] => {
+            let old = expect_atom(name, atom.clone())?.reset(value.clone())?;
+            Ok((old, value.clone()))
+        }
+        _ => Err(arity(name, 2, "2", list)),
+    }
+}
+
+pub fn reset(list: &[T]) -> Result<T, Error> {
+    Ok(reset_with("reset!", list)?.1)
+}
+
+pub fn reset_vals(list: &[T]) -> Result<T, Error> {
+    let (old, new) = reset_with("reset-vals!", list)?;
+    Ok(T::vector(vec![old, new]))
+}
+
+pub fn compare_and_set(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [atom, old, new] => Ok(T::Bool(
+            expect_atom("compare-and-set!", atom.clone())?.compare_and_set(old, new.clone())?,
+        )),
+        _ => Err(arity("compare-and-set!", 3, "3", list)),
+    }
+}
+
+/// `(set-validator! atom f)` checks the current value against `f` before installing it.
+pub fn set_validator(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [atom, validator] => {
+            let atom = expect_atom("set-validator!", atom.clone())?;
+            let validator = Some(validator.clone()).filter(T::is_truthy);
+            if let Some(validator) = &validator {
+                if !apply(validator, &[atom.deref()])?.is_truthy() {
+                    return Err(Error::illegal_state("Invalid reference state"));
+                }
+            }
+            atom.0.validator.store(validator.map(Arc::new));
+            Ok(T::Nil)
+        }
+        _ => Err(arity("set-validator!", 2, "2", list)),
+    }
+}
+
+pub fn get_validator(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [atom] => Ok(expect_atom("get-validator", atom.clone())?
+            .0
+            .validator
+            .load_full()
+            .map_or(T::Nil, |validator| (*validator).clone())),
+        _ => Err(arity("get-validator", 1, "1", list)),
+    }
+}
+
+/// `(add-watch atom key f)` calls `(f key atom old new)` after every change,
+/// replacing any watch with the same key.
+pub fn add_watch(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [atom, key, watch] => {
+            let reference = expect_atom("add-watch", atom.clone())?;
+            reference.update_watches(|watches| {
+                watches.retain(|(k, _)| k != key);
+                watches.push((key.clone(), watch.clone()));
+            });
+            Ok(atom.clone())
+        }
+        _ => Err(arity("add-watch", 3, "3", list)),
+    }
+}
+
+pub fn remove_watch(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [atom, key] => {
+            expect_atom("remove-watch", atom.clone())?
+                .update_watches(|watches| watches.retain(|(k, _)| k != key));
+            Ok(atom.clone())
+        }
+        _ => Err(arity("remove-watch", 2, "2", list)),
+    }
+}
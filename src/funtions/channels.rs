@@ -0,0 +1,1242 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{channel, Sender},
+        Arc, Condvar, Mutex, OnceLock,
+    },
+    thread,
+    time::Instant,
+};
+
+use generator::{Generator, Gn};
+use im::HashMap as Hamt;
+use num_traits::ToPrimitive;
+
+use crate::{
+    definitions::DefinitionTypes as T,
+    env::{current_env, Env},
+    error::Error,
+    BINDINGS, CURRENT_NS, DEPTH, ENV, SCOPE,
+};
+
+use super::{
+    apply, arity,
+    collections::items,
+    concurrency::{lock, millis, spawn, Job},
+    namespaces::current_ns,
+    transducers::Transform,
+    vars::{current_bindings, with_bindings_frame},
+    THREAD_STACK_SIZE,
+};
+
+/// Puts or takes that may wait on a single channel, like core.async.
+const MAX_PENDING: usize = 1024;
+
+/// Threads running go blocks, like core.async's dispatch pool.
+const GO_THREADS: usize = 8;
+
+static FLAG_IDS: AtomicU64 = AtomicU64::new(0);
+
+/// Shared by the operations an `alts!` offers, so that only one of them happens.
+struct Flag {
+    id: u64,
+    active: Mutex<bool>,
+}
+
+impl Flag {
+    fn new() -> Arc<Flag> {
+        Arc::new(Flag {
+            id: FLAG_IDS.fetch_add(1, Ordering::Relaxed),
+            active: Mutex::new(true),
+        })
+    }
+
+    fn is_active(&self) -> bool {
+        *lock(&self.active)
+    }
+
+    /// Claims the flag, returning false if another operation did first.
+    fn commit(&self) -> bool {
+        std::mem::replace(&mut *lock(&self.active), false)
+    }
+}
+
+/// Claims both flags or neither, returning whether each was still active.
+/// Locking in id order keeps two `alts!` meeting on channels from deadlocking.
+fn commit_pair(ours: &Flag, theirs: &Flag) -> (bool, bool) {
+    if std::ptr::eq(ours, theirs) {
+        // An `alts!` can't complete against itself.
+        return (ours.is_active(), false);
+    }
+    let ours_first = ours.id < theirs.id;
+    let (first, second) = if ours_first {
+        (ours, theirs)
+    } else {
+        (theirs, ours)
+    };
+    let mut first = lock(&first.active);
+    let mut second = lock(&second.active);
+    let active = if ours_first {
+        (*first, *second)
+    } else {
+        (*second, *first)
+    };
+    if *first && *second {
+        *first = false;
+        *second = false;
+    }
+    active
+}
+
+type Callback<R> = Box<dyn FnOnce(R) + Send>;
+
+/// An operation waiting on a channel, called back once it completes.
+struct Handler<R> {
+    flag: Arc<Flag>,
+    callback: Callback<R>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BufferKind {
+    Fixed,
+    /// Drops the newest items when full.
+    Dropping,
+    /// Drops the oldest items when full.
+    Sliding,
+}
+
+/// What `buffer`, `dropping-buffer` and `sliding-buffer` return for `chan`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Buffer {
+    pub kind: BufferKind,
+    pub size: usize,
+}
+
+impl Buffer {
+    fn fixed(size: usize) -> Buffer {
+        Buffer {
+            kind: BufferKind::Fixed,
+            size,
+        }
+    }
+
+    pub fn print(&self) -> String {
+        let kind = match self.kind {
+            BufferKind::Fixed => "fixed",
+            BufferKind::Dropping => "dropping",
+            BufferKind::Sliding => "sliding",
+        };
+        format!("#buffer[{} {}]", kind, self.size)
+    }
+}
+
+struct ChanState {
+    buffer: Option<(Buffer, VecDeque<T>)>,
+    puts: VecDeque<(T, Handler<bool>)>,
+    takes: VecDeque<Handler<T>>,
+    closed: bool,
+}
+
+/// A CSP channel. Operations that can't complete right away leave a handler
+/// behind for the operation that later completes them to call.
+#[derive(Clone)]
+pub struct Chan(Arc<Mutex<ChanState>>);
+
+impl Chan {
+    pub fn new(buffer: Option<Buffer>) -> Chan {
+        Chan(Arc::new(Mutex::new(ChanState {
+            buffer: buffer.map(|buffer| (buffer, VecDeque::with_capacity(buffer.size))),
+            puts: VecDeque::new(),
+            takes: VecDeque::new(),
+            closed: false,
+        })))
+    }
+
+    pub fn is_closed(&self) -> bool {
+        lock(&self.0).closed
+    }
+
+    /// Puts `value`, returning whether the channel took it, or `None` if the
+    /// handler is called later. With `wait` false the handler is dropped instead.
+    fn put(&self, value: T, handler: Handler<bool>, wait: bool) -> Result<Option<bool>, Error> {
+        let mut wakeups: Vec<Job> = Vec::new();
+        let result = self.put_locked(value, handler, wait, &mut wakeups);
+        for wakeup in wakeups {
+            wakeup();
+        }
+        result
+    }
+
+    fn put_locked(
+        &self,
+        value: T,
+        handler: Handler<bool>,
+        wait: bool,
+        wakeups: &mut Vec<Job>,
+    ) -> Result<Option<bool>, Error> {
+        let mut state = lock(&self.0);
+        if state.closed {
+            return Ok(handler.flag.commit().then_some(false));
+        }
+        while let Some(taker) = state.takes.pop_front() {
+            match commit_pair(&handler.flag, &taker.flag) {
+                (true, true) => {
+                    let callback = taker.callback;
+                    wakeups.push(Box::new(move || callback(value)));
+                    return Ok(Some(true));
+                }
+                (false, _) => {
+                    state.takes.push_front(taker);
+                    return Ok(None);
+                }
+                // Left behind by an `alts!` that completed elsewhere.
+                (true, false) => {}
+            }
+        }
+        if let Some((buffer, items)) = &mut state.buffer {
+            if buffer.kind != BufferKind::Fixed || items.len() < buffer.size {
+                if !handler.flag.commit() {
+                    return Ok(None);
+                }
+                if items.len() == buffer.size {
+                    match buffer.kind {
+                        BufferKind::Sliding => {
+                            items.pop_front();
+                        }
+                        _ => return Ok(Some(true)),
+                    }
+                }
+                items.push_back(value);
+                return Ok(Some(true));
+            }
+        }
+        if wait && handler.flag.is_active() {
+            state.puts.retain(|(_, putter)| putter.flag.is_active());
+            if state.puts.len() >= MAX_PENDING {
+                return Err(Error::illegal_state(format!(
+                    "No more than {} pending puts are allowed on a single channel",
+                    MAX_PENDING
+                )));
+            }
+            state.puts.push_back((value, handler));
+        }
+        Ok(None)
+    }
+
+    /// Takes a value, nil once the channel is closed and drained, or returns
+    /// `None` if the handler is called later.
+    fn take(&self, handler: Handler<T>, wait: bool) -> Result<Option<T>, Error> {
+        let mut wakeups: Vec<Job> = Vec::new();
+        let result = self.take_locked(handler, wait, &mut wakeups);
+        for wakeup in wakeups {
+            wakeup();
+        }
+        result
+    }
+
+    fn take_locked(
+        &self,
+        handler: Handler<T>,
+        wait: bool,
+        wakeups: &mut Vec<Job>,
+    ) -> Result<Option<T>, Error> {
+        let mut state = lock(&self.0);
+        let state = &mut *state;
+        if let Some((buffer, items)) = &mut state.buffer {
+            if !items.is_empty() {
+                if !handler.flag.commit() {
+                    return Ok(None);
+                }
+                let value = items.pop_front();
+                // The room made lets a waiting put in.
+                while items.len() < buffer.size {
+                    match state.puts.pop_front() {
+                        Some((value, putter)) => {
+                            if putter.flag.commit() {
+                                items.push_back(value);
+                                let callback = putter.callback;
+                                wakeups.push(Box::new(move || callback(true)));
+                            }
+                        }
+                        None => break,
+                    }
+                }
+                return Ok(value);
+            }
+        }
+        while let Some((value, putter)) = state.puts.pop_front() {
+            match commit_pair(&handler.flag, &putter.flag) {
+                (true, true) => {
+                    let callback = putter.callback;
+                    wakeups.push(Box::new(move || callback(true)));
+                    return Ok(Some(value));
+                }
+                (false, _) => {
+                    state.puts.push_front((value, putter));
+                    return Ok(None);
+                }
+                (true, false) => {}
+            }
+        }
+        if state.closed {
+            return Ok(handler.flag.commit().then_some(T::Nil));
+        }
+        if wait && handler.flag.is_active() {
+            state.takes.retain(|taker| taker.flag.is_active());
+            if state.takes.len() >= MAX_PENDING {
+                return Err(Error::illegal_state(format!(
+                    "No more than {} pending takes are allowed on a single channel",
+                    MAX_PENDING
+                )));
+            }
+            state.takes.push_back(handler);
+        }
+        Ok(None)
+    }
+
+    /// Closes the channel, giving nil to the takes waiting on it. Puts already
+    /// waiting still go through.
+    pub fn close(&self) {
+        let takers = {
+            let mut state = lock(&self.0);
+            if state.closed {
+                return;
+            }
+            state.closed = true;
+            std::mem::take(&mut state.takes)
+        };
+        for taker in takers {
+            if taker.flag.commit() {
+                (taker.callback)(T::Nil);
+            }
+        }
+    }
+
+    /// Puts `value` only if that needs no waiting.
+    fn offer(&self, value: T) -> Result<Option<bool>, Error> {
+        self.put(
+            value,
+            Handler {
+                flag: Flag::new(),
+                callback: Box::new(drop),
+            },
+            false,
+        )
+    }
+}
+
+impl PartialEq for Chan {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Chan {}
+
+// Channels are identities, told apart by address rather than by contents.
+impl fmt::Debug for Chan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Chan({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+thread_local! {
+    // The go block running on this thread, which parking operations suspend.
+    static CURRENT_TASK: RefCell<Option<Arc<GoTask>>> = const { RefCell::new(None) };
+}
+
+/// A go block: a coroutine resumed by the worker thread it was given, each
+/// time an operation it parked on completes.
+struct GoTask {
+    coroutine: Mutex<Option<Generator<'static, (), ()>>>,
+    /// The context of the coroutine while it is parked.
+    context: Mutex<TaskContext>,
+    worker: usize,
+}
+
+/// The thread locals a go block carries between the workers' threads.
+struct TaskContext {
+    scope: Hamt<String, T>,
+    ns: String,
+    bindings: Hamt<String, T>,
+    depth: usize,
+    env: &'static Env,
+}
+
+impl Default for TaskContext {
+    fn default() -> TaskContext {
+        TaskContext {
+            scope: Hamt::new(),
+            ns: String::new(),
+            bindings: Hamt::new(),
+            depth: 0,
+            env: Env::root(),
+        }
+    }
+}
+
+fn workers() -> &'static [Mutex<Sender<Arc<GoTask>>>] {
+    static WORKERS: OnceLock<Vec<Mutex<Sender<Arc<GoTask>>>>> = OnceLock::new();
+    WORKERS.get_or_init(|| {
+        (0..GO_THREADS)
+            .map(|i| {
+                let (tasks, receiver) = channel::<Arc<GoTask>>();
+                // Without a worker its go blocks never run, as with a full pool.
+                let _ = thread::Builder::new()
+                    .name(format!("async-dispatch-{}", i))
+                    .stack_size(THREAD_STACK_SIZE)
+                    .spawn(move || {
+                        for task in receiver {
+                            task.resume();
+                        }
+                    });
+                Mutex::new(tasks)
+            })
+            .collect()
+    })
+}
+
+impl GoTask {
+    fn wake(self: &Arc<Self>) {
+        // Workers live as long as the process.
+        let _ = lock(&workers()[self.worker]).send(self.clone());
+    }
+
+    /// Runs the coroutine until it parks or finishes, with its own locals,
+    /// namespace, bindings and environment in place of the worker's.
+    fn resume(self: &Arc<Self>) {
+        let mut coroutine = lock(&self.coroutine);
+        let generator = match coroutine.as_mut() {
+            Some(generator) => generator,
+            None => return,
+        };
+        let TaskContext {
+            scope,
+            ns,
+            bindings,
+            depth,
+            env,
+        } = std::mem::take(&mut *lock(&self.context));
+        let worker_scope = SCOPE.with(|current| current.replace(scope));
+        let worker_ns = CURRENT_NS.with(|current| current.replace(ns));
+        let worker_bindings = BINDINGS.with(|current| current.replace(bindings));
+        let worker_depth = DEPTH.with(|current| current.replace(depth));
+        let worker_env = ENV.with(|current| current.replace(env));
+        CURRENT_TASK.with(|task| task.replace(Some(self.clone())));
+        generator.resume();
+        CURRENT_TASK.with(|task| task.replace(None));
+        *lock(&self.context) = TaskContext {
+            scope: SCOPE.with(|current| current.replace(worker_scope)),
+            ns: CURRENT_NS.with(|current| current.replace(worker_ns)),
+            bindings: BINDINGS.with(|current| current.replace(worker_bindings)),
+            depth: DEPTH.with(|current| current.replace(worker_depth)),
+            env: ENV.with(|current| current.replace(worker_env)),
+        };
+        if generator.is_done() {
+            *coroutine = None;
+        }
+    }
+}
+
+fn report(name: &str, err: &Error) {
+    eprintln!("Exception in {}: {}", name, err.trace());
+}
+
+/// Runs `f` as a go block, reporting the error it fails with.
+fn go_spawn(name: &'static str, f: impl FnOnce() -> Result<(), Error> + Send + 'static) {
+    static NEXT_WORKER: AtomicUsize = AtomicUsize::new(0);
+    // Stacks are mapped lazily, so parked go blocks only hold the pages they used.
+    let stack_words = THREAD_STACK_SIZE / std::mem::size_of::<usize>();
+    let coroutine = Gn::<()>::new_opt(stack_words, move || {
+        if let Err(err) = f() {
+            report(name, &err);
+        }
+    });
+    let task = Arc::new(GoTask {
+        coroutine: Mutex::new(Some(coroutine)),
+        context: Mutex::new(TaskContext {
+            scope: Hamt::new(),
+            ns: current_ns(),
+            bindings: current_bindings(),
+            depth: 0,
+            env: current_env(),
+        }),
+        worker: NEXT_WORKER.fetch_add(1, Ordering::Relaxed) % GO_THREADS,
+    });
+    task.wake();
+}
+
+/// How an operation that can't complete right away waits for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    /// Blocks the thread, for `>!!`, `<!!` and `alts!!`.
+    Block,
+    /// Parks the go block, freeing its thread, for `>!`, `<!` and `alts!`.
+    Park,
+}
+
+/// Offers operations under one flag, then waits for the one completing if
+/// none did right away. `offer` gets the flag and makes the callbacks ending the wait.
+fn wait_for<R: Send + 'static>(
+    name: &str,
+    wait: Wait,
+    offer: impl FnOnce(&Arc<Flag>, &dyn Fn() -> Callback<R>) -> Result<Option<R>, Error>,
+) -> Result<R, Error> {
+    let flag = Flag::new();
+    match wait {
+        Wait::Block => {
+            let (sender, receiver) = channel();
+            let make = || -> Callback<R> {
+                let sender = sender.clone();
+                Box::new(move |result| {
+                    let _ = sender.send(result);
+                })
+            };
+            if let Some(result) = offer(&flag, &make)? {
+                return Ok(result);
+            }
+            receiver
+                .recv()
+                .map_err(|_| Error::illegal_state("Channel operation abandoned"))
+        }
+        Wait::Park => {
+            let task = CURRENT_TASK
+                .with(|task| task.borrow().clone())
+                .ok_or_else(|| {
+                    Error::illegal_state(format!("`{}` used not in (go ...) block", name))
+                })?;
+            let slot = Arc::new(Mutex::new(None));
+            let make = || -> Callback<R> {
+                let slot = slot.clone();
+                let task = task.clone();
+                Box::new(move |result| {
+                    *lock(&slot) = Some(result);
+                    task.wake();
+                })
+            };
+            if let Some(result) = offer(&flag, &make)? {
+                return Ok(result);
+            }
+            loop {
+                // The scoped yield replacing this one needs the scope handed down
+                // to here, through every native between the go block and the take.
+                #[allow(deprecated)]
+                generator::yield_with(());
+                if let Some(result) = lock(&slot).take() {
+                    return Ok(result);
+                }
+            }
+        }
+    }
+}
+
+fn nil_put() -> Error {
+    Error::illegal_argument("Can't put nil on channel")
+}
+
+fn take_from(name: &str, wait: Wait, chan: &Chan) -> Result<T, Error> {
+    wait_for(name, wait, |flag, make| {
+        chan.take(
+            Handler {
+                flag: flag.clone(),
+                callback: make(),
+            },
+            true,
+        )
+    })
+}
+
+fn put_onto(name: &str, wait: Wait, chan: &Chan, value: T) -> Result<bool, Error> {
+    if value == T::Nil {
+        return Err(nil_put());
+    }
+    wait_for(name, wait, |flag, make| {
+        chan.put(
+            value,
+            Handler {
+                flag: flag.clone(),
+                callback: make(),
+            },
+            true,
+        )
+    })
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+fn expect_chan(name: &str, value: T) -> Result<Chan, Error> {
+    match value {
+        T::Chan(chan) => Ok(chan),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects a channel but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+fn size_of(name: &str, value: &T) -> Result<usize, Error> {
+    match value {
+        T::Int(n) => n.to_usize().ok_or_else(|| {
+            Error::illegal_argument(format!("`{}` expects a size but received {}", name, n))
+        }),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects a size but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+fn buffer_with(name: &str, kind: BufferKind, list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [size] => match size_of(name, size)? {
+            0 => Err(Error::illegal_argument(format!(
+                "`{}` expects a positive size",
+                name
+            ))),
+            size => Ok(T::Buffer(Buffer { kind, size })),
+        },
+        _ => Err(arity(name, 1, "1", list)),
+    }
+}
+
+/// `(buffer n)` holds up to `n` items, after which puts wait.
+pub fn buffer(list: &[T]) -> Result<T, Error> {
+    buffer_with("buffer", BufferKind::Fixed, list)
+}
+
+pub fn dropping_buffer(list: &[T]) -> Result<T, Error> {
+    buffer_with("dropping-buffer", BufferKind::Dropping, list)
+}
+
+pub fn sliding_buffer(list: &[T]) -> Result<T, Error> {
+    buffer_with("sliding-buffer", BufferKind::Sliding, list)
+}
+
+/// `(chan)` is unbuffered, `(chan n)` has a fixed buffer of `n` and
+/// `(chan buf)` uses one made by `buffer`, `dropping-buffer` or `sliding-buffer`.
+pub fn chan(list: &[T]) -> Result<T, Error> {
+    let buffer = match &eval_all(list)?[..] {
+        [] | [T::Nil] => None,
+        [T::Buffer(buffer)] => Some(*buffer),
+        [size] => Some(Buffer::fixed(size_of("chan", size)?)).filter(|buffer| buffer.size > 0),
+        _ => return Err(arity("chan", 0, "0 or 1", list)),
+    };
+    Ok(T::Chan(Chan::new(buffer)))
+}
+
+pub fn close(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [chan] => {
+            expect_chan("close!", chan.clone())?.close();
+            Ok(T::Nil)
+        }
+        _ => Err(arity("close!", 1, "1", list)),
+    }
+}
+
+fn put_with(name: &str, wait: Wait, list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [chan, value] => Ok(T::Bool(put_onto(
+            name,
+            wait,
+            &expect_chan(name, chan.clone())?,
+            value.clone(),
+        )?)),
+        _ => Err(arity(name, 2, "2", list)),
+    }
+}
+
+fn take_with(name: &str, wait: Wait, list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [chan] => take_from(name, wait, &expect_chan(name, chan.clone())?),
+        _ => Err(arity(name, 1, "1", list)),
+    }
+}
+
+/// `(>!! ch v)` blocks until `v` is put, returning false if `ch` is closed.
+pub fn put_blocking(list: &[T]) -> Result<T, Error> {
+    put_with(">!!", Wait::Block, list)
+}
+
+/// `(<!! ch)` blocks until a value is taken, returning nil once `ch` is closed.
+pub fn take_blocking(list: &[T]) -> Result<T, Error> {
+    take_with("<!!", Wait::Block, list)
+}
+
+/// Like `>!!`, parking the go block instead of blocking its thread.
+pub fn put_parking(list: &[T]) -> Result<T, Error> {
+    put_with(">!", Wait::Park, list)
+}
+
+/// Like `<!!`, parking the go block instead of blocking its thread.
+pub fn take_parking(list: &[T]) -> Result<T, Error> {
+    take_with("<!", Wait::Park, list)
+}
+
+/// Calls a `put!` or `take!` callback in a go block of its own.
+fn call_back(name: &'static str, f: T, value: T) {
+    go_spawn(name, move || apply(&f, &[value]).map(drop));
+}
+
+/// `(put! ch v fn1?)` puts without waiting, calling `fn1` with the result
+/// once the put completes.
+pub fn put_async(list: &[T]) -> Result<T, Error> {
+    let args = eval_all(list)?;
+    let (chan, value, f) = match &args[..] {
+        [chan, value] => (chan, value, None),
+        [chan, value, f] => (chan, value, Some(f.clone())),
+        _ => return Err(arity("put!", 2, "2 or 3", list)),
+    };
+    let chan = expect_chan("put!", chan.clone())?;
+    if *value == T::Nil {
+        return Err(nil_put());
+    }
+    let callback: Callback<bool> = match f.clone() {
+        Some(f) => Box::new(move |ok| call_back("put!", f, T::Bool(ok))),
+        None => Box::new(drop),
+    };
+    let handler = Handler {
+        flag: Flag::new(),
+        callback,
+    };
+    match chan.put(value.clone(), handler, true)? {
+        Some(ok) => {
+            if let Some(f) = f {
+                apply(&f, &[T::Bool(ok)])?;
+            }
+            Ok(T::Bool(ok))
+        }
+        None => Ok(T::Bool(true)),
+    }
+}
+
+/// `(take! ch fn1)` takes without waiting, calling `fn1` with the value once
+/// there is one.
+pub fn take_async(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [chan, f] => {
+            let chan = expect_chan("take!", chan.clone())?;
+            let callback = f.clone();
+            let handler = Handler {
+                flag: Flag::new(),
+                callback: Box::new(move |value| call_back("take!", callback, value)),
+            };
+            if let Some(value) = chan.take(handler, true)? {
+                apply(f, &[value])?;
+            }
+            Ok(T::Nil)
+        }
+        _ => Err(arity("take!", 2, "2", list)),
+    }
+}
+
+enum Op {
+    Take(Chan),
+    Put(Chan, T),
+}
+
+fn alts_with(name: &str, wait: Wait, list: &[T]) -> Result<T, Error> {
+    static ROTATION: AtomicUsize = AtomicUsize::new(0);
+    let args = eval_all(list)?;
+    let (ports, options) = match args.split_first() {
+        Some((ports, options)) if options.len() % 2 == 0 => (ports, options),
+        _ => return Err(arity(name, 1, "1 plus option pairs", list)),
+    };
+    let mut default = None;
+    let mut priority = false;
+    for option in options.chunks(2) {
+        match &option[0] {
+            T::Keyword(key) if key == ":default" => default = Some(option[1].clone()),
+            T::Keyword(key) if key == ":priority" => priority = option[1].is_truthy(),
+            key => {
+                return Err(Error::cant_eval(format!(
+                    "Unknown `{}` option {}",
+                    name,
+                    key.print()?
+                )))
+            }
+        }
+    }
+    let ops = items(ports.clone())?
+        .into_iter()
+        .map(|port| match port {
+            T::Vector(put, _) => match &put[..] {
+                [_, T::Nil] => Err(nil_put()),
+                [chan, value] => Ok(Op::Put(expect_chan(name, chan.clone())?, value.clone())),
+                _ => Err(Error::cant_eval(format!(
+                    "`{}` expects puts as [channel value]",
+                    name
+                ))),
+            },
+            port => Ok(Op::Take(expect_chan(name, port)?)),
+        })
+        .collect::<Result<Vec<Op>, Error>>()?;
+    if ops.is_empty() {
+        return Err(Error::illegal_argument(format!(
+            "`{}` expects at least one port",
+            name
+        )));
+    }
+    // Without :priority each call starts at another port, so none starves.
+    let start = if priority {
+        0
+    } else {
+        ROTATION.fetch_add(1, Ordering::Relaxed)
+    };
+    let offer = |flag: &Arc<Flag>,
+                 make: &dyn Fn() -> Callback<(T, T)>,
+                 wait: bool|
+     -> Result<Option<(T, T)>, Error> {
+        for i in 0..ops.len() {
+            let done = match &ops[(start + i) % ops.len()] {
+                Op::Take(chan) => {
+                    let port = T::Chan(chan.clone());
+                    let callback = make();
+                    let handler = Handler {
+                        flag: flag.clone(),
+                        callback: Box::new(move |value| callback((value, port))),
+                    };
+                    chan.take(handler, wait)?
+                        .map(|value| (value, T::Chan(chan.clone())))
+                }
+                Op::Put(chan, value) => {
+                    let port = T::Chan(chan.clone());
+                    let callback = make();
+                    let handler = Handler {
+                        flag: flag.clone(),
+                        callback: Box::new(move |ok| callback((T::Bool(ok), port))),
+                    };
+                    chan.put(value.clone(), handler, wait)?
+                        .map(|ok| (T::Bool(ok), T::Chan(chan.clone())))
+                }
+            };
+            if done.is_some() {
+                return Ok(done);
+            }
+        }
+        Ok(None)
+    };
+    let (value, port) = match default {
+        Some(default) => {
+            let ignore = || -> Callback<(T, T)> { Box::new(drop) };
+            offer(&Flag::new(), &ignore, false)?
+                .unwrap_or((default, T::Keyword(String::from(":default"))))
+        }
+        None => wait_for(name, wait, |flag, make| offer(flag, make, true))?,
+    };
+    Ok(T::vector(vec![value, port]))
+}
+
+/// `(alts!! ports & {:keys [default priority]})` completes the first of the
+/// takes and `[channel value]` puts that can, returning `[value port]`.
+pub fn alts_blocking(list: &[T]) -> Result<T, Error> {
+    alts_with("alts!!", Wait::Block, list)
+}
+
+/// Like `alts!!`, parking the go block instead of blocking its thread.
+pub fn alts_parking(list: &[T]) -> Result<T, Error> {
+    alts_with("alts!", Wait::Park, list)
+}
+
+/// Puts a go block's or thread's result on the channel returned for it, then
+/// closes it.
+fn deliver(chan: &Chan, result: Result<T, Error>) -> Result<(), Error> {
+    let result = match result {
+        Ok(T::Nil) => Ok(()),
+        Ok(value) => chan.offer(value).map(drop),
+        Err(err) => Err(err),
+    };
+    chan.close();
+    result
+}
+
+/// `(go-call f)` calls `f` in a go block, returning a channel that gets its
+/// result. `go` expands to it.
+pub fn go_call(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [f] => {
+            let f = f.clone();
+            let result = Chan::new(Some(Buffer::fixed(1)));
+            let chan = result.clone();
+            go_spawn("go block", move || deliver(&chan, apply(&f, &[])));
+            Ok(T::Chan(result))
+        }
+        _ => Err(arity("go-call", 1, "1", list)),
+    }
+}
+
+/// `(thread-call f)` calls `f` on a thread of its own, for work that blocks.
+/// `thread` expands to it.
+pub fn thread_call(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [f] => {
+            let f = f.clone();
+            let result = Chan::new(Some(Buffer::fixed(1)));
+            let chan = result.clone();
+            let frame = current_bindings();
+            spawn(Box::new(move || {
+                if let Err(err) = deliver(&chan, with_bindings_frame(frame, || apply(&f, &[]))) {
+                    report("thread", &err);
+                }
+            }))?;
+            Ok(T::Chan(result))
+        }
+        _ => Err(arity("thread-call", 1, "1", list)),
+    }
+}
+
+type Timers = (Mutex<BTreeMap<(Instant, u64), Chan>>, Condvar);
+
+/// The channels `timeout` made, by the time they close.
+fn timers() -> &'static Timers {
+    static TIMERS: OnceLock<Timers> = OnceLock::new();
+    TIMERS.get_or_init(|| {
+        // Without the thread timeouts never close, which `alts!` callers notice.
+        let _ = thread::Builder::new()
+            .name(String::from("async-timers"))
+            .spawn(close_timeouts);
+        (Mutex::new(BTreeMap::new()), Condvar::new())
+    })
+}
+
+fn close_timeouts() {
+    let (timers, added) = timers();
+    let mut pending = lock(timers);
+    loop {
+        let now = Instant::now();
+        pending = match pending.keys().next().copied() {
+            Some(key) if key.0 <= now => {
+                let chan = pending.remove(&key);
+                drop(pending);
+                if let Some(chan) = chan {
+                    chan.close();
+                }
+                lock(timers)
+            }
+            Some((deadline, _)) => {
+                added
+                    .wait_timeout(pending, deadline - now)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .0
+            }
+            None => added
+                .wait(pending)
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        };
+    }
+}
+
+/// `(timeout ms)` returns a channel that closes after `ms` milliseconds.
+pub fn timeout(list: &[T]) -> Result<T, Error> {
+    static TIMER_IDS: AtomicU64 = AtomicU64::new(0);
+    match &eval_all(list)?[..] {
+        [ms] => {
+            let deadline = Instant::now() + millis("timeout", ms)?;
+            let chan = Chan::new(None);
+            let (timers, added) = timers();
+            lock(timers).insert(
+                (deadline, TIMER_IDS.fetch_add(1, Ordering::Relaxed)),
+                chan.clone(),
+            );
+            added.notify_one();
+            Ok(T::Chan(chan))
+        }
+        _ => Err(arity("timeout", 1, "1", list)),
+    }
+}
+
+/// `(pipeline n to xf from close? ex-handler)` moves the items of `from`
+/// through the transducer `xf` into `to`, in order, with up to `n` transformed
+/// items waiting for `to`. The transducer keeps its state from one item to the
+/// next and, once it wants no more, e.g. after `take`, leaves the rest of `from`.
+/// `to` is closed after that unless `close?` is false, and failures go to
+/// `ex-handler`, whose non-nil results are put on `to`.
+pub fn pipeline(list: &[T]) -> Result<T, Error> {
+    let args = eval_all(list)?;
+    let (n, to, xf, from, options) = match &args[..] {
+        [n, to, xf, from, options @ ..] if options.len() <= 2 => (n, to, xf, from, options),
+        _ => return Err(arity("pipeline", 4, "4 to 6", list)),
+    };
+    let n = size_of("pipeline", n)?.max(1);
+    let to = expect_chan("pipeline", to.clone())?;
+    let from = expect_chan("pipeline", from.clone())?;
+    if !matches!(xf, T::Xform(_)) {
+        return Err(Error::cant_eval(format!(
+            "`pipeline` expects a transducer but received {}",
+            xf.print()?
+        )));
+    }
+    let mut transform = Transform::new(xf)?;
+    let close = options.first().is_none_or(T::is_truthy);
+    let ex_handler = options.get(1).cloned().filter(T::is_truthy);
+
+    let results = Chan::new(Some(Buffer::fixed(n)));
+    {
+        let results = results.clone();
+        go_spawn("pipeline", move || loop {
+            let value = take_from("pipeline", Wait::Park, &from)?;
+            let end = value == T::Nil;
+            let mut transformed = if end {
+                Ok(Vec::new())
+            } else {
+                transform.step(value)
+            };
+            if end || transform.is_done() {
+                transformed = transformed.and_then(|mut values| {
+                    values.extend(transform.complete()?);
+                    Ok(values)
+                });
+            }
+            let values = match (transformed, &ex_handler) {
+                (Ok(values), _) => values,
+                (Err(err), Some(ex_handler)) => {
+                    vec![apply(ex_handler, &[T::Throwable(Box::new(err))])?]
+                }
+                (Err(err), None) => {
+                    report("pipeline", &err);
+                    Vec::new()
+                }
+            };
+            // Channels can't carry nil, so nil results are dropped.
+            for value in values.into_iter().filter(|value| *value != T::Nil) {
+                put_onto("pipeline", Wait::Park, &results, value)?;
+            }
+            if end || transform.is_done() {
+                results.close();
+                return Ok(());
+            }
+        });
+    }
+    go_spawn("pipeline", move || loop {
+        match take_from("pipeline", Wait::Park, &results)? {
+            T::Nil => {
+                if close {
+                    to.close();
+                }
+                return Ok(());
+            }
+            value => {
+                put_onto("pipeline", Wait::Park, &to, value)?;
+            }
+        }
+    });
+    Ok(T::Nil)
+}
+
+struct Tap {
+    /// The topic subscribed to, for a `pub`.
+    topic: Option<T>,
+    chan: Chan,
+    /// Whether the tap is closed with the source.
+    close: bool,
+}
+
+struct MuxState {
+    /// Gives the topic of every item, for a `pub`.
+    topic_fn: Option<T>,
+    taps: Mutex<Vec<Tap>>,
+}
+
+/// What `mult` and `pub` return: a channel whose items are copied to the
+/// channels tapping it, or subscribed to their topic.
+#[derive(Clone)]
+pub struct Mux(Arc<MuxState>);
+
+impl Mux {
+    fn start(source: Chan, topic_fn: Option<T>) -> Mux {
+        let mux = Mux(Arc::new(MuxState {
+            topic_fn,
+            taps: Mutex::new(Vec::new()),
+        }));
+        let distributing = mux.clone();
+        go_spawn(mux.name(), move || distributing.distribute(&source));
+        mux
+    }
+
+    fn name(&self) -> &'static str {
+        if self.0.topic_fn.is_some() {
+            "pub"
+        } else {
+            "mult"
+        }
+    }
+
+    /// Copies every item of `source` to the taps, each taking it before the
+    /// next item is read.
+    fn distribute(&self, source: &Chan) -> Result<(), Error> {
+        loop {
+            let value = take_from(self.name(), Wait::Park, source)?;
+            if value == T::Nil {
+                for tap in std::mem::take(&mut *lock(&self.0.taps)) {
+                    if tap.close {
+                        tap.chan.close();
+                    }
+                }
+                return Ok(());
+            }
+            let topic = match &self.0.topic_fn {
+                Some(topic_fn) => Some(apply(topic_fn, std::slice::from_ref(&value))?),
+                None => None,
+            };
+            let taps = lock(&self.0.taps)
+                .iter()
+                .filter(|tap| tap.topic == topic)
+                .map(|tap| tap.chan.clone())
+                .collect::<Vec<Chan>>();
+            for chan in taps {
+                if !put_onto(self.name(), Wait::Park, &chan, value.clone())? {
+                    // Closed taps are dropped.
+                    lock(&self.0.taps).retain(|tap| tap.chan != chan);
+                }
+            }
+        }
+    }
+
+    fn add(&self, topic: Option<T>, chan: Chan, close: bool) {
+        let mut taps = lock(&self.0.taps);
+        taps.retain(|tap| !(tap.topic == topic && tap.chan == chan));
+        taps.push(Tap { topic, chan, close });
+    }
+
+    fn remove(&self, keep: impl Fn(&Tap) -> bool) {
+        lock(&self.0.taps).retain(keep);
+    }
+
+    pub fn print(&self) -> String {
+        format!("#{}[{} taps]", self.name(), lock(&self.0.taps).len())
+    }
+}
+
+impl PartialEq for Mux {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Mux {}
+
+impl fmt::Debug for Mux {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mux({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+fn expect_mux(name: &str, kind: &str, value: T) -> Result<Mux, Error> {
+    match value {
+        T::Mux(mux) if mux.name() == kind => Ok(mux),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects a {} but received {}",
+            name,
+            kind,
+            value.print()?
+        ))),
+    }
+}
+
+/// `(mult ch)` copies every item of `ch` to the channels tapping it.
+pub fn mult(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [chan] => Ok(T::Mux(Mux::start(expect_chan("mult", chan.clone())?, None))),
+        _ => Err(arity("mult", 1, "1", list)),
+    }
+}
+
+/// `(tap mult ch close?)` copies the items of `mult` to `ch`, closing it with
+/// the source unless `close?` is false.
+pub fn tap_mult(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [mult, chan, options @ ..] if options.len() <= 1 => {
+            expect_mux("tap", "mult", mult.clone())?.add(
+                None,
+                expect_chan("tap", chan.clone())?,
+                options.first().is_none_or(T::is_truthy),
+            );
+            Ok(chan.clone())
+        }
+        _ => Err(arity("tap", 2, "2 or 3", list)),
+    }
+}
+
+pub fn untap(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [mult, chan] => {
+            let chan = expect_chan("untap", chan.clone())?;
+            expect_mux("untap", "mult", mult.clone())?.remove(|tap| tap.chan != chan);
+            Ok(T::Nil)
+        }
+        _ => Err(arity("untap", 2, "2", list)),
+    }
+}
+
+pub fn untap_all(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [mult] => {
+            expect_mux("untap-all", "mult", mult.clone())?.remove(|_| false);
+            Ok(T::Nil)
+        }
+        _ => Err(arity("untap-all", 1, "1", list)),
+    }
+}
+
+/// `(pub ch topic-fn)` sends every item of `ch` to the channels subscribed to
+/// its `(topic-fn item)`, dropping those nobody subscribed to.
+pub fn publication(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [chan, topic_fn] => Ok(T::Mux(Mux::start(
+            expect_chan("pub", chan.clone())?,
+            Some(topic_fn.clone()),
+        ))),
+        _ => Err(arity("pub", 2, "2", list)),
+    }
+}
+
+/// `(sub pub topic ch close?)` subscribes `ch` to `topic`, closing it with the
+/// source unless `close?` is false.
+pub fn subscribe(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [publication, topic, chan, options @ ..] if options.len() <= 1 => {
+            expect_mux("sub", "pub", publication.clone())?.add(
+                Some(topic.clone()),
+                expect_chan("sub", chan.clone())?,
+                options.first().is_none_or(T::is_truthy),
+            );
+            Ok(chan.clone())
+        }
+        _ => Err(arity("sub", 3, "3 or 4", list)),
+    }
+}
+
+pub fn unsubscribe(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [publication, topic, chan] => {
+            let chan = expect_chan("unsub", chan.clone())?;
+            expect_mux("unsub", "pub", publication.clone())?
+                .remove(|tap| tap.topic.as_ref() != Some(topic) || tap.chan != chan);
+            Ok(T::Nil)
+        }
+        _ => Err(arity("unsub", 3, "3", list)),
+    }
+}
+
+/// `(unsub-all pub topic?)` removes the subscriptions to `topic`, or all of them.
+pub fn unsubscribe_all(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [publication] => {
+            expect_mux("unsub-all", "pub", publication.clone())?.remove(|_| false);
+            Ok(T::Nil)
+        }
+        [publication, topic] => {
+            expect_mux("unsub-all", "pub", publication.clone())?
+                .remove(|tap| tap.topic.as_ref() != Some(topic));
+            Ok(T::Nil)
+        }
+        _ => Err(arity("unsub-all", 1, "1 or 2", list)),
+    }
+}
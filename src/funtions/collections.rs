@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use num_traits::ToPrimitive;
+
+use crate::{definitions::DefinitionTypes as T, error::Error};
+
+pub(crate) fn items(coll: T) -> Result<Vec<T>, Error> {
+    match coll {
+        T::Vector(vec, _) | T::Seq(vec, _) => Ok(vec),
+        T::HashSet(set, _) => Ok(set.into_iter().collect()),
+        T::OrderedSet(set) => Ok(set.into_iter().collect()),
+        T::HashMap(map, _) => Ok(map
+            .into_iter()
+            .map(|(k, v)| T::vector(vec![k, v]))
+            .collect()),
+        T::OrderedMap(map) => Ok(map
+            .into_iter()
+            .map(|(k, v)| T::vector(vec![k, v]))
+            .collect()),
+        T::Record(record) => Ok(record
+            .entries()
+            .iter()
+            .map(|(k, v)| T::vector(vec![k.clone(), v.clone()]))
+            .collect()),
+        T::String(s) => Ok(s.chars().map(T::Char).collect()),
+        T::Nil => Ok(Vec::new()),
+        T::List(_, _) => items(coll.eval()?),
+        _ => Err(Error::cant_eval(Some(format!(
+            "Don't know how to create a seq from {}",
+            coll.print().unwrap_or_default()
+        )))),
+    }
+}
+
+pub(crate) fn conj_one(coll: T, item: T) -> Result<T, Error> {
+    match (coll, item) {
+        (T::Vector(mut vec, meta), item) => {
+            vec.push(item);
+            Ok(T::Vector(vec, meta))
+        }
+        (T::Seq(mut seq, _), item) => {
+            seq.insert(0, item);
+            Ok(T::seq(seq))
+        }
+        (T::List(mut list, _), item) => {
+            list.insert(0, item);
+            Ok(T::seq(list))
+        }
+        (T::Nil, item) => Ok(T::seq(vec![item])),
+        (T::HashSet(mut set, meta), item) => {
+            set.insert(item);
+            Ok(T::HashSet(set, meta))
+        }
+        (T::OrderedSet(mut set), item) => {
+            set.insert(item);
+            Ok(T::OrderedSet(set))
+        }
+        (T::HashMap(mut map, meta), T::Vector(entry, _)) if entry.len() == 2 => {
+            map.insert(entry[0].clone(), entry[1].clone());
+            Ok(T::HashMap(map, meta))
+        }
+        (T::HashMap(mut map, meta), T::HashMap(other, _)) => {
+            map.extend(other);
+            Ok(T::HashMap(map, meta))
+        }
+        (map @ (T::HashMap(..) | T::OrderedMap(_) | T::Record(_)), T::Nil) => Ok(map),
+        (T::Record(record), T::Vector(entry, _)) if entry.len() == 2 => {
+            Ok(T::Record(record.assoc(entry[0].clone(), entry[1].clone())))
+        }
+        (T::Record(record), T::HashMap(other, _)) => Ok(T::Record(
+            other
+                .into_iter()
+                .fold(record, |record, (k, v)| record.assoc(k, v)),
+        )),
+        (T::Record(record), T::OrderedMap(other)) => Ok(T::Record(
+            other
+                .into_iter()
+                .fold(record, |record, (k, v)| record.assoc(k, v)),
+        )),
+        (T::OrderedMap(mut map), T::Vector(entry, _)) if entry.len() == 2 => {
+            map.insert(entry[0].clone(), entry[1].clone());
+            Ok(T::OrderedMap(map))
+        }
+        (T::OrderedMap(mut map), T::OrderedMap(mut other)) => {
+            map.append(&mut other);
+            Ok(T::OrderedMap(map))
+        }
+        (coll, item) => Err(Error::cant_eval(Some(format!(
+            "Can't conj {} into {}",
+            item.print().unwrap_or_default(),
+            coll.print().unwrap_or_default()
+        )))),
+    }
+}
+
+pub fn conj(list: &[T]) -> Result<T, Error> {
+    if let Some((coll, rest)) = list.split_first() {
+        rest.iter().try_fold(coll.clone().eval()?, |acc, e| {
+            conj_one(acc, e.clone().eval()?)
+        })
+    } else {
+        Ok(T::vector(Vec::new()))
+    }
+}
+
+pub(crate) fn lookup(coll: &T, key: &T) -> Option<T> {
+    match coll {
+        T::HashMap(map, _) => map.get(key).cloned(),
+        T::OrderedMap(map) => map.get(key).cloned(),
+        T::Record(record) => record.get(key),
+        T::HashSet(set, _) if set.contains(key) => Some(key.clone()),
+        T::OrderedSet(set) if set.contains(key) => Some(key.clone()),
+        T::Vector(vec, _) => match key {
+            T::Int(index) => index.to_usize().and_then(|i| vec.get(i)).cloned(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn get(list: &[T]) -> Result<T, Error> {
+    match list {
+        [coll, key] => Ok(lookup(&coll.clone().eval()?, &key.clone().eval()?).unwrap_or(T::Nil)),
+        [coll, key, not_found] => match lookup(&coll.clone().eval()?, &key.clone().eval()?) {
+            Some(value) => Ok(value),
+            None => not_found.clone().eval(),
+        },
+        _ => Err(Error::arity(
+            2,
+            format!("`get` has arity of 2 or 3 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn nth(list: &[T]) -> Result<T, Error> {
+    let (coll, index, not_found) = match list {
+        [coll, index] => (coll, index, None),
+        [coll, index, not_found] => (coll, index, Some(not_found)),
+        _ => {
+            return Err(Error::arity(
+                2,
+                format!("`nth` has arity of 2 or 3 but received {}", list.len()),
+            ))
+        }
+    };
+    let index = match index.clone().eval()? {
+        T::Int(index) => index.to_usize(),
+        index => {
+            return Err(Error::cant_eval(Some(format!(
+                "`nth` expects an integer index but received {}",
+                index.print()?
+            ))))
+        }
+    };
+
+    match (
+        index.and_then(|i| items(coll.clone().eval().ok()?).ok()?.into_iter().nth(i)),
+        not_found,
+    ) {
+        (Some(item), _) => Ok(item),
+        (None, Some(not_found)) => not_found.clone().eval(),
+        (None, None) => Err(Error::cant_eval(Some(String::from("Index out of bounds")))),
+    }
+}
+
+pub fn nthnext(list: &[T]) -> Result<T, Error> {
+    if list.len() != 2 {
+        return Err(Error::arity(
+            2,
+            format!("`nthnext` has arity of 2 but received {}", list.len()),
+        ));
+    }
+    let n = match list[1].clone().eval()? {
+        T::Int(n) => n.to_usize().unwrap_or(0),
+        n => {
+            return Err(Error::cant_eval(Some(format!(
+                "`nthnext` expects an integer but received {}",
+                n.print()?
+            ))))
+        }
+    };
+    let rest = items(list[0].clone().eval()?)?
+        .into_iter()
+        .skip(n)
+        .collect::<Vec<T>>();
+
+    if rest.is_empty() {
+        Ok(T::Nil)
+    } else {
+        Ok(T::seq(rest))
+    }
+}
+
+pub fn hash_map(list: &[T]) -> Result<T, Error> {
+    if !list.len().is_multiple_of(2) {
+        return Err(Error::cant_eval(Some(String::from(
+            "`hash-map` expects an even number of arguments",
+        ))));
+    }
+    let mut map = HashMap::new();
+    for pair in list.chunks(2) {
+        map.insert(pair[0].clone().eval()?, pair[1].clone().eval()?);
+    }
+    Ok(T::hash_map(map))
+}
+
+pub fn is_seq(list: &[T]) -> Result<T, Error> {
+    if list.len() != 1 {
+        return Err(Error::arity(
+            1,
+            format!("`seq?` has arity of 1 but received {}", list.len()),
+        ));
+    }
+    Ok(T::Bool(matches!(list[0].clone().eval()?, T::Seq(_, _))))
+}
+
+fn single(name: &str, list: &[T]) -> Result<T, Error> {
+    match list {
+        [el] => el.clone().eval(),
+        _ => Err(Error::arity(
+            1,
+            format!("`{}` has arity of 1 but received {}", name, list.len()),
+        )),
+    }
+}
+
+fn seq_or_nil(items: Vec<T>) -> T {
+    if items.is_empty() {
+        T::Nil
+    } else {
+        T::seq(items)
+    }
+}
+
+pub fn list(list: &[T]) -> Result<T, Error> {
+    Ok(T::seq(
+        list.iter()
+            .map(|el| el.clone().eval())
+            .collect::<Result<Vec<T>, Error>>()?,
+    ))
+}
+
+pub fn vector(list: &[T]) -> Result<T, Error> {
+    Ok(T::vector(
+        list.iter()
+            .map(|el| el.clone().eval())
+            .collect::<Result<Vec<T>, Error>>()?,
+    ))
+}
+
+pub fn cons(list: &[T]) -> Result<T, Error> {
+    match list {
+        [x, coll] => {
+            let mut seq = vec![x.clone().eval()?];
+            seq.extend(items(coll.clone().eval()?)?);
+            Ok(T::seq(seq))
+        }
+        _ => Err(Error::arity(
+            2,
+            format!("`cons` has arity of 2 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn concat(list: &[T]) -> Result<T, Error> {
+    let mut seq = Vec::new();
+    for coll in list {
+        seq.extend(items(coll.clone().eval()?)?);
+    }
+    Ok(T::seq(seq))
+}
+
+pub fn first(list: &[T]) -> Result<T, Error> {
+    Ok(items(single("first", list)?)?
+        .into_iter()
+        .next()
+        .unwrap_or(T::Nil))
+}
+
+pub fn second(list: &[T]) -> Result<T, Error> {
+    Ok(items(single("second", list)?)?
+        .into_iter()
+        .nth(1)
+        .unwrap_or(T::Nil))
+}
+
+pub fn next(list: &[T]) -> Result<T, Error> {
+    Ok(seq_or_nil(
+        items(single("next", list)?)?.into_iter().skip(1).collect(),
+    ))
+}
+
+pub fn rest(list: &[T]) -> Result<T, Error> {
+    Ok(T::seq(
+        items(single("rest", list)?)?.into_iter().skip(1).collect(),
+    ))
+}
+
+pub fn seq(list: &[T]) -> Result<T, Error> {
+    Ok(seq_or_nil(items(single("seq", list)?)?))
+}
+
+pub fn count(list: &[T]) -> Result<T, Error> {
+    Ok(T::Int(items(single("count", list)?)?.len().into()))
+}
+
+pub fn is_empty(list: &[T]) -> Result<T, Error> {
+    Ok(T::Bool(items(single("empty?", list)?)?.is_empty()))
+}
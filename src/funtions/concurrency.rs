@@ -0,0 +1,598 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Sender},
+        Arc, Condvar, Mutex, MutexGuard, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use arc_swap::ArcSwap;
+use im::HashMap as Hamt;
+
+use crate::{
+    definitions::DefinitionTypes as T,
+    env::{current_env, with_env, Env},
+    error::Error,
+};
+
+use super::{
+    apply, arity,
+    collections::items,
+    current_scope,
+    namespaces::{current_ns, with_ns},
+    special_forms::eval_body,
+    vars::{current_bindings, with_bindings_frame},
+    with_scope, THREAD_STACK_SIZE,
+};
+
+pub(crate) type Job = Box<dyn FnOnce() + Send>;
+
+pub(crate) fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+    // Jobs never panic while holding these locks; a poisoned one still holds valid data.
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn parallelism() -> usize {
+    thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+/// Runs `job` on a thread of its own, in this thread's environment, like
+/// Clojure's unbounded pool for futures and `send-off`.
+pub(crate) fn spawn(job: Job) -> Result<(), Error> {
+    let env = current_env();
+    thread::Builder::new()
+        .stack_size(THREAD_STACK_SIZE)
+        .spawn(move || with_env(env, job))
+        .map(|_| ())
+        .map_err(|err| Error::reason(format!("Could not start a thread: {}", err)))
+}
+
+/// The fixed pool `send` runs agent actions on, sized for CPU bound work.
+fn pool() -> &'static Mutex<Sender<Job>> {
+    static POOL: OnceLock<Mutex<Sender<Job>>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let (jobs, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..parallelism() + 2 {
+            let receiver = receiver.clone();
+            // Without workers `send` fails when queueing, so startup errors surface there.
+            let _ = thread::Builder::new()
+                .name(format!("agent-pool-{}", i))
+                .stack_size(THREAD_STACK_SIZE)
+                .spawn(move || loop {
+                    let job = lock(&receiver).recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                });
+        }
+        Mutex::new(jobs)
+    })
+}
+
+/// A value computed or delivered later, which `deref` waits for.
+#[derive(Clone)]
+pub struct Pending(Arc<PendingState>);
+
+struct PendingState {
+    result: Mutex<Option<Result<T, Error>>>,
+    done: Condvar,
+}
+
+impl Pending {
+    fn new() -> Pending {
+        Pending(Arc::new(PendingState {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        }))
+    }
+
+    /// Sets the result unless one was set before, returning whether it did.
+    fn complete(&self, result: Result<T, Error>) -> bool {
+        let mut current = lock(&self.0.result);
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(result);
+        self.0.done.notify_all();
+        true
+    }
+
+    pub fn is_realized(&self) -> bool {
+        lock(&self.0.result).is_some()
+    }
+
+    /// The result, or `None` if it isn't there before `timeout`.
+    fn wait(&self, timeout: Option<Duration>) -> Option<Result<T, Error>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut result = lock(&self.0.result);
+        loop {
+            if let Some(result) = result.as_ref() {
+                return Some(result.clone());
+            }
+            result = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None;
+                    }
+                    self.0
+                        .done
+                        .wait_timeout(result, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+                None => self
+                    .0
+                    .done
+                    .wait(result)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+    }
+
+    /// How `#future[...]` and `#promise[...]` show the value.
+    pub fn print(&self) -> Result<String, Error> {
+        match lock(&self.0.result).as_ref() {
+            Some(Ok(value)) => value.print(),
+            Some(Err(err)) => T::Throwable(Box::new(err.clone())).print(),
+            None => Ok(String::from(":pending")),
+        }
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Pending {}
+
+impl fmt::Debug for Pending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pending({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+struct Action {
+    f: T,
+    args: Vec<T>,
+    /// Sent with `send-off`, so it may block and gets a thread of its own.
+    solo: bool,
+    /// The dynamic bindings of the sender.
+    frame: Hamt<String, T>,
+    /// The environment of the sender.
+    env: &'static Env,
+}
+
+struct AgentQueue {
+    actions: VecDeque<Action>,
+    running: bool,
+    error: Option<Error>,
+}
+
+struct AgentState {
+    value: ArcSwap<T>,
+    queue: Mutex<AgentQueue>,
+    idle: Condvar,
+}
+
+/// State changed by actions that run one at a time, away from the thread
+/// sending them.
+#[derive(Clone)]
+pub struct Agent(Arc<AgentState>);
+
+impl Agent {
+    fn new(value: T) -> Agent {
+        Agent(Arc::new(AgentState {
+            value: ArcSwap::from_pointee(value),
+            queue: Mutex::new(AgentQueue {
+                actions: VecDeque::new(),
+                running: false,
+                error: None,
+            }),
+            idle: Condvar::new(),
+        }))
+    }
+
+    pub fn deref(&self) -> T {
+        (**self.0.value.load()).clone()
+    }
+
+    fn send(&self, action: Action) -> Result<(), Error> {
+        let mut queue = lock(&self.0.queue);
+        if let Some(err) = &queue.error {
+            return Err(Error::reason(format!(
+                "Agent is failed, needs restart: {}",
+                err.message
+            )));
+        }
+        let solo = action.solo;
+        queue.actions.push_back(action);
+        if queue.running {
+            return Ok(());
+        }
+        queue.running = true;
+        drop(queue);
+        self.schedule(solo).inspect_err(|_| {
+            let mut queue = lock(&self.0.queue);
+            queue.actions.clear();
+            queue.running = false;
+        })
+    }
+
+    fn schedule(&self, solo: bool) -> Result<(), Error> {
+        let agent = self.clone();
+        let job: Job = Box::new(move || agent.run_next());
+        if solo {
+            spawn(job)
+        } else {
+            lock(pool())
+                .send(job)
+                .map_err(|_| Error::reason("The agent pool has shut down"))
+        }
+    }
+
+    /// Runs the oldest action, then schedules the next one the way it was sent.
+    fn run_next(&self) {
+        let action = lock(&self.0.queue).actions.pop_front();
+        if let Some(action) = action {
+            let mut call = vec![self.deref()];
+            call.extend(action.args);
+            let f = action.f;
+            let frame = action.frame;
+            match with_env(action.env, || {
+                with_bindings_frame(frame, || apply(&f, &call))
+            }) {
+                Ok(value) => self.0.value.store(Arc::new(value)),
+                Err(err) => {
+                    let mut queue = lock(&self.0.queue);
+                    queue.error = Some(err);
+                    queue.actions.clear();
+                }
+            }
+        }
+        let mut queue = lock(&self.0.queue);
+        match queue.actions.front().map(|action| action.solo) {
+            Some(solo) => {
+                drop(queue);
+                if let Err(err) = self.schedule(solo) {
+                    let mut queue = lock(&self.0.queue);
+                    queue.error = Some(err);
+                    queue.actions.clear();
+                    queue.running = false;
+                    self.0.idle.notify_all();
+                }
+            }
+            None => {
+                queue.running = false;
+                self.0.idle.notify_all();
+            }
+        }
+    }
+
+    /// Waits until the queued actions ran, returning false on timeout.
+    fn wait_idle(&self, deadline: Option<Instant>) -> bool {
+        let mut queue = lock(&self.0.queue);
+        while queue.running {
+            queue = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    self.0
+                        .idle
+                        .wait_timeout(queue, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+                None => self
+                    .0
+                    .idle
+                    .wait(queue)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+        true
+    }
+}
+
+impl PartialEq for Agent {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Agent {}
+
+impl fmt::Debug for Agent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Agent({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+fn expect_agent(name: &str, value: T) -> Result<Agent, Error> {
+    match value {
+        T::Agent(agent) => Ok(agent),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects an agent but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+pub(crate) fn millis(name: &str, value: &T) -> Result<Duration, Error> {
+    match value {
+        T::Int(ms) => Ok(Duration::from_millis(
+            num_traits::ToPrimitive::to_u64(ms).unwrap_or_default(),
+        )),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects a timeout in milliseconds but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+/// Runs `f` on a new thread with this thread's dynamic bindings, returning
+/// the future its result is delivered to.
+fn start_future(f: impl FnOnce() -> Result<T, Error> + Send + 'static) -> Result<T, Error> {
+    let pending = Pending::new();
+    let result = pending.clone();
+    let frame = current_bindings();
+    spawn(Box::new(move || {
+        result.complete(with_bindings_frame(frame, f));
+    }))?;
+    Ok(T::Future(pending))
+}
+
+/// `(future body*)` evaluates `body` on another thread, with the locals and
+/// namespace it was created in.
+pub fn future(list: &[T]) -> Result<T, Error> {
+    let body = list.to_vec();
+    let scope = current_scope();
+    let ns = current_ns();
+    start_future(move || with_ns(&ns, || with_scope(scope, || eval_body(&body))))
+}
+
+pub fn future_call(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [f] => {
+            let f = f.clone();
+            start_future(move || apply(&f, &[]))
+        }
+        _ => Err(arity("future-call", 1, "1", list)),
+    }
+}
+
+pub fn is_future_done(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Future(pending)] => Ok(T::Bool(pending.is_realized())),
+        [value] => Err(Error::cant_eval(format!(
+            "`future-done?` expects a future but received {}",
+            value.print()?
+        ))),
+        _ => Err(arity("future-done?", 1, "1", list)),
+    }
+}
+
+pub fn is_realized(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Future(pending) | T::Promise(pending)] => Ok(T::Bool(pending.is_realized())),
+        [value] => Err(Error::cant_eval(format!(
+            "`realized?` expects a future or promise but received {}",
+            value.print()?
+        ))),
+        _ => Err(arity("realized?", 1, "1", list)),
+    }
+}
+
+/// `(deref ref)`, also read as `@ref`, or `(deref ref timeout-ms timeout-val)`
+/// for futures and promises.
+pub fn deref(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Atom(atom)] => Ok(atom.deref()),
+        [T::Ref(reference)] => reference.deref(),
+        [T::Agent(agent)] => Ok(agent.deref()),
+        [T::Future(pending) | T::Promise(pending)] => {
+            pending.wait(None).unwrap_or_else(|| Ok(T::Nil))
+        }
+        [T::Future(pending) | T::Promise(pending), timeout, timeout_value] => {
+            match pending.wait(Some(millis("deref", timeout)?)) {
+                Some(result) => result,
+                None => Ok(timeout_value.clone()),
+            }
+        }
+        [value] | [value, _, _] => Err(Error::cant_eval(format!(
+            "`deref` expects a reference but received {}",
+            value.print()?
+        ))),
+        _ => Err(arity("deref", 1, "1 or 3", list)),
+    }
+}
+
+pub fn promise(list: &[T]) -> Result<T, Error> {
+    match list {
+        [] => Ok(T::Promise(Pending::new())),
+        _ => Err(arity("promise", 0, "0", list)),
+    }
+}
+
+/// `(deliver promise value)` sets the value once; later deliveries do nothing.
+pub fn deliver(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Promise(pending), value] => Ok(if pending.complete(Ok(value.clone())) {
+            T::Promise(pending.clone())
+        } else {
+            T::Nil
+        }),
+        [value, _] => Err(Error::cant_eval(format!(
+            "`deliver` expects a promise but received {}",
+            value.print()?
+        ))),
+        _ => Err(arity("deliver", 2, "2", list)),
+    }
+}
+
+pub fn agent(list: &[T]) -> Result<T, Error> {
+    match list {
+        [value] => Ok(T::Agent(Agent::new(value.clone().eval()?))),
+        _ => Err(arity("agent", 1, "1", list)),
+    }
+}
+
+fn send_with(name: &str, solo: bool, list: &[T]) -> Result<T, Error> {
+    let mut args = eval_all(list)?;
+    if args.len() < 2 {
+        return Err(arity(name, 2, "at least 2", list));
+    }
+    let agent = args.remove(0);
+    let f = args.remove(0);
+    expect_agent(name, agent.clone())?.send(Action {
+        f,
+        args,
+        solo,
+        frame: current_bindings(),
+        env: current_env(),
+    })?;
+    Ok(agent)
+}
+
+/// `(send agent f & args)` queues `(apply f @agent args)` on the fixed pool.
+pub fn send(list: &[T]) -> Result<T, Error> {
+    send_with("send", false, list)
+}
+
+/// Like `send`, for actions that may block.
+pub fn send_off(list: &[T]) -> Result<T, Error> {
+    send_with("send-off", true, list)
+}
+
+/// `(await & agents)` blocks until the actions queued on `agents` have run.
+pub fn await_agents(list: &[T]) -> Result<T, Error> {
+    for agent in eval_all(list)? {
+        expect_agent("await", agent)?.wait_idle(None);
+    }
+    Ok(T::Nil)
+}
+
+/// `(await-for timeout-ms & agents)`, returning false if the time ran out.
+pub fn await_for(list: &[T]) -> Result<T, Error> {
+    let args = eval_all(list)?;
+    let (timeout, agents) = match args.split_first() {
+        Some((timeout, agents)) => (millis("await-for", timeout)?, agents),
+        None => return Err(arity("await-for", 1, "at least 1", list)),
+    };
+    let deadline = Instant::now() + timeout;
+    for agent in agents {
+        if !expect_agent("await-for", agent.clone())?.wait_idle(Some(deadline)) {
+            return Ok(T::Bool(false));
+        }
+    }
+    Ok(T::Bool(true))
+}
+
+/// The error that failed an agent, or nil.
+pub fn agent_error(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [agent] => Ok(lock(&expect_agent("agent-error", agent.clone())?.0.queue)
+            .error
+            .clone()
+            .map_or(T::Nil, |err| T::Throwable(Box::new(err)))),
+        _ => Err(arity("agent-error", 1, "1", list)),
+    }
+}
+
+/// Calls every job on a few worker threads, keeping the results in order.
+fn parallel(calls: Vec<(T, Vec<T>)>) -> Result<T, Error> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; calls.len()]);
+    let frame = current_bindings();
+    let env = current_env();
+    thread::scope(|scope| {
+        for _ in 0..parallelism().min(calls.len()) {
+            let worker = || {
+                with_env(env, || {
+                    with_bindings_frame(frame.clone(), || loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        match calls.get(i) {
+                            Some((f, args)) => {
+                                let result = apply(f, args);
+                                lock(&results)[i] = Some(result);
+                            }
+                            None => return,
+                        }
+                    })
+                })
+            };
+            thread::Builder::new()
+                .stack_size(THREAD_STACK_SIZE)
+                .spawn_scoped(scope, worker)
+                .map_err(|err| Error::reason(format!("Could not start a thread: {}", err)))?;
+        }
+        Ok::<(), Error>(())
+    })?;
+    let results = results
+        .into_inner()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(T::seq(
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Ok(T::Nil)))
+            .collect::<Result<Vec<T>, Error>>()?,
+    ))
+}
+
+/// `(pmap f & colls)` is `map` with `f` called in parallel. Unlike Clojure's,
+/// it realizes the whole result.
+pub fn pmap(list: &[T]) -> Result<T, Error> {
+    let mut args = eval_all(list)?;
+    if args.len() < 2 {
+        return Err(arity("pmap", 2, "at least 2", list));
+    }
+    let f = args.remove(0);
+    let colls = args
+        .into_iter()
+        .map(items)
+        .collect::<Result<Vec<Vec<T>>, Error>>()?;
+    let len = colls.iter().map(Vec::len).min().unwrap_or(0);
+    parallel(
+        (0..len)
+            .map(|i| {
+                (
+                    f.clone(),
+                    colls.iter().map(|coll| coll[i].clone()).collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// `(pcalls & fns)` calls every function in parallel.
+pub fn pcalls(list: &[T]) -> Result<T, Error> {
+    parallel(
+        eval_all(list)?
+            .into_iter()
+            .map(|f| (f, Vec::new()))
+            .collect(),
+    )
+}
+
+/// Agent threads stop with the process, so there is nothing to shut down.
+pub fn shutdown_agents(_list: &[T]) -> Result<T, Error> {
+    Ok(T::Nil)
+}
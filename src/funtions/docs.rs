@@ -0,0 +1,339 @@
+use super::namespaces::{split_qualified, CORE};
+
+/// A builtin's name, arglists and docstring.
+type NativeDoc = (&'static str, &'static str, &'static str);
+
+/// The builtins of `clojure.core`. Special forms are documented here as well,
+/// since they are natives too.
+const CORE_DOCS: &[NativeDoc] = &[
+    ("+", "([& xs])", "Returns the sum of the numbers. (+) returns 0."),
+    ("-", "([x & ys])", "Subtracts the ys from x, in order."),
+    ("*", "([& xs])", "Returns the product of the numbers. (*) returns 1."),
+    ("/", "([x & ys])", "Divides x by the ys, in order. Integers divide to ratios."),
+    ("meaning-of-life?", "([& _])", "Returns 42."),
+    ("neg?", "([x])", "Returns true if x is less than zero."),
+    ("pos?", "([x])", "Returns true if x is greater than zero."),
+    ("num?", "([x])", "Returns true if x is a number."),
+    ("=", "([x & more])", "Returns true if all the arguments are equal."),
+    (">=", "([x & more])", "Returns true if the numbers are in non-increasing order."),
+    (">", "([x & more])", "Returns true if the numbers are in decreasing order."),
+    ("<=", "([x & more])", "Returns true if the numbers are in non-decreasing order."),
+    ("<", "([x & more])", "Returns true if the numbers are in increasing order."),
+    ("true?", "([x])", "Returns true if x is the value true."),
+    ("false?", "([x])", "Returns true if x is the value false."),
+    ("nil?", "([x])", "Returns true if x is nil."),
+    ("throw", "([x])", "Throws x: an ex-info, or a string as the message of one."),
+    ("str", "([& xs])", "Returns the printed forms of the xs joined into one string."),
+    ("println!", "([& xs])", "Prints the xs, like str, to *out* followed by a newline."),
+    ("keyword", "([& names])", "Returns a vector of the names as keywords."),
+    ("subs", "([s start] [s start end])", "Returns the chars of s from start up to end, or to its end."),
+    ("char", "([x])", "Returns the char with the code point x."),
+    ("int", "([x])", "Returns the code point of a char, or a number truncated to an integer."),
+    ("char?", "([x])", "Returns true if x is a char."),
+    ("inc", "([x])", "Returns x plus one."),
+    ("dec", "([x])", "Returns x minus one."),
+    ("even?", "([n])", "Returns true if n is even."),
+    ("odd?", "([n])", "Returns true if n is odd."),
+    ("conj", "([coll & xs])", "Returns coll with the xs added where the collection adds them."),
+    ("get", "([map key] [map key not-found])", "Returns the value at key, or not-found or nil."),
+    ("nth", "([coll index] [coll index not-found])", "Returns the item at index, or not-found."),
+    ("nthnext", "([coll n])", "Returns the seq of coll after its first n items, or nil."),
+    ("hash-map", "([& keyvals])", "Returns a map of the keys and values."),
+    ("seq?", "([x])", "Returns true if x is a seq."),
+    ("apply", "([f & args])", "Calls f with the args, the last of which is a collection spread as arguments."),
+    ("fn", "([name? [params*] body*] [name? ([params*] body*)+])", "Special form. Returns a function closing over the locals in scope."),
+    ("let", "([[bindings*] body*])", "Special form. Evaluates body with the bindings, which may destructure, as locals."),
+    ("loop", "([[bindings*] body*])", "Special form. Like let, and a target for recur."),
+    ("recur", "([exprs*])", "Special form. Rebinds the closest loop or fn and evaluates it again."),
+    ("if", "([test then else?])", "Special form. Evaluates then if test is truthy, else otherwise."),
+    ("do", "([exprs*])", "Special form. Evaluates the exprs in order, returning the last."),
+    ("quote", "([form])", "Special form. Returns form unevaluated."),
+    ("map", "([f] [f coll] [f coll & colls])", "Returns f applied to each item of the colls, or a transducer."),
+    ("filter", "([pred] [pred coll])", "Returns the items of coll for which pred is truthy, or a transducer."),
+    ("remove", "([pred] [pred coll])", "Returns the items of coll for which pred is falsey, or a transducer."),
+    ("take", "([n] [n coll])", "Returns the first n items of coll, or a transducer."),
+    ("drop", "([n] [n coll])", "Returns coll without its first n items, or a transducer."),
+    ("partition-all", "([n] [n coll])", "Returns coll in vectors of n items, the last possibly shorter."),
+    ("dedupe", "([] [coll])", "Returns coll without consecutive duplicates, or a transducer."),
+    ("mapcat", "([f] [f & colls])", "Returns the concatenation of map f over the colls, or a transducer."),
+    ("keep", "([f] [f coll])", "Returns the non-nil results of f over coll, or a transducer."),
+    ("comp", "([& fs])", "Returns the composition of the fs, applied right to left."),
+    ("transduce", "([xform f coll] [xform f init coll])", "Reduces coll with f transformed by xform."),
+    ("into", "([to from] [to xform from])", "Returns to with the items of from conjoined, through xform if given."),
+    ("sequence", "([coll] [xform coll])", "Returns the items of coll as a seq, through xform if given."),
+    ("eduction", "([xform* coll])", "Returns the items of coll through the xforms."),
+    ("def", "([name doc-string? init?])", "Special form. Defines name as a var in the current namespace."),
+    ("defmacro", "([name doc-string? [params*] body*])", "Defines name as a macro."),
+    ("var", "([symbol])", "Special form. Returns the var symbol names, also read as #'symbol."),
+    ("binding", "([[var value*] body*])", "Evaluates body with the dynamic vars rebound on this thread."),
+    ("with-bindings", "([bindings body*])", "Evaluates body with a map of vars to values as thread bindings."),
+    ("set!", "([var value])", "Special form. Sets the innermost thread binding of var."),
+    ("alter-var-root", "([var f & args])", "Sets the root of var to (apply f root args)."),
+    ("meta", "([x])", "Returns the metadata of x, or nil."),
+    ("with-meta", "([x meta])", "Returns x with meta as its metadata."),
+    ("vary-meta", "([x f & args])", "Returns x with (apply f (meta x) args) as its metadata."),
+    ("alter-meta!", "([ref f & args])", "Sets the metadata of a var or atom to (apply f (meta ref) args)."),
+    ("reset-meta!", "([ref meta])", "Sets the metadata of a var or atom to meta."),
+    ("macroexpand-1", "([form])", "Returns form with its macro call expanded once."),
+    ("macroexpand", "([form])", "Returns form expanded until it is no longer a macro call."),
+    ("macroexpand-all", "([form])", "Returns form with every macro call in it expanded."),
+    ("gensym", "([] [prefix])", "Returns a new unique symbol."),
+    ("syntax-quote", "([form])", "Returns form as a template, also read as `form."),
+    ("list", "([& items])", "Returns a list of the items."),
+    ("vector", "([& items])", "Returns a vector of the items."),
+    ("cons", "([x coll])", "Returns a seq of x followed by the items of coll."),
+    ("concat", "([& colls])", "Returns a seq of the items of the colls in order."),
+    ("first", "([coll])", "Returns the first item of coll, or nil."),
+    ("second", "([coll])", "Returns the second item of coll, or nil."),
+    ("next", "([coll])", "Returns the items of coll after the first, or nil."),
+    ("rest", "([coll])", "Returns the items of coll after the first, possibly empty."),
+    ("seq", "([coll])", "Returns a seq of coll, or nil when it is empty."),
+    ("count", "([coll])", "Returns the number of items in coll, or chars in a string."),
+    ("empty?", "([coll])", "Returns true if coll has no items."),
+    ("string?", "([x])", "Returns true if x is a string."),
+    ("symbol?", "([x])", "Returns true if x is a symbol."),
+    ("and", "([& xs])", "Returns the first falsey x without evaluating the rest, or the last."),
+    ("or", "([& xs])", "Returns the first truthy x without evaluating the rest, or the last."),
+    ("not", "([x])", "Returns true if x is falsey."),
+    ("not=", "([x & more])", "Same as (not (= x & more))."),
+    ("boolean", "([x])", "Returns x as a boolean."),
+    ("some?", "([x])", "Returns true if x is not nil."),
+    ("any?", "([x])", "Returns true."),
+    ("try", "([expr* catch-clause* finally-clause?])", "Special form. Evaluates the exprs, handling errors with catch and cleaning up with finally."),
+    ("ex-info", "([msg data] [msg data cause])", "Returns an error carrying msg and a map of data."),
+    ("ex-data", "([ex])", "Returns the data of an ex-info, or nil."),
+    ("ex-message", "([ex])", "Returns the message of an error, or nil."),
+    ("ex-cause", "([ex])", "Returns the cause of an error, or nil."),
+    ("ns", "([name references*])", "Makes name the current namespace, with its :require and :refer-clojure clauses."),
+    ("in-ns", "([name])", "Makes name the current namespace, creating it if needed."),
+    ("require", "([& specs])", "Loads namespaces, with :as aliases and :refer lists."),
+    ("load-file", "([path])", "Evaluates the forms of the file at path."),
+    ("load", "([& paths])", "Loads resources on the source paths, relative to the current namespace."),
+    ("doc", "([name])", "Prints the arglists and docstring of a var or builtin."),
+    ("source", "([name])", "Prints the text a var was defined with."),
+    ("apropos", "([str])", "Returns the qualified names of the vars whose name contains str."),
+    ("find-doc", "([str])", "Prints the docs of vars whose name or docstring contains str."),
+    ("dir", "([ns])", "Prints the public vars of a namespace or alias."),
+    ("tap>", "([x])", "Sends x to every tap and returns true."),
+    ("atom", "([x & options])", "Returns an atom holding x, with :meta and :validator options."),
+    ("deref", "([ref] [ref timeout-ms timeout-val])", "Returns the value of an atom, ref, agent, future or promise, also read as @ref."),
+    ("swap!", "([atom f & args])", "Sets the atom to (apply f value args), retrying on conflict. Returns the new value."),
+    ("swap-vals!", "([atom f & args])", "Like swap!, returning [old new]."),
+    ("reset!", "([atom value])", "Sets the atom to value. Returns value."),
+    ("reset-vals!", "([atom value])", "Like reset!, returning [old new]."),
+    ("compare-and-set!", "([atom old new])", "Sets the atom to new if it holds old. Returns whether it did."),
+    ("set-validator!", "([ref f])", "Sets the function that new values of ref must satisfy."),
+    ("get-validator", "([ref])", "Returns the validator of ref, or nil."),
+    ("add-watch", "([ref key f])", "Calls (f key ref old new) whenever ref changes."),
+    ("remove-watch", "([ref key])", "Removes the watch added to ref under key."),
+    ("ref", "([x & options])", "Returns a ref holding x, changed within dosync."),
+    ("dosync", "([exprs*])", "Evaluates the exprs in a transaction, retrying on conflict."),
+    ("alter", "([ref f & args])", "Sets ref in the transaction to (apply f value args)."),
+    ("commute", "([ref f & args])", "Like alter, reapplied at commit instead of conflicting."),
+    ("ref-set", "([ref value])", "Sets ref in the transaction to value."),
+    ("ensure", "([ref])", "Returns the value of ref, protecting it from other transactions."),
+    ("future", "([body*])", "Evaluates body on another thread. Returns a future to deref."),
+    ("future-call", "([f])", "Calls f on another thread. Returns a future to deref."),
+    ("future-done?", "([future])", "Returns true if future has finished."),
+    ("realized?", "([x])", "Returns true if a future or promise has a value."),
+    ("promise", "([])", "Returns a promise, delivered once and dereffed until then."),
+    ("deliver", "([promise value])", "Delivers value to promise."),
+    ("agent", "([x & options])", "Returns an agent holding x, changed by send and send-off."),
+    ("send", "([agent f & args])", "Sets agent to (apply f value args) on a fixed thread pool."),
+    ("send-off", "([agent f & args])", "Sets agent to (apply f value args) on a thread of its own."),
+    ("await", "([& agents])", "Waits for the actions sent so far to the agents."),
+    ("await-for", "([timeout-ms & agents])", "Like await, giving up after timeout-ms. Returns false if it did."),
+    ("agent-error", "([agent])", "Returns the error that failed agent, or nil."),
+    ("shutdown-agents", "([])", "Stops the agent thread pool from taking new actions."),
+    ("pmap", "([f coll & colls])", "Like map, with f called in parallel."),
+    ("pcalls", "([& fs])", "Returns the results of calling the fs in parallel."),
+    ("refer", "([ns & filters])", "Refers the public vars of ns, with :only, :exclude and :rename."),
+    ("alias", "([alias ns])", "Adds alias for ns in the current namespace."),
+    ("ns-publics", "([ns])", "Returns a map of the public vars of ns by name."),
+    ("ns-resolve", "([ns symbol])", "Returns the var symbol names in ns, or nil."),
+    ("resolve", "([symbol])", "Returns the var symbol names in the current namespace, or nil."),
+    ("defprotocol", "([name doc-string? (method [params*] doc-string?)*])", "Defines a protocol and its methods, dispatching on the type of their first argument."),
+    ("extend-type", "([type (protocol (method [params*] body*)*)*])", "Implements protocols for type."),
+    ("extend-protocol", "([protocol (type (method [params*] body*)*)*])", "Implements protocol for types."),
+    ("satisfies?", "([protocol x])", "Returns true if the type of x implements protocol."),
+    ("defrecord", "([name [fields*] (protocol methods*)*])", "Defines a record type, a map with its fields, with ->name and map->name constructors."),
+    ("deftype", "([name [fields*] (protocol methods*)*])", "Defines a type with fields, with a ->name constructor."),
+    ("new", "([type & args])", "Returns an instance of a record or type."),
+    ("reify", "([(protocol methods*)*])", "Returns an object implementing the protocols."),
+    ("type", "([x])", "Returns the type of x."),
+    ("record?", "([x])", "Returns true if x is a record."),
+    ("defmulti", "([name doc-string? attr-map? dispatch-fn & options])", "Defines a multimethod dispatching on the value of dispatch-fn, with :default and :hierarchy options."),
+    ("defmethod", "([multifn dispatch-value [params*] body*])", "Adds the method of multifn for dispatch-value."),
+    ("remove-method", "([multifn dispatch-value])", "Removes the method of multifn for dispatch-value."),
+    ("prefer-method", "([multifn x y])", "Makes multifn prefer the method for x over y when both match."),
+    ("methods", "([multifn])", "Returns a map of the methods of multifn by dispatch value."),
+    ("make-hierarchy", "([])", "Returns an empty hierarchy."),
+    ("derive", "([tag parent] [h tag parent])", "Makes tag a child of parent, in the global hierarchy or returning h with it."),
+    ("underive", "([tag parent] [h tag parent])", "Removes the parent of tag, in the global hierarchy or returning h without it."),
+    ("isa?", "([child parent] [h child parent])", "Returns true if child equals parent or derives from it."),
+    ("parents", "([tag] [h tag])", "Returns the immediate parents of tag, or nil."),
+    ("ancestors", "([tag] [h tag])", "Returns every ancestor of tag, or nil."),
+    ("descendants", "([tag] [h tag])", "Returns every descendant of tag, or nil."),
+];
+
+const STRING_DOCS: &[NativeDoc] = &[
+    ("join", "([coll] [separator coll])", "Returns the items of coll as one string, with separator between them."),
+    ("split", "([s re] [s re limit])", "Returns a vector of the parts of s between matches of re, at most limit of them."),
+    ("split-lines", "([s])", "Splits s on \\n and \\r\\n."),
+    ("trim", "([s])", "Removes whitespace from both ends of s."),
+    ("triml", "([s])", "Removes whitespace from the start of s."),
+    ("trimr", "([s])", "Removes whitespace from the end of s."),
+    ("upper-case", "([s])", "Converts s to upper case."),
+    ("lower-case", "([s])", "Converts s to lower case."),
+    ("capitalize", "([s])", "Converts the first char of s to upper case and the rest to lower case."),
+    ("replace", "([s match replacement])", "Replaces every match in s. A string or char is replaced by a string or char, a regex by a $1 template or a function of the match."),
+    ("replace-first", "([s match replacement])", "Like replace, replacing only the first match."),
+    ("starts-with?", "([s substr])", "Returns true if s starts with substr."),
+    ("ends-with?", "([s substr])", "Returns true if s ends with substr."),
+    ("includes?", "([s substr])", "Returns true if s contains substr."),
+    ("index-of", "([s value] [s value from-index])", "Returns the index of value, a string or char, in s at or after from-index, or nil."),
+    ("blank?", "([s])", "Returns true if s is nil, empty or only whitespace."),
+    ("reverse", "([s])", "Returns s with its chars in reverse order."),
+    ("escape", "([s cmap])", "Returns s with every char c that (cmap c) maps to something other than nil replaced by it."),
+];
+
+const ASYNC_DOCS: &[NativeDoc] = &[
+    (
+        "chan",
+        "([] [buf-or-n])",
+        "Returns a channel, unbuffered or with a buffer.",
+    ),
+    (
+        "buffer",
+        "([n])",
+        "Returns a fixed buffer of n, which blocks puts when full.",
+    ),
+    (
+        "dropping-buffer",
+        "([n])",
+        "Returns a buffer of n that drops new puts when full.",
+    ),
+    (
+        "sliding-buffer",
+        "([n])",
+        "Returns a buffer of n that drops the oldest value when full.",
+    ),
+    (
+        "close!",
+        "([ch])",
+        "Closes ch. Takes drain what is buffered, then get nil.",
+    ),
+    (
+        ">!!",
+        "([ch value])",
+        "Puts value on ch, blocking. Returns false if ch is closed.",
+    ),
+    (
+        "<!!",
+        "([ch])",
+        "Takes a value from ch, blocking. Returns nil once ch is closed and drained.",
+    ),
+    (">!", "([ch value])", "Like >!!, parking inside a go block."),
+    ("<!", "([ch])", "Like <!!, parking inside a go block."),
+    (
+        "put!",
+        "([ch value] [ch value f])",
+        "Puts value on ch without waiting, calling f with the result.",
+    ),
+    (
+        "take!",
+        "([ch f])",
+        "Takes from ch without waiting, calling f with the value.",
+    ),
+    (
+        "alts!!",
+        "([ports & options])",
+        "Completes the first ready take or [ch value] put, blocking. Returns [value port].",
+    ),
+    (
+        "alts!",
+        "([ports & options])",
+        "Like alts!!, parking inside a go block.",
+    ),
+    (
+        "timeout",
+        "([ms])",
+        "Returns a channel that closes after ms.",
+    ),
+    (
+        "go-call",
+        "([f])",
+        "Calls f in a go block. Returns a channel that gets the result.",
+    ),
+    (
+        "thread-call",
+        "([f])",
+        "Calls f on a thread of its own. Returns a channel that gets the result.",
+    ),
+    (
+        "pipeline",
+        "([n to xf from] [n to xf from close?] [n to xf from close? ex-handler])",
+        "Moves the values of from through xf to to, n at a time and in order.",
+    ),
+    (
+        "mult",
+        "([ch])",
+        "Returns a mult that copies every value of ch to its taps.",
+    ),
+    (
+        "tap",
+        "([mult ch] [mult ch close?])",
+        "Copies the values of mult to ch.",
+    ),
+    (
+        "untap",
+        "([mult ch])",
+        "Stops copying the values of mult to ch.",
+    ),
+    ("untap-all", "([mult])", "Removes every tap of mult."),
+    (
+        "pub",
+        "([ch topic-fn])",
+        "Returns a publication of the values of ch by (topic-fn value).",
+    ),
+    (
+        "sub",
+        "([pub topic ch] [pub topic ch close?])",
+        "Sends the values of pub with topic to ch.",
+    ),
+    (
+        "unsub",
+        "([pub topic ch])",
+        "Stops sending the values of pub with topic to ch.",
+    ),
+    (
+        "unsub-all",
+        "([pub] [pub topic])",
+        "Removes every subscription of pub, or those to topic.",
+    ),
+];
+
+/// The arglists and docstring of the builtin `ns/name`, which has no source to
+/// take them from.
+pub(crate) fn native_doc(key: &str) -> Option<(&'static str, &'static str)> {
+    let (ns, name) = split_qualified(key)?;
+    docs_of(ns)
+        .iter()
+        .find(|(native, _, _)| *native == name)
+        .map(|(_, arglists, doc)| (*arglists, *doc))
+}
+
+/// The names of the builtins in `ns` that have docs, so they can be checked
+/// against the natives actually registered there.
+pub fn documented_natives(ns: &str) -> impl Iterator<Item = &'static str> {
+    docs_of(ns).iter().map(|(name, _, _)| *name)
+}
+
+fn docs_of(ns: &str) -> &'static [NativeDoc] {
+    match ns {
+        CORE => CORE_DOCS,
+        "clojure.string" => STRING_DOCS,
+        "clojure.core.async" => ASYNC_DOCS,
+        _ => &[],
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    definitions::DefinitionTypes as T,
+    error::{Error, ErrorKind},
+};
+
+use super::{current_scope, special_forms::eval_body, with_scope};
+
+struct Catch<'a> {
+    class: String,
+    name: String,
+    body: &'a [T],
+}
+
+fn clause<'a>(form: &'a T, head: &str) -> Option<&'a [T]> {
+    match form {
+        T::List(list, _) if matches!(list.first(), Some(T::Symbol(s, _)) if s == head) => {
+            Some(&list[1..])
+        }
+        _ => None,
+    }
+}
+
+fn parse_catch(args: &[T]) -> Result<Catch<'_>, Error> {
+    match args {
+        [T::Symbol(class, _) | T::Keyword(class), T::Symbol(name, _), body @ ..] => Ok(Catch {
+            class: class.to_owned(),
+            name: name.to_owned(),
+            body,
+        }),
+        _ => Err(Error::cant_eval(Some(String::from(
+            "`catch` expects a class, a binding symbol and a body",
+        )))),
+    }
+}
+
+impl Catch<'_> {
+    fn matches(&self, err: &Error) -> bool {
+        let class = self.class.rsplit(['.', '/']).next().unwrap_or_default();
+        match class {
+            _ if err.kind == ErrorKind::Retry => false,
+            "Throwable" | "Exception" | ":default" => true,
+            "RuntimeException" => !matches!(
+                err.kind,
+                ErrorKind::Thrown
+                    | ErrorKind::ExInfo
+                    | ErrorKind::Interrupted
+                    | ErrorKind::StackOverflow
+            ),
+            class => class == err.kind.class_name(),
+        }
+    }
+}
+
+/// `(try body* (catch Class e handler*)* (finally cleanup*)?)`
+pub fn try_form(list: &[T]) -> Result<T, Error> {
+    let split = list
+        .iter()
+        .position(|form| clause(form, "catch").is_some() || clause(form, "finally").is_some())
+        .unwrap_or(list.len());
+    let (body, clauses) = list.split_at(split);
+
+    let mut catches = Vec::new();
+    let mut finally = None;
+    for (i, form) in clauses.iter().enumerate() {
+        if let Some(args) = clause(form, "catch") {
+            catches.push(parse_catch(args)?);
+        } else if let (Some(body), true) = (clause(form, "finally"), i == clauses.len() - 1) {
+            finally = Some(body);
+        } else {
+            return Err(Error::cant_eval(Some(String::from(
+                "Only `catch` and a final `finally` clause can follow the body of `try`",
+            ))));
+        }
+    }
+
+    let result = match eval_body(body) {
+        Err(err) => match catches.iter().find(|catch| catch.matches(&err)) {
+            Some(catch) => {
+                let scope = current_scope().update(catch.name.clone(), T::Throwable(Box::new(err)));
+                with_scope(scope, || eval_body(catch.body))
+            }
+            None => Err(err),
+        },
+        result => result,
+    };
+
+    if let Some(finally) = finally {
+        eval_body(finally)?;
+    }
+    result
+}
+
+pub fn ex_info(list: &[T]) -> Result<T, Error> {
+    let (message, data, cause) = match list {
+        [message, data] => (message, data, None),
+        [message, data, cause] => (message, data, Some(cause)),
+        _ => {
+            return Err(Error::arity(
+                2,
+                format!("`ex-info` has arity of 2 or 3 but received {}", list.len()),
+            ))
+        }
+    };
+
+    let message = match message.clone().eval()? {
+        T::String(message) => message,
+        message => {
+            return Err(Error::cant_eval(Some(format!(
+                "`ex-info` expects a string message but received {}",
+                message.print()?
+            ))))
+        }
+    };
+    let data = match data.clone().eval()? {
+        data @ (T::HashMap(_, _) | T::OrderedMap(_)) => data,
+        data => {
+            return Err(Error::cant_eval(Some(format!(
+                "`ex-info` expects a map but received {}",
+                data.print()?
+            ))))
+        }
+    };
+    let cause = match cause.map(|cause| cause.clone().eval()).transpose()? {
+        Some(T::Throwable(cause)) => Some(*cause),
+        None | Some(T::Nil) => None,
+        Some(cause) => {
+            return Err(Error::cant_eval(Some(format!(
+                "`ex-info` expects an exception as cause but received {}",
+                cause.print()?
+            ))))
+        }
+    };
+
+    Ok(T::Throwable(Box::new(Error::ex_info(message, data, cause))))
+}
+
+fn throwable(name: &str, list: &[T]) -> Result<Option<Box<Error>>, Error> {
+    match list {
+        [e] => match e.clone().eval()? {
+            T::Throwable(err) => Ok(Some(err)),
+            _ => Ok(None),
+        },
+        _ => Err(Error::arity(
+            1,
+            format!("`{}` has arity of 1 but received {}", name, list.len()),
+        )),
+    }
+}
+
+pub fn ex_data(list: &[T]) -> Result<T, Error> {
+    Ok(throwable("ex-data", list)?
+        .and_then(|err| err.data)
+        .map(|data| *data)
+        .unwrap_or(T::Nil))
+}
+
+pub fn ex_message(list: &[T]) -> Result<T, Error> {
+    Ok(throwable("ex-message", list)?
+        .map(|err| T::String(err.message))
+        .unwrap_or(T::Nil))
+}
+
+pub fn ex_cause(list: &[T]) -> Result<T, Error> {
+    Ok(throwable("ex-cause", list)?
+        .and_then(|err| err.cause)
+        .map(T::Throwable)
+        .unwrap_or(T::Nil))
+}
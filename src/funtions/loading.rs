@@ -0,0 +1,234 @@
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use im::HashMap as Hamt;
+
+use crate::{
+    definitions::{DefinitionTypes as T, Function},
+    env::current_env,
+    error::Error,
+    parser::read_forms,
+    LIBRARIES, SOURCE_PATHS,
+};
+
+use super::{
+    apply,
+    macros::expand_all,
+    namespaces::{current_ns, lookup_var, ns_exists, require_spec, with_ns, CORE},
+    repl::record_source,
+    vars::{current_bindings, with_bindings_frame},
+    Func,
+};
+
+/// Environment variable listing the source roots, separated like `PATH`.
+pub const SOURCE_PATH_VAR: &str = "CLOJURS_PATH";
+
+const EXTENSIONS: [&str; 2] = ["clj", "cljrs"];
+
+thread_local! {
+    // Namespaces being loaded on this thread, outermost first.
+    static LOADING: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A namespace shipped with the interpreter: its natives become its vars, then
+/// `source` defines the rest.
+#[derive(Clone)]
+pub struct Library {
+    pub natives: Hamt<String, Func>,
+    pub source: &'static str,
+}
+
+pub(crate) fn default_source_paths() -> Vec<PathBuf> {
+    match std::env::var_os(SOURCE_PATH_VAR) {
+        Some(paths) => std::env::split_paths(&paths).collect(),
+        None => vec![PathBuf::from("src"), PathBuf::from(".")],
+    }
+}
+
+/// Replaces the source roots with `paths`, separated like `PATH`.
+pub fn set_source_paths(paths: &str) {
+    if let Ok(mut roots) = SOURCE_PATHS.lock() {
+        *roots = std::env::split_paths(paths).collect();
+    }
+}
+
+pub fn add_source_path(path: impl Into<PathBuf>) {
+    if let Ok(mut roots) = SOURCE_PATHS.lock() {
+        roots.push(path.into());
+    }
+}
+
+/// Evaluates every form in `source`, restoring the current namespace afterwards.
+pub fn eval_source(source: &str) -> Result<T, Error> {
+    with_ns(&current_ns(), || {
+        read_forms(source).try_fold(T::Nil, |_, form| {
+            let (form, text) = form?;
+            record_source(&form, &text);
+            expand_all(form)?.eval()
+        })
+    })
+}
+
+pub fn eval_file(path: &Path) -> Result<T, Error> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| Error::reason(format!("Could not read {}: {}", path.display(), err)))?;
+    let mut frame = current_bindings();
+    frame.insert(
+        format!("{}/*file*", CORE),
+        T::String(path.display().to_string()),
+    );
+    with_bindings_frame(frame, || eval_source(&source))
+        .map_err(|err| err.push_frame(String::from("load"), path.display().to_string()))
+}
+
+/// `my-app.core` lives in `my_app/core`.
+fn ns_path(ns: &str) -> String {
+    ns.replace('-', "_").replace('.', "/")
+}
+
+fn find_source(path: &str) -> Option<PathBuf> {
+    let roots = SOURCE_PATHS.lock().ok()?.clone();
+    roots.iter().find_map(|root| {
+        EXTENSIONS
+            .iter()
+            .map(|ext| root.join(format!("{}.{}", path, ext)))
+            .find(|file| file.is_file())
+    })
+}
+
+/// Loads the file defining `ns` from the source roots unless it was loaded before.
+pub(crate) fn ensure_loaded(ns: &str, reload: bool) -> Result<(), Error> {
+    let loaded = current_env()
+        .loaded
+        .read()
+        .map(|l| l.contains(ns))
+        .unwrap_or(false);
+    if loaded && !reload {
+        return Ok(());
+    }
+    if let Some(cycle) = LOADING.with(|loading| {
+        let loading = loading.borrow();
+        loading.iter().position(|l| l == ns).map(|start| {
+            let mut cycle = loading[start..].to_vec();
+            cycle.push(ns.to_owned());
+            cycle.join(" -> ")
+        })
+    }) {
+        return Err(Error::reason(format!("Cyclic load dependency: {}", cycle)));
+    }
+
+    if let Some(library) = LIBRARIES.get(ns) {
+        load_library(ns, library)?;
+        if let Ok(mut loaded) = current_env().loaded.write() {
+            loaded.insert(ns.to_owned());
+        }
+        return Ok(());
+    }
+
+    let path = ns_path(ns);
+    let file = match find_source(&path) {
+        Some(file) => file,
+        None if ns_exists(ns) => return Ok(()),
+        None => {
+            return Err(Error::reason(format!(
+                "Could not locate {}.clj or {}.cljrs on the source path",
+                path, path
+            )))
+        }
+    };
+
+    LOADING.with(|loading| loading.borrow_mut().push(ns.to_owned()));
+    let result = eval_file(&file);
+    LOADING.with(|loading| loading.borrow_mut().pop());
+    result?;
+
+    if !ns_exists(ns) {
+        return Err(Error::reason(format!(
+            "Namespace {} not found after loading {}",
+            ns,
+            file.display()
+        )));
+    }
+    if let Ok(mut loaded) = current_env().loaded.write() {
+        loaded.insert(ns.to_owned());
+    }
+    Ok(())
+}
+
+fn load_library(ns: &str, library: &Library) -> Result<(), Error> {
+    current_env()
+        .data
+        .write()
+        .map_err(|_| Error::reason("Global definitions are poisoned"))?
+        .extend(library.natives.iter().map(|(name, f)| {
+            (
+                format!("{}/{}", ns, name),
+                T::Function(Function::Native(name.clone(), *f)),
+            )
+        }));
+    eval_source(library.source)
+        .map(drop)
+        .map_err(|err| err.push_frame(String::from("load"), ns.to_owned()))
+}
+
+fn string_arg(name: &str, arg: &T) -> Result<String, Error> {
+    match arg.clone().eval()? {
+        T::String(s) => Ok(s),
+        arg => Err(Error::cant_eval(format!(
+            "`{}` expects a string but received {}",
+            name,
+            arg.print()?
+        ))),
+    }
+}
+
+pub fn load_file(list: &[T]) -> Result<T, Error> {
+    match list {
+        [path] => eval_file(Path::new(&string_arg("load-file", path)?)),
+        _ => Err(Error::arity(
+            1,
+            format!("`load-file` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
+
+/// `(load "my/app/core")` loads a resource relative to the source roots.
+pub fn load(list: &[T]) -> Result<T, Error> {
+    let mut result = T::Nil;
+    for path in list {
+        let path = string_arg("load", path)?;
+        let path = path.trim_start_matches('/');
+        let file = find_source(path).ok_or_else(|| {
+            Error::reason(format!(
+                "Could not locate {}.clj or {}.cljrs on the source path",
+                path, path
+            ))
+        })?;
+        result = eval_file(&file)?;
+    }
+    Ok(result)
+}
+
+/// Binds `*command-line-args*` to the arguments following the script or `-main` namespace.
+pub fn set_command_line_args(args: &[String]) {
+    let value = if args.is_empty() {
+        T::Nil
+    } else {
+        T::seq(args.iter().cloned().map(T::String).collect())
+    };
+    if let Ok(mut data) = current_env().data.write() {
+        data.insert(format!("{}/*command-line-args*", CORE), value);
+    }
+}
+
+/// Requires `ns` and calls its `-main` with `args` as strings.
+pub fn run_main(ns: &str, args: &[String]) -> Result<T, Error> {
+    require_spec(&T::symbol(ns.to_owned()), false)?;
+    let main = lookup_var(&format!("{}/-main", ns))
+        .ok_or_else(|| Error::unknown_symbol(&format!("{}/-main", ns)))?;
+    let args = args.iter().cloned().map(T::String).collect::<Vec<T>>();
+    with_ns(ns, || apply(&main, &args))
+}
@@ -1,5 +1,3 @@
-use crate::DATA;
-
 use crate::{definitions::DefinitionTypes as T, error::Error};
 
 pub fn eq(list: &[T]) -> Result<T, Error> {
@@ -34,7 +32,7 @@ pub fn lesser(list: &[T]) -> Result<T, Error> {
 
 pub fn is_false(list: &[T]) -> Result<T, Error> {
     Ok(T::Bool(list.iter().all(|e| match e {
-        T::Symbol(key) => DATA.lock().map_or(false, |m| match m.get(key) {
+        T::Symbol(_, _) => match e.clone().eval().ok().as_ref() {
             Some(T::Bool(b)) => !*b,
             Some(T::Nil) => true,
             Some(list) => {
@@ -45,10 +43,10 @@ pub fn is_false(list: &[T]) -> Result<T, Error> {
                 }
             }
             _ => false,
-        }),
+        },
         T::Bool(b) => !*b,
         T::Nil => true,
-        T::List(_) => {
+        T::List(_, _) => {
             if let Ok(T::Bool(b)) = is_false(&[e.clone().eval().unwrap_or(T::Bool(true))]) {
                 b
             } else {
@@ -61,7 +59,7 @@ pub fn is_false(list: &[T]) -> Result<T, Error> {
 
 pub fn is_true(list: &[T]) -> Result<T, Error> {
     Ok(T::Bool(list.iter().all(|e| match e {
-        T::Symbol(key) => DATA.lock().map_or(false, |m| match m.get(key) {
+        T::Symbol(_, _) => match e.clone().eval().ok().as_ref() {
             Some(T::Bool(b)) => *b,
             Some(T::Nil) => false,
             Some(list) => {
@@ -72,10 +70,10 @@ pub fn is_true(list: &[T]) -> Result<T, Error> {
                 }
             }
             _ => true,
-        }),
+        },
         T::Bool(b) => *b,
         T::Nil => false,
-        T::List(_) => {
+        T::List(_, _) => {
             if let Ok(T::Bool(b)) = is_true(&[e.clone().eval().unwrap_or(T::Nil)]) {
                 b
             } else {
@@ -88,21 +86,87 @@ pub fn is_true(list: &[T]) -> Result<T, Error> {
 
 pub fn is_nil(list: &[T]) -> Result<T, Error> {
     Ok(T::Bool(list.iter().all(|e| match e {
-        T::Symbol(key) => DATA.lock().map_or(false, |m| match m.get(key) {
-            Some(T::Nil) => true,
-            Some(list) => matches!(
-                is_false(&[list.clone().eval().unwrap_or(T::Bool(true))]),
-                Ok(T::Nil)
-            ),
-            _ => false,
-        }),
+        T::Symbol(_, _) => matches!(e.clone().eval(), Ok(T::Nil)),
         T::Nil => true,
-        T::List(_) => matches!(
-            is_false(&[e.clone().eval().unwrap_or(T::Bool(true))]),
-            Ok(T::Nil)
-        ),
+        T::List(_, _) => matches!(e.clone().eval(), Ok(T::Nil)),
         _ => false,
     })))
 }
 
 // fn every? and fn some? Issue 16
+
+pub fn and(list: &[T]) -> Result<T, Error> {
+    let mut last = T::Bool(true);
+    for e in list {
+        last = e.clone().eval()?;
+        if !last.is_truthy() {
+            break;
+        }
+    }
+    Ok(last)
+}
+
+pub fn or(list: &[T]) -> Result<T, Error> {
+    let mut last = T::Nil;
+    for e in list {
+        last = e.clone().eval()?;
+        if last.is_truthy() {
+            break;
+        }
+    }
+    Ok(last)
+}
+
+fn single(name: &str, list: &[T]) -> Result<T, Error> {
+    match list {
+        [e] => e.clone().eval(),
+        _ => Err(Error::arity(
+            1,
+            format!("`{}` has arity of 1 but received {}", name, list.len()),
+        )),
+    }
+}
+
+pub fn not(list: &[T]) -> Result<T, Error> {
+    Ok(T::Bool(!single("not", list)?.is_truthy()))
+}
+
+pub fn not_eq(list: &[T]) -> Result<T, Error> {
+    if list.is_empty() {
+        return Err(Error::arity(
+            1,
+            String::from("`not=` has arity of at least 1 but received 0"),
+        ));
+    }
+    match eq(list)? {
+        T::Bool(b) => Ok(T::Bool(!b)),
+        other => Ok(other),
+    }
+}
+
+pub fn boolean(list: &[T]) -> Result<T, Error> {
+    Ok(T::Bool(single("boolean", list)?.is_truthy()))
+}
+
+pub fn is_some(list: &[T]) -> Result<T, Error> {
+    Ok(T::Bool(!matches!(single("some?", list)?, T::Nil)))
+}
+
+pub fn is_any(list: &[T]) -> Result<T, Error> {
+    single("any?", list)?;
+    Ok(T::Bool(true))
+}
+
+pub fn is_string(list: &[T]) -> Result<T, Error> {
+    Ok(T::Bool(
+        list.iter()
+            .all(|e| matches!(e.clone().eval(), Ok(T::String(_)))),
+    ))
+}
+
+pub fn is_symbol(list: &[T]) -> Result<T, Error> {
+    Ok(T::Bool(
+        list.iter()
+            .all(|e| matches!(e.clone().eval(), Ok(T::Symbol(_, _)))),
+    ))
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use crate::{
+    definitions::{Arity, DefinitionTypes as T, Function, Lambda, Meta},
+    error::Error,
+    SCOPE,
+};
+
+use super::{
+    apply_macro,
+    collections::items,
+    current_scope,
+    namespaces::{current_ns, define, lookup_var, qualify_template},
+    repl::document,
+    special_forms::{destructure, next_gensym, parse_arity, quoted, sym, unquoted},
+};
+
+fn macro_arity(params: &T, body: &[T]) -> Result<Arity, Error> {
+    match params {
+        T::Vector(params, _) => {
+            let mut params_with_env = vec![sym("&form"), sym("&env")];
+            params_with_env.extend(params.iter().cloned());
+            parse_arity(&T::vector(params_with_env), body)
+        }
+        _ => parse_arity(params, body),
+    }
+}
+
+pub fn defmacro(list: &[T]) -> Result<T, Error> {
+    let (name, rest) = match list.split_first() {
+        Some((T::Symbol(name, _), rest)) => (name, rest),
+        _ => {
+            return Err(Error::cant_eval(Some(String::from(
+                "`defmacro` expects a name",
+            ))))
+        }
+    };
+    let rest = match rest.split_first() {
+        Some((T::String(doc), rest)) => {
+            document(name, doc);
+            rest
+        }
+        _ => rest,
+    };
+
+    let arities = match rest.first() {
+        Some(params @ T::Vector(_, _)) => vec![macro_arity(params, &rest[1..])?],
+        Some(T::List(_, _)) => rest
+            .iter()
+            .map(|arity| match arity {
+                T::List(arity, _) if !arity.is_empty() => macro_arity(&arity[0], &arity[1..]),
+                _ => Err(Error::cant_eval(arity.print().ok())),
+            })
+            .collect::<Result<Vec<Arity>, Error>>()?,
+        _ => {
+            return Err(Error::cant_eval(Some(String::from(
+                "`defmacro` expects a parameter vector",
+            ))))
+        }
+    };
+
+    let lambda = Lambda {
+        name: Some(name.to_owned()),
+        arities,
+        scope: current_scope(),
+        ns: current_ns(),
+        meta: Meta::default(),
+    };
+    define(name, T::Function(Function::Macro(Box::new(lambda))))
+}
+
+/// The names bound by the `let`, `loop`, `fn` and `catch` forms around a form.
+/// They shadow macros and are what a macro gets as `&env`.
+type Locals = im::HashSet<String>;
+
+fn scope_locals() -> Locals {
+    current_scope().keys().cloned().collect()
+}
+
+/// The macro `list` calls, unless a local shadows its name.
+fn called_macro(list: &[T], is_local: impl Fn(&str) -> bool) -> Option<Box<Lambda>> {
+    match list.first() {
+        Some(T::Symbol(name, _)) if !is_local(name) => match lookup_var(name) {
+            Some(T::Function(Function::Macro(lambda))) => Some(lambda),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Calls `lambda` with `list` and its metadata `meta` as `&form` and `locals` as `&env`.
+fn expand_call(lambda: &Lambda, list: &[T], meta: &Meta, locals: &Locals) -> Result<T, Error> {
+    let env = locals
+        .iter()
+        .map(|local| (sym(local), T::Nil))
+        .collect::<HashMap<T, T>>();
+    let form = T::List(list.to_vec(), meta.clone());
+    let mut args = vec![quoted(form), T::hash_map(env)];
+    args.extend(list[1..].iter().cloned().map(quoted));
+
+    Ok(unquoted(apply_macro(lambda, &args)?))
+}
+
+/// Expands `list` once if its head names a macro that isn't shadowed by a local
+/// in scope, passing it with its metadata `meta` as `&form`.
+pub(crate) fn expand_list(list: &[T], meta: &Meta) -> Result<Option<T>, Error> {
+    let is_local = |name: &str| SCOPE.with(|scope| scope.borrow().contains_key(name));
+    match called_macro(list, is_local) {
+        Some(lambda) => expand_call(&lambda, list, meta, &scope_locals()).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn expand_once(form: T) -> Result<(bool, T), Error> {
+    if let T::List(list, meta) = &form {
+        if let Some(expanded) = expand_list(list, meta)? {
+            return Ok((true, expanded));
+        }
+    }
+    Ok((false, form))
+}
+
+pub(crate) fn expand(form: T) -> Result<T, Error> {
+    let mut form = form;
+    loop {
+        match expand_once(form)? {
+            (true, expanded) => form = expanded,
+            (false, form) => return Ok(form),
+        }
+    }
+}
+
+/// Like `expand`, for a form inside the bindings of `locals`.
+fn expand_in(form: T, locals: &Locals) -> Result<T, Error> {
+    let mut form = form;
+    loop {
+        let lambda = match &form {
+            T::List(list, _) => called_macro(list, |name| locals.contains(name)),
+            _ => None,
+        };
+        form = match (lambda, form) {
+            (Some(lambda), T::List(list, meta)) => expand_call(&lambda, &list, &meta, locals)?,
+            (_, form) => return Ok(form),
+        };
+    }
+}
+
+/// The macroexpansion phase run on every top level form before it is evaluated.
+/// Quoted forms are left untouched and syntax-quoted templates get their symbols qualified.
+pub(crate) fn expand_all(form: T) -> Result<T, Error> {
+    expand_all_in(form, &scope_locals())
+}
+
+fn expand_all_in(form: T, locals: &Locals) -> Result<T, Error> {
+    let expand_each = |forms: Vec<T>, locals: &Locals| {
+        forms
+            .into_iter()
+            .map(|form| expand_all_in(form, locals))
+            .collect::<Result<Vec<T>, Error>>()
+    };
+    match expand_in(form, locals)? {
+        T::List(list, meta) if matches!(list.first(), Some(T::Symbol(head, _)) if head == "quote") => {
+            Ok(T::List(list, meta))
+        }
+        T::List(list, meta) if list.len() == 2 && list[0] == sym("syntax-quote") => {
+            let template = qualify_template(list[1].clone(), &|form| expand_all_in(form, locals))?;
+            Ok(T::List(vec![sym("syntax-quote"), template], meta))
+        }
+        T::List(list, meta) => {
+            let head = match list.first() {
+                Some(T::Symbol(head, _)) if !locals.contains(head) => head.as_str(),
+                _ => "",
+            };
+            let list = match head {
+                "let" | "loop" => expand_bindings(list, locals)?,
+                "fn" => expand_fn(list, locals)?,
+                "catch" => expand_catch(list, locals)?,
+                _ => expand_each(list, locals)?,
+            };
+            Ok(T::List(list, meta))
+        }
+        T::Vector(vec, meta) => Ok(T::Vector(expand_each(vec, locals)?, meta)),
+        T::HashSet(set, meta) => Ok(T::HashSet(
+            set.into_iter()
+                .map(|form| expand_all_in(form, locals))
+                .collect::<Result<_, Error>>()?,
+            meta,
+        )),
+        T::HashMap(map, meta) => Ok(T::HashMap(
+            map.into_iter()
+                .map(|(k, v)| Ok((expand_all_in(k, locals)?, expand_all_in(v, locals)?)))
+                .collect::<Result<_, Error>>()?,
+            meta,
+        )),
+        form => Ok(form),
+    }
+}
+
+/// Adds the names `pattern` binds to `locals`. A malformed pattern binds
+/// nothing here and fails once it is evaluated.
+fn bind_pattern(pattern: &T, locals: &mut Locals) {
+    let mut bindings = Vec::new();
+    if destructure(pattern, T::Nil, &mut bindings).is_ok() {
+        locals.extend(bindings.into_iter().map(|(name, _)| name));
+    }
+}
+
+/// `(let [pattern value*] body*)` and `loop`: each value is expanded with the
+/// names bound before it, the body with all of them.
+fn expand_bindings(list: Vec<T>, locals: &Locals) -> Result<Vec<T>, Error> {
+    let mut locals = locals.clone();
+    let mut list = list.into_iter();
+    let mut expanded = list.next().into_iter().collect::<Vec<T>>();
+    match list.next() {
+        Some(T::Vector(bindings, meta)) => {
+            let mut pairs = Vec::new();
+            let mut bindings = bindings.into_iter();
+            while let Some(pattern) = bindings.next() {
+                let pattern = expand_all_in(pattern, &locals)?;
+                let value = bindings
+                    .next()
+                    .map(|value| expand_all_in(value, &locals))
+                    .transpose()?;
+                bind_pattern(&pattern, &mut locals);
+                pairs.push(pattern);
+                pairs.extend(value);
+            }
+            expanded.push(T::Vector(pairs, meta));
+        }
+        Some(form) => expanded.push(expand_all_in(form, &locals)?),
+        None => (),
+    }
+    for form in list {
+        expanded.push(expand_all_in(form, &locals)?);
+    }
+    Ok(expanded)
+}
+
+/// `(fn name? [params] body*)` or `(fn name? ([params] body*)+)`: every body is
+/// expanded with the name and its parameters.
+fn expand_fn(list: Vec<T>, locals: &Locals) -> Result<Vec<T>, Error> {
+    let mut locals = locals.clone();
+    let mut list = list.into_iter().peekable();
+    let mut expanded = list.next().into_iter().collect::<Vec<T>>();
+    if let Some(T::Symbol(name, _)) = list.peek() {
+        locals.insert(name.to_owned());
+        expanded.extend(list.next());
+    }
+    if let Some(T::Vector(_, _)) = list.peek() {
+        expanded.extend(expand_arity(list.collect(), &locals)?);
+        return Ok(expanded);
+    }
+    for arity in list {
+        expanded.push(match arity {
+            T::List(arity, meta) => T::List(expand_arity(arity, &locals)?, meta),
+            form => expand_all_in(form, &locals)?,
+        });
+    }
+    Ok(expanded)
+}
+
+/// `[params] body*`, the body expanded with the parameters bound.
+fn expand_arity(arity: Vec<T>, locals: &Locals) -> Result<Vec<T>, Error> {
+    let mut locals = locals.clone();
+    if let Some(T::Vector(params, _)) = arity.first() {
+        for param in params.iter().filter(|param| **param != sym("&")) {
+            bind_pattern(param, &mut locals);
+        }
+    }
+    arity
+        .into_iter()
+        .map(|form| expand_all_in(form, &locals))
+        .collect()
+}
+
+/// `(catch Class e body*)`, the body expanded with `e` bound.
+fn expand_catch(list: Vec<T>, locals: &Locals) -> Result<Vec<T>, Error> {
+    let mut locals = locals.clone();
+    if let Some(T::Symbol(name, _)) = list.get(2) {
+        locals.insert(name.to_owned());
+    }
+    list.into_iter()
+        .map(|form| expand_all_in(form, &locals))
+        .collect()
+}
+
+fn form_arg(name: &str, list: &[T]) -> Result<T, Error> {
+    match list {
+        [form] => Ok(unquoted(form.clone().eval()?)),
+        _ => Err(Error::arity(
+            1,
+            format!("`{}` has arity of 1 but received {}", name, list.len()),
+        )),
+    }
+}
+
+pub fn macroexpand_1(list: &[T]) -> Result<T, Error> {
+    Ok(quoted(expand_once(form_arg("macroexpand-1", list)?)?.1))
+}
+
+pub fn macroexpand(list: &[T]) -> Result<T, Error> {
+    Ok(quoted(expand(form_arg("macroexpand", list)?)?))
+}
+
+pub fn macroexpand_all(list: &[T]) -> Result<T, Error> {
+    Ok(quoted(expand_all(form_arg("macroexpand-all", list)?)?))
+}
+
+pub fn gensym(list: &[T]) -> Result<T, Error> {
+    match list {
+        [] => Ok(T::symbol(next_gensym("G__"))),
+        [prefix] => match prefix.clone().eval()? {
+            T::String(prefix) | T::Symbol(prefix, _) => Ok(T::symbol(next_gensym(&prefix))),
+            prefix => Err(Error::cant_eval(Some(format!(
+                "`gensym` expects a string prefix but received {}",
+                prefix.print()?
+            )))),
+        },
+        _ => Err(Error::arity(
+            1,
+            format!("`gensym` has arity of 0 or 1 but received {}", list.len()),
+        )),
+    }
+}
+
+fn unwrap<'a>(form: &'a T, name: &str) -> Option<&'a T> {
+    match form {
+        T::List(list, _) if list.len() == 2 && list[0] == sym(name) => Some(&list[1]),
+        _ => None,
+    }
+}
+
+pub fn syntax_quote(list: &[T]) -> Result<T, Error> {
+    match list {
+        [form] => template(form, &mut HashMap::new()),
+        _ => Err(Error::arity(
+            1,
+            format!("`syntax-quote` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
+
+// Builds the data described by a syntax-quoted `form`, evaluating `~x`,
+// splicing `~@xs` and replacing `name#` with one generated symbol per template.
+fn template(form: &T, gensyms: &mut HashMap<String, String>) -> Result<T, Error> {
+    if let Some(form) = unwrap(form, "unquote") {
+        return form.clone().eval();
+    }
+    if unwrap(form, "unquote-splicing").is_some() {
+        return Err(Error::cant_eval(Some(String::from(
+            "`~@` must be used inside a collection",
+        ))));
+    }
+
+    match form {
+        T::List(list, _) => Ok(T::seq(splice(list, gensyms)?)),
+        T::Vector(vec, _) => Ok(T::vector(splice(vec, gensyms)?)),
+        T::HashSet(set, _) => Ok(T::hash_set(
+            splice(&set.iter().cloned().collect::<Vec<T>>(), gensyms)?
+                .into_iter()
+                .collect(),
+        )),
+        T::HashMap(map, _) => {
+            let mut templated = HashMap::new();
+            for (k, v) in map {
+                templated.insert(template(k, gensyms)?, template(v, gensyms)?);
+            }
+            Ok(T::hash_map(templated))
+        }
+        T::Symbol(name, _) if name.len() > 1 && name.ends_with('#') => {
+            let generated = gensyms.entry(name.to_owned()).or_insert_with(|| {
+                next_gensym(&format!("{}__", &name[..name.len() - 1])) + "__auto__"
+            });
+            Ok(T::symbol(generated.to_owned()))
+        }
+        form => Ok(form.clone()),
+    }
+}
+
+fn splice(forms: &[T], gensyms: &mut HashMap<String, String>) -> Result<Vec<T>, Error> {
+    let mut spliced = Vec::new();
+    for form in forms {
+        match unwrap(form, "unquote-splicing") {
+            Some(coll) => spliced.extend(items(coll.clone().eval()?)?),
+            None => spliced.push(template(form, gensyms)?),
+        }
+    }
+    Ok(spliced)
+}
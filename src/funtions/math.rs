@@ -1,16 +1,18 @@
 use num_bigint::{BigInt, ToBigInt};
 use num_traits::{One, Zero};
 
-use crate::{definitions::DefinitionTypes as T, error::Error, DATA};
+use crate::{
+    definitions::DefinitionTypes as T,
+    error::{Error, ErrorKind},
+};
 
 use super::eval_list;
 
 pub fn is_numeric(list: &[T]) -> Result<T, Error> {
     Ok(T::Bool(list.iter().enumerate().all(
         |(i, e)| match e.clone() {
-            T::Symbol(symbol) if i == 0 => {
-                if let Some(data) = DATA.lock().unwrap().get(&symbol) {
-                    let data = data.clone();
+            T::Symbol(_, _) if i == 0 => {
+                if let Ok(data) = e.clone().eval() {
                     if let Ok(T::Bool(b)) = is_numeric(&[data]) {
                         b
                     } else {
@@ -20,18 +22,10 @@ pub fn is_numeric(list: &[T]) -> Result<T, Error> {
                     false
                 }
             }
-            T::List(mut l) => {
-                let eval = eval_list(&mut l);
-                eval.clone().map(|op| op.parse::<BigInt>()).is_ok()
-                    || eval.clone().map(|op| op.parse::<f64>()).is_ok()
-                    || eval
-                        .map(|op| {
-                            op.split('/').all(|num| {
-                                num.parse::<BigInt>().is_ok() || num.parse::<f64>().is_ok()
-                            })
-                        })
-                        .unwrap_or(false)
-            }
+            T::List(_, _) => matches!(
+                e.clone().eval(),
+                Ok(T::Double(_) | T::Int(_) | T::Rational(_, _))
+            ),
             T::Double(_) | T::Int(_) | T::Rational(_, _) => true,
             _ => false,
         },
@@ -40,9 +34,8 @@ pub fn is_numeric(list: &[T]) -> Result<T, Error> {
 
 pub fn is_positive(list: &[T]) -> Result<T, Error> {
     Ok(T::Bool(list.iter().all(|e| match e.clone() {
-        T::Symbol(symbol) => {
-            if let Some(data) = DATA.lock().unwrap().get(&symbol) {
-                let data = data.clone();
+        T::Symbol(_, _) => {
+            if let Ok(data) = e.clone().eval() {
                 if let Ok(T::Bool(b)) = is_positive(&[data]) {
                     b
                 } else {
@@ -55,7 +48,7 @@ pub fn is_positive(list: &[T]) -> Result<T, Error> {
         T::Double(num) if num.0 > 0.0f64 => true,
         T::Int(num) if num > 0.to_bigint().unwrap() => true,
         T::Rational(num, _) if num > BigInt::zero() => true,
-        T::List(mut l) => {
+        T::List(mut l, _) => {
             let eval = eval_list(&mut l).unwrap_or_default();
 
             if eval.contains('/')
@@ -86,9 +79,8 @@ pub fn is_positive(list: &[T]) -> Result<T, Error> {
 pub fn is_negative(list: &[T]) -> Result<T, Error> {
     println!("{:?}", list);
     Ok(T::Bool(list.iter().all(|e| match e.clone() {
-        T::Symbol(symbol) => {
-            if let Some(data) = DATA.lock().unwrap().get(&symbol) {
-                let data = data.clone();
+        T::Symbol(_, _) => {
+            if let Ok(data) = e.clone().eval() {
                 if let Ok(T::Bool(b)) = is_negative(&[data]) {
                     b
                 } else {
@@ -101,7 +93,7 @@ pub fn is_negative(list: &[T]) -> Result<T, Error> {
         T::Double(num) if num.0 < 0.0f64 => true,
         T::Int(num) if num < 0.to_bigint().unwrap() => true,
         T::Rational(num, _) if num < BigInt::zero() => true,
-        T::List(mut l) => {
+        T::List(mut l, _) => {
             let eval = eval_list(&mut l).unwrap_or_default();
 
             if eval.contains('/')
@@ -131,28 +123,78 @@ pub fn is_negative(list: &[T]) -> Result<T, Error> {
 
 pub fn plus(list: &[T]) -> Result<T, Error> {
     list.iter()
-        .try_fold(T::Int(BigInt::zero()), |acc, e| acc + e.clone())
+        .try_fold(T::Int(BigInt::zero()), |acc, e| acc + e.clone().eval()?)
 }
 
 pub fn sub(list: &[T]) -> Result<T, Error> {
     if let Some((first, rest)) = list.split_first() {
         rest.iter()
-            .try_fold(first.to_owned(), |acc, e| acc - e.to_owned())
+            .try_fold(first.clone().eval()?, |acc, e| acc - e.clone().eval()?)
     } else {
-        Err(Error::Reason(String::from("Couldn't parse form content")))
+        Err(Error::reason(String::from("Couldn't parse form content")))
     }
 }
 
 pub fn mul(list: &[T]) -> Result<T, Error> {
     list.iter()
-        .try_fold(T::Int(BigInt::one()), |acc, e| acc * e.clone())
+        .try_fold(T::Int(BigInt::one()), |acc, e| acc * e.clone().eval()?)
 }
 
 pub fn div(list: &[T]) -> Result<T, Error> {
     if let Some((first, rest)) = list.split_first() {
         rest.iter()
-            .try_fold(first.to_owned(), |acc, e| acc / e.to_owned())
+            .try_fold(first.clone().eval()?, |acc, e| acc / e.clone().eval()?)
+    } else {
+        Err(Error::reason(String::from("Couldn't parse form content")))
+    }
+}
+
+pub fn inc(list: &[T]) -> Result<T, Error> {
+    if list.len() != 1 {
+        return Err(Error::arity(
+            1,
+            format!("`inc` has arity of 1 but received {}", list.len()),
+        ));
+    }
+    list[0].clone().eval()? + T::Int(BigInt::one())
+}
+
+pub fn dec(list: &[T]) -> Result<T, Error> {
+    if list.len() != 1 {
+        return Err(Error::arity(
+            1,
+            format!("`dec` has arity of 1 but received {}", list.len()),
+        ));
+    }
+    list[0].clone().eval()? - T::Int(BigInt::one())
+}
+
+pub fn is_even(list: &[T]) -> Result<T, Error> {
+    if list.len() != 1 {
+        return Err(Error::arity(
+            1,
+            format!("`even?` has arity of 1 but received {}", list.len()),
+        ));
+    }
+    match list[0].clone().eval()? {
+        T::Int(num) => Ok(T::Bool(num % 2 == BigInt::zero())),
+        el => Err(Error::cant_eval(Some(format!(
+            "Argument must be an integer: {}",
+            el.print()?
+        )))),
+    }
+}
+
+pub fn is_odd(list: &[T]) -> Result<T, Error> {
+    if let T::Bool(even) = is_even(list).map_err(|err| match err.kind {
+        ErrorKind::Arity(n) => Error::arity(
+            n,
+            format!("`odd?` has arity of 1 but received {}", list.len()),
+        ),
+        _ => err,
+    })? {
+        Ok(T::Bool(!even))
     } else {
-        Err(Error::Reason(String::from("Couldn't parse form content")))
+        Err(Error::cant_eval(None))
     }
 }
@@ -0,0 +1,109 @@
+use crate::{
+    definitions::{DefinitionTypes as T, Meta},
+    error::Error,
+};
+
+use super::{
+    apply, arity,
+    vars::{alter_var_meta, as_var, var_meta},
+};
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+/// Metadata is a map, or nil for none.
+pub(crate) fn expect_meta(name: &str, meta: T) -> Result<T, Error> {
+    match meta {
+        T::HashMap(..) | T::Nil => Ok(meta),
+        meta => Err(Error::cant_eval(format!(
+            "`{}` expects a metadata map but received {}",
+            name,
+            meta.print()?
+        ))),
+    }
+}
+
+/// `(apply f meta args)`, as `vary-meta` and `alter-meta!` call it.
+fn apply_to_meta(f: &T, meta: T, args: &[T]) -> Result<T, Error> {
+    let mut call = vec![meta];
+    call.extend(args.iter().cloned());
+    apply(f, &call)
+}
+
+fn attach(name: &str, value: T, meta: T) -> Result<T, Error> {
+    let meta = Meta::new(expect_meta(name, meta)?);
+    value.with_meta(meta).or_else(|value| {
+        Err(Error::cant_eval(format!(
+            "`{}` can't attach metadata to {}",
+            name,
+            value.print()?
+        )))
+    })
+}
+
+/// `(meta x)` is the metadata of a value, var or atom, or nil.
+pub fn meta(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Atom(atom)] => Ok(atom.meta()),
+        [value] => Ok(as_var(value).map_or_else(|| value.meta(), var_meta)),
+        _ => Err(arity("meta", 1, "1", list)),
+    }
+}
+
+/// `(with-meta x map)` is `x` with `map` in place of its metadata.
+pub fn with_meta(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [value, meta] => attach("with-meta", value.clone(), meta.clone()),
+        _ => Err(arity("with-meta", 2, "2", list)),
+    }
+}
+
+/// `(vary-meta x f & args)` is `x` with `(apply f (meta x) args)` as its metadata.
+pub fn vary_meta(list: &[T]) -> Result<T, Error> {
+    let mut args = eval_all(list)?;
+    if args.len() < 2 {
+        return Err(arity("vary-meta", 2, "at least 2", list));
+    }
+    let value = args.remove(0);
+    let f = args.remove(0);
+    let meta = apply_to_meta(&f, value.meta(), &args)?;
+    attach("vary-meta", value, meta)
+}
+
+fn update_meta(name: &str, reference: &T, f: impl Fn(T) -> Result<T, Error>) -> Result<T, Error> {
+    match (reference, as_var(reference)) {
+        (T::Atom(atom), _) => atom.alter_meta(|meta| expect_meta(name, f(meta.clone())?)),
+        (_, Some(key)) => alter_var_meta(key, f),
+        _ => Err(Error::cant_eval(format!(
+            "`{}` expects a var or atom but received {}",
+            name,
+            reference.print()?
+        ))),
+    }
+}
+
+/// `(alter-meta! ref f & args)` sets the metadata of a var or atom to
+/// `(apply f (meta ref) args)`, returning it.
+pub fn alter_meta(list: &[T]) -> Result<T, Error> {
+    let mut args = eval_all(list)?;
+    if args.len() < 2 {
+        return Err(arity("alter-meta!", 2, "at least 2", list));
+    }
+    let reference = args.remove(0);
+    let f = args.remove(0);
+    update_meta("alter-meta!", &reference, |meta| {
+        apply_to_meta(&f, meta, &args)
+    })
+}
+
+/// `(reset-meta! ref map)` replaces the metadata of a var or atom.
+pub fn reset_meta(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [reference, meta] => {
+            let meta = expect_meta("reset-meta!", meta.clone())?;
+            update_meta("reset-meta!", reference, |_| Ok(meta.clone()))
+        }
+        _ => Err(arity("reset-meta!", 2, "2", list)),
+    }
+}
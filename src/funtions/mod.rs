@@ -1,46 +1,359 @@
-use num_bigint::ToBigInt;
+use im::HashMap as Hamt;
 
-use crate::{definitions::DefinitionTypes as T, error::Error, STD};
+use ::std::{
+    cell::Cell,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    definitions::{Arity, DefinitionTypes as T, Function, Lambda},
+    error::{Error, ErrorKind},
+    DEPTH, INTERRUPT, OUTPUT, SCOPE,
+};
+
+use self::{
+    collections::{items, lookup},
+    namespaces::with_ns,
+    special_forms::{eval_tail_body, rest_args},
+};
+pub mod atoms;
+pub mod channels;
+pub mod collections;
+pub mod concurrency;
+pub mod docs;
+pub mod exceptions;
+pub mod loading;
 pub mod logic;
+pub mod macros;
 pub mod math;
+pub mod metadata;
+pub mod multimethods;
+pub mod namespaces;
+pub mod protocols;
+pub mod refs;
+pub mod repl;
+pub mod special_forms;
 pub mod std;
+pub mod strings;
+pub mod transducers;
+pub mod vars;
 // pub mod adapter_consumers Issue 14
-// pub mod collections Issue 15
 
 pub type Func = fn(&[T]) -> Result<T, Error>;
 
-pub fn eval_list(list: &mut Vec<T>) -> Result<String, Error> {
+pub fn eval_list(list: &mut [T]) -> Result<String, Error> {
     if list.is_empty() {
         return Ok(String::from("()"));
     }
 
-    let mut list = list.iter_mut();
-    let next = list.next();
-    if let Some(T::Symbol(symbol)) = next {
-        let rest: Vec<T> = list.map(|e| e.clone()).collect();
-        STD.get(symbol)
-            .ok_or_else(|| Error::UnknownSymbol(symbol.to_string()))?(&rest)?
-        .print()
+    T::list(list.to_vec()).eval()?.print()
+}
+
+/// Whether `symbol` is a local or a var, which shadows `STD`.
+pub(crate) fn is_bound(symbol: &str) -> bool {
+    SCOPE.with(|scope| scope.borrow().contains_key(symbol))
+        || namespaces::resolve_var(symbol).is_some()
+}
+
+/// Interpreters recurse deeply, so the threads they run on get the stack size
+/// of a main thread.
+pub(crate) const THREAD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Half as much again as the most stack one level of nested calls takes,
+/// which optimisation shrinks to a fifth.
+const CALL_FRAME_SIZE: usize = if cfg!(debug_assertions) {
+    24 * 1024
+} else {
+    5 * 1024
+};
+
+/// How deeply calls nest on one thread before they fail with a `StackOverflowError`
+/// rather than overflow a `THREAD_STACK_SIZE` stack and abort the process.
+const MAX_DEPTH: usize = THREAD_STACK_SIZE / CALL_FRAME_SIZE;
+
+/// Runs `f` one call deeper than the caller.
+pub(crate) fn nested<R>(f: impl FnOnce() -> Result<R, Error>) -> Result<R, Error> {
+    let depth = DEPTH.with(Cell::get);
+    if depth >= MAX_DEPTH {
+        return Err(Error::new(
+            ErrorKind::StackOverflow,
+            "Calls nested too deeply",
+        ));
+    }
+    DEPTH.with(|current| current.set(depth + 1));
+    let result = f();
+    DEPTH.with(|current| current.set(depth));
+    result
+}
+
+pub(crate) fn current_scope() -> Hamt<String, T> {
+    SCOPE.with(|scope| scope.borrow().clone())
+}
+
+/// Fails once the evaluation running on this thread has been asked to stop.
+pub(crate) fn check_interrupt() -> Result<(), Error> {
+    let interrupted = INTERRUPT.with(|flag| {
+        flag.borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.swap(false, Ordering::SeqCst))
+    });
+    if interrupted {
+        Err(Error::new(ErrorKind::Interrupted, "Evaluation interrupted"))
     } else {
-        let next = next.map(|e| e.print().unwrap_or_default());
-        Err(Error::CantEval(next))
+        Ok(())
+    }
+}
+
+/// Lets another thread stop the evaluations run on this one by setting `flag`.
+pub fn set_interrupt_flag(flag: Option<Arc<AtomicBool>>) {
+    INTERRUPT.with(|current| current.replace(flag));
+}
+
+/// Writes `text` to `*out*`. Bound to an atom, it appends to the string the atom
+/// holds; otherwise it goes to the output being captured on this thread, or to stdout.
+pub(crate) fn write_out(text: &str) {
+    if let Some(T::Atom(out)) = vars::var_value("clojure.core/*out*") {
+        // A validator that rejects the text loses it, as a failing writer would.
+        let _ = out.swap(|written| match written {
+            T::String(written) => Ok(T::String(format!("{}{}", written, text))),
+            _ => Ok(T::String(text.to_owned())),
+        });
+        return;
+    }
+    OUTPUT.with(|output| match output.borrow_mut().as_mut() {
+        Some(output) => output.push_str(text),
+        None => {
+            print!("{}", text);
+            let _ = ::std::io::stdout().flush();
+        }
+    })
+}
+
+/// Runs `f`, returning what it printed instead of writing it to stdout.
+pub fn capture_output<R>(f: impl FnOnce() -> R) -> (R, String) {
+    let previous = OUTPUT.with(|output| output.replace(Some(String::new())));
+    let result = f();
+    let captured = OUTPUT.with(|output| output.replace(previous));
+    (result, captured.unwrap_or_default())
+}
+
+/// Runs `f` with `scope` as the local bindings, restoring the previous ones afterwards.
+pub(crate) fn with_scope<R>(scope: Hamt<String, T>, f: impl FnOnce() -> R) -> R {
+    let previous = SCOPE.with(|s| s.replace(scope));
+    let result = f();
+    SCOPE.with(|s| s.replace(previous));
+    result
+}
+
+/// `expected` is the smallest arity `name` accepts and `arities` describes them all.
+pub(crate) fn arity(name: &str, expected: u16, arities: &str, list: &[T]) -> Error {
+    Error::arity(
+        expected,
+        format!(
+            "`{}` has arity of {} but received {}",
+            name,
+            arities,
+            list.len()
+        ),
+    )
+}
+
+pub fn apply(f: &T, args: &[T]) -> Result<T, Error> {
+    match f {
+        T::Function(Function::Native(_, func)) if args.iter().all(is_self_evaluating) => func(args),
+        T::Function(Function::Native(_, func)) => func(
+            &args
+                .iter()
+                .map(|arg| {
+                    if is_self_evaluating(arg) {
+                        arg.clone()
+                    } else {
+                        T::list(vec![T::symbol(String::from("quote")), arg.clone()])
+                    }
+                })
+                .collect::<Vec<T>>(),
+        ),
+        T::Function(Function::Comp(fns)) => {
+            let mut fns = fns.iter().rev();
+            match fns.next() {
+                Some(first) => fns.try_fold(apply(first, args)?, |acc, f| apply(f, &[acc])),
+                None if args.len() == 1 => Ok(args[0].clone()),
+                None => Err(Error::arity(
+                    1,
+                    format!("`comp` has arity of 1 but received {}", args.len()),
+                )),
+            }
+        }
+        T::Function(Function::Lambda(lambda)) => apply_lambda(lambda, args),
+        T::Function(Function::Method(protocol, method)) => {
+            protocols::dispatch(protocol, method, args)
+        }
+        T::Function(Function::Multi(var)) => multimethods::dispatch(var, args),
+        T::Function(Function::Macro(lambda)) => Err(Error::cant_eval(Some(format!(
+            "Can't take value of a macro: #'{}",
+            lambda.name.as_deref().unwrap_or("fn")
+        )))),
+        T::Keyword(_) => match args {
+            [coll] => Ok(lookup(coll, f).unwrap_or(T::Nil)),
+            [coll, not_found] => Ok(lookup(coll, f).unwrap_or_else(|| not_found.clone())),
+            _ => Err(Error::arity(
+                1,
+                format!("keyword has arity of 1 or 2 but received {}", args.len()),
+            )),
+        },
+        T::HashMap(_, _) | T::OrderedMap(_) | T::HashSet(_, _) | T::OrderedSet(_) => match args {
+            [key] => Ok(lookup(f, key).unwrap_or(T::Nil)),
+            [key, not_found] => Ok(lookup(f, key).unwrap_or_else(|| not_found.clone())),
+            _ => Err(Error::arity(
+                1,
+                format!("collection has arity of 1 or 2 but received {}", args.len()),
+            )),
+        },
+        T::Vector(_, _) => match args {
+            [index] => lookup(f, index).ok_or_else(|| {
+                Error::cant_eval(Some(format!(
+                    "Index {} out of bounds",
+                    index.print().unwrap_or_default()
+                )))
+            }),
+            [index, not_found] => Ok(lookup(f, index).unwrap_or_else(|| not_found.clone())),
+            _ => Err(Error::arity(
+                1,
+                format!("vector has arity of 1 or 2 but received {}", args.len()),
+            )),
+        },
+        _ => Err(Error::cant_eval(f.print().ok())),
+    }
+}
+
+// Natives evaluate their own arguments, so values that would evaluate
+// to something else are handed over quoted.
+fn is_self_evaluating(value: &T) -> bool {
+    match value {
+        T::Symbol(_, _) | T::List(_, _) => false,
+        T::Vector(vec, _) => vec.iter().all(is_self_evaluating),
+        T::HashSet(set, _) => set.iter().all(is_self_evaluating),
+        T::OrderedSet(set) => set.iter().all(is_self_evaluating),
+        T::HashMap(map, _) => map
+            .iter()
+            .all(|(k, v)| is_self_evaluating(k) && is_self_evaluating(v)),
+        T::OrderedMap(map) => map
+            .iter()
+            .all(|(k, v)| is_self_evaluating(k) && is_self_evaluating(v)),
+        _ => true,
+    }
+}
+
+/// Calls a macro's expander, which never refers to itself as a function.
+pub(crate) fn apply_macro(lambda: &Lambda, args: &[T]) -> Result<T, Error> {
+    apply_lambda(
+        &Lambda {
+            name: None,
+            ..lambda.clone()
+        },
+        args,
+    )
+}
+
+fn apply_lambda(lambda: &Lambda, args: &[T]) -> Result<T, Error> {
+    let mut args = args.to_vec();
+    loop {
+        let arity = select_arity(lambda, args.len())?;
+
+        let mut scope = lambda.scope.clone();
+        if let Some(name) = &lambda.name {
+            scope.insert(
+                name.clone(),
+                T::Function(Function::Lambda(Box::new(lambda.clone()))),
+            );
+        }
+        for (param, arg) in arity.params.iter().zip(&args) {
+            scope.insert(param.clone(), arg.clone());
+        }
+        if let Some(rest) = &arity.rest {
+            let rest_args = &args[arity.params.len()..];
+            let rest_value = if rest_args.is_empty() {
+                T::Nil
+            } else {
+                T::seq(rest_args.to_vec())
+            };
+            scope.insert(rest.clone(), rest_value);
+        }
+
+        match with_ns(&lambda.ns, || {
+            with_scope(scope, || eval_tail_body(&arity.body))
+        })? {
+            T::Recur(recur_args) if arity.rest.is_some() => {
+                args = rest_args(recur_args, arity.params.len())?
+            }
+            T::Recur(recur_args) => args = recur_args,
+            result => return Ok(result),
+        }
+    }
+}
+
+fn select_arity(lambda: &Lambda, argc: usize) -> Result<&Arity, Error> {
+    lambda
+        .arities
+        .iter()
+        .find(|arity| arity.rest.is_none() && arity.params.len() == argc)
+        .or_else(|| {
+            lambda
+                .arities
+                .iter()
+                .find(|arity| arity.rest.is_some() && arity.params.len() <= argc)
+        })
+        .ok_or_else(|| {
+            Error::arity(
+                lambda.arities.first().map_or(0, |a| a.params.len() as u16),
+                format!(
+                    "Wrong number of args ({}) passed to: {}",
+                    argc,
+                    lambda.name.as_deref().unwrap_or("fn")
+                ),
+            )
+        })
+}
+
+pub fn apply_fn(list: &[T]) -> Result<T, Error> {
+    match list {
+        [f, args @ .., coll] => {
+            let f = f.clone().eval()?;
+            let mut args = args
+                .iter()
+                .map(|arg| arg.clone().eval())
+                .collect::<Result<Vec<T>, Error>>()?;
+            args.extend(items(coll.clone().eval()?)?);
+            apply(&f, &args)
+        }
+        _ => Err(Error::arity(
+            2,
+            format!(
+                "`apply` has arity of at least 2 but received {}",
+                list.len()
+            ),
+        )),
     }
 }
 
 pub fn meaning_of_life(_: &[T]) -> Result<T, Error> {
-    Ok(T::Int(42.to_bigint().ok_or(Error::IntParseError)?))
+    Ok(T::Int(42.into()))
 }
 
 pub fn throw(message: &[T]) -> Result<T, Error> {
     if message.len() != 1 {
-        return Err(Error::ArityException(
+        return Err(Error::arity(
             1,
             format!("`throw` has arity of 1 but received {}", message.len()),
         ));
     }
-    if let T::String(msg) = message[0].clone() {
-        Err(Error::Thrown(msg))
-    } else {
-        Err(Error::CantEval(Some(format!("{:?}", message))))
+    match message[0].clone().eval() {
+        Ok(T::String(msg)) => Err(Error::thrown(msg)),
+        Ok(T::Throwable(err)) => Err(*err),
+        _ => Err(Error::cant_eval(Some(format!("{:?}", message)))),
     }
 }
@@ -0,0 +1,541 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLockWriteGuard,
+};
+
+use im::HashMap as Hamt;
+
+use crate::{
+    definitions::{DefinitionTypes as T, Function, Meta},
+    env::current_env,
+    error::Error,
+};
+
+use super::{
+    apply, arity,
+    collections::lookup,
+    namespaces::{current_ns, split_qualified},
+    special_forms::{def, fn_form},
+    vars::{as_var, var_value},
+};
+
+/// Tags and their parents, from which ancestors and descendants follow.
+#[derive(Clone, Default)]
+pub struct Hierarchy {
+    parents: HashMap<T, HashSet<T>>,
+}
+
+impl Hierarchy {
+    /// Reads a hierarchy made by `make-hierarchy` and `derive`.
+    fn from_value(value: &T) -> Result<Hierarchy, Error> {
+        let parents = match lookup(value, &keyword("parents")) {
+            Some(T::HashMap(parents, _)) => parents,
+            _ => {
+                return Err(Error::illegal_argument(format!(
+                    "Expected a hierarchy but received {}",
+                    value.print()?
+                )))
+            }
+        };
+        let parents = parents
+            .into_iter()
+            .map(|(tag, parents)| match parents {
+                T::HashSet(parents, _) => Ok((tag, parents)),
+                parents => Err(Error::illegal_argument(format!(
+                    "Expected a set of parents but received {}",
+                    parents.print()?
+                ))),
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Hierarchy { parents })
+    }
+
+    fn to_value(&self) -> T {
+        let tags = self
+            .parents
+            .iter()
+            .flat_map(|(tag, parents)| parents.iter().chain([tag]))
+            .cloned()
+            .collect::<HashSet<T>>();
+        let relation = |f: &dyn Fn(&T) -> HashSet<T>| {
+            T::hash_map(
+                tags.iter()
+                    .map(|tag| (tag.clone(), f(tag)))
+                    .filter(|(_, related)| !related.is_empty())
+                    .map(|(tag, related)| (tag, T::hash_set(related)))
+                    .collect(),
+            )
+        };
+        T::hash_map(HashMap::from([
+            (keyword("parents"), relation(&|tag| self.parents(tag))),
+            (keyword("ancestors"), relation(&|tag| self.ancestors(tag))),
+            (
+                keyword("descendants"),
+                relation(&|tag| self.descendants(tag)),
+            ),
+        ]))
+    }
+
+    fn parents(&self, tag: &T) -> HashSet<T> {
+        self.parents.get(tag).cloned().unwrap_or_default()
+    }
+
+    fn ancestors(&self, tag: &T) -> HashSet<T> {
+        let mut ancestors = HashSet::new();
+        let mut pending = self.parents(tag).into_iter().collect::<Vec<T>>();
+        while let Some(parent) = pending.pop() {
+            if ancestors.insert(parent.clone()) {
+                pending.extend(self.parents(&parent));
+            }
+        }
+        ancestors
+    }
+
+    fn descendants(&self, tag: &T) -> HashSet<T> {
+        self.parents
+            .keys()
+            .filter(|child| self.ancestors(child).contains(tag))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether `child` is `parent`, derives from it, or is a vector of tags
+    /// that each are `isa?` the tag at the same place in `parent`.
+    fn isa(&self, child: &T, parent: &T) -> bool {
+        match (child, parent) {
+            _ if child == parent => true,
+            (T::Vector(children, _), T::Vector(parents, _)) => {
+                children.len() == parents.len()
+                    && children.iter().zip(parents).all(|(c, p)| self.isa(c, p))
+            }
+            _ => self.ancestors(child).contains(parent),
+        }
+    }
+
+    fn derive(&mut self, tag: T, parent: T) -> Result<(), Error> {
+        if tag == parent {
+            return Err(Error::illegal_argument(format!(
+                "Can't derive {} from itself",
+                tag.print()?
+            )));
+        }
+        if self.parents(&tag).contains(&parent) {
+            return Ok(());
+        }
+        if self.ancestors(&tag).contains(&parent) {
+            return Err(Error::illegal_argument(format!(
+                "{} already has {} as ancestor",
+                tag.print()?,
+                parent.print()?
+            )));
+        }
+        if self.ancestors(&parent).contains(&tag) {
+            return Err(Error::illegal_argument(format!(
+                "Cyclic derivation: {} has {} as ancestor",
+                parent.print()?,
+                tag.print()?
+            )));
+        }
+        self.parents.entry(tag).or_default().insert(parent);
+        Ok(())
+    }
+
+    fn underive(&mut self, tag: &T, parent: &T) {
+        if let Some(parents) = self.parents.get_mut(tag) {
+            parents.remove(parent);
+            if parents.is_empty() {
+                self.parents.remove(tag);
+            }
+        }
+    }
+}
+
+/// A multimethod: its dispatch function and the methods for each dispatch value.
+#[derive(Clone)]
+pub struct MultiFn {
+    name: String,
+    dispatch: T,
+    default: T,
+    /// The var holding the hierarchy dispatch values are matched in, or the global one.
+    hierarchy: Option<String>,
+    methods: Hamt<T, T>,
+    /// The dispatch values each one is preferred to when both match.
+    prefers: Hamt<T, HashSet<T>>,
+}
+
+impl MultiFn {
+    fn prefers(&self, hierarchy: &Hierarchy, x: &T, y: &T) -> bool {
+        self.prefers
+            .get(x)
+            .is_some_and(|preferred| preferred.contains(y))
+            || hierarchy
+                .parents(y)
+                .iter()
+                .any(|parent| self.prefers(hierarchy, x, parent))
+            || hierarchy
+                .parents(x)
+                .iter()
+                .any(|parent| self.prefers(hierarchy, parent, y))
+    }
+
+    fn dominates(&self, hierarchy: &Hierarchy, x: &T, y: &T) -> bool {
+        self.prefers(hierarchy, x, y) || hierarchy.isa(x, y)
+    }
+
+    /// The method for `value`: the most specific one it `isa?`, or the default.
+    fn find_method(&self, hierarchy: &Hierarchy, value: &T) -> Result<Option<T>, Error> {
+        if let Some(method) = self.methods.get(value) {
+            return Ok(Some(method.clone()));
+        }
+        let mut best: Option<(&T, &T)> = None;
+        for (dispatch, method) in &self.methods {
+            if !hierarchy.isa(value, dispatch) {
+                continue;
+            }
+            best = match best {
+                Some((current, _)) if self.dominates(hierarchy, current, dispatch) => best,
+                Some((current, _)) if !self.dominates(hierarchy, dispatch, current) => {
+                    return Err(Error::illegal_argument(format!(
+                        "Multiple methods in multimethod '{}' match dispatch value: {} -> {} and {}, and neither is preferred",
+                        self.name,
+                        value.print()?,
+                        dispatch.print()?,
+                        current.print()?
+                    )))
+                }
+                _ => Some((dispatch, method)),
+            };
+        }
+        Ok(best
+            .map(|(_, method)| method.clone())
+            .or_else(|| self.methods.get(&self.default).cloned()))
+    }
+}
+
+fn keyword(name: &str) -> T {
+    T::Keyword(format!(":{}", name))
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+fn multimethods() -> Result<RwLockWriteGuard<'static, Hamt<String, MultiFn>>, Error> {
+    current_env()
+        .multimethods
+        .write()
+        .map_err(|_| Error::reason("Multimethods are poisoned"))
+}
+
+fn global_hierarchy() -> Result<RwLockWriteGuard<'static, Hierarchy>, Error> {
+    current_env()
+        .hierarchy
+        .write()
+        .map_err(|_| Error::reason("The global hierarchy is poisoned"))
+}
+
+fn hierarchy_of(multi: &MultiFn) -> Result<Hierarchy, Error> {
+    match &multi.hierarchy {
+        Some(var) => Hierarchy::from_value(&var_value(var).unwrap_or(T::Nil)),
+        None => Ok(global_hierarchy()?.clone()),
+    }
+}
+
+/// Calls the method of the multimethod in the var `key` for the dispatch value of `args`.
+pub(crate) fn dispatch(key: &str, args: &[T]) -> Result<T, Error> {
+    let multi = multimethods()?
+        .get(key)
+        .cloned()
+        .ok_or_else(|| Error::illegal_state(format!("Multimethod {} is not defined", key)))?;
+    let value = apply(&multi.dispatch, args)?;
+    match multi.find_method(&hierarchy_of(&multi)?, &value)? {
+        Some(method) => apply(&method, args),
+        None => Err(Error::illegal_argument(format!(
+            "No method in multimethod '{}' for dispatch value: {}",
+            multi.name,
+            value.print()?
+        ))),
+    }
+}
+
+/// The var key of the multimethod `form` evaluates to.
+fn multi_key(name: &str, form: &T) -> Result<String, Error> {
+    match form.clone().eval()? {
+        T::Function(Function::Multi(key)) => Ok(key),
+        value => Err(Error::illegal_argument(format!(
+            "`{}` expects a multimethod but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+/// Changes the multimethod in the var `key` with `f`.
+fn update(key: &str, f: impl FnOnce(&mut MultiFn) -> Result<(), Error>) -> Result<T, Error> {
+    let mut multimethods = multimethods()?;
+    let multi = multimethods
+        .get_mut(key)
+        .ok_or_else(|| Error::illegal_state(format!("Multimethod {} is not defined", key)))?;
+    f(multi)?;
+    Ok(T::Function(Function::Multi(key.to_owned())))
+}
+
+/// `(defmulti name doc? attr-map? dispatch-fn & options)` with the options
+/// `:default value` and `:hierarchy #'var`. A multimethod that is already
+/// defined keeps its methods.
+pub fn defmulti(list: &[T]) -> Result<T, Error> {
+    let (name, meta, rest) = match list {
+        [T::Symbol(name, meta), rest @ ..] => (name, meta, rest),
+        _ => {
+            return Err(Error::cant_eval(Some(String::from(
+                "`defmulti` expects a name and a dispatch function",
+            ))))
+        }
+    };
+    let (doc, rest) = match rest {
+        [T::String(doc), rest @ ..] if !rest.is_empty() => (Some(doc), rest),
+        _ => (None, rest),
+    };
+    let (attrs, rest) = match rest {
+        [attrs @ T::HashMap(..), rest @ ..] if !rest.is_empty() => (Some(attrs), rest),
+        _ => (None, rest),
+    };
+    let (dispatch, options) = rest
+        .split_first()
+        .ok_or_else(|| arity("defmulti", 2, "at least 2", list))?;
+    if options.len() % 2 != 0 {
+        return Err(Error::cant_eval(Some(String::from(
+            "`defmulti` expects options as keyword value pairs",
+        ))));
+    }
+
+    let key = format!("{}/{}", current_ns(), name);
+    if var_value(&key) == Some(T::Function(Function::Multi(key.clone()))) {
+        return Ok(T::Nil);
+    }
+    let mut multi = MultiFn {
+        name: name.clone(),
+        dispatch: dispatch.clone().eval()?,
+        default: keyword("default"),
+        hierarchy: None,
+        methods: Hamt::new(),
+        prefers: Hamt::new(),
+    };
+    for option in options.chunks(2) {
+        let value = option[1].clone().eval()?;
+        match &option[0] {
+            T::Keyword(option) if option == ":default" => multi.default = value,
+            T::Keyword(option) if option == ":hierarchy" => {
+                multi.hierarchy = Some(as_var(&value).map(str::to_owned).ok_or_else(|| {
+                    Error::illegal_argument(String::from(
+                        "`defmulti` expects the :hierarchy as a var, e.g. #'h",
+                    ))
+                })?)
+            }
+            option => {
+                return Err(Error::cant_eval(format!(
+                    "`defmulti` has no option {}",
+                    option.print()?
+                )))
+            }
+        }
+    }
+    multimethods()?.insert(key.clone(), multi);
+
+    let mut var_meta = match meta.get() {
+        T::HashMap(meta, _) => meta,
+        _ => HashMap::new(),
+    };
+    if let Some(T::HashMap(attrs, _)) = attrs {
+        var_meta.extend(attrs.clone());
+    }
+    let name = T::Symbol(name.clone(), Meta::new(T::hash_map(var_meta)));
+    let value = T::Function(Function::Multi(key));
+    match doc {
+        Some(doc) => def(&[name, T::String(doc.clone()), value]),
+        None => def(&[name, value]),
+    }
+}
+
+/// `(defmethod multifn dispatch-value [params*] body*)`, also with several arities.
+pub fn defmethod(list: &[T]) -> Result<T, Error> {
+    let (multi, dispatch, tail) = match list {
+        [multi, dispatch, tail @ ..] if !tail.is_empty() => (multi, dispatch, tail),
+        _ => return Err(arity("defmethod", 3, "at least 3", list)),
+    };
+    let key = multi_key("defmethod", multi)?;
+    let dispatch = dispatch.clone().eval()?;
+    let method = fn_form(tail)?;
+    update(&key, |multi| {
+        multi.methods.insert(dispatch, method);
+        Ok(())
+    })
+}
+
+/// `(remove-method multifn dispatch-value)`.
+pub fn remove_method(list: &[T]) -> Result<T, Error> {
+    match list {
+        [multi, dispatch] => {
+            let key = multi_key("remove-method", multi)?;
+            let dispatch = dispatch.clone().eval()?;
+            update(&key, |multi| {
+                multi.methods.remove(&dispatch);
+                Ok(())
+            })
+        }
+        _ => Err(arity("remove-method", 2, "2", list)),
+    }
+}
+
+/// `(prefer-method multifn x y)` picks the method for `x` over the one for `y`
+/// when a dispatch value matches both.
+pub fn prefer_method(list: &[T]) -> Result<T, Error> {
+    match list {
+        [multi, x, y] => {
+            let key = multi_key("prefer-method", multi)?;
+            let (x, y) = (x.clone().eval()?, y.clone().eval()?);
+            let hierarchy = match multimethods()?.get(&key) {
+                Some(multi) => hierarchy_of(multi)?,
+                None => Hierarchy::default(),
+            };
+            update(&key, |multi| {
+                if multi.prefers(&hierarchy, &y, &x) {
+                    return Err(Error::illegal_state(format!(
+                        "Preference conflict in multimethod '{}': {} is already preferred to {}",
+                        multi.name,
+                        y.print()?,
+                        x.print()?
+                    )));
+                }
+                multi.prefers.entry(x).or_default().insert(y);
+                Ok(())
+            })
+        }
+        _ => Err(arity("prefer-method", 3, "3", list)),
+    }
+}
+
+/// `(methods multifn)`, a map of the dispatch values to their methods.
+pub fn methods(list: &[T]) -> Result<T, Error> {
+    match list {
+        [multi] => {
+            let key = multi_key("methods", multi)?;
+            let methods = multimethods()?
+                .get(&key)
+                .map(|multi| multi.methods.clone())
+                .unwrap_or_default();
+            Ok(T::hash_map(methods.into_iter().collect()))
+        }
+        _ => Err(arity("methods", 1, "1", list)),
+    }
+}
+
+/// `(make-hierarchy)`, an empty hierarchy to `derive` in.
+pub fn make_hierarchy(list: &[T]) -> Result<T, Error> {
+    match list {
+        [] => Ok(Hierarchy::default().to_value()),
+        _ => Err(arity("make-hierarchy", 0, "0", list)),
+    }
+}
+
+/// Tags in the global hierarchy are namespace-qualified keywords, or symbols.
+fn expect_global_tag(name: &str, tag: &T) -> Result<(), Error> {
+    match tag {
+        T::Keyword(keyword) if split_qualified(keyword.trim_start_matches(':')).is_some() => Ok(()),
+        T::Symbol(_, _) => Ok(()),
+        tag => Err(Error::illegal_argument(format!(
+            "`{}` expects namespace-qualified keywords in the global hierarchy but received {}",
+            name,
+            tag.print()?
+        ))),
+    }
+}
+
+/// Runs `f` on the hierarchy given before the other arguments, returning the
+/// changed hierarchy, or on the global one, returning nil.
+fn change_hierarchy(
+    name: &str,
+    list: &[T],
+    f: impl FnOnce(&mut Hierarchy, T, T) -> Result<(), Error>,
+) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [tag, parent] => {
+            expect_global_tag(name, tag)?;
+            expect_global_tag(name, parent)?;
+            f(&mut *global_hierarchy()?, tag.clone(), parent.clone())?;
+            Ok(T::Nil)
+        }
+        [hierarchy, tag, parent] => {
+            let mut hierarchy = Hierarchy::from_value(hierarchy)?;
+            f(&mut hierarchy, tag.clone(), parent.clone())?;
+            Ok(hierarchy.to_value())
+        }
+        _ => Err(arity(name, 2, "2 or 3", list)),
+    }
+}
+
+/// `(derive tag parent)` in the global hierarchy, or `(derive h tag parent)`
+/// returning the hierarchy `h` with `tag` deriving from `parent`.
+pub fn derive(list: &[T]) -> Result<T, Error> {
+    change_hierarchy("derive", list, |hierarchy, tag, parent| {
+        hierarchy.derive(tag, parent)
+    })
+}
+
+/// `(underive tag parent)` or `(underive h tag parent)`, undoing a `derive`.
+pub fn underive(list: &[T]) -> Result<T, Error> {
+    change_hierarchy("underive", list, |hierarchy, tag, parent| {
+        hierarchy.underive(&tag, &parent);
+        Ok(())
+    })
+}
+
+/// The hierarchy given before `count` other arguments, or the global one.
+fn query_args(name: &str, list: &[T], count: usize) -> Result<(Hierarchy, Vec<T>), Error> {
+    let mut args = eval_all(list)?;
+    if args.len() == count {
+        Ok((global_hierarchy()?.clone(), args))
+    } else if args.len() == count + 1 {
+        let hierarchy = Hierarchy::from_value(&args.remove(0))?;
+        Ok((hierarchy, args))
+    } else {
+        Err(arity(
+            name,
+            count as u16,
+            &format!("{} or {}", count, count + 1),
+            list,
+        ))
+    }
+}
+
+/// `(isa? child parent)` or `(isa? h child parent)`.
+pub fn isa(list: &[T]) -> Result<T, Error> {
+    let (hierarchy, args) = query_args("isa?", list, 2)?;
+    Ok(T::Bool(hierarchy.isa(&args[0], &args[1])))
+}
+
+fn set_or_nil(set: HashSet<T>) -> T {
+    if set.is_empty() {
+        T::Nil
+    } else {
+        T::hash_set(set)
+    }
+}
+
+/// `(parents tag)` or `(parents h tag)`, the set of tags `tag` derives from directly.
+pub fn parents(list: &[T]) -> Result<T, Error> {
+    let (hierarchy, args) = query_args("parents", list, 1)?;
+    Ok(set_or_nil(hierarchy.parents(&args[0])))
+}
+
+/// `(ancestors tag)` or `(ancestors h tag)`.
+pub fn ancestors(list: &[T]) -> Result<T, Error> {
+    let (hierarchy, args) = query_args("ancestors", list, 1)?;
+    Ok(set_or_nil(hierarchy.ancestors(&args[0])))
+}
+
+/// `(descendants tag)` or `(descendants h tag)`.
+pub fn descendants(list: &[T]) -> Result<T, Error> {
+    let (hierarchy, args) = query_args("descendants", list, 1)?;
+    Ok(set_or_nil(hierarchy.descendants(&args[0])))
+}
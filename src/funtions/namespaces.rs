@@ -0,0 +1,534 @@
+use std::collections::BTreeSet;
+
+use im::HashMap as Hamt;
+
+use crate::{
+    definitions::DefinitionTypes as T, env::current_env, error::Error, funtions::Func, CURRENT_NS,
+    SCOPE, STD,
+};
+
+use super::{loading::ensure_loaded, special_forms::quoted, vars::bound_value};
+
+pub(crate) const CORE: &str = "clojure.core";
+
+/// Aliases and referred vars of a namespace. Its own vars live in the environment's
+/// `data` under `ns/name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Namespace {
+    pub aliases: Hamt<String, String>,
+    pub refers: Hamt<String, String>,
+}
+
+pub(crate) fn current_ns() -> String {
+    CURRENT_NS.with(|ns| ns.borrow().clone())
+}
+
+/// Runs `f` with `ns` as the current namespace, restoring the previous one afterwards.
+pub(crate) fn with_ns<R>(ns: &str, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT_NS.with(|current| current.replace(ns.to_owned()));
+    let result = f();
+    CURRENT_NS.with(|current| current.replace(previous));
+    result
+}
+
+/// Makes `name` the current namespace, creating it if needed.
+pub(crate) fn in_ns_named(name: &str) -> Result<(), Error> {
+    namespaces()?.entry(name.to_owned()).or_default();
+    CURRENT_NS.with(|ns| ns.replace(name.to_owned()));
+    Ok(())
+}
+
+fn namespaces() -> Result<std::sync::RwLockWriteGuard<'static, Hamt<String, Namespace>>, Error> {
+    current_env()
+        .namespaces
+        .write()
+        .map_err(|_| Error::reason("Namespaces are poisoned"))
+}
+
+pub(crate) fn ns_exists(name: &str) -> bool {
+    current_env()
+        .namespaces
+        .read()
+        .map(|nss| nss.contains_key(name))
+        .unwrap_or(false)
+}
+
+/// Splits `ns/name`, leaving `/` and unqualified symbols alone.
+pub(crate) fn split_qualified(symbol: &str) -> Option<(&str, &str)> {
+    match symbol.split_once('/') {
+        Some((ns, name)) if !ns.is_empty() && !name.is_empty() => Some((ns, name)),
+        _ => None,
+    }
+}
+
+/// The namespace `alias` names from the current namespace, either as an alias or in full.
+pub(crate) fn resolve_ns(alias: &str) -> Option<String> {
+    let nss = current_env().namespaces.read().ok()?;
+    nss.get(&current_ns())
+        .and_then(|ns| ns.aliases.get(alias).cloned())
+        .or_else(|| nss.get(alias).map(|_| alias.to_owned()))
+}
+
+/// The `ns/name` key `symbol` refers to in the environment's `data`: the current namespace, its refers, then `clojure.core`.
+pub(crate) fn resolve_var(symbol: &str) -> Option<String> {
+    let candidates = match split_qualified(symbol) {
+        Some((ns, name)) => vec![format!("{}/{}", resolve_ns(ns)?, name)],
+        None => {
+            let current = current_ns();
+            let referred = current_env()
+                .namespaces
+                .read()
+                .ok()
+                .and_then(|nss| nss.get(&current)?.refers.get(symbol).cloned());
+            let mut candidates = vec![format!("{}/{}", current, symbol)];
+            candidates.extend(referred);
+            candidates.push(format!("{}/{}", CORE, symbol));
+            candidates
+        }
+    };
+    let data = current_env().data.read().ok()?;
+    candidates.into_iter().find(|key| data.contains_key(key))
+}
+
+pub(crate) fn lookup_var(symbol: &str) -> Option<T> {
+    let key = resolve_var(symbol)?;
+    bound_value(&key).or_else(|| current_env().data.read().ok()?.get(&key).cloned())
+}
+
+/// The builtin `symbol` names, unqualified or through `clojure.core`.
+pub(crate) fn resolve_native(symbol: &str) -> Option<(&'static str, Func)> {
+    let name = match split_qualified(symbol) {
+        Some((ns, name)) if resolve_ns(ns).as_deref() == Some(CORE) => name,
+        Some(_) => return None,
+        None => symbol,
+    };
+    STD.get_key_value(name).map(|(name, f)| (name.as_str(), *f))
+}
+
+pub(crate) fn define(name: &str, value: T) -> Result<T, Error> {
+    let key = format!("{}/{}", current_ns(), name);
+    current_env()
+        .data
+        .write()
+        .map_err(|_| Error::reason("Global definitions are poisoned"))?
+        .insert(key.clone(), value);
+    Ok(T::symbol(format!("#'{}", key)))
+}
+
+const SPECIAL_FORMS: [&str; 13] = [
+    "def", "if", "do", "quote", "var", "recur", "throw", "try", "catch", "finally", "&", ".",
+    "set!",
+];
+
+/// Namespace qualifies a symbol inside a syntax-quote the way the reader would.
+fn qualify(symbol: &str) -> String {
+    if SPECIAL_FORMS.contains(&symbol)
+        || symbol.ends_with('#')
+        || symbol.starts_with('.')
+        || split_qualified(symbol).is_some()
+    {
+        return symbol.to_owned();
+    }
+    match (resolve_var(symbol), resolve_native(symbol)) {
+        (Some(key), _) => key,
+        (None, Some(_)) => format!("{}/{}", CORE, symbol),
+        (None, None) => format!("{}/{}", current_ns(), symbol),
+    }
+}
+
+/// Qualifies the symbols of a syntax-quoted template, expanding the unquoted forms with `expand`.
+pub(crate) fn qualify_template(
+    form: T,
+    expand: &dyn Fn(T) -> Result<T, Error>,
+) -> Result<T, Error> {
+    match form {
+        T::List(list, meta)
+            if list.len() == 2
+                && matches!(&list[0], T::Symbol(s, _) if s == "unquote" || s == "unquote-splicing") =>
+        {
+            let mut list = list;
+            let inner = expand(list.remove(1))?;
+            list.push(inner);
+            Ok(T::List(list, meta))
+        }
+        T::List(list, meta) => Ok(T::List(
+            list.into_iter()
+                .map(|form| qualify_template(form, expand))
+                .collect::<Result<_, Error>>()?,
+            meta,
+        )),
+        T::Vector(vec, meta) => Ok(T::Vector(
+            vec.into_iter()
+                .map(|form| qualify_template(form, expand))
+                .collect::<Result<_, Error>>()?,
+            meta,
+        )),
+        T::HashSet(set, meta) => Ok(T::HashSet(
+            set.into_iter()
+                .map(|form| qualify_template(form, expand))
+                .collect::<Result<_, Error>>()?,
+            meta,
+        )),
+        T::HashMap(map, meta) => Ok(T::HashMap(
+            map.into_iter()
+                .map(|(k, v)| Ok((qualify_template(k, expand)?, qualify_template(v, expand)?)))
+                .collect::<Result<_, Error>>()?,
+            meta,
+        )),
+        T::Symbol(symbol, meta) => Ok(T::Symbol(qualify(&symbol), meta)),
+        form => Ok(form),
+    }
+}
+
+/// Resolves `::name` and `::alias/name` keywords against the current namespace.
+pub(crate) fn auto_resolve_keyword(name: &str) -> Result<String, Error> {
+    match split_qualified(name) {
+        Some((alias, name)) => match resolve_ns(alias) {
+            Some(ns) => Ok(format!(":{}/{}", ns, name)),
+            None => Err(Error::reason(format!(
+                "Invalid token: ::{}/{}",
+                alias, name
+            ))),
+        },
+        None => Ok(format!(":{}/{}", current_ns(), name)),
+    }
+}
+
+fn symbol_arg(name: &str, arg: &T) -> Result<String, Error> {
+    match arg.clone().eval()? {
+        T::Symbol(symbol, _) => Ok(symbol),
+        arg => Err(Error::cant_eval(format!(
+            "`{}` expects a symbol but received {}",
+            name,
+            arg.print()?
+        ))),
+    }
+}
+
+fn symbols(name: &str, coll: &T) -> Result<Vec<String>, Error> {
+    match coll {
+        T::Vector(items, _) | T::Seq(items, _) => items
+            .iter()
+            .map(|item| match item {
+                T::Symbol(symbol, _) => Ok(symbol.to_owned()),
+                item => Err(Error::cant_eval(format!(
+                    "`{}` expects symbols but received {}",
+                    name,
+                    item.print()?
+                ))),
+            })
+            .collect(),
+        coll => Err(Error::cant_eval(format!(
+            "`{}` expects a vector of symbols but received {}",
+            name,
+            coll.print()?
+        ))),
+    }
+}
+
+/// The public vars of `ns` by their unqualified name.
+pub(crate) fn publics(ns: &str) -> Result<Vec<String>, Error> {
+    let prefix = format!("{}/", ns);
+    let mut names = current_env()
+        .data
+        .read()
+        .map_err(|_| Error::reason("Global definitions are poisoned"))?
+        .keys()
+        .filter_map(|key| key.strip_prefix(&prefix).map(str::to_owned))
+        .collect::<Vec<String>>();
+    if ns == CORE {
+        names.extend(STD.keys().cloned());
+    }
+    Ok(names)
+}
+
+/// What `prefix` can complete to: keywords read so far, the symbols visible from the
+/// current namespace with namespace and alias names, or vars qualified by either.
+pub fn completions(prefix: &str) -> Vec<String> {
+    let current = current_ns();
+    let (nss, aliases, refers) = match current_env().namespaces.read() {
+        Ok(nss) => {
+            let ns = nss.get(&current).cloned().unwrap_or_default();
+            (
+                nss.keys().cloned().collect::<Vec<String>>(),
+                ns.aliases,
+                ns.refers,
+            )
+        }
+        Err(_) => return Vec::new(),
+    };
+
+    let mut candidates = BTreeSet::new();
+    if prefix.starts_with(':') {
+        if let Ok(keywords) = current_env().keywords.read() {
+            candidates.extend(keywords.iter().cloned());
+        }
+    } else if prefix.contains('/') {
+        let qualified = nss.iter().map(|ns| (ns, ns)).chain(aliases.iter());
+        for (qualifier, ns) in qualified {
+            for name in publics(ns).unwrap_or_default() {
+                candidates.insert(format!("{}/{}", qualifier, name));
+            }
+        }
+    } else {
+        candidates.extend(publics(&current).unwrap_or_default());
+        candidates.extend(publics(CORE).unwrap_or_default());
+        candidates.extend(refers.keys().cloned());
+        candidates.extend(aliases.keys().cloned());
+        candidates.extend(nss);
+    }
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect()
+}
+
+fn ensure_ns(ns: &str) -> Result<(), Error> {
+    if ns_exists(ns) {
+        Ok(())
+    } else {
+        Err(Error::reason(format!("No namespace: {} found", ns)))
+    }
+}
+
+fn refer_names(ns: &str, names: Vec<String>) -> Result<(), Error> {
+    let current = current_ns();
+    let mut nss = namespaces()?;
+    let refers = &mut nss.entry(current).or_default().refers;
+    for name in names {
+        refers.insert(name.clone(), format!("{}/{}", ns, name));
+    }
+    Ok(())
+}
+
+fn add_alias(alias: &str, ns: &str) -> Result<(), Error> {
+    let current = current_ns();
+    namespaces()?
+        .entry(current)
+        .or_default()
+        .aliases
+        .insert(alias.to_owned(), ns.to_owned());
+    Ok(())
+}
+
+/// Loads one `require` spec given as data: `ns` or `[ns :as alias :refer [names]]`.
+pub(crate) fn require_spec(spec: &T, reload: bool) -> Result<(), Error> {
+    let (ns, options) = match spec {
+        T::Symbol(ns, _) => (ns.to_owned(), &[][..]),
+        T::Vector(spec, _) => match spec.split_first() {
+            Some((T::Symbol(ns, _), options)) => (ns.to_owned(), options),
+            _ => {
+                return Err(Error::cant_eval(format!(
+                    "Invalid require spec {}",
+                    quoted(T::vector(spec.clone())).print()?
+                )))
+            }
+        },
+        spec => {
+            return Err(Error::cant_eval(format!(
+                "Invalid require spec {}",
+                spec.print()?
+            )))
+        }
+    };
+    ensure_loaded(&ns, reload)?;
+    ensure_ns(&ns)?;
+
+    for option in options.chunks(2) {
+        match option {
+            [T::Keyword(key), T::Symbol(alias, _)] if key == ":as" => add_alias(alias, &ns)?,
+            [T::Keyword(key), T::Keyword(all)] if key == ":refer" && all == ":all" => {
+                refer_names(&ns, publics(&ns)?)?
+            }
+            [T::Keyword(key), names] if key == ":refer" => {
+                refer_names(&ns, symbols("require", names)?)?
+            }
+            option => {
+                return Err(Error::cant_eval(format!(
+                    "Unsupported require option {}",
+                    quoted(T::vector(option.to_vec())).print()?
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `(ns name docstring? (:require specs*)*)`
+pub fn ns(list: &[T]) -> Result<T, Error> {
+    let (name, references) = match list.split_first() {
+        Some((T::Symbol(name, _), references)) => (name, references),
+        _ => return Err(Error::cant_eval(String::from("`ns` expects a name"))),
+    };
+    in_ns_named(name)?;
+
+    for reference in references {
+        match reference {
+            T::String(_) => (),
+            T::List(clause, _) => match clause.split_first() {
+                Some((T::Keyword(kind), specs)) if kind == ":require" => {
+                    require_specs(specs.iter().map(|spec| quoted(spec.clone())).collect())?
+                }
+                Some((T::Keyword(kind), _)) if kind == ":refer-clojure" => (),
+                _ => {
+                    return Err(Error::cant_eval(format!(
+                        "Unsupported ns clause {}",
+                        quoted(reference.clone()).print()?
+                    )))
+                }
+            },
+            reference => {
+                return Err(Error::cant_eval(format!(
+                    "Unsupported ns clause {}",
+                    reference.print()?
+                )))
+            }
+        }
+    }
+    Ok(T::Nil)
+}
+
+pub fn in_ns(list: &[T]) -> Result<T, Error> {
+    match list {
+        [name] => {
+            let name = symbol_arg("in-ns", name)?;
+            in_ns_named(&name)?;
+            Ok(T::symbol(name))
+        }
+        _ => Err(Error::arity(
+            1,
+            format!("`in-ns` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
+
+/// Specs followed or preceded by `:reload` or `:reload-all` are loaded again.
+fn require_specs(specs: Vec<T>) -> Result<(), Error> {
+    let reload = specs
+        .iter()
+        .any(|spec| matches!(spec, T::Keyword(flag) if flag == ":reload" || flag == ":reload-all"));
+    for spec in specs.iter().filter(|spec| !matches!(spec, T::Keyword(_))) {
+        require_spec(spec, reload)?;
+    }
+    Ok(())
+}
+
+pub fn require(list: &[T]) -> Result<T, Error> {
+    require_specs(
+        list.iter()
+            .map(|spec| spec.clone().eval())
+            .collect::<Result<_, _>>()?,
+    )?;
+    Ok(T::Nil)
+}
+
+/// `(refer 'ns)`, `(refer 'ns :only '[names])` or `(refer 'ns :exclude '[names])`
+pub fn refer(list: &[T]) -> Result<T, Error> {
+    let (ns, filters) = match list.split_first() {
+        Some((ns, filters)) => (symbol_arg("refer", ns)?, filters),
+        None => {
+            return Err(Error::arity(
+                1,
+                String::from("`refer` has arity of at least 1 but received 0"),
+            ))
+        }
+    };
+    ensure_ns(&ns)?;
+
+    let mut names = publics(&ns)?;
+    for filter in filters.chunks(2) {
+        match filter {
+            [T::Keyword(key), only] if key == ":only" => {
+                names = symbols("refer", &only.clone().eval()?)?
+            }
+            [T::Keyword(key), exclude] if key == ":exclude" => {
+                let exclude = symbols("refer", &exclude.clone().eval()?)?;
+                names.retain(|name| !exclude.contains(name));
+            }
+            _ => {
+                return Err(Error::cant_eval(String::from(
+                    "`refer` filters are :only or :exclude followed by a vector",
+                )))
+            }
+        }
+    }
+    refer_names(&ns, names)?;
+    Ok(T::Nil)
+}
+
+pub fn alias(list: &[T]) -> Result<T, Error> {
+    match list {
+        [alias, ns] => {
+            let (alias, ns) = (symbol_arg("alias", alias)?, symbol_arg("alias", ns)?);
+            ensure_ns(&ns)?;
+            add_alias(&alias, &ns)?;
+            Ok(T::Nil)
+        }
+        _ => Err(Error::arity(
+            2,
+            format!("`alias` has arity of 2 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn ns_publics(list: &[T]) -> Result<T, Error> {
+    match list {
+        [ns] => {
+            let ns = symbol_arg("ns-publics", ns)?;
+            ensure_ns(&ns)?;
+            Ok(T::hash_map(
+                publics(&ns)?
+                    .into_iter()
+                    .map(|name| {
+                        let var = T::symbol(format!("#'{}/{}", ns, name));
+                        (T::symbol(name), var)
+                    })
+                    .collect(),
+            ))
+        }
+        _ => Err(Error::arity(
+            1,
+            format!("`ns-publics` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
+
+fn var_of(symbol: &str) -> T {
+    let local = SCOPE.with(|scope| scope.borrow().contains_key(symbol));
+    match (local, resolve_var(symbol), resolve_native(symbol)) {
+        (true, _, _) => T::Nil,
+        (_, Some(key), _) => T::symbol(format!("#'{}", key)),
+        (_, None, Some((name, _))) => T::symbol(format!("#'{}/{}", CORE, name)),
+        _ => T::Nil,
+    }
+}
+
+pub fn ns_resolve(list: &[T]) -> Result<T, Error> {
+    match list {
+        [ns, symbol] => {
+            let (ns, symbol) = (
+                symbol_arg("ns-resolve", ns)?,
+                symbol_arg("ns-resolve", symbol)?,
+            );
+            ensure_ns(&ns)?;
+            let previous = CURRENT_NS.with(|current| current.replace(ns));
+            let var = var_of(&symbol);
+            CURRENT_NS.with(|current| current.replace(previous));
+            Ok(var)
+        }
+        _ => Err(Error::arity(
+            2,
+            format!("`ns-resolve` has arity of 2 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn resolve(list: &[T]) -> Result<T, Error> {
+    match list {
+        [symbol] => Ok(var_of(&symbol_arg("resolve", symbol)?)),
+        _ => Err(Error::arity(
+            1,
+            format!("`resolve` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
@@ -0,0 +1,759 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLockWriteGuard},
+};
+
+use im::HashMap as Hamt;
+
+use crate::{
+    definitions::{DefinitionTypes as T, Function},
+    env::current_env,
+    error::Error,
+};
+
+use super::{
+    apply, arity,
+    collections::lookup,
+    namespaces::{current_ns, define, lookup_var, resolve_var},
+    special_forms::{def, fn_form, next_gensym, sym},
+    vars::{as_var, set_stored_meta},
+};
+
+/// A protocol's methods and the types extended to it.
+#[derive(Clone, Default)]
+pub struct Protocol {
+    methods: Vec<String>,
+    /// Implementations by type name, then method name.
+    impls: Hamt<String, Hamt<String, Method>>,
+}
+
+#[derive(Clone)]
+struct Method {
+    f: T,
+    /// Given inline by `defrecord` or `deftype`, so the body sees the fields as locals.
+    inline: bool,
+}
+
+/// The fields of a type created by `defrecord` or `deftype`.
+#[derive(Clone)]
+pub struct TypeDef {
+    fields: Vec<String>,
+    record: bool,
+}
+
+/// An instance of a `defrecord`: a map of its fields and any other keys added to it.
+#[derive(Debug, Clone, Eq)]
+pub struct Record {
+    name: String,
+    /// The declared fields first, then other keys in the order they were added.
+    entries: Vec<(T, T)>,
+}
+
+impl Record {
+    pub fn get(&self, key: &T) -> Option<T> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+
+    pub fn assoc(mut self, key: T, value: T) -> Record {
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key, value)),
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn entries(&self) -> &[(T, T)] {
+        &self.entries
+    }
+
+    pub fn print(&self) -> Result<String, Error> {
+        let mut s = format!("#{}{{", self.name);
+        for (key, val) in &self.entries {
+            s.push_str(&key.print()?);
+            s.push(' ');
+            s.push_str(&val.print()?);
+            s.push(' ');
+        }
+        s.push('}');
+        Ok(s)
+    }
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.entries.len() == other.entries.len()
+            && self
+                .entries
+                .iter()
+                .all(|(k, v)| other.get(k).as_ref() == Some(v))
+    }
+}
+
+/// An instance of a `deftype` or `reify`. Instances are only equal to themselves.
+#[derive(Clone)]
+pub struct Object(Arc<ObjectState>);
+
+struct ObjectState {
+    name: String,
+    fields: Vec<(String, T)>,
+    /// The methods given to `reify`, by protocol then method name.
+    methods: Hamt<String, Hamt<String, T>>,
+}
+
+impl Object {
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Object {}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Object({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+const OBJECT: &str = "java.lang.Object";
+
+/// The types builtin values are known by, for `extend-type` and friends.
+const BUILTIN_TYPES: [&str; 37] = [
+    OBJECT,
+    "java.lang.Boolean",
+    "java.lang.Number",
+    "java.lang.Long",
+    "java.lang.Double",
+    "clojure.lang.Ratio",
+    "java.lang.String",
+    "java.lang.Character",
+    "clojure.lang.Keyword",
+    "clojure.lang.Symbol",
+    "clojure.lang.IFn",
+    "clojure.lang.Fn",
+    "clojure.lang.IPersistentCollection",
+    "clojure.lang.Sequential",
+    "clojure.lang.IPersistentVector",
+    "clojure.lang.PersistentVector",
+    "clojure.lang.ISeq",
+    "clojure.lang.PersistentList",
+    "clojure.lang.IPersistentMap",
+    "clojure.lang.PersistentHashMap",
+    "clojure.lang.PersistentTreeMap",
+    "clojure.lang.IPersistentSet",
+    "clojure.lang.PersistentHashSet",
+    "clojure.lang.PersistentTreeSet",
+    "clojure.lang.IRecord",
+    "clojure.lang.IDeref",
+    "clojure.lang.IPending",
+    "clojure.lang.Atom",
+    "clojure.lang.Ref",
+    "clojure.lang.Agent",
+    "java.util.concurrent.Future",
+    "java.lang.Throwable",
+    "clojure.core.async.impl.channels.ManyToManyChannel",
+    "clojure.core.async.impl.protocols.Buffer",
+    "clojure.core.async.Mux",
+    "clojure.lang.Recur",
+    "java.util.regex.Pattern",
+];
+
+/// The builtin types `value` belongs to, most specific first, before `java.lang.Object`.
+fn builtin_types(value: &T) -> &'static [&'static str] {
+    match value {
+        T::Nil => &[],
+        T::Bool(_) => &["java.lang.Boolean"],
+        T::Int(_) => &["java.lang.Long", "java.lang.Number"],
+        T::Double(_) => &["java.lang.Double", "java.lang.Number"],
+        T::Rational(_, _) => &["clojure.lang.Ratio", "java.lang.Number"],
+        T::String(_) => &["java.lang.String"],
+        T::Char(_) => &["java.lang.Character"],
+        T::Keyword(_) => &["clojure.lang.Keyword", "clojure.lang.IFn"],
+        T::Symbol(_, _) => &["clojure.lang.Symbol"],
+        T::Function(_) | T::Xform(_) => &["clojure.lang.Fn", "clojure.lang.IFn"],
+        T::Vector(_, _) => &[
+            "clojure.lang.PersistentVector",
+            "clojure.lang.IPersistentVector",
+            "clojure.lang.Sequential",
+            "clojure.lang.IPersistentCollection",
+            "clojure.lang.IFn",
+        ],
+        T::List(_, _) | T::Seq(_, _) => &[
+            "clojure.lang.PersistentList",
+            "clojure.lang.ISeq",
+            "clojure.lang.Sequential",
+            "clojure.lang.IPersistentCollection",
+        ],
+        T::HashMap(_, _) => &[
+            "clojure.lang.PersistentHashMap",
+            "clojure.lang.IPersistentMap",
+            "clojure.lang.IPersistentCollection",
+            "clojure.lang.IFn",
+        ],
+        T::OrderedMap(_) => &[
+            "clojure.lang.PersistentTreeMap",
+            "clojure.lang.IPersistentMap",
+            "clojure.lang.IPersistentCollection",
+            "clojure.lang.IFn",
+        ],
+        T::HashSet(_, _) => &[
+            "clojure.lang.PersistentHashSet",
+            "clojure.lang.IPersistentSet",
+            "clojure.lang.IPersistentCollection",
+            "clojure.lang.IFn",
+        ],
+        T::OrderedSet(_) => &[
+            "clojure.lang.PersistentTreeSet",
+            "clojure.lang.IPersistentSet",
+            "clojure.lang.IPersistentCollection",
+            "clojure.lang.IFn",
+        ],
+        T::Record(_) => &[
+            "clojure.lang.IRecord",
+            "clojure.lang.IPersistentMap",
+            "clojure.lang.IPersistentCollection",
+        ],
+        T::Object(_) => &[],
+        T::Atom(_) => &["clojure.lang.Atom", "clojure.lang.IDeref"],
+        T::Ref(_) => &["clojure.lang.Ref", "clojure.lang.IDeref"],
+        T::Agent(_) => &["clojure.lang.Agent", "clojure.lang.IDeref"],
+        T::Future(_) => &[
+            "java.util.concurrent.Future",
+            "clojure.lang.IPending",
+            "clojure.lang.IDeref",
+        ],
+        T::Promise(_) => &["clojure.lang.IPending", "clojure.lang.IDeref"],
+        T::Throwable(_) => &["java.lang.Throwable"],
+        T::Chan(_) => &["clojure.core.async.impl.channels.ManyToManyChannel"],
+        T::Buffer(_) => &["clojure.core.async.impl.protocols.Buffer"],
+        T::Mux(_) => &["clojure.core.async.Mux"],
+        T::Recur(_) => &["clojure.lang.Recur"],
+        T::Regex(_) => &["java.util.regex.Pattern"],
+    }
+}
+
+/// Every type `value` belongs to, most specific first. Protocol methods
+/// dispatch on the first of them that has an implementation.
+fn type_names(value: &T) -> Vec<String> {
+    let mut names = match value {
+        T::Nil => return vec![String::from("nil")],
+        T::Record(record) => vec![record.name.clone()],
+        T::Object(object) => vec![object.name().to_owned()],
+        _ => Vec::new(),
+    };
+    names.extend(builtin_types(value).iter().map(|name| (*name).to_owned()));
+    names.push(String::from(OBJECT));
+    names
+}
+
+fn protocols() -> Result<RwLockWriteGuard<'static, Hamt<String, Protocol>>, Error> {
+    current_env()
+        .protocols
+        .write()
+        .map_err(|_| Error::reason("Protocols are poisoned"))
+}
+
+fn types() -> Result<RwLockWriteGuard<'static, Hamt<String, TypeDef>>, Error> {
+    current_env()
+        .types
+        .write()
+        .map_err(|_| Error::reason("Types are poisoned"))
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+fn keyword(name: &str) -> T {
+    T::Keyword(format!(":{}", name))
+}
+
+/// Types defined in a namespace are named after it, e.g. `my_app.core.Point`.
+fn type_name(name: &str) -> String {
+    format!("{}.{}", current_ns().replace('-', "_"), name)
+}
+
+/// The type a symbol in `extend-type`, `extend-protocol` or `new` names:
+/// a record or type, `nil`, or a builtin by its full or short name.
+fn resolve_type(form: &T) -> Result<String, Error> {
+    let symbol = match form {
+        T::Nil => return Ok(String::from("nil")),
+        T::Symbol(symbol, _) => symbol,
+        form => {
+            return Err(Error::cant_eval(format!(
+                "Expected a type name but received {}",
+                form.print()?
+            )))
+        }
+    };
+    let types = types()?;
+    if types.contains_key(symbol) {
+        return Ok(symbol.to_owned());
+    }
+    let defined = lookup_var(symbol).and_then(|value| match value {
+        T::Symbol(name, _) if types.contains_key(&name) => Some(name),
+        _ => None,
+    });
+    defined
+        .or_else(|| {
+            BUILTIN_TYPES
+                .iter()
+                .find(|name| *name == symbol || name.rsplit('.').next() == Some(symbol))
+                .map(|name| (*name).to_owned())
+        })
+        .ok_or_else(|| Error::unknown_symbol(symbol))
+}
+
+fn protocol_key(form: &T) -> Result<String, Error> {
+    let key = match form {
+        T::Symbol(symbol, _) => resolve_var(symbol).ok_or_else(|| Error::unknown_symbol(symbol))?,
+        form => {
+            return Err(Error::cant_eval(format!(
+                "Expected a protocol but received {}",
+                form.print()?
+            )))
+        }
+    };
+    if protocols()?.contains_key(&key) {
+        Ok(key)
+    } else {
+        Err(Error::illegal_argument(format!(
+            "{} is not a protocol",
+            key
+        )))
+    }
+}
+
+/// The items of a method implementation, `(name [params*] body*)`.
+type MethodSpec = Vec<T>;
+
+/// Splits `head spec* head spec* ...`, where the specs are the lists after each head.
+fn groups(name: &str, forms: &[T]) -> Result<Vec<(T, Vec<MethodSpec>)>, Error> {
+    let mut groups: Vec<(T, Vec<MethodSpec>)> = Vec::new();
+    for form in forms {
+        match (form, groups.last_mut()) {
+            (T::List(spec, _), Some((_, specs))) => specs.push(spec.clone()),
+            (T::List(_, _), None) => {
+                return Err(Error::cant_eval(format!(
+                    "`{}` expects a name before {}",
+                    name,
+                    form.print()?
+                )))
+            }
+            (head, _) => groups.push((head.clone(), Vec::new())),
+        }
+    }
+    Ok(groups)
+}
+
+/// Builds the functions of method specs like `(name [this] body*)` for `protocol`.
+fn methods(protocol: &str, specs: &[MethodSpec]) -> Result<Hamt<String, T>, Error> {
+    let known = protocols()?
+        .get(protocol)
+        .map(|protocol| protocol.methods.clone())
+        .unwrap_or_default();
+    let mut methods = Hamt::new();
+    for spec in specs {
+        match &spec[..] {
+            [T::Symbol(name, _), arities @ ..] if known.contains(name) => {
+                methods.insert(name.clone(), fn_form(arities)?);
+            }
+            [T::Symbol(name, _), ..] => {
+                return Err(Error::illegal_argument(format!(
+                    "{} is not a method of protocol {}",
+                    name, protocol
+                )))
+            }
+            _ => {
+                return Err(Error::cant_eval(Some(String::from(
+                    "Method implementations are (name [params*] body*)",
+                ))))
+            }
+        }
+    }
+    Ok(methods)
+}
+
+fn implement(
+    protocol: &str,
+    type_name: &str,
+    methods: Hamt<String, T>,
+    inline: bool,
+) -> Result<(), Error> {
+    let mut protocols = protocols()?;
+    if let Some(protocol) = protocols.get_mut(protocol) {
+        let impls = protocol.impls.entry(type_name.to_owned()).or_default();
+        for (name, f) in methods {
+            impls.insert(name, Method { f, inline });
+        }
+    }
+    Ok(())
+}
+
+/// Extends `type_name` to the protocols of `specs`, given as `Protocol (method ...)*`.
+fn implement_inline(type_name: &str, specs: &[T]) -> Result<(), Error> {
+    for (protocol, specs) in groups("deftype", specs)? {
+        let protocol = protocol_key(&protocol)?;
+        implement(&protocol, type_name, methods(&protocol, &specs)?, true)?;
+    }
+    Ok(())
+}
+
+/// The fields of a record or type, bound as locals by its inline methods.
+fn field_locals(target: &T) -> Vec<(String, T)> {
+    match target {
+        T::Record(record) => types()
+            .ok()
+            .and_then(|types| types.get(&record.name).cloned())
+            .map(|def| {
+                def.fields
+                    .into_iter()
+                    .map(|field| {
+                        let value = record.get(&keyword(&field)).unwrap_or(T::Nil);
+                        (field, value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        T::Object(object) => object.0.fields.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn find_method(protocol: &str, method: &str, target: &T) -> Result<Option<T>, Error> {
+    if let T::Object(object) = target {
+        if let Some(f) = object.0.methods.get(protocol).and_then(|m| m.get(method)) {
+            return Ok(Some(f.clone()));
+        }
+    }
+    let impls = match protocols()?.get(protocol) {
+        Some(protocol) => protocol.impls.clone(),
+        None => return Ok(None),
+    };
+    let found = type_names(target)
+        .iter()
+        .find_map(|name| impls.get(name)?.get(method).cloned());
+    Ok(found.map(|Method { f, inline }| match f {
+        T::Function(Function::Lambda(mut lambda)) if inline => {
+            for (field, value) in field_locals(target) {
+                lambda.scope.insert(field, value);
+            }
+            T::Function(Function::Lambda(lambda))
+        }
+        f => f,
+    }))
+}
+
+/// Calls the implementation of `protocol`'s `method` for the type of the first argument.
+pub(crate) fn dispatch(protocol: &str, method: &str, args: &[T]) -> Result<T, Error> {
+    let target = args
+        .first()
+        .ok_or_else(|| arity(method, 1, "at least 1", args))?;
+    match find_method(protocol, method, target)? {
+        Some(f) => apply(&f, args),
+        None => Err(Error::illegal_argument(format!(
+            "No implementation of method: :{} of protocol: #'{} found for class: {}",
+            method,
+            protocol,
+            type_names(target)[0]
+        ))),
+    }
+}
+
+/// `(defprotocol Name doc? (method [this params*]+ doc?)*)` defines the
+/// protocol and a var for each method, dispatching on the type of `this`.
+pub fn defprotocol(list: &[T]) -> Result<T, Error> {
+    let (name, doc, sigs) = match list {
+        [T::Symbol(name, _), T::String(doc), sigs @ ..] => (name, Some(doc), sigs),
+        [T::Symbol(name, _), sigs @ ..] => (name, None, sigs),
+        _ => return Err(arity("defprotocol", 1, "at least 1", list)),
+    };
+    let key = format!("{}/{}", current_ns(), name);
+    let mut methods = Vec::new();
+    let mut described = HashMap::new();
+    for sig in sigs {
+        let (method, rest) = match sig {
+            T::List(sig, _) => match sig.split_first() {
+                Some((T::Symbol(method, _), rest)) => (method, rest),
+                _ => return Err(Error::cant_eval(sig_error(name))),
+            },
+            _ => return Err(Error::cant_eval(sig_error(name))),
+        };
+        let arglists = rest
+            .iter()
+            .filter(|form| matches!(form, T::Vector(_, _)))
+            .cloned()
+            .collect::<Vec<T>>();
+        if arglists.is_empty() {
+            return Err(Error::cant_eval(sig_error(name)));
+        }
+        let mut meta = HashMap::from([
+            (keyword("arglists"), T::seq(arglists.clone())),
+            (keyword("protocol"), T::symbol(format!("#'{}", key))),
+        ]);
+        if let Some(T::String(doc)) = rest.last() {
+            meta.insert(keyword("doc"), T::String(doc.clone()));
+        }
+        define(
+            method,
+            T::Function(Function::Method(key.clone(), method.clone())),
+        )?;
+        set_stored_meta(&format!("{}/{}", current_ns(), method), meta)?;
+        described.insert(
+            keyword(method),
+            T::hash_map(HashMap::from([
+                (keyword("name"), T::symbol(method.clone())),
+                (keyword("arglists"), T::seq(arglists)),
+            ])),
+        );
+        methods.push(method.clone());
+    }
+
+    protocols()?.insert(
+        key.clone(),
+        Protocol {
+            methods,
+            impls: Hamt::new(),
+        },
+    );
+    let protocol = T::hash_map(HashMap::from([
+        (keyword("var"), T::symbol(format!("#'{}", key))),
+        (keyword("sigs"), T::hash_map(described)),
+    ]));
+    define(name, protocol)?;
+    let mut meta = HashMap::new();
+    if let Some(doc) = doc {
+        meta.insert(keyword("doc"), T::String(doc.clone()));
+    }
+    set_stored_meta(&key, meta)?;
+    Ok(T::symbol(name.clone()))
+}
+
+fn sig_error(protocol: &str) -> Option<String> {
+    Some(format!(
+        "`defprotocol` {} expects method signatures like (name [this params*] doc?)",
+        protocol
+    ))
+}
+
+/// `(extend-type Type Protocol (method [this params*] body*)* ...)`.
+pub fn extend_type(list: &[T]) -> Result<T, Error> {
+    let (type_form, specs) = list
+        .split_first()
+        .ok_or_else(|| arity("extend-type", 1, "at least 1", list))?;
+    let type_name = resolve_type(type_form)?;
+    for (protocol, specs) in groups("extend-type", specs)? {
+        let protocol = protocol_key(&protocol)?;
+        implement(&protocol, &type_name, methods(&protocol, &specs)?, false)?;
+    }
+    Ok(T::Nil)
+}
+
+/// `(extend-protocol Protocol Type (method [this params*] body*)* ...)`.
+pub fn extend_protocol(list: &[T]) -> Result<T, Error> {
+    let (protocol, specs) = list
+        .split_first()
+        .ok_or_else(|| arity("extend-protocol", 1, "at least 1", list))?;
+    let protocol = protocol_key(protocol)?;
+    for (type_form, specs) in groups("extend-protocol", specs)? {
+        let type_name = resolve_type(&type_form)?;
+        implement(&protocol, &type_name, methods(&protocol, &specs)?, false)?;
+    }
+    Ok(T::Nil)
+}
+
+/// `(satisfies? Protocol x)`, whether the type of `x` implements the protocol.
+pub fn satisfies(list: &[T]) -> Result<T, Error> {
+    let (protocol, value) = match &eval_all(list)?[..] {
+        [protocol, value] => (protocol.clone(), value.clone()),
+        _ => return Err(arity("satisfies?", 2, "2", list)),
+    };
+    let key = match lookup(&protocol, &keyword("var")) {
+        Some(var) => as_var(&var).map(str::to_owned),
+        None => None,
+    }
+    .ok_or_else(|| {
+        Error::illegal_argument(format!(
+            "`satisfies?` expects a protocol but received {}",
+            protocol.print().unwrap_or_default()
+        ))
+    })?;
+    if let T::Object(object) = &value {
+        if object.0.methods.contains_key(&key) {
+            return Ok(T::Bool(true));
+        }
+    }
+    let impls = protocols()?
+        .get(&key)
+        .map(|protocol| protocol.impls.clone())
+        .unwrap_or_default();
+    Ok(T::Bool(
+        type_names(&value)
+            .iter()
+            .any(|name| impls.contains_key(name)),
+    ))
+}
+
+fn define_type(list: &[T], record: bool) -> Result<T, Error> {
+    let form = if record { "defrecord" } else { "deftype" };
+    let (name, fields, specs) = match list {
+        [T::Symbol(name, _), T::Vector(fields, _), specs @ ..] => (name, fields, specs),
+        _ => {
+            return Err(Error::cant_eval(format!(
+                "`{}` expects a name and a vector of fields",
+                form
+            )))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(|field| match field {
+            T::Symbol(field, _) => Ok(field.clone()),
+            field => Err(Error::cant_eval(format!(
+                "`{}` fields must be symbols but received {}",
+                form,
+                field.print()?
+            ))),
+        })
+        .collect::<Result<Vec<String>, Error>>()?;
+
+    let full_name = type_name(name);
+    types()?.insert(
+        full_name.clone(),
+        TypeDef {
+            fields: fields.clone(),
+            record,
+        },
+    );
+    implement_inline(&full_name, specs)?;
+
+    let quote = |form: T| T::list(vec![sym("quote"), form]);
+    def(&[T::symbol(name.clone()), quote(T::symbol(full_name.clone()))])?;
+    let params = fields
+        .iter()
+        .map(|f| T::symbol(f.clone()))
+        .collect::<Vec<T>>();
+    let mut construct = vec![sym("clojure.core/new"), T::symbol(full_name.clone())];
+    construct.extend(params.iter().cloned());
+    let constructor = format!("->{}", name);
+    def(&[
+        T::symbol(constructor.clone()),
+        T::list(vec![
+            sym("fn"),
+            T::symbol(constructor),
+            T::vector(params),
+            T::list(construct),
+        ]),
+    ])?;
+    if record {
+        let mut empty = vec![sym("clojure.core/new"), T::symbol(full_name.clone())];
+        empty.extend(fields.iter().map(|_| T::Nil));
+        let from_map = format!("map->{}", name);
+        def(&[
+            T::symbol(from_map.clone()),
+            T::list(vec![
+                sym("fn"),
+                T::symbol(from_map),
+                T::vector(vec![sym("m")]),
+                T::list(vec![sym("clojure.core/conj"), T::list(empty), sym("m")]),
+            ]),
+        ])?;
+    }
+    Ok(T::symbol(full_name))
+}
+
+/// `(defrecord Name [fields*] Protocol (method ...)* ...)` defines a map-like
+/// type with the constructors `->Name` and `map->Name`. Inline methods see the
+/// fields as locals.
+pub fn defrecord(list: &[T]) -> Result<T, Error> {
+    define_type(list, true)
+}
+
+/// `(deftype Name [fields*] Protocol (method ...)* ...)` defines a type whose
+/// fields are only seen by its inline methods, with the constructor `->Name`.
+pub fn deftype(list: &[T]) -> Result<T, Error> {
+    define_type(list, false)
+}
+
+/// `(new Type args*)` creates a record or type from its field values.
+pub fn new(list: &[T]) -> Result<T, Error> {
+    let (type_form, args) = list
+        .split_first()
+        .ok_or_else(|| arity("new", 1, "at least 1", list))?;
+    let name = resolve_type(type_form)?;
+    let def = types()?
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| Error::illegal_argument(format!("Can't create an instance of {}", name)))?;
+    if def.fields.len() != args.len() {
+        return Err(Error::arity(
+            def.fields.len() as u16,
+            format!("Wrong number of args ({}) passed to: {}", args.len(), name),
+        ));
+    }
+    let values = eval_all(args)?;
+    if def.record {
+        Ok(T::Record(Record {
+            name,
+            entries: def
+                .fields
+                .iter()
+                .map(|field| keyword(field))
+                .zip(values)
+                .collect(),
+        }))
+    } else {
+        Ok(T::Object(Object(Arc::new(ObjectState {
+            name,
+            fields: def.fields.into_iter().zip(values).collect(),
+            methods: Hamt::new(),
+        }))))
+    }
+}
+
+/// `(reify Protocol (method [this params*] body*)* ...)` creates an object
+/// implementing the protocols with methods closing over the local scope.
+pub fn reify(list: &[T]) -> Result<T, Error> {
+    let mut methods_by_protocol = Hamt::new();
+    for (protocol, specs) in groups("reify", list)? {
+        let protocol = protocol_key(&protocol)?;
+        let methods = methods(&protocol, &specs)?;
+        methods_by_protocol.insert(protocol, methods);
+    }
+    Ok(T::Object(Object(Arc::new(ObjectState {
+        name: type_name(&next_gensym("reify__")),
+        fields: Vec::new(),
+        methods: methods_by_protocol,
+    }))))
+}
+
+/// `(type x)`, the name of the type of `x`, or nil.
+pub fn type_of(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Nil] => Ok(T::Nil),
+        [value] => Ok(T::symbol(type_names(value).remove(0))),
+        _ => Err(arity("type", 1, "1", list)),
+    }
+}
+
+/// `(record? x)`.
+pub fn is_record(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [value] => Ok(T::Bool(matches!(value, T::Record(_)))),
+        _ => Err(arity("record?", 1, "1", list)),
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    definitions::DefinitionTypes as T,
+    error::{Error, ErrorKind},
+};
+
+use super::{apply, arity, special_forms::eval_body};
+
+/// Attempts after which `dosync` gives up on a contended transaction.
+const RETRY_LIMIT: usize = 10_000;
+
+/// Committed values a ref remembers for transactions that started before them.
+const MAX_HISTORY: usize = 10;
+
+/// The point of the last commit. Transactions read the values committed at or
+/// before the point they started at.
+static CLOCK: AtomicU64 = AtomicU64::new(0);
+
+static REF_IDS: AtomicU64 = AtomicU64::new(0);
+
+/// Tickets ordering transactions by age.
+static TX_IDS: AtomicU64 = AtomicU64::new(0);
+
+const RUNNING: u8 = 0;
+const COMMITTING: u8 = 1;
+const RETRY: u8 = 2;
+const KILLED: u8 = 3;
+const COMMITTED: u8 = 4;
+
+thread_local! {
+    static TRANSACTION: RefCell<Option<Transaction>> = const { RefCell::new(None) };
+}
+
+/// One attempt of a transaction, as seen by the refs it claimed.
+struct TxInfo {
+    /// When the first attempt started. Older transactions win conflicts.
+    start: u64,
+    status: AtomicU8,
+}
+
+impl TxInfo {
+    fn is_live(&self) -> bool {
+        matches!(self.status.load(Ordering::SeqCst), RUNNING | COMMITTING)
+    }
+}
+
+struct RefCore {
+    /// Committed values with the point they were committed at, newest first.
+    history: VecDeque<(u64, T)>,
+    /// The transaction that claimed the ref for writing.
+    writer: Option<Arc<TxInfo>>,
+}
+
+struct RefState {
+    /// Orders locking, so committing transactions never deadlock.
+    id: u64,
+    core: Mutex<RefCore>,
+}
+
+/// A transactional reference, changed only inside `dosync`.
+#[derive(Clone)]
+pub struct Ref(Arc<RefState>);
+
+impl Ref {
+    pub fn new(value: T) -> Ref {
+        // Point 0 makes a new ref readable by every transaction that can reach it.
+        Ref(Arc::new(RefState {
+            id: REF_IDS.fetch_add(1, Ordering::SeqCst),
+            core: Mutex::new(RefCore {
+                history: VecDeque::from(vec![(0, value)]),
+                writer: None,
+            }),
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RefCore> {
+        // A panic while holding the lock leaves the history itself intact.
+        self.0
+            .core
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The value in the running transaction, or the last committed one.
+    pub fn deref(&self) -> Result<T, Error> {
+        match in_transaction(|tx| tx.read(self)) {
+            Some(value) => value,
+            None => Ok(self.lock().history[0].1.clone()),
+        }
+    }
+
+    fn release(&self, info: &Arc<TxInfo>) {
+        let mut core = self.lock();
+        if core.writer.as_ref().is_some_and(|w| Arc::ptr_eq(w, info)) {
+            core.writer = None;
+        }
+    }
+}
+
+impl PartialEq for Ref {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Ref {}
+
+impl fmt::Debug for Ref {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ref({})", self.0.id)
+    }
+}
+
+fn retry() -> Error {
+    Error::new(ErrorKind::Retry, "Transaction retry")
+}
+
+/// Kills `owner` if `info` is older, so long transactions can't starve.
+fn barge(info: &TxInfo, owner: &TxInfo) -> bool {
+    info.start < owner.start
+        && owner
+            .status
+            .compare_exchange(RUNNING, KILLED, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+}
+
+/// A function and the arguments after the ref's value `commute` was called with.
+type Commute = (T, Vec<T>);
+
+struct Transaction {
+    info: Arc<TxInfo>,
+    read_point: u64,
+    /// Values set or computed in this transaction, by ref id.
+    values: HashMap<u64, T>,
+    /// Refs claimed by `alter`, `ref-set` or `ensure`.
+    claimed: BTreeMap<u64, Ref>,
+    /// Claimed refs with a new value to commit.
+    written: BTreeSet<u64>,
+    commutes: BTreeMap<u64, (Ref, Vec<Commute>)>,
+}
+
+impl Transaction {
+    fn new(start: u64) -> Transaction {
+        Transaction {
+            info: Arc::new(TxInfo {
+                start,
+                status: AtomicU8::new(RUNNING),
+            }),
+            read_point: CLOCK.load(Ordering::SeqCst),
+            values: HashMap::new(),
+            claimed: BTreeMap::new(),
+            written: BTreeSet::new(),
+            commutes: BTreeMap::new(),
+        }
+    }
+
+    fn check_running(&self) -> Result<(), Error> {
+        match self.info.status.load(Ordering::SeqCst) {
+            RUNNING => Ok(()),
+            _ => Err(retry()),
+        }
+    }
+
+    fn read(&self, reference: &Ref) -> Result<T, Error> {
+        self.check_running()?;
+        if let Some(value) = self.values.get(&reference.0.id) {
+            return Ok(value.clone());
+        }
+        let core = reference.lock();
+        // Without a value old enough the transaction starts over at a later point.
+        core.history
+            .iter()
+            .find(|(point, _)| *point <= self.read_point)
+            .map(|(_, value)| value.clone())
+            .ok_or_else(retry)
+    }
+
+    /// Claims `reference` for writing, failing when it changed since the
+    /// transaction started or another transaction holds it.
+    fn claim(&mut self, reference: &Ref) -> Result<(), Error> {
+        self.check_running()?;
+        if self.claimed.contains_key(&reference.0.id) {
+            return Ok(());
+        }
+        let mut core = reference.lock();
+        if core.history[0].0 > self.read_point {
+            return Err(retry());
+        }
+        if let Some(owner) = &core.writer {
+            if owner.is_live() && !Arc::ptr_eq(owner, &self.info) && !barge(&self.info, owner) {
+                return Err(retry());
+            }
+        }
+        core.writer = Some(self.info.clone());
+        self.claimed.insert(reference.0.id, reference.clone());
+        Ok(())
+    }
+
+    fn set(&mut self, reference: &Ref, value: T) -> Result<(), Error> {
+        self.check_running()?;
+        if self.commutes.contains_key(&reference.0.id) {
+            return Err(Error::illegal_state("Can't set after commute"));
+        }
+        self.values.insert(reference.0.id, value);
+        self.written.insert(reference.0.id);
+        Ok(())
+    }
+
+    fn release(&self) {
+        for reference in self.claimed.values() {
+            reference.release(&self.info);
+        }
+    }
+
+    fn abort(self) {
+        self.info.status.store(RETRY, Ordering::SeqCst);
+        self.release();
+    }
+
+    fn commit(mut self) -> Result<(), Error> {
+        if self
+            .info
+            .status
+            .compare_exchange(RUNNING, COMMITTING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            self.abort();
+            return Err(retry());
+        }
+
+        // Commuted refs are claimed only now, so concurrent commutes don't conflict
+        // while their transactions run.
+        let commutes = std::mem::take(&mut self.commutes);
+        for (id, (reference, _)) in &commutes {
+            if self.claimed.contains_key(id) {
+                continue;
+            }
+            let mut core = reference.lock();
+            if let Some(owner) = &core.writer {
+                if owner.is_live() && !barge(&self.info, owner) {
+                    drop(core);
+                    self.abort();
+                    return Err(retry());
+                }
+            }
+            core.writer = Some(self.info.clone());
+            self.claimed.insert(*id, reference.clone());
+        }
+        // No one else can commit a claimed ref, so its newest value stays current.
+        // Refs this transaction also set already hold the commuted value.
+        for (id, (reference, calls)) in commutes {
+            if self.written.contains(&id) {
+                continue;
+            }
+            let mut value = reference.lock().history[0].1.clone();
+            for (f, args) in calls {
+                let mut call = vec![value];
+                call.extend(args);
+                value = match apply(&f, &call) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        self.abort();
+                        return Err(err);
+                    }
+                };
+            }
+            self.values.insert(id, value);
+            self.written.insert(id);
+        }
+
+        // Holding every lock before taking the point keeps readers from seeing
+        // part of the commit.
+        let mut cores = self
+            .claimed
+            .iter()
+            .map(|(id, reference)| (*id, reference.lock()))
+            .collect::<Vec<_>>();
+        let point = CLOCK.fetch_add(1, Ordering::SeqCst) + 1;
+        for (id, core) in &mut cores {
+            if self.written.contains(id) {
+                if let Some(value) = self.values.remove(id) {
+                    core.history.push_front((point, value));
+                    core.history.truncate(MAX_HISTORY);
+                }
+            }
+            core.writer = None;
+        }
+        drop(cores);
+        self.info.status.store(COMMITTED, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Runs `f` on this thread's transaction, if one is running.
+fn in_transaction<R>(f: impl FnOnce(&mut Transaction) -> R) -> Option<R> {
+    TRANSACTION.with(|tx| tx.borrow_mut().as_mut().map(f))
+}
+
+fn running(f: impl FnOnce(&mut Transaction) -> Result<T, Error>) -> Result<T, Error> {
+    in_transaction(f).unwrap_or_else(|| Err(Error::illegal_state("No transaction running")))
+}
+
+fn expect_ref(name: &str, value: T) -> Result<Ref, Error> {
+    match value {
+        T::Ref(reference) => Ok(reference),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects a ref but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+/// The ref, function and arguments of `alter` and `commute`.
+fn ref_call(name: &str, list: &[T]) -> Result<(Ref, T, Vec<T>), Error> {
+    let mut args = list
+        .iter()
+        .map(|arg| arg.clone().eval())
+        .collect::<Result<Vec<T>, Error>>()?;
+    if args.len() < 2 {
+        return Err(arity(name, 2, "at least 2", list));
+    }
+    let reference = expect_ref(name, args.remove(0))?;
+    let f = args.remove(0);
+    Ok((reference, f, args))
+}
+
+pub fn ref_form(list: &[T]) -> Result<T, Error> {
+    match list {
+        [value] => Ok(T::Ref(Ref::new(value.clone().eval()?))),
+        _ => Err(arity("ref", 1, "1", list)),
+    }
+}
+
+/// `(dosync body*)` runs `body` in a transaction, retrying it until it commits.
+/// Nested `dosync`s join the running transaction.
+pub fn dosync(list: &[T]) -> Result<T, Error> {
+    if in_transaction(|_| ()).is_some() {
+        return eval_body(list);
+    }
+    let start = TX_IDS.fetch_add(1, Ordering::SeqCst);
+    for attempt in 0..RETRY_LIMIT {
+        TRANSACTION.with(|tx| tx.replace(Some(Transaction::new(start))));
+        let result = eval_body(list);
+        let tx = match TRANSACTION.with(|tx| tx.take()) {
+            Some(tx) => tx,
+            None => return result,
+        };
+        let result = match result {
+            Ok(value) => tx.commit().map(|_| value),
+            Err(err) => {
+                tx.abort();
+                Err(err)
+            }
+        };
+        match result {
+            Err(err) if err.kind == ErrorKind::Retry => {
+                // Backing off a little longer each time lets the winner finish.
+                thread::sleep(Duration::from_micros((attempt % 64) as u64))
+            }
+            result => return result,
+        }
+    }
+    Err(Error::reason(
+        "Transaction failed after reaching retry limit",
+    ))
+}
+
+/// `(alter ref f & args)` sets the ref to `(apply f @ref args)` in the transaction.
+pub fn alter(list: &[T]) -> Result<T, Error> {
+    let (reference, f, args) = ref_call("alter", list)?;
+    let current = running(|tx| {
+        tx.claim(&reference)?;
+        tx.read(&reference)
+    })?;
+    let mut call = vec![current];
+    call.extend(args);
+    let value = apply(&f, &call)?;
+    running(|tx| {
+        tx.set(&reference, value.clone())?;
+        Ok(value)
+    })
+}
+
+/// `(commute ref f & args)` is `alter` for commutative `f`: at commit `f` runs
+/// again on the newest value, so it never conflicts.
+pub fn commute(list: &[T]) -> Result<T, Error> {
+    let (reference, f, args) = ref_call("commute", list)?;
+    let current = running(|tx| tx.read(&reference))?;
+    let mut call = vec![current];
+    call.extend(args.iter().cloned());
+    let value = apply(&f, &call)?;
+    running(|tx| {
+        tx.check_running()?;
+        tx.values.insert(reference.0.id, value.clone());
+        tx.commutes
+            .entry(reference.0.id)
+            .or_insert_with(|| (reference.clone(), Vec::new()))
+            .1
+            .push((f, args));
+        Ok(value)
+    })
+}
+
+pub fn ref_set(list: &[T]) -> Result<T, Error> {
+    match list {
+        [reference, value] => {
+            let reference = expect_ref("ref-set", reference.clone().eval()?)?;
+            let value = value.clone().eval()?;
+            running(|tx| {
+                tx.claim(&reference)?;
+                tx.set(&reference, value.clone())?;
+                Ok(value)
+            })
+        }
+        _ => Err(arity("ref-set", 2, "2", list)),
+    }
+}
+
+/// `(ensure ref)` keeps other transactions from changing the ref until this one ends.
+pub fn ensure(list: &[T]) -> Result<T, Error> {
+    match list {
+        [reference] => {
+            let reference = expect_ref("ensure", reference.clone().eval()?)?;
+            running(|tx| {
+                tx.claim(&reference)?;
+                tx.read(&reference)
+            })
+        }
+        _ => Err(arity("ensure", 1, "1", list)),
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{channel, Receiver},
+};
+
+use im::HashMap as Hamt;
+
+use crate::{
+    definitions::{DefinitionTypes as T, Function, Lambda},
+    env::current_env,
+    error::Error,
+    STD, TAPS,
+};
+
+use super::{
+    docs::native_doc,
+    namespaces::{
+        current_ns, publics, resolve_native, resolve_ns, resolve_var, split_qualified, CORE,
+    },
+    vars::{set_stored_meta, stored_meta},
+    write_out,
+};
+
+/// Top level forms whose source `source` can show.
+const DEFINING_FORMS: [&str; 5] = ["def", "defn", "defn-", "defmacro", "defonce"];
+
+pub(crate) struct VarDoc {
+    pub(crate) name: String,
+    pub(crate) arglists: Option<String>,
+    pub(crate) is_macro: bool,
+    pub(crate) doc: Option<String>,
+}
+
+fn var_key(name: &str) -> String {
+    format!("{}/{}", current_ns(), name)
+}
+
+/// Remembers the docstring of `name` in the current namespace as its `:doc`.
+pub(crate) fn document(name: &str, doc: &str) {
+    let key = var_key(name);
+    let mut meta = stored_meta(&key);
+    meta.insert(T::Keyword(String::from(":doc")), T::String(doc.to_owned()));
+    // Docstrings are best effort, like the sources `source` shows.
+    let _ = set_stored_meta(&key, meta);
+}
+
+fn doc_of(key: &str) -> Option<String> {
+    match stored_meta(key).remove(&T::Keyword(String::from(":doc"))) {
+        Some(T::String(doc)) => Some(doc),
+        _ => native_doc(key).map(|(_, doc)| doc.to_owned()),
+    }
+}
+
+/// Remembers `source` as the text of the var `form` defines, if it defines one.
+pub fn record_source(form: &T, source: &str) {
+    if let T::List(list, _) = form {
+        if let [T::Symbol(head, _), T::Symbol(name, _), ..] = &list[..] {
+            let head = split_qualified(head).map_or(head.as_str(), |(_, head)| head);
+            if DEFINING_FORMS.contains(&head) {
+                if let Ok(mut sources) = current_env().sources.write() {
+                    sources.insert(var_key(name), source.trim_end().to_owned());
+                }
+            }
+        }
+    }
+}
+
+fn set_core_var(name: &str, value: T) {
+    if let Ok(mut data) = current_env().data.write() {
+        data.insert(format!("{}/{}", CORE, name), value);
+    }
+}
+
+fn core_var(name: &str) -> T {
+    current_env()
+        .data
+        .read()
+        .ok()
+        .and_then(|data| data.get(&format!("{}/{}", CORE, name)).cloned())
+        .unwrap_or(T::Nil)
+}
+
+/// Shifts `value` into `*1`, moving the previous results to `*2` and `*3`.
+pub fn record_result(value: &T) {
+    set_core_var("*3", core_var("*2"));
+    set_core_var("*2", core_var("*1"));
+    set_core_var("*1", value.clone());
+}
+
+/// `history` with `value` as `*1` and the previous results moved to `*2` and `*3`,
+/// for sessions that keep their own results.
+pub(crate) fn push_result(history: &Hamt<String, T>, value: T) -> Hamt<String, T> {
+    let shift = |name: &str| history.get(name).cloned().unwrap_or(T::Nil);
+    history
+        .update(String::from("*3"), shift("*2"))
+        .update(String::from("*2"), shift("*1"))
+        .update(String::from("*1"), value)
+}
+
+/// Binds `*e` to the last uncaught error.
+pub fn record_error(err: &Error) {
+    set_core_var("*e", T::Throwable(Box::new(err.clone())));
+}
+
+fn arglists(lambda: &Lambda, implicit: usize) -> String {
+    let arities = lambda
+        .arities
+        .iter()
+        .map(|arity| {
+            let params = arity.arglist[implicit..]
+                .iter()
+                .map(|param| param.print().unwrap_or_default())
+                .collect::<Vec<String>>();
+            format!("[{}]", params.join(" "))
+        })
+        .collect::<Vec<String>>();
+    format!("({})", arities.join(" "))
+}
+
+pub(crate) fn var_doc(symbol: &str) -> Option<VarDoc> {
+    let defined = resolve_var(symbol).and_then(|key| {
+        let value = current_env().data.read().ok()?.get(&key).cloned()?;
+        Some((key, value))
+    });
+    match defined {
+        Some((key, value)) => {
+            let (arglists, is_macro) = match &value {
+                T::Function(Function::Lambda(lambda)) => (Some(arglists(lambda, 0)), false),
+                // Skips `&form` and `&env`.
+                T::Function(Function::Macro(lambda)) => (Some(arglists(lambda, 2)), true),
+                _ => (
+                    native_doc(&key).map(|(arglists, _)| arglists.to_owned()),
+                    false,
+                ),
+            };
+            let doc = doc_of(&key);
+            Some(VarDoc {
+                name: key,
+                arglists,
+                is_macro,
+                doc,
+            })
+        }
+        None => resolve_native(symbol).map(|(name, _)| {
+            let key = format!("{}/{}", CORE, name);
+            VarDoc {
+                arglists: native_doc(&key).map(|(arglists, _)| arglists.to_owned()),
+                is_macro: false,
+                doc: doc_of(&key),
+                name: key,
+            }
+        }),
+    }
+}
+
+fn print_doc(doc: &VarDoc) {
+    write_out("-------------------------\n");
+    write_out(&format!("{}\n", doc.name));
+    if let Some(arglists) = &doc.arglists {
+        write_out(&format!("{}\n", arglists));
+    }
+    if doc.is_macro {
+        write_out("Macro\n");
+    }
+    if let Some(doc) = &doc.doc {
+        for line in doc.lines() {
+            write_out(&format!("  {}\n", line.trim()));
+        }
+    }
+}
+
+fn symbol_name<'a>(name: &str, list: &'a [T]) -> Result<&'a str, Error> {
+    match list {
+        [T::Symbol(symbol, _)] => Ok(symbol),
+        [_] => Err(Error::cant_eval(format!("`{}` expects a symbol", name))),
+        _ => Err(Error::arity(
+            1,
+            format!("`{}` has arity of 1 but received {}", name, list.len()),
+        )),
+    }
+}
+
+fn pattern(name: &str, list: &[T]) -> Result<String, Error> {
+    match list {
+        [pattern] => match pattern.clone().eval()? {
+            T::String(pattern) | T::Symbol(pattern, _) => Ok(pattern),
+            pattern => Err(Error::cant_eval(format!(
+                "`{}` expects a string but received {}",
+                name,
+                pattern.print()?
+            ))),
+        },
+        _ => Err(Error::arity(
+            1,
+            format!("`{}` has arity of 1 but received {}", name, list.len()),
+        )),
+    }
+}
+
+/// Every var by its qualified name, builtins included.
+fn all_vars() -> BTreeMap<String, Option<String>> {
+    let keys = current_env()
+        .data
+        .read()
+        .map(|data| data.keys().cloned().collect::<Vec<String>>())
+        .unwrap_or_default();
+    let mut vars = keys
+        .into_iter()
+        .map(|key| {
+            let doc = doc_of(&key);
+            (key, doc)
+        })
+        .collect::<BTreeMap<String, Option<String>>>();
+    vars.extend(STD.keys().map(|name| {
+        let key = format!("{}/{}", CORE, name);
+        let doc = doc_of(&key);
+        (key, doc)
+    }));
+    vars
+}
+
+/// `(doc name)` prints the arglists and docstring of a var or builtin.
+pub fn doc(list: &[T]) -> Result<T, Error> {
+    if let Some(doc) = var_doc(symbol_name("doc", list)?) {
+        print_doc(&doc);
+    }
+    Ok(T::Nil)
+}
+
+/// `(source name)` prints the text a var was defined with.
+pub fn source(list: &[T]) -> Result<T, Error> {
+    let symbol = symbol_name("source", list)?;
+    let source = resolve_var(symbol)
+        .and_then(|key| current_env().sources.read().ok()?.get(&key).cloned())
+        .unwrap_or_else(|| String::from("Source not found"));
+    write_out(&format!("{}\n", source));
+    Ok(T::Nil)
+}
+
+/// `(apropos "str")` returns the qualified names containing `str`.
+pub fn apropos(list: &[T]) -> Result<T, Error> {
+    let pattern = pattern("apropos", list)?;
+    Ok(T::seq(
+        all_vars()
+            .into_keys()
+            .filter(|name| name.contains(&pattern))
+            .map(T::symbol)
+            .collect(),
+    ))
+}
+
+/// `(find-doc "str")` prints the docs of vars whose name or docstring contains `str`.
+pub fn find_doc(list: &[T]) -> Result<T, Error> {
+    let pattern = pattern("find-doc", list)?;
+    for (name, doc) in all_vars() {
+        let matches = name.contains(&pattern) || doc.is_some_and(|doc| doc.contains(&pattern));
+        if let (true, Some(doc)) = (matches, var_doc(&name)) {
+            print_doc(&doc);
+        }
+    }
+    Ok(T::Nil)
+}
+
+/// `(dir ns)` prints the public vars of a namespace or alias.
+pub fn dir(list: &[T]) -> Result<T, Error> {
+    let alias = symbol_name("dir", list)?;
+    let ns =
+        resolve_ns(alias).ok_or_else(|| Error::reason(format!("No namespace: {} found", alias)))?;
+    let mut names = publics(&ns)?;
+    names.sort();
+    names.dedup();
+    for name in names {
+        write_out(&format!("{}\n", name));
+    }
+    Ok(T::Nil)
+}
+
+/// Receives the printed value of every later `tap>`.
+pub(crate) fn add_tap() -> Receiver<String> {
+    let (tap, receiver) = channel();
+    if let Ok(mut taps) = TAPS.lock() {
+        taps.push(tap);
+    }
+    receiver
+}
+
+/// `(tap> x)` sends `x` to every tap, such as connected prepl clients, and returns true.
+pub fn tap(list: &[T]) -> Result<T, Error> {
+    match list {
+        [value] => {
+            let printed = value.clone().eval()?.print()?;
+            if let Ok(mut taps) = TAPS.lock() {
+                // Taps whose receiver is gone are dropped.
+                taps.retain(|tap| tap.send(printed.clone()).is_ok());
+            }
+            Ok(T::Bool(true))
+        }
+        _ => Err(Error::arity(
+            1,
+            format!("`tap>` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
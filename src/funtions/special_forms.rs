@@ -0,0 +1,535 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    definitions::{Arity, DefinitionTypes as T, Function, Lambda, Meta},
+    error::Error,
+    SCOPE,
+};
+
+use super::{
+    collections::items,
+    current_scope,
+    namespaces::{current_ns, define, CORE},
+    vars::{set_stored_meta, var_value},
+    with_scope,
+};
+
+static GENSYM_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn next_gensym(prefix: &str) -> String {
+    format!(
+        "{}{}",
+        prefix,
+        GENSYM_COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+pub(crate) fn sym(name: &str) -> T {
+    T::symbol(name.to_owned())
+}
+
+pub(crate) fn call(f: &str, args: Vec<T>) -> T {
+    let mut list = vec![sym(f)];
+    list.extend(args);
+    T::list(list)
+}
+
+pub(crate) fn binding_pairs<'a>(name: &str, bindings: &'a T) -> Result<&'a [T], Error> {
+    match bindings {
+        T::Vector(bindings, _) if bindings.len() % 2 == 0 => Ok(bindings),
+        T::Vector(_, _) => Err(Error::cant_eval(Some(format!(
+            "`{}` requires an even number of forms in binding vector",
+            name
+        )))),
+        _ => Err(Error::cant_eval(Some(format!(
+            "`{}` requires a vector for its binding",
+            name
+        )))),
+    }
+}
+
+/// Desugars a binding `pattern` over the form `value` into plain symbol bindings,
+/// evaluated in order. Shared by `let`, `loop` and `fn` parameters.
+pub(crate) fn destructure(pattern: &T, value: T, out: &mut Vec<(String, T)>) -> Result<(), Error> {
+    match pattern {
+        T::Symbol(name, _) => out.push((name.to_owned(), value)),
+        T::Vector(patterns, _) => {
+            let vec = next_gensym("vec__");
+            out.push((vec.clone(), value));
+
+            let mut index = 0;
+            let mut patterns = patterns.iter();
+            while let Some(pattern) = patterns.next() {
+                match pattern {
+                    T::Symbol(amp, _) if amp == "&" => {
+                        let rest = patterns.next().ok_or_else(|| {
+                            Error::cant_eval(Some(String::from(
+                                "`&` must be followed by a binding",
+                            )))
+                        })?;
+                        destructure(
+                            rest,
+                            call("nthnext", vec![sym(&vec), T::Int(index.into())]),
+                            out,
+                        )?;
+                    }
+                    T::Keyword(kw) if kw == ":as" => match patterns.next() {
+                        Some(T::Symbol(name, _)) => out.push((name.to_owned(), sym(&vec))),
+                        _ => {
+                            return Err(Error::cant_eval(Some(String::from(
+                                "`:as` must be followed by a symbol",
+                            ))))
+                        }
+                    },
+                    pattern => {
+                        destructure(
+                            pattern,
+                            call("nth", vec![sym(&vec), T::Int(index.into()), T::Nil]),
+                            out,
+                        )?;
+                        index += 1;
+                    }
+                }
+            }
+        }
+        T::HashMap(entries, _) => {
+            let map = next_gensym("map__");
+            out.push((map.clone(), value));
+            out.push((
+                map.clone(),
+                call(
+                    "if",
+                    vec![
+                        call("seq?", vec![sym(&map)]),
+                        call("apply", vec![sym("hash-map"), sym(&map)]),
+                        sym(&map),
+                    ],
+                ),
+            ));
+
+            let defaults = match entries.get(&T::Keyword(String::from(":or"))) {
+                Some(T::HashMap(defaults, _)) => defaults.clone(),
+                Some(_) => {
+                    return Err(Error::cant_eval(Some(String::from(
+                        "`:or` must be followed by a map",
+                    ))))
+                }
+                None => Default::default(),
+            };
+            let lookup = |name: &str, key: T| {
+                let mut args = vec![sym(&map), key];
+                args.extend(defaults.get(&sym(name)).cloned());
+                call("get", args)
+            };
+
+            for (key, val) in entries {
+                match key {
+                    T::Keyword(kw) if kw == ":or" => (),
+                    T::Keyword(kw) if kw == ":as" => match val {
+                        T::Symbol(name, _) => out.push((name.to_owned(), sym(&map))),
+                        _ => {
+                            return Err(Error::cant_eval(Some(String::from(
+                                "`:as` must be followed by a symbol",
+                            ))))
+                        }
+                    },
+                    T::Keyword(kw)
+                        if matches!(
+                            kw.rsplit(['/', ':']).next(),
+                            Some("keys" | "strs" | "syms")
+                        ) =>
+                    {
+                        let (ns, kind) = match kw[1..].rsplit_once('/') {
+                            Some((ns, kind)) => (Some(ns), kind),
+                            None => (None, &kw[1..]),
+                        };
+                        let names = match val {
+                            T::Vector(names, _) => names,
+                            _ => {
+                                return Err(Error::cant_eval(Some(format!(
+                                    "{} must be followed by a vector",
+                                    kw
+                                ))))
+                            }
+                        };
+                        for name in names {
+                            let qualified = match name {
+                                T::Symbol(name, _) => name.to_owned(),
+                                T::Keyword(name) if kind == "keys" => name[1..].to_owned(),
+                                _ => {
+                                    return Err(Error::cant_eval(Some(format!(
+                                        "Unsupported binding form: {}",
+                                        name.print()?
+                                    ))))
+                                }
+                            };
+                            let (sym_ns, local) = match qualified.rsplit_once('/') {
+                                Some((ns, local)) => (Some(ns), local),
+                                None => (None, &qualified[..]),
+                            };
+                            let full = match sym_ns.or(ns) {
+                                Some(ns) => format!("{}/{}", ns, local),
+                                None => local.to_owned(),
+                            };
+                            let key = match kind {
+                                "keys" => T::Keyword(format!(":{}", full)),
+                                "strs" => T::String(full),
+                                "syms" => call("quote", vec![sym(&full)]),
+                                _ => {
+                                    return Err(Error::cant_eval(Some(format!(
+                                        "Unsupported binding key: {}",
+                                        kw
+                                    ))))
+                                }
+                            };
+                            out.push((local.to_owned(), lookup(local, key)));
+                        }
+                    }
+                    T::Symbol(name, _) => out.push((name.to_owned(), lookup(name, val.clone()))),
+                    pattern => {
+                        destructure(pattern, call("get", vec![sym(&map), val.clone()]), out)?
+                    }
+                }
+            }
+        }
+        _ => {
+            return Err(Error::cant_eval(Some(format!(
+                "Unsupported binding form: {}",
+                pattern.print()?
+            ))))
+        }
+    }
+    Ok(())
+}
+
+fn bind(name: String, value: T) {
+    SCOPE.with(|scope| scope.borrow_mut().insert(name, value));
+}
+
+pub(crate) fn eval_body(body: &[T]) -> Result<T, Error> {
+    body.iter().try_fold(T::Nil, |_, form| form.clone().eval())
+}
+
+/// Like `eval_body`, with the last form in tail position.
+pub(crate) fn eval_tail_body(body: &[T]) -> Result<T, Error> {
+    match body.split_last() {
+        Some((last, init)) => {
+            eval_body(init)?;
+            last.clone().eval_tail()
+        }
+        None => Ok(T::Nil),
+    }
+}
+
+pub(crate) fn parse_arity(params: &T, body: &[T]) -> Result<Arity, Error> {
+    let arglist = match params {
+        T::Vector(params, _) => params,
+        _ => {
+            return Err(Error::cant_eval(Some(format!(
+                "Parameter declaration {} should be a vector",
+                params.print().unwrap_or_default()
+            ))))
+        }
+    };
+
+    // Non-symbol parameters are bound to generated names and destructured
+    // by a `let` wrapping the body.
+    let mut patterns = Vec::new();
+    let mut name_of = |param: &T| match param {
+        T::Symbol(name, _) => name.to_owned(),
+        pattern => {
+            let name = next_gensym("p__");
+            patterns.push(pattern.clone());
+            patterns.push(sym(&name));
+            name
+        }
+    };
+
+    let mut names = Vec::new();
+    let mut rest = None;
+    let mut params = arglist.iter();
+    while let Some(param) = params.next() {
+        match param {
+            T::Symbol(amp, _) if amp == "&" => match (params.next(), params.next()) {
+                (Some(param), None) => rest = Some(name_of(param)),
+                _ => {
+                    return Err(Error::cant_eval(Some(String::from(
+                        "`&` must be followed by a single parameter",
+                    ))))
+                }
+            },
+            param => names.push(name_of(param)),
+        }
+    }
+
+    let body = if patterns.is_empty() {
+        body.to_vec()
+    } else {
+        let mut form = vec![sym("let"), T::vector(patterns)];
+        form.extend_from_slice(body);
+        vec![T::list(form)]
+    };
+
+    Ok(Arity {
+        arglist: arglist.clone(),
+        params: names,
+        rest,
+        body,
+    })
+}
+
+pub fn fn_form(list: &[T]) -> Result<T, Error> {
+    let (name, list) = match list.split_first() {
+        Some((T::Symbol(name, _), rest)) => (Some(name.to_owned()), rest),
+        _ => (None, list),
+    };
+
+    let arities = match list.first() {
+        Some(params @ T::Vector(_, _)) => vec![parse_arity(params, &list[1..])?],
+        Some(T::List(_, _)) => list
+            .iter()
+            .map(|arity| match arity {
+                T::List(arity, _) if !arity.is_empty() => parse_arity(&arity[0], &arity[1..]),
+                _ => Err(Error::cant_eval(arity.print().ok())),
+            })
+            .collect::<Result<Vec<Arity>, Error>>()?,
+        _ => {
+            return Err(Error::cant_eval(Some(String::from(
+                "`fn` expects a parameter vector",
+            ))))
+        }
+    };
+
+    Ok(T::Function(Function::Lambda(Box::new(Lambda {
+        name,
+        arities,
+        scope: current_scope(),
+        ns: current_ns(),
+        meta: Meta::default(),
+    }))))
+}
+
+/// The parameter vectors of `lambda`, as `:arglists` lists them.
+fn arglists(lambda: &Lambda) -> T {
+    T::seq(
+        lambda
+            .arities
+            .iter()
+            .map(|arity| T::vector(arity.arglist.clone()))
+            .collect(),
+    )
+}
+
+/// `(def name doc? value?)` gives the var the metadata of `name`, with the
+/// docstring as `:doc`, the file being loaded as `:file` and, for functions,
+/// their `:arglists`.
+pub fn def(list: &[T]) -> Result<T, Error> {
+    let (name, meta, doc, value) = match list {
+        [T::Symbol(name, meta)] => (name, meta, None, T::Nil),
+        [T::Symbol(name, meta), value] => (name, meta, None, value.clone().eval()?),
+        [T::Symbol(name, meta), T::String(doc), value] => {
+            (name, meta, Some(doc), value.clone().eval()?)
+        }
+        _ => {
+            return Err(Error::cant_eval(Some(String::from(
+                "`def` expects a symbol and an optional value",
+            ))))
+        }
+    };
+    let mut var_meta = match meta.get() {
+        T::HashMap(map, _) => map,
+        _ => HashMap::new(),
+    };
+    let file = var_value(&format!("{}/*file*", CORE)).unwrap_or(T::Nil);
+    var_meta.insert(T::Keyword(String::from(":file")), file);
+    if let Some(doc) = doc {
+        var_meta.insert(T::Keyword(String::from(":doc")), T::String(doc.to_owned()));
+    }
+    if let T::Function(Function::Lambda(lambda)) = &value {
+        var_meta
+            .entry(T::Keyword(String::from(":arglists")))
+            .or_insert_with(|| arglists(lambda));
+    }
+    set_stored_meta(&format!("{}/{}", current_ns(), name), var_meta)?;
+    define(name, value)
+}
+
+pub fn let_form(list: &[T]) -> Result<T, Error> {
+    let (bindings, body) = list
+        .split_first()
+        .ok_or_else(|| Error::cant_eval(Some(String::from("`let` requires a binding vector"))))?;
+    let mut pairs = Vec::new();
+    for pair in binding_pairs("let", bindings)?.chunks(2) {
+        destructure(&pair[0], pair[1].clone(), &mut pairs)?;
+    }
+
+    with_scope(current_scope(), || {
+        for (name, form) in pairs {
+            let value = form.eval()?;
+            bind(name, value);
+        }
+        eval_tail_body(body)
+    })
+}
+
+pub fn loop_form(list: &[T]) -> Result<T, Error> {
+    let (bindings, body) = list
+        .split_first()
+        .ok_or_else(|| Error::cant_eval(Some(String::from("`loop` requires a binding vector"))))?;
+    let bindings = binding_pairs("loop", bindings)?;
+
+    // `(loop [[a b] v] ...)` becomes `(let [g v] (loop [g g] (let [[a b] g] ...)))`.
+    if bindings
+        .chunks(2)
+        .any(|pair| !matches!(pair[0], T::Symbol(_, _)))
+    {
+        let mut outer = Vec::new();
+        let mut inner = Vec::new();
+        let mut destructured = Vec::new();
+        for pair in bindings.chunks(2) {
+            let name = next_gensym("loop__");
+            outer.extend([sym(&name), pair[1].clone()]);
+            inner.extend([sym(&name), sym(&name)]);
+            destructured.extend([pair[0].clone(), sym(&name)]);
+        }
+        let mut destructured_body = vec![sym("let"), T::vector(destructured)];
+        destructured_body.extend_from_slice(body);
+        let form = call(
+            "let",
+            vec![
+                T::vector(outer),
+                call("loop", vec![T::vector(inner), T::list(destructured_body)]),
+            ],
+        );
+        return form.eval();
+    }
+
+    let names = bindings
+        .chunks(2)
+        .map(|pair| match &pair[0] {
+            T::Symbol(name, _) => name.to_owned(),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<String>>();
+
+    with_scope(current_scope(), || {
+        for (name, pair) in names.iter().zip(bindings.chunks(2)) {
+            let value = pair[1].clone().eval()?;
+            bind(name.to_owned(), value);
+        }
+        loop {
+            match eval_tail_body(body)? {
+                T::Recur(args) if args.len() == names.len() => {
+                    for (name, value) in names.iter().zip(args) {
+                        bind(name.to_owned(), value);
+                    }
+                }
+                T::Recur(args) => {
+                    return Err(Error::arity(
+                        names.len() as u16,
+                        format!(
+                            "Mismatched argument count to recur, expected: {} args, got: {}",
+                            names.len(),
+                            args.len()
+                        ),
+                    ))
+                }
+                result => return Ok(result),
+            }
+        }
+    })
+}
+
+pub fn recur(list: &[T]) -> Result<T, Error> {
+    Ok(T::Recur(
+        list.iter()
+            .map(|arg| arg.clone().eval())
+            .collect::<Result<Vec<T>, Error>>()?,
+    ))
+}
+
+pub fn if_form(list: &[T]) -> Result<T, Error> {
+    match list {
+        [test, then] => {
+            if test.clone().eval()?.is_truthy() {
+                then.clone().eval_tail()
+            } else {
+                Ok(T::Nil)
+            }
+        }
+        [test, then, otherwise] => {
+            if test.clone().eval()?.is_truthy() {
+                then.clone().eval_tail()
+            } else {
+                otherwise.clone().eval_tail()
+            }
+        }
+        _ => Err(Error::arity(
+            2,
+            format!("`if` has arity of 2 or 3 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn do_form(list: &[T]) -> Result<T, Error> {
+    eval_tail_body(list)
+}
+
+/// Returns its argument unevaluated; quoted lists become `T::Seq` data.
+pub fn quote(list: &[T]) -> Result<T, Error> {
+    match list {
+        [form] => Ok(quoted(form.clone())),
+        _ => Err(Error::arity(
+            1,
+            format!("`quote` has arity of 1 but received {}", list.len()),
+        )),
+    }
+}
+
+/// Converts quoted data back into evaluable code, e.g. a macro's expansion.
+pub(crate) fn unquoted(form: T) -> T {
+    match form {
+        T::Seq(list, meta) | T::List(list, meta) => {
+            T::List(list.into_iter().map(unquoted).collect(), meta)
+        }
+        T::Vector(vec, meta) => T::Vector(vec.into_iter().map(unquoted).collect(), meta),
+        T::HashSet(set, meta) => T::HashSet(set.into_iter().map(unquoted).collect(), meta),
+        T::HashMap(map, meta) => T::HashMap(
+            map.into_iter()
+                .map(|(k, v)| (unquoted(k), unquoted(v)))
+                .collect(),
+            meta,
+        ),
+        form => form,
+    }
+}
+
+pub(crate) fn quoted(form: T) -> T {
+    match form {
+        T::List(list, meta) | T::Seq(list, meta) => {
+            T::Seq(list.into_iter().map(quoted).collect(), meta)
+        }
+        T::Vector(vec, meta) => T::Vector(vec.into_iter().map(quoted).collect(), meta),
+        T::HashSet(set, meta) => T::HashSet(set.into_iter().map(quoted).collect(), meta),
+        T::HashMap(map, meta) => T::HashMap(
+            map.into_iter()
+                .map(|(k, v)| (quoted(k), quoted(v)))
+                .collect(),
+            meta,
+        ),
+        form => form,
+    }
+}
+
+// `recur` into a variadic arity passes the rest parameter as a single seq.
+pub(crate) fn rest_args(mut args: Vec<T>, params: usize) -> Result<Vec<T>, Error> {
+    if args.len() == params + 1 {
+        if let Some(rest) = args.pop() {
+            args.extend(items(rest)?);
+        }
+    }
+    Ok(args)
+}
@@ -1,14 +1,32 @@
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, ToPrimitive};
+
 use crate::{definitions::DefinitionTypes as T, error::Error};
 
+use super::{arity, write_out};
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
 pub fn str(list: &[T]) -> Result<T, Error> {
     Ok(T::String(list.iter().fold(String::new(), |acc, el| {
-        acc + &el.print().unwrap_or_default()
+        match el {
+            T::Symbol(_, _) => {
+                acc + &el
+                    .clone()
+                    .eval()
+                    .and_then(|el| el.print())
+                    .unwrap_or_default()
+            }
+            _ => acc + &el.print().unwrap_or_default(),
+        }
     })))
 }
 
 pub fn println(list: &[T]) -> Result<T, Error> {
     if let Ok(T::String(str)) = str(list) {
-        println!("{}", str);
+        write_out(&format!("{}\n", str));
     }
     Ok(T::Nil)
 }
@@ -17,7 +35,7 @@ pub fn to_keyword(list: &[T]) -> Result<T, Error> {
     let keywords = list
         .iter()
         .map(|el| match el {
-            T::Symbol(s) => Ok(T::Keyword(format!(":{}", s))),
+            T::Symbol(s, _) => Ok(T::Keyword(format!(":{}", s))),
             T::Keyword(_) => Ok(el.to_owned()),
             T::String(s) => Ok(T::Keyword(format!(":{}", s))),
             T::Char(s) => Ok(T::Keyword(format!(":{}", s))),
@@ -26,19 +44,103 @@ pub fn to_keyword(list: &[T]) -> Result<T, Error> {
             T::Int(s) => Ok(T::Keyword(format!(":{}", s))),
             T::Rational(n, d) => Ok(T::Keyword(format!(":{}/{}", n, d))),
             T::Nil => Ok(T::Keyword(":nil".to_owned())),
-            T::List(_) => {
-                if let Ok(T::Vector(item)) = to_keyword(&[el.clone().eval()?]) {
+            T::List(_, _) => {
+                if let Ok(T::Vector(item, _)) = to_keyword(&[el.clone().eval()?]) {
                     if item.len() == 1 {
                         Ok(item[0].clone())
                     } else {
-                        Err(Error::Thrown(String::from("Can't keywordize a collection")))
+                        Err(Error::thrown(String::from("Can't keywordize a collection")))
                     }
                 } else {
-                    Err(Error::CantEval(el.print().ok()))
+                    Err(Error::cant_eval(el.print().ok()))
                 }
             }
-            _ => Err(Error::Thrown(String::from("Can't keywordize a collection"))),
+            _ => Err(Error::thrown(String::from("Can't keywordize a collection"))),
         })
         .collect::<Result<Vec<T>, Error>>();
-    Ok(T::Vector(keywords?))
+    Ok(T::vector(keywords?))
+}
+
+fn char_index(s: &str, index: &T) -> Result<usize, Error> {
+    let length = s.chars().count();
+    match index {
+        T::Int(i) => i
+            .to_usize()
+            .filter(|i| *i <= length)
+            .ok_or_else(|| Error::illegal_argument(format!("String index out of range: {}", i))),
+        index => Err(Error::cant_eval(format!(
+            "`subs` expects an integer index but received {}",
+            index.print()?
+        ))),
+    }
+}
+
+/// `(subs s start)` or `(subs s start end)`, by char index.
+pub fn subs(list: &[T]) -> Result<T, Error> {
+    let args = eval_all(list)?;
+    let s = match args.first() {
+        Some(T::String(s)) => s,
+        Some(value) => {
+            return Err(Error::cant_eval(format!(
+                "`subs` expects a string but received {}",
+                value.print()?
+            )))
+        }
+        None => return Err(arity("subs", 2, "2 or 3", list)),
+    };
+    let (start, end) = match &args[1..] {
+        [start] => (char_index(s, start)?, s.chars().count()),
+        [start, end] => (char_index(s, start)?, char_index(s, end)?),
+        _ => return Err(arity("subs", 2, "2 or 3", list)),
+    };
+    if start > end {
+        return Err(Error::illegal_argument(format!(
+            "String index out of range: {}",
+            end as i64 - start as i64
+        )));
+    }
+    Ok(T::String(s.chars().skip(start).take(end - start).collect()))
+}
+
+/// `(char x)` is the char with the code point `x`.
+pub fn to_char(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Char(c)] => Ok(T::Char(*c)),
+        [T::Int(code)] => code
+            .to_u32()
+            .and_then(char::from_u32)
+            .map(T::Char)
+            .ok_or_else(|| {
+                Error::illegal_argument(format!("Value out of range for char: {}", code))
+            }),
+        [value] => Err(Error::cant_eval(format!(
+            "`char` expects a number but received {}",
+            value.print()?
+        ))),
+        _ => Err(arity("char", 1, "1", list)),
+    }
+}
+
+/// `(int x)` is the code point of a char, or a number truncated to an integer.
+pub fn to_int(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Char(c)] => Ok(T::Int(BigInt::from(*c as u32))),
+        [T::Int(i)] => Ok(T::Int(i.clone())),
+        [T::Double(d)] => BigInt::from_f64(d.trunc())
+            .map(T::Int)
+            .ok_or_else(|| Error::illegal_argument(format!("Value out of range for int: {}", d))),
+        [T::Rational(num, den)] => Ok(T::Int(num / den)),
+        [value] => Err(Error::cant_eval(format!(
+            "`int` expects a number or char but received {}",
+            value.print()?
+        ))),
+        _ => Err(arity("int", 1, "1", list)),
+    }
+}
+
+pub fn is_char(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [value] => Ok(T::Bool(matches!(value, T::Char(_)))),
+        _ => Err(arity("char?", 1, "1", list)),
+    }
 }
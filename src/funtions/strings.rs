@@ -0,0 +1,324 @@
+use std::fmt;
+
+use num_traits::ToPrimitive;
+use regex::{Captures, Regex};
+
+use crate::{definitions::DefinitionTypes as T, error::Error};
+
+use super::{apply, arity, collections::items};
+
+lazy_static! {
+    static ref LINE_BREAK: Regex = Regex::new(r"\r?\n").expect("line break regex");
+}
+
+/// A compiled `#"..."` regex. Regexes are equal when their sources are.
+#[derive(Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Pattern, regex::Error> {
+        Regex::new(source).map(Pattern)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#\"{}\"", self.as_str())
+    }
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+/// The text of `value` as `join` and replacement functions use it: strings and
+/// chars as they are, nil as nothing and anything else printed.
+fn display(value: &T) -> Result<String, Error> {
+    match value {
+        T::String(s) => Ok(s.clone()),
+        T::Char(c) => Ok(c.to_string()),
+        T::Nil => Ok(String::new()),
+        value => value.print(),
+    }
+}
+
+fn text<'a>(name: &str, value: &'a T) -> Result<&'a str, Error> {
+    match value {
+        T::String(s) => Ok(s),
+        value => Err(Error::cant_eval(format!(
+            "`{}` expects a string but received {}",
+            name,
+            value.print()?
+        ))),
+    }
+}
+
+/// A string or char matches itself and a regex matches what it describes.
+fn matcher(name: &str, value: &T) -> Result<Regex, Error> {
+    let literal = match value {
+        T::Regex(pattern) => return Ok(pattern.0.clone()),
+        T::String(s) => regex::escape(s),
+        T::Char(c) => regex::escape(&c.to_string()),
+        value => {
+            return Err(Error::cant_eval(format!(
+                "`{}` expects a string, char or regex but received {}",
+                name,
+                value.print()?
+            )))
+        }
+    };
+    Regex::new(&literal).map_err(|e| Error::reason(e.to_string()))
+}
+
+/// A function replacement gets the match, or a vector of the match and its
+/// groups when the regex has any.
+fn match_value(captures: &Captures) -> T {
+    let group = |m: Option<regex::Match>| m.map_or(T::Nil, |m| T::String(m.as_str().to_owned()));
+    if captures.len() == 1 {
+        group(captures.get(0))
+    } else {
+        T::vector(captures.iter().map(group).collect())
+    }
+}
+
+/// Replaces up to `limit` matches of `needle` in `s`. Strings and chars are
+/// replaced literally, a regex by a `$1` style template or a function of the match.
+fn replace_matches(name: &str, list: &[T], limit: usize) -> Result<T, Error> {
+    let (s, needle, replacement) = match &eval_all(list)?[..] {
+        [s, needle, replacement] => (s.clone(), needle.clone(), replacement.clone()),
+        _ => return Err(arity(name, 3, "3", list)),
+    };
+    let s = text(name, &s)?;
+    let regex = matcher(name, &needle)?;
+    let mut result = String::new();
+    let mut last = 0;
+    for captures in regex.captures_iter(s).take(limit) {
+        let whole = match captures.get(0) {
+            Some(whole) => whole,
+            None => continue,
+        };
+        result.push_str(&s[last..whole.start()]);
+        match (&needle, &replacement) {
+            (T::String(_), T::String(literal)) => result.push_str(literal),
+            (T::Char(_), T::Char(literal)) => result.push(*literal),
+            (T::Regex(_), T::String(template)) => captures.expand(template, &mut result),
+            (T::Regex(_), f) => result.push_str(&display(&apply(f, &[match_value(&captures)])?)?),
+            (needle, replacement) => {
+                return Err(Error::illegal_argument(format!(
+                    "`{}` can't replace {} with {}",
+                    name,
+                    needle.print()?,
+                    replacement.print()?
+                )))
+            }
+        }
+        last = whole.end();
+    }
+    result.push_str(&s[last..]);
+    Ok(T::String(result))
+}
+
+/// Splits like Java's `String.split`: a match at the very start doesn't make an
+/// empty first part, and without a positive limit trailing empty parts are dropped.
+fn split_on(s: &str, regex: &Regex, limit: i64) -> T {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for m in regex.find_iter(s) {
+        if limit > 0 && parts.len() as i64 + 1 == limit {
+            break;
+        }
+        if m.end() == 0 {
+            continue;
+        }
+        parts.push(&s[last..m.start()]);
+        last = m.end();
+    }
+    parts.push(&s[last..]);
+    if limit == 0 && parts.len() > 1 {
+        while parts.last() == Some(&"") {
+            parts.pop();
+        }
+    }
+    T::vector(
+        parts
+            .into_iter()
+            .map(|part| T::String(part.to_owned()))
+            .collect(),
+    )
+}
+
+fn with_text(name: &str, list: &[T], f: impl Fn(&str) -> T) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [s] => Ok(f(text(name, s)?)),
+        _ => Err(arity(name, 1, "1", list)),
+    }
+}
+
+fn with_substring(name: &str, list: &[T], f: impl Fn(&str, &str) -> bool) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [s, substring] => Ok(T::Bool(f(text(name, s)?, text(name, substring)?))),
+        _ => Err(arity(name, 2, "2", list)),
+    }
+}
+
+/// `(join coll)` or `(join separator coll)`.
+pub fn join(list: &[T]) -> Result<T, Error> {
+    let (separator, coll) = match &eval_all(list)?[..] {
+        [coll] => (String::new(), coll.clone()),
+        [separator, coll] => (display(separator)?, coll.clone()),
+        _ => return Err(arity("join", 1, "1 or 2", list)),
+    };
+    let parts = items(coll)?
+        .iter()
+        .map(display)
+        .collect::<Result<Vec<String>, Error>>()?;
+    Ok(T::String(parts.join(&separator)))
+}
+
+/// `(split s re)` or `(split s re limit)` is a vector of the parts of `s`
+/// between matches of `re`.
+pub fn split(list: &[T]) -> Result<T, Error> {
+    let (s, needle, limit) = match &eval_all(list)?[..] {
+        [s, needle] => (s.clone(), needle.clone(), 0),
+        [s, needle, T::Int(limit)] => (s.clone(), needle.clone(), limit.to_i64().unwrap_or(0)),
+        [_, _, limit] => {
+            return Err(Error::cant_eval(format!(
+                "`split` expects an integer limit but received {}",
+                limit.print()?
+            )))
+        }
+        _ => return Err(arity("split", 2, "2 or 3", list)),
+    };
+    Ok(split_on(
+        text("split", &s)?,
+        &matcher("split", &needle)?,
+        limit,
+    ))
+}
+
+/// `(split-lines s)` splits on `\n` and `\r\n`.
+pub fn split_lines(list: &[T]) -> Result<T, Error> {
+    with_text("split-lines", list, |s| split_on(s, &LINE_BREAK, 0))
+}
+
+pub fn trim(list: &[T]) -> Result<T, Error> {
+    with_text("trim", list, |s| T::String(s.trim().to_owned()))
+}
+
+pub fn triml(list: &[T]) -> Result<T, Error> {
+    with_text("triml", list, |s| T::String(s.trim_start().to_owned()))
+}
+
+pub fn trimr(list: &[T]) -> Result<T, Error> {
+    with_text("trimr", list, |s| T::String(s.trim_end().to_owned()))
+}
+
+pub fn upper_case(list: &[T]) -> Result<T, Error> {
+    with_text("upper-case", list, |s| T::String(s.to_uppercase()))
+}
+
+pub fn lower_case(list: &[T]) -> Result<T, Error> {
+    with_text("lower-case", list, |s| T::String(s.to_lowercase()))
+}
+
+/// `(capitalize s)` upper-cases the first char of `s` and lower-cases the rest.
+pub fn capitalize(list: &[T]) -> Result<T, Error> {
+    with_text("capitalize", list, |s| {
+        let mut chars = s.chars();
+        T::String(chars.next().map_or_else(String::new, |first| {
+            first
+                .to_uppercase()
+                .chain(chars.as_str().to_lowercase().chars())
+                .collect()
+        }))
+    })
+}
+
+/// `(replace s match replacement)` replaces every match.
+pub fn replace(list: &[T]) -> Result<T, Error> {
+    replace_matches("replace", list, usize::MAX)
+}
+
+/// `(replace-first s match replacement)` replaces the first match.
+pub fn replace_first(list: &[T]) -> Result<T, Error> {
+    replace_matches("replace-first", list, 1)
+}
+
+pub fn starts_with(list: &[T]) -> Result<T, Error> {
+    with_substring("starts-with?", list, |s, prefix| s.starts_with(prefix))
+}
+
+pub fn ends_with(list: &[T]) -> Result<T, Error> {
+    with_substring("ends-with?", list, |s, suffix| s.ends_with(suffix))
+}
+
+pub fn includes(list: &[T]) -> Result<T, Error> {
+    with_substring("includes?", list, |s, substring| s.contains(substring))
+}
+
+/// `(index-of s value)` or `(index-of s value from)` is the char index of the
+/// first string or char `value` in `s` at or after `from`, or nil.
+pub fn index_of(list: &[T]) -> Result<T, Error> {
+    let (s, value, from) = match &eval_all(list)?[..] {
+        [s, value] => (s.clone(), value.clone(), 0),
+        [s, value, T::Int(from)] => (s.clone(), value.clone(), from.to_usize().unwrap_or(0)),
+        [_, _, from] => {
+            return Err(Error::cant_eval(format!(
+                "`index-of` expects an integer index but received {}",
+                from.print()?
+            )))
+        }
+        _ => return Err(arity("index-of", 2, "2 or 3", list)),
+    };
+    let s = text("index-of", &s)?;
+    let value = match value {
+        T::Char(c) => c.to_string(),
+        value => text("index-of", &value)?.to_owned(),
+    };
+    let start = s.char_indices().nth(from).map_or(s.len(), |(i, _)| i);
+    Ok(s[start..].find(&value).map_or(T::Nil, |i| {
+        T::Int((from + s[start..start + i].chars().count()).into())
+    }))
+}
+
+/// `(blank? s)` is true for nil and strings of only whitespace.
+pub fn is_blank(list: &[T]) -> Result<T, Error> {
+    match &eval_all(list)?[..] {
+        [T::Nil] => Ok(T::Bool(true)),
+        [s] => Ok(T::Bool(text("blank?", s)?.trim().is_empty())),
+        _ => Err(arity("blank?", 1, "1", list)),
+    }
+}
+
+pub fn reverse(list: &[T]) -> Result<T, Error> {
+    with_text("reverse", list, |s| T::String(s.chars().rev().collect()))
+}
+
+/// `(escape s cmap)` replaces every char of `s` that `cmap` maps to something
+/// other than nil.
+pub fn escape(list: &[T]) -> Result<T, Error> {
+    let (s, cmap) = match &eval_all(list)?[..] {
+        [s, cmap] => (s.clone(), cmap.clone()),
+        _ => return Err(arity("escape", 2, "2", list)),
+    };
+    let mut result = String::new();
+    for c in text("escape", &s)?.chars() {
+        match apply(&cmap, &[T::Char(c)])? {
+            T::Nil => result.push(c),
+            replacement => result.push_str(&display(&replacement)?),
+        }
+    }
+    Ok(T::String(result))
+}
@@ -0,0 +1,489 @@
+use num_bigint::Sign;
+use num_traits::ToPrimitive;
+
+use crate::{
+    definitions::{DefinitionTypes as T, Function},
+    error::Error,
+};
+
+use super::{
+    apply,
+    collections::{conj_one, items},
+};
+
+/// A single step of a transducer pipeline. Composed transducers are kept as a
+/// flat `Vec<Transducer>` inside `T::Xform`, applied left to right.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Transducer {
+    Map(T),
+    Filter(T),
+    Remove(T),
+    Take(usize),
+    Drop(usize),
+    PartitionAll(usize),
+    Dedupe,
+    Cat,
+    Mapcat(T),
+    Keep(T),
+}
+
+impl Transducer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transducer::Map(_) => "map",
+            Transducer::Filter(_) => "filter",
+            Transducer::Remove(_) => "remove",
+            Transducer::Take(_) => "take",
+            Transducer::Drop(_) => "drop",
+            Transducer::PartitionAll(_) => "partition-all",
+            Transducer::Dedupe => "dedupe",
+            Transducer::Cat => "cat",
+            Transducer::Mapcat(_) => "mapcat",
+            Transducer::Keep(_) => "keep",
+        }
+    }
+}
+
+// Per-reduction state of a transducer, so that the same `T::Xform` can be reused.
+enum Stage {
+    Map(T),
+    Filter(T),
+    Remove(T),
+    Take(usize),
+    Drop(usize),
+    PartitionAll(usize, Vec<T>),
+    Dedupe(Option<T>),
+    Cat,
+    Mapcat(T),
+    Keep(T),
+}
+
+impl From<&Transducer> for Stage {
+    fn from(xform: &Transducer) -> Self {
+        match xform {
+            Transducer::Map(f) => Stage::Map(f.clone()),
+            Transducer::Filter(f) => Stage::Filter(f.clone()),
+            Transducer::Remove(f) => Stage::Remove(f.clone()),
+            Transducer::Take(n) => Stage::Take(*n),
+            Transducer::Drop(n) => Stage::Drop(*n),
+            Transducer::PartitionAll(n) => Stage::PartitionAll(*n, Vec::with_capacity(*n)),
+            Transducer::Dedupe => Stage::Dedupe(None),
+            Transducer::Cat => Stage::Cat,
+            Transducer::Mapcat(f) => Stage::Mapcat(f.clone()),
+            Transducer::Keep(f) => Stage::Keep(f.clone()),
+        }
+    }
+}
+
+type Reducer<'r> = dyn FnMut(T) -> Result<(), Error> + 'r;
+
+// Pushes `item` through `stages` into `rf`. Returns `false` once the reduction
+// must stop early, e.g. when `take` has seen enough items.
+fn step(stages: &mut [Stage], item: T, rf: &mut Reducer) -> Result<bool, Error> {
+    let (stage, rest) = match stages.split_first_mut() {
+        Some(split) => split,
+        None => {
+            rf(item)?;
+            return Ok(true);
+        }
+    };
+
+    match stage {
+        Stage::Map(f) => step(rest, apply(f, &[item])?, rf),
+        Stage::Filter(f) => {
+            if apply(f, std::slice::from_ref(&item))?.is_truthy() {
+                step(rest, item, rf)
+            } else {
+                Ok(true)
+            }
+        }
+        Stage::Remove(f) => {
+            if apply(f, std::slice::from_ref(&item))?.is_truthy() {
+                Ok(true)
+            } else {
+                step(rest, item, rf)
+            }
+        }
+        Stage::Take(n) => {
+            if *n == 0 {
+                return Ok(false);
+            }
+            *n -= 1;
+            Ok(step(rest, item, rf)? && *n > 0)
+        }
+        Stage::Drop(n) => {
+            if *n > 0 {
+                *n -= 1;
+                Ok(true)
+            } else {
+                step(rest, item, rf)
+            }
+        }
+        Stage::PartitionAll(n, buffer) => {
+            buffer.push(item);
+            if buffer.len() == *n {
+                let chunk = std::mem::replace(buffer, Vec::with_capacity(*n));
+                step(rest, T::vector(chunk), rf)
+            } else {
+                Ok(true)
+            }
+        }
+        Stage::Dedupe(last) => {
+            if last.as_ref() == Some(&item) {
+                Ok(true)
+            } else {
+                *last = Some(item.clone());
+                step(rest, item, rf)
+            }
+        }
+        Stage::Cat => concat(rest, item, rf),
+        Stage::Mapcat(f) => concat(rest, apply(f, &[item])?, rf),
+        Stage::Keep(f) => match apply(f, &[item])? {
+            T::Nil => Ok(true),
+            kept => step(rest, kept, rf),
+        },
+    }
+}
+
+fn concat(stages: &mut [Stage], coll: T, rf: &mut Reducer) -> Result<bool, Error> {
+    for item in items(coll)? {
+        if !step(stages, item, rf)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Flushes stateful stages (`partition-all`) in pipeline order.
+fn complete(stages: &mut [Stage], rf: &mut Reducer) -> Result<(), Error> {
+    for i in 0..stages.len() {
+        let (head, rest) = stages.split_at_mut(i + 1);
+        if let Stage::PartitionAll(_, buffer) = &mut head[i] {
+            if !buffer.is_empty() {
+                let chunk = std::mem::take(buffer);
+                step(rest, T::vector(chunk), rf)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run(xform: &[Transducer], coll: T, rf: &mut Reducer) -> Result<(), Error> {
+    let mut stages = xform.iter().map(Stage::from).collect::<Vec<Stage>>();
+    for item in items(coll)? {
+        if !step(&mut stages, item, rf)? {
+            break;
+        }
+    }
+    complete(&mut stages, rf)
+}
+
+fn collect(xform: &[Transducer], coll: T) -> Result<T, Error> {
+    let mut seq = Vec::new();
+    run(xform, coll, &mut |item| {
+        seq.push(item);
+        Ok(())
+    })?;
+    Ok(T::seq(seq))
+}
+
+/// A transducer fed one item at a time, as `pipeline` takes them, keeping the
+/// state of `take`, `dedupe` and `partition-all` from one item to the next.
+pub(crate) struct Transform {
+    stages: Vec<Stage>,
+    done: bool,
+}
+
+impl Transform {
+    pub(crate) fn new(xform: &T) -> Result<Transform, Error> {
+        Ok(Transform {
+            stages: xform_of(xform.clone())?.iter().map(Stage::from).collect(),
+            done: false,
+        })
+    }
+
+    /// What `item` turns into.
+    pub(crate) fn step(&mut self, item: T) -> Result<Vec<T>, Error> {
+        let mut transformed = Vec::new();
+        if !self.done {
+            self.done = !step(&mut self.stages, item, &mut |item| {
+                transformed.push(item);
+                Ok(())
+            })?;
+        }
+        Ok(transformed)
+    }
+
+    /// What the stages still hold once there are no more items, e.g. the
+    /// last chunk of `partition-all`.
+    pub(crate) fn complete(&mut self) -> Result<Vec<T>, Error> {
+        let mut transformed = Vec::new();
+        complete(&mut self.stages, &mut |item| {
+            transformed.push(item);
+            Ok(())
+        })?;
+        Ok(transformed)
+    }
+
+    /// Whether the transducer wants no more items, e.g. `take` has seen enough.
+    pub(crate) fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+fn xform_of(value: T) -> Result<Vec<Transducer>, Error> {
+    match value {
+        T::Xform(xform) => Ok(xform),
+        _ => Err(Error::cant_eval(Some(format!(
+            "{} is not a transducer",
+            value.print().unwrap_or_default()
+        )))),
+    }
+}
+
+fn size_of(name: &str, value: T) -> Result<usize, Error> {
+    match value {
+        T::Int(n) if n.sign() == Sign::Minus => Ok(0),
+        T::Int(n) => Ok(n.to_usize().unwrap_or(usize::MAX)),
+        _ => Err(Error::cant_eval(Some(format!(
+            "`{}` expects an integer but received {}",
+            name,
+            value.print().unwrap_or_default()
+        )))),
+    }
+}
+
+// Transducer constructors return the xform when called without a collection and
+// a realized seq when a collection is given, e.g. `(map inc)` vs `(map inc [1 2])`.
+fn xform_or_seq(name: &str, arity: u16, xform: Transducer, colls: &[T]) -> Result<T, Error> {
+    match colls {
+        [] => Ok(T::Xform(vec![xform])),
+        [coll] => collect(&[xform], coll.clone().eval()?),
+        _ => Err(Error::arity(
+            arity,
+            format!(
+                "`{}` has arity of {} or {} but received {}",
+                name,
+                arity,
+                arity + 1,
+                colls.len() + arity as usize
+            ),
+        )),
+    }
+}
+
+pub fn map(list: &[T]) -> Result<T, Error> {
+    match list {
+        [] => Err(Error::arity(
+            1,
+            String::from("`map` has arity of at least 1 but received 0"),
+        )),
+        [f, colls @ ..] if colls.len() > 1 => {
+            let f = f.clone().eval()?;
+            let mut colls = colls
+                .iter()
+                .map(|c| items(c.clone().eval()?).map(Vec::into_iter))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let mut seq = Vec::new();
+            loop {
+                let args = colls
+                    .iter_mut()
+                    .map(Iterator::next)
+                    .collect::<Option<Vec<T>>>();
+                match args {
+                    Some(args) => seq.push(apply(&f, &args)?),
+                    None => return Ok(T::seq(seq)),
+                }
+            }
+        }
+        [f, colls @ ..] => xform_or_seq("map", 1, Transducer::Map(f.clone().eval()?), colls),
+    }
+}
+
+pub fn filter(list: &[T]) -> Result<T, Error> {
+    match list {
+        [pred, colls @ ..] => {
+            xform_or_seq("filter", 1, Transducer::Filter(pred.clone().eval()?), colls)
+        }
+        [] => Err(Error::arity(
+            1,
+            String::from("`filter` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn remove(list: &[T]) -> Result<T, Error> {
+    match list {
+        [pred, colls @ ..] => {
+            xform_or_seq("remove", 1, Transducer::Remove(pred.clone().eval()?), colls)
+        }
+        [] => Err(Error::arity(
+            1,
+            String::from("`remove` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn take(list: &[T]) -> Result<T, Error> {
+    match list {
+        [n, colls @ ..] => {
+            let n = size_of("take", n.clone().eval()?)?;
+            xform_or_seq("take", 1, Transducer::Take(n), colls)
+        }
+        [] => Err(Error::arity(
+            1,
+            String::from("`take` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn drop(list: &[T]) -> Result<T, Error> {
+    match list {
+        [n, colls @ ..] => {
+            let n = size_of("drop", n.clone().eval()?)?;
+            xform_or_seq("drop", 1, Transducer::Drop(n), colls)
+        }
+        [] => Err(Error::arity(
+            1,
+            String::from("`drop` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn partition_all(list: &[T]) -> Result<T, Error> {
+    match list {
+        [n, colls @ ..] => match size_of("partition-all", n.clone().eval()?)? {
+            0 => Err(Error::cant_eval(Some(String::from(
+                "`partition-all` expects a positive size",
+            )))),
+            n => xform_or_seq("partition-all", 1, Transducer::PartitionAll(n), colls),
+        },
+        [] => Err(Error::arity(
+            1,
+            String::from("`partition-all` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn dedupe(list: &[T]) -> Result<T, Error> {
+    xform_or_seq("dedupe", 0, Transducer::Dedupe, list)
+}
+
+pub fn mapcat(list: &[T]) -> Result<T, Error> {
+    match list {
+        [f, colls @ ..] => xform_or_seq("mapcat", 1, Transducer::Mapcat(f.clone().eval()?), colls),
+        [] => Err(Error::arity(
+            1,
+            String::from("`mapcat` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn keep(list: &[T]) -> Result<T, Error> {
+    match list {
+        [f, colls @ ..] => xform_or_seq("keep", 1, Transducer::Keep(f.clone().eval()?), colls),
+        [] => Err(Error::arity(
+            1,
+            String::from("`keep` has arity of 1 but received 0"),
+        )),
+    }
+}
+
+pub fn comp(list: &[T]) -> Result<T, Error> {
+    let fns = list
+        .iter()
+        .map(|f| f.clone().eval())
+        .collect::<Result<Vec<T>, Error>>()?;
+    let xforms = fns.iter().filter(|f| matches!(f, T::Xform(_))).count();
+
+    if xforms == 0 {
+        Ok(T::Function(Function::Comp(fns)))
+    } else if xforms == fns.len() {
+        Ok(T::Xform(
+            fns.into_iter()
+                .map(xform_of)
+                .collect::<Result<Vec<_>, Error>>()?
+                .concat(),
+        ))
+    } else {
+        Err(Error::cant_eval(Some(String::from(
+            "Can't compose transducers with other functions",
+        ))))
+    }
+}
+
+pub fn transduce(list: &[T]) -> Result<T, Error> {
+    let (xform, f, init, coll) = match list {
+        [xform, f, coll] => {
+            let f = f.clone().eval()?;
+            let init = apply(&f, &[])?;
+            (xform, f, init, coll)
+        }
+        [xform, f, init, coll] => (xform, f.clone().eval()?, init.clone().eval()?, coll),
+        _ => {
+            return Err(Error::arity(
+                3,
+                format!(
+                    "`transduce` has arity of 3 or 4 but received {}",
+                    list.len()
+                ),
+            ))
+        }
+    };
+    let xform = xform_of(xform.clone().eval()?)?;
+
+    let mut acc = init;
+    run(&xform, coll.clone().eval()?, &mut |item| {
+        acc = apply(&f, &[std::mem::replace(&mut acc, T::Nil), item])?;
+        Ok(())
+    })?;
+    apply(&f, &[acc])
+}
+
+pub fn into(list: &[T]) -> Result<T, Error> {
+    match list {
+        [to, from] => items(from.clone().eval()?)?
+            .into_iter()
+            .try_fold(to.clone().eval()?, conj_one),
+        [to, xform, from] => {
+            let xform = xform_of(xform.clone().eval()?)?;
+            let mut acc = to.clone().eval()?;
+            run(&xform, from.clone().eval()?, &mut |item| {
+                acc = conj_one(std::mem::replace(&mut acc, T::Nil), item)?;
+                Ok(())
+            })?;
+            Ok(acc)
+        }
+        _ => Err(Error::arity(
+            2,
+            format!("`into` has arity of 2 or 3 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn sequence(list: &[T]) -> Result<T, Error> {
+    match list {
+        [coll] => Ok(T::seq(items(coll.clone().eval()?)?)),
+        [xform, coll] => collect(&xform_of(xform.clone().eval()?)?, coll.clone().eval()?),
+        _ => Err(Error::arity(
+            2,
+            format!("`sequence` has arity of 1 or 2 but received {}", list.len()),
+        )),
+    }
+}
+
+pub fn eduction(list: &[T]) -> Result<T, Error> {
+    if let Some((coll, xforms)) = list.split_last() {
+        let xform = xforms
+            .iter()
+            .map(|x| xform_of(x.clone().eval()?))
+            .collect::<Result<Vec<_>, Error>>()?
+            .concat();
+        collect(&xform, coll.clone().eval()?)
+    } else {
+        Err(Error::arity(
+            1,
+            String::from("`eduction` has arity of at least 1 but received 0"),
+        ))
+    }
+}
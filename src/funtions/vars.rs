@@ -0,0 +1,262 @@
+use std::{collections::HashMap, sync::RwLockWriteGuard};
+
+use im::HashMap as Hamt;
+use num_traits::ToPrimitive;
+
+use crate::{definitions::DefinitionTypes as T, env::current_env, error::Error, BINDINGS};
+
+use super::{
+    apply, arity,
+    namespaces::{resolve_native, resolve_var, split_qualified, CORE},
+    special_forms::{binding_pairs, eval_body},
+};
+
+/// The dynamic bindings in effect on this thread, by `ns/name`.
+pub(crate) fn current_bindings() -> Hamt<String, T> {
+    BINDINGS.with(|frame| frame.borrow().clone())
+}
+
+/// Runs `f` with `frame` as the dynamic bindings, restoring the previous ones
+/// afterwards. Futures, agents and go blocks run with the frame they were
+/// started from.
+pub(crate) fn with_bindings_frame<R>(frame: Hamt<String, T>, f: impl FnOnce() -> R) -> R {
+    let previous = BINDINGS.with(|current| current.replace(frame));
+    let result = f();
+    BINDINGS.with(|current| current.replace(previous));
+    result
+}
+
+/// The value the var `key` is bound to on this thread, if it is.
+pub(crate) fn bound_value(key: &str) -> Option<T> {
+    BINDINGS.with(|frame| frame.borrow().get(key).cloned())
+}
+
+/// The thread binding of the var `key`, or its root value.
+pub(crate) fn var_value(key: &str) -> Option<T> {
+    bound_value(key).or_else(|| current_env().data.read().ok()?.get(key).cloned())
+}
+
+/// `*print-length*`, how many items of a collection printing shows, or none to show all.
+pub(crate) fn print_length() -> Option<usize> {
+    match var_value("clojure.core/*print-length*")? {
+        T::Int(n) => n.to_usize(),
+        _ => None,
+    }
+}
+
+fn metadata() -> Result<RwLockWriteGuard<'static, Hamt<String, T>>, Error> {
+    current_env()
+        .metadata
+        .write()
+        .map_err(|_| Error::reason("Var metadata is poisoned"))
+}
+
+/// The metadata the var `key` was defined with, without its `:ns` and `:name`.
+pub(crate) fn stored_meta(key: &str) -> HashMap<T, T> {
+    match metadata()
+        .ok()
+        .and_then(|metadata| metadata.get(key).cloned())
+    {
+        Some(T::HashMap(map, _)) => map,
+        _ => HashMap::new(),
+    }
+}
+
+pub(crate) fn set_stored_meta(key: &str, meta: HashMap<T, T>) -> Result<(), Error> {
+    metadata()?.insert(key.to_owned(), T::hash_map(meta));
+    Ok(())
+}
+
+/// The metadata of the var `key`, as `meta` returns it.
+pub(crate) fn var_meta(key: &str) -> T {
+    let mut meta = stored_meta(key);
+    if let Some((ns, name)) = split_qualified(key) {
+        meta.insert(T::Keyword(String::from(":ns")), T::symbol(ns));
+        meta.insert(T::Keyword(String::from(":name")), T::symbol(name));
+    }
+    T::hash_map(meta)
+}
+
+/// Replaces the metadata of the var `key` with `f` of it, retrying if another
+/// thread changed it meanwhile.
+pub(crate) fn alter_var_meta(key: &str, f: impl Fn(T) -> Result<T, Error>) -> Result<T, Error> {
+    loop {
+        let current = metadata()?.get(key).cloned();
+        let meta = match f(var_meta(key))? {
+            T::HashMap(mut map, _) => {
+                map.remove(&T::Keyword(String::from(":ns")));
+                map.remove(&T::Keyword(String::from(":name")));
+                map
+            }
+            T::Nil => HashMap::new(),
+            meta => {
+                return Err(Error::cant_eval(format!(
+                    "Metadata must be a map but received {}",
+                    meta.print()?
+                )))
+            }
+        };
+        let mut metadata = metadata()?;
+        if metadata.get(key) == current.as_ref() {
+            metadata.insert(key.to_owned(), T::hash_map(meta));
+            drop(metadata);
+            return Ok(var_meta(key));
+        }
+    }
+}
+
+fn eval_all(list: &[T]) -> Result<Vec<T>, Error> {
+    list.iter().map(|arg| arg.clone().eval()).collect()
+}
+
+fn unresolved(name: &str) -> Error {
+    Error::illegal_state(format!("Unable to resolve var: {} in this context", name))
+}
+
+/// The `ns/name` key of a var, as returned by `var` and printed `#'ns/name`.
+pub(crate) fn as_var(value: &T) -> Option<&str> {
+    match value {
+        T::Symbol(var, _) => var.strip_prefix("#'"),
+        _ => None,
+    }
+}
+
+fn var_key(name: &str, var: &T) -> Result<String, Error> {
+    match as_var(var) {
+        Some(key) => Ok(key.to_owned()),
+        None => Err(Error::cant_eval(format!(
+            "`{}` expects a var but received {}",
+            name,
+            var.print()?
+        ))),
+    }
+}
+
+fn expect_dynamic(key: String) -> Result<String, Error> {
+    let dynamic = stored_meta(&key)
+        .get(&T::Keyword(String::from(":dynamic")))
+        .is_some_and(T::is_truthy);
+    if dynamic {
+        Ok(key)
+    } else {
+        Err(Error::illegal_state(format!(
+            "Can't dynamically bind non-dynamic var: {}",
+            key
+        )))
+    }
+}
+
+/// `(var sym)`, also read as `#'sym`.
+pub fn var(list: &[T]) -> Result<T, Error> {
+    match list {
+        [T::Symbol(symbol, _)] => match (resolve_var(symbol), resolve_native(symbol)) {
+            (Some(key), _) => Ok(T::symbol(format!("#'{}", key))),
+            (None, Some((name, _))) => Ok(T::symbol(format!("#'{}/{}", CORE, name))),
+            (None, None) => Err(unresolved(symbol)),
+        },
+        [form] => Err(Error::cant_eval(format!(
+            "`var` expects a symbol but received {}",
+            form.print()?
+        ))),
+        _ => Err(arity("var", 1, "1", list)),
+    }
+}
+
+/// `(binding [var value ...] body*)` evaluates `body` with dynamic vars
+/// rebound on this thread. The values are evaluated before any is bound.
+pub fn binding(list: &[T]) -> Result<T, Error> {
+    let (bindings, body) = list.split_first().ok_or_else(|| {
+        Error::cant_eval(Some(String::from("`binding` requires a binding vector")))
+    })?;
+    let mut frame = current_bindings();
+    for pair in binding_pairs("binding", bindings)?.chunks(2) {
+        let key = match &pair[0] {
+            T::Symbol(symbol, _) => resolve_var(symbol).ok_or_else(|| unresolved(symbol))?,
+            form => {
+                return Err(Error::cant_eval(format!(
+                    "`binding` expects symbols but received {}",
+                    form.print()?
+                )))
+            }
+        };
+        frame.insert(expect_dynamic(key)?, pair[1].clone().eval()?);
+    }
+    with_bindings_frame(frame, || eval_body(body))
+}
+
+/// `(with-bindings {#'var value ...} body*)`, like `binding` with the vars
+/// given as a map.
+pub fn with_bindings(list: &[T]) -> Result<T, Error> {
+    let (bindings, body) = list
+        .split_first()
+        .ok_or_else(|| arity("with-bindings", 1, "at least 1", list))?;
+    let bindings = match bindings.clone().eval()? {
+        T::HashMap(map, _) => map.into_iter().collect::<Vec<(T, T)>>(),
+        T::OrderedMap(map) => map.into_iter().collect(),
+        T::Nil => Vec::new(),
+        value => {
+            return Err(Error::cant_eval(format!(
+                "`with-bindings` expects a map of vars but received {}",
+                value.print()?
+            )))
+        }
+    };
+    let mut frame = current_bindings();
+    for (var, value) in bindings {
+        frame.insert(expect_dynamic(var_key("with-bindings", &var)?)?, value);
+    }
+    with_bindings_frame(frame, || eval_body(body))
+}
+
+/// `(set! var value)` changes the innermost thread binding of `var`.
+pub fn set(list: &[T]) -> Result<T, Error> {
+    match list {
+        [T::Symbol(symbol, _), value] => {
+            let key = resolve_var(symbol).ok_or_else(|| unresolved(symbol))?;
+            if bound_value(&key).is_none() {
+                return Err(Error::illegal_state(format!(
+                    "Can't change/establish root binding of: {} with set",
+                    symbol
+                )));
+            }
+            let value = value.clone().eval()?;
+            BINDINGS.with(|frame| frame.borrow_mut().insert(key, value.clone()));
+            Ok(value)
+        }
+        [_, _] => Err(Error::cant_eval(Some(String::from(
+            "`set!` expects a var name",
+        )))),
+        _ => Err(arity("set!", 2, "2", list)),
+    }
+}
+
+/// `(alter-var-root var f & args)` sets the root value of `var` to
+/// `(apply f root args)`, retrying if another thread changed it meanwhile.
+pub fn alter_var_root(list: &[T]) -> Result<T, Error> {
+    let mut args = eval_all(list)?;
+    if args.len() < 2 {
+        return Err(arity("alter-var-root", 2, "at least 2", list));
+    }
+    let key = var_key("alter-var-root", &args.remove(0))?;
+    let f = args.remove(0);
+    loop {
+        let root = current_env()
+            .data
+            .read()
+            .map_err(|_| Error::reason("Global definitions are poisoned"))?
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| Error::illegal_state(format!("Var {} has no root value", key)))?;
+        let mut call = vec![root.clone()];
+        call.extend(args.iter().cloned());
+        let value = apply(&f, &call)?;
+        let mut data = current_env()
+            .data
+            .write()
+            .map_err(|_| Error::reason("Global definitions are poisoned"))?;
+        if data.get(&key) == Some(&root) {
+            data.insert(key, value.clone());
+            return Ok(value);
+        }
+    }
+}
@@ -2,29 +2,86 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::sync::Mutex;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    sync::{atomic::AtomicBool, mpsc::Sender, Arc, Mutex},
+};
 
 use definitions::DefinitionTypes as T;
+use env::{current_env, Env};
 use error::Error;
 use funtions::Func;
-use helper::MaybeReplaceExt;
-use parser::{parse, tokenize};
+use parser::{parse, read_forms, tokenize};
 
 use im::{hashmap, HashMap as Hamt};
 
 use crate::funtions::{
-    logic::{eq, ge, greater, is_false, is_nil, is_true, le, lesser},
-    math::{div, is_negative, is_numeric, is_positive, mul, plus, sub},
+    apply_fn,
+    atoms::{
+        add_watch, atom, compare_and_set, get_validator, remove_watch, reset, reset_vals,
+        set_validator, swap, swap_vals,
+    },
+    channels::{
+        alts_blocking, alts_parking, buffer, chan, close, dropping_buffer, go_call, mult, pipeline,
+        publication, put_async, put_blocking, put_parking, sliding_buffer, subscribe, take_async,
+        take_blocking, take_parking, tap_mult, thread_call, timeout, unsubscribe, unsubscribe_all,
+        untap, untap_all,
+    },
+    collections::{
+        concat, conj, cons, count, first, get, hash_map, is_empty, is_seq, list, next, nth,
+        nthnext, rest, second, seq, vector,
+    },
+    concurrency::{
+        agent, agent_error, await_agents, await_for, deliver, deref, future, future_call,
+        is_future_done, is_realized, pcalls, pmap, promise, send, send_off, shutdown_agents,
+    },
+    exceptions::{ex_cause, ex_data, ex_info, ex_message, try_form},
+    loading::{default_source_paths, load, load_file, Library},
+    logic::{
+        and, boolean, eq, ge, greater, is_any, is_false, is_nil, is_some, is_string, is_symbol,
+        is_true, le, lesser, not, not_eq, or,
+    },
+    macros::{
+        defmacro, expand_all, gensym, macroexpand, macroexpand_1, macroexpand_all, syntax_quote,
+    },
+    math::{dec, div, inc, is_even, is_negative, is_numeric, is_odd, is_positive, mul, plus, sub},
     meaning_of_life,
-    std::{println, str, to_keyword},
+    metadata::{alter_meta, meta, reset_meta, vary_meta, with_meta},
+    multimethods::{
+        ancestors, defmethod, defmulti, derive, descendants, isa, make_hierarchy, methods, parents,
+        prefer_method, remove_method, underive,
+    },
+    namespaces::{alias, in_ns, ns, ns_publics, ns_resolve, refer, require, resolve, CORE},
+    protocols::{
+        defprotocol, defrecord, deftype, extend_protocol, extend_type, is_record, new, reify,
+        satisfies, type_of,
+    },
+    refs::{alter, commute, dosync, ensure, ref_form, ref_set},
+    repl::{apropos, dir, doc, find_doc, record_source, source, tap},
+    special_forms::{def, do_form, fn_form, if_form, let_form, loop_form, quote, recur},
+    std::{is_char, println, str, subs, to_char, to_int, to_keyword},
+    strings::{
+        capitalize, ends_with, escape, includes, index_of, is_blank, join, lower_case, replace,
+        replace_first, reverse, split, split_lines, starts_with, trim, triml, trimr, upper_case,
+    },
     throw,
+    transducers::{
+        comp, dedupe, drop, eduction, filter, into, keep, map, mapcat, partition_all, remove,
+        sequence, take, transduce,
+    },
+    vars::{alter_var_root, binding, set, var, with_bindings},
 };
 
 pub(crate) mod definitions;
+pub mod env;
 pub mod error;
 pub mod funtions;
-pub(crate) mod helper;
+pub mod nrepl;
 pub(crate) mod parser;
+pub mod socket_repl;
+
+pub use parser::is_balanced;
 
 lazy_static! {
     pub static ref STD: Hamt<String, Func> = hashmap! {
@@ -48,14 +105,244 @@ lazy_static! {
         String::from("str") => str as Func,
         String::from("println!") => println as Func,
         String::from("keyword") => to_keyword as Func,
+        String::from("subs") => subs as Func,
+        String::from("char") => to_char as Func,
+        String::from("int") => to_int as Func,
+        String::from("char?") => is_char as Func,
+        String::from("inc") => inc as Func,
+        String::from("dec") => dec as Func,
+        String::from("even?") => is_even as Func,
+        String::from("odd?") => is_odd as Func,
+        String::from("conj") => conj as Func,
+        String::from("get") => get as Func,
+        String::from("nth") => nth as Func,
+        String::from("nthnext") => nthnext as Func,
+        String::from("hash-map") => hash_map as Func,
+        String::from("seq?") => is_seq as Func,
+        String::from("apply") => apply_fn as Func,
+        String::from("fn") => fn_form as Func,
+        String::from("let") => let_form as Func,
+        String::from("loop") => loop_form as Func,
+        String::from("recur") => recur as Func,
+        String::from("if") => if_form as Func,
+        String::from("do") => do_form as Func,
+        String::from("quote") => quote as Func,
+        String::from("map") => map as Func,
+        String::from("filter") => filter as Func,
+        String::from("remove") => remove as Func,
+        String::from("take") => take as Func,
+        String::from("drop") => drop as Func,
+        String::from("partition-all") => partition_all as Func,
+        String::from("dedupe") => dedupe as Func,
+        String::from("mapcat") => mapcat as Func,
+        String::from("keep") => keep as Func,
+        String::from("comp") => comp as Func,
+        String::from("transduce") => transduce as Func,
+        String::from("into") => into as Func,
+        String::from("sequence") => sequence as Func,
+        String::from("eduction") => eduction as Func,
+        String::from("def") => def as Func,
+        String::from("defmacro") => defmacro as Func,
+        String::from("var") => var as Func,
+        String::from("binding") => binding as Func,
+        String::from("with-bindings") => with_bindings as Func,
+        String::from("set!") => set as Func,
+        String::from("alter-var-root") => alter_var_root as Func,
+        String::from("meta") => meta as Func,
+        String::from("with-meta") => with_meta as Func,
+        String::from("vary-meta") => vary_meta as Func,
+        String::from("alter-meta!") => alter_meta as Func,
+        String::from("reset-meta!") => reset_meta as Func,
+        String::from("macroexpand-1") => macroexpand_1 as Func,
+        String::from("macroexpand") => macroexpand as Func,
+        String::from("macroexpand-all") => macroexpand_all as Func,
+        String::from("gensym") => gensym as Func,
+        String::from("syntax-quote") => syntax_quote as Func,
+        String::from("list") => list as Func,
+        String::from("vector") => vector as Func,
+        String::from("cons") => cons as Func,
+        String::from("concat") => concat as Func,
+        String::from("first") => first as Func,
+        String::from("second") => second as Func,
+        String::from("next") => next as Func,
+        String::from("rest") => rest as Func,
+        String::from("seq") => seq as Func,
+        String::from("count") => count as Func,
+        String::from("empty?") => is_empty as Func,
+        String::from("string?") => is_string as Func,
+        String::from("symbol?") => is_symbol as Func,
+        String::from("and") => and as Func,
+        String::from("or") => or as Func,
+        String::from("not") => not as Func,
+        String::from("not=") => not_eq as Func,
+        String::from("boolean") => boolean as Func,
+        String::from("some?") => is_some as Func,
+        String::from("any?") => is_any as Func,
+        String::from("try") => try_form as Func,
+        String::from("ex-info") => ex_info as Func,
+        String::from("ex-data") => ex_data as Func,
+        String::from("ex-message") => ex_message as Func,
+        String::from("ex-cause") => ex_cause as Func,
+        String::from("ns") => ns as Func,
+        String::from("in-ns") => in_ns as Func,
+        String::from("require") => require as Func,
+        String::from("load-file") => load_file as Func,
+        String::from("load") => load as Func,
+        String::from("doc") => doc as Func,
+        String::from("source") => source as Func,
+        String::from("apropos") => apropos as Func,
+        String::from("find-doc") => find_doc as Func,
+        String::from("dir") => dir as Func,
+        String::from("tap>") => tap as Func,
+        String::from("atom") => atom as Func,
+        String::from("deref") => deref as Func,
+        String::from("swap!") => swap as Func,
+        String::from("swap-vals!") => swap_vals as Func,
+        String::from("reset!") => reset as Func,
+        String::from("reset-vals!") => reset_vals as Func,
+        String::from("compare-and-set!") => compare_and_set as Func,
+        String::from("set-validator!") => set_validator as Func,
+        String::from("get-validator") => get_validator as Func,
+        String::from("add-watch") => add_watch as Func,
+        String::from("remove-watch") => remove_watch as Func,
+        String::from("ref") => ref_form as Func,
+        String::from("dosync") => dosync as Func,
+        String::from("alter") => alter as Func,
+        String::from("commute") => commute as Func,
+        String::from("ref-set") => ref_set as Func,
+        String::from("ensure") => ensure as Func,
+        String::from("future") => future as Func,
+        String::from("future-call") => future_call as Func,
+        String::from("future-done?") => is_future_done as Func,
+        String::from("realized?") => is_realized as Func,
+        String::from("promise") => promise as Func,
+        String::from("deliver") => deliver as Func,
+        String::from("agent") => agent as Func,
+        String::from("send") => send as Func,
+        String::from("send-off") => send_off as Func,
+        String::from("await") => await_agents as Func,
+        String::from("await-for") => await_for as Func,
+        String::from("agent-error") => agent_error as Func,
+        String::from("shutdown-agents") => shutdown_agents as Func,
+        String::from("pmap") => pmap as Func,
+        String::from("pcalls") => pcalls as Func,
+        String::from("refer") => refer as Func,
+        String::from("alias") => alias as Func,
+        String::from("ns-publics") => ns_publics as Func,
+        String::from("ns-resolve") => ns_resolve as Func,
+        String::from("resolve") => resolve as Func,
+        String::from("defprotocol") => defprotocol as Func,
+        String::from("extend-type") => extend_type as Func,
+        String::from("extend-protocol") => extend_protocol as Func,
+        String::from("satisfies?") => satisfies as Func,
+        String::from("defrecord") => defrecord as Func,
+        String::from("deftype") => deftype as Func,
+        String::from("new") => new as Func,
+        String::from("reify") => reify as Func,
+        String::from("type") => type_of as Func,
+        String::from("record?") => is_record as Func,
+        String::from("defmulti") => defmulti as Func,
+        String::from("defmethod") => defmethod as Func,
+        String::from("remove-method") => remove_method as Func,
+        String::from("prefer-method") => prefer_method as Func,
+        String::from("methods") => methods as Func,
+        String::from("make-hierarchy") => make_hierarchy as Func,
+        String::from("derive") => derive as Func,
+        String::from("underive") => underive as Func,
+        String::from("isa?") => isa as Func,
+        String::from("parents") => parents as Func,
+        String::from("ancestors") => ancestors as Func,
+        String::from("descendants") => descendants as Func,
+    };
+    pub static ref LIBRARIES: Hamt<String, Library> = hashmap! {
+        String::from("clojure.core.async") => Library {
+            natives: hashmap! {
+                String::from("chan") => chan as Func,
+                String::from("buffer") => buffer as Func,
+                String::from("dropping-buffer") => dropping_buffer as Func,
+                String::from("sliding-buffer") => sliding_buffer as Func,
+                String::from("close!") => close as Func,
+                String::from(">!!") => put_blocking as Func,
+                String::from("<!!") => take_blocking as Func,
+                String::from(">!") => put_parking as Func,
+                String::from("<!") => take_parking as Func,
+                String::from("put!") => put_async as Func,
+                String::from("take!") => take_async as Func,
+                String::from("alts!!") => alts_blocking as Func,
+                String::from("alts!") => alts_parking as Func,
+                String::from("timeout") => timeout as Func,
+                String::from("go-call") => go_call as Func,
+                String::from("thread-call") => thread_call as Func,
+                String::from("pipeline") => pipeline as Func,
+                String::from("mult") => mult as Func,
+                String::from("tap") => tap_mult as Func,
+                String::from("untap") => untap as Func,
+                String::from("untap-all") => untap_all as Func,
+                String::from("pub") => publication as Func,
+                String::from("sub") => subscribe as Func,
+                String::from("unsub") => unsubscribe as Func,
+                String::from("unsub-all") => unsubscribe_all as Func,
+            },
+            source: include_str!("async.clj"),
+        },
+        String::from("clojure.string") => Library {
+            natives: hashmap! {
+                String::from("join") => join as Func,
+                String::from("split") => split as Func,
+                String::from("split-lines") => split_lines as Func,
+                String::from("trim") => trim as Func,
+                String::from("triml") => triml as Func,
+                String::from("trimr") => trimr as Func,
+                String::from("upper-case") => upper_case as Func,
+                String::from("lower-case") => lower_case as Func,
+                String::from("capitalize") => capitalize as Func,
+                String::from("replace") => replace as Func,
+                String::from("replace-first") => replace_first as Func,
+                String::from("starts-with?") => starts_with as Func,
+                String::from("ends-with?") => ends_with as Func,
+                String::from("includes?") => includes as Func,
+                String::from("index-of") => index_of as Func,
+                String::from("blank?") => is_blank as Func,
+                String::from("reverse") => reverse as Func,
+                String::from("escape") => escape as Func,
+            },
+            source: include_str!("string.clj"),
+        },
     };
     pub static ref LOCAL: Mutex<Hamt<String, Func>> = Mutex::new(Hamt::new());
-    pub static ref DATA: Mutex<Hamt<String, T>> = Mutex::new(Hamt::new());
+    pub static ref SOURCE_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(default_source_paths());
+    pub static ref TAPS: Mutex<Vec<Sender<String>>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    pub static SCOPE: RefCell<Hamt<String, T>> = RefCell::new(Hamt::new());
+    pub static BINDINGS: RefCell<Hamt<String, T>> = RefCell::new(Hamt::new());
+    pub static CURRENT_NS: RefCell<String> = RefCell::new(String::from("user"));
+    pub static OUTPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+    pub static INTERRUPT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+    pub static DEPTH: Cell<usize> = const { Cell::new(0) };
+    pub static ENV: Cell<&'static Env> = Cell::new(Env::root());
 }
 
 pub fn read(list: &str) -> Result<String, Error> {
-    let clean = String::from(list.maybe_replace("#{", "@").trim_start());
-    let mut tokens = tokenize(&clean);
+    bootstrap();
+    let mut tokens = tokenize(list.trim_start());
     let parsed = parse(tokens.next(), &mut tokens)?;
-    parsed.print()
+    expand_all(parsed)?.print()
+}
+
+/// Loads the macros and functions defined in `core.clj` the first time a form
+/// is read in the current environment.
+pub(crate) fn bootstrap() {
+    current_env().bootstrap.call_once(|| {
+        let previous = CURRENT_NS.with(|ns| ns.replace(String::from(CORE)));
+        for form in read_forms(include_str!("core.clj")) {
+            let (form, text) = form.expect("core.clj should parse");
+            record_source(&form, &text);
+            expand_all(form)
+                .and_then(T::eval)
+                .expect("core.clj should load");
+        }
+        CURRENT_NS.with(|ns| ns.replace(previous));
+    });
 }
@@ -2,28 +2,96 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::{io::Write, sync::Mutex};
+use std::{
+    cell::{Cell, RefCell},
+    io::{IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, mpsc::Sender, Arc, Mutex},
+};
 
 use definitions::DefinitionTypes as T;
+use env::{current_env, Env};
 use error::Error;
-use helper::MaybeReplaceExt;
-use parser::{parse, tokenize};
+use nrepl::Server;
+use parser::{is_balanced, read_forms};
+use socket_repl::{Protocol, SocketServer};
 
 use im::{hashmap, HashMap as Hamt};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    validate::Validator, Context, Editor, Helper,
+};
 
 use crate::funtions::{
-    logic::{eq, ge, greater, is_false, is_nil, is_true, le, lesser},
-    math::{div, is_negative, is_numeric, is_positive, mul, plus, sub},
+    apply_fn,
+    atoms::{
+        add_watch, atom, compare_and_set, get_validator, remove_watch, reset, reset_vals,
+        set_validator, swap, swap_vals,
+    },
+    channels::{
+        alts_blocking, alts_parking, buffer, chan, close, dropping_buffer, go_call, mult, pipeline,
+        publication, put_async, put_blocking, put_parking, sliding_buffer, subscribe, take_async,
+        take_blocking, take_parking, tap_mult, thread_call, timeout, unsubscribe, unsubscribe_all,
+        untap, untap_all,
+    },
+    collections::{
+        concat, conj, cons, count, first, get, hash_map, is_empty, is_seq, list, next, nth,
+        nthnext, rest, second, seq, vector,
+    },
+    concurrency::{
+        agent, agent_error, await_agents, await_for, deliver, deref, future, future_call,
+        is_future_done, is_realized, pcalls, pmap, promise, send, send_off, shutdown_agents,
+    },
+    exceptions::{ex_cause, ex_data, ex_info, ex_message, try_form},
+    loading::{
+        default_source_paths, eval_file, eval_source, load, load_file, run_main,
+        set_command_line_args, set_source_paths, Library,
+    },
+    logic::{
+        and, boolean, eq, ge, greater, is_any, is_false, is_nil, is_some, is_string, is_symbol,
+        is_true, le, lesser, not, not_eq, or,
+    },
+    macros::{
+        defmacro, expand_all, gensym, macroexpand, macroexpand_1, macroexpand_all, syntax_quote,
+    },
+    math::{dec, div, inc, is_even, is_negative, is_numeric, is_odd, is_positive, mul, plus, sub},
     meaning_of_life,
-    std::{println, str, to_keyword},
-    throw, Func,
+    metadata::{alter_meta, meta, reset_meta, vary_meta, with_meta},
+    multimethods::{
+        ancestors, defmethod, defmulti, derive, descendants, isa, make_hierarchy, methods, parents,
+        prefer_method, remove_method, underive,
+    },
+    namespaces::{
+        alias, completions, in_ns, ns, ns_publics, ns_resolve, refer, require, resolve, CORE,
+    },
+    protocols::{
+        defprotocol, defrecord, deftype, extend_protocol, extend_type, is_record, new, reify,
+        satisfies, type_of,
+    },
+    refs::{alter, commute, dosync, ensure, ref_form, ref_set},
+    repl::{apropos, dir, doc, find_doc, record_error, record_result, record_source, source, tap},
+    special_forms::{def, do_form, fn_form, if_form, let_form, loop_form, quote, recur},
+    std::{is_char, println, str, subs, to_char, to_int, to_keyword},
+    strings::{
+        capitalize, ends_with, escape, includes, index_of, is_blank, join, lower_case, replace,
+        replace_first, reverse, split, split_lines, starts_with, trim, triml, trimr, upper_case,
+    },
+    throw,
+    transducers::{
+        comp, dedupe, drop, eduction, filter, into, keep, map, mapcat, partition_all, remove,
+        sequence, take, transduce,
+    },
+    vars::{alter_var_root, binding, set, var, with_bindings},
+    Func,
 };
 
 pub(crate) mod definitions;
+pub mod env;
 pub mod error;
 pub mod funtions;
-pub(crate) mod helper;
+pub mod nrepl;
 pub(crate) mod parser;
+pub mod socket_repl;
 
 lazy_static! {
     pub static ref STD: Hamt<String, Func> = hashmap! {
@@ -47,37 +115,506 @@ lazy_static! {
         String::from("str") => str as Func,
         String::from("println!") => println as Func,
         String::from("keyword") => to_keyword as Func,
+        String::from("subs") => subs as Func,
+        String::from("char") => to_char as Func,
+        String::from("int") => to_int as Func,
+        String::from("char?") => is_char as Func,
+        String::from("inc") => inc as Func,
+        String::from("dec") => dec as Func,
+        String::from("even?") => is_even as Func,
+        String::from("odd?") => is_odd as Func,
+        String::from("conj") => conj as Func,
+        String::from("get") => get as Func,
+        String::from("nth") => nth as Func,
+        String::from("nthnext") => nthnext as Func,
+        String::from("hash-map") => hash_map as Func,
+        String::from("seq?") => is_seq as Func,
+        String::from("apply") => apply_fn as Func,
+        String::from("fn") => fn_form as Func,
+        String::from("let") => let_form as Func,
+        String::from("loop") => loop_form as Func,
+        String::from("recur") => recur as Func,
+        String::from("if") => if_form as Func,
+        String::from("do") => do_form as Func,
+        String::from("quote") => quote as Func,
+        String::from("map") => map as Func,
+        String::from("filter") => filter as Func,
+        String::from("remove") => remove as Func,
+        String::from("take") => take as Func,
+        String::from("drop") => drop as Func,
+        String::from("partition-all") => partition_all as Func,
+        String::from("dedupe") => dedupe as Func,
+        String::from("mapcat") => mapcat as Func,
+        String::from("keep") => keep as Func,
+        String::from("comp") => comp as Func,
+        String::from("transduce") => transduce as Func,
+        String::from("into") => into as Func,
+        String::from("sequence") => sequence as Func,
+        String::from("eduction") => eduction as Func,
+        String::from("def") => def as Func,
+        String::from("defmacro") => defmacro as Func,
+        String::from("var") => var as Func,
+        String::from("binding") => binding as Func,
+        String::from("with-bindings") => with_bindings as Func,
+        String::from("set!") => set as Func,
+        String::from("alter-var-root") => alter_var_root as Func,
+        String::from("meta") => meta as Func,
+        String::from("with-meta") => with_meta as Func,
+        String::from("vary-meta") => vary_meta as Func,
+        String::from("alter-meta!") => alter_meta as Func,
+        String::from("reset-meta!") => reset_meta as Func,
+        String::from("macroexpand-1") => macroexpand_1 as Func,
+        String::from("macroexpand") => macroexpand as Func,
+        String::from("macroexpand-all") => macroexpand_all as Func,
+        String::from("gensym") => gensym as Func,
+        String::from("syntax-quote") => syntax_quote as Func,
+        String::from("list") => list as Func,
+        String::from("vector") => vector as Func,
+        String::from("cons") => cons as Func,
+        String::from("concat") => concat as Func,
+        String::from("first") => first as Func,
+        String::from("second") => second as Func,
+        String::from("next") => next as Func,
+        String::from("rest") => rest as Func,
+        String::from("seq") => seq as Func,
+        String::from("count") => count as Func,
+        String::from("empty?") => is_empty as Func,
+        String::from("string?") => is_string as Func,
+        String::from("symbol?") => is_symbol as Func,
+        String::from("and") => and as Func,
+        String::from("or") => or as Func,
+        String::from("not") => not as Func,
+        String::from("not=") => not_eq as Func,
+        String::from("boolean") => boolean as Func,
+        String::from("some?") => is_some as Func,
+        String::from("any?") => is_any as Func,
+        String::from("try") => try_form as Func,
+        String::from("ex-info") => ex_info as Func,
+        String::from("ex-data") => ex_data as Func,
+        String::from("ex-message") => ex_message as Func,
+        String::from("ex-cause") => ex_cause as Func,
+        String::from("ns") => ns as Func,
+        String::from("in-ns") => in_ns as Func,
+        String::from("require") => require as Func,
+        String::from("load-file") => load_file as Func,
+        String::from("load") => load as Func,
+        String::from("doc") => doc as Func,
+        String::from("source") => source as Func,
+        String::from("apropos") => apropos as Func,
+        String::from("find-doc") => find_doc as Func,
+        String::from("dir") => dir as Func,
+        String::from("tap>") => tap as Func,
+        String::from("atom") => atom as Func,
+        String::from("deref") => deref as Func,
+        String::from("swap!") => swap as Func,
+        String::from("swap-vals!") => swap_vals as Func,
+        String::from("reset!") => reset as Func,
+        String::from("reset-vals!") => reset_vals as Func,
+        String::from("compare-and-set!") => compare_and_set as Func,
+        String::from("set-validator!") => set_validator as Func,
+        String::from("get-validator") => get_validator as Func,
+        String::from("add-watch") => add_watch as Func,
+        String::from("remove-watch") => remove_watch as Func,
+        String::from("ref") => ref_form as Func,
+        String::from("dosync") => dosync as Func,
+        String::from("alter") => alter as Func,
+        String::from("commute") => commute as Func,
+        String::from("ref-set") => ref_set as Func,
+        String::from("ensure") => ensure as Func,
+        String::from("future") => future as Func,
+        String::from("future-call") => future_call as Func,
+        String::from("future-done?") => is_future_done as Func,
+        String::from("realized?") => is_realized as Func,
+        String::from("promise") => promise as Func,
+        String::from("deliver") => deliver as Func,
+        String::from("agent") => agent as Func,
+        String::from("send") => send as Func,
+        String::from("send-off") => send_off as Func,
+        String::from("await") => await_agents as Func,
+        String::from("await-for") => await_for as Func,
+        String::from("agent-error") => agent_error as Func,
+        String::from("shutdown-agents") => shutdown_agents as Func,
+        String::from("pmap") => pmap as Func,
+        String::from("pcalls") => pcalls as Func,
+        String::from("refer") => refer as Func,
+        String::from("alias") => alias as Func,
+        String::from("ns-publics") => ns_publics as Func,
+        String::from("ns-resolve") => ns_resolve as Func,
+        String::from("resolve") => resolve as Func,
+        String::from("defprotocol") => defprotocol as Func,
+        String::from("extend-type") => extend_type as Func,
+        String::from("extend-protocol") => extend_protocol as Func,
+        String::from("satisfies?") => satisfies as Func,
+        String::from("defrecord") => defrecord as Func,
+        String::from("deftype") => deftype as Func,
+        String::from("new") => new as Func,
+        String::from("reify") => reify as Func,
+        String::from("type") => type_of as Func,
+        String::from("record?") => is_record as Func,
+        String::from("defmulti") => defmulti as Func,
+        String::from("defmethod") => defmethod as Func,
+        String::from("remove-method") => remove_method as Func,
+        String::from("prefer-method") => prefer_method as Func,
+        String::from("methods") => methods as Func,
+        String::from("make-hierarchy") => make_hierarchy as Func,
+        String::from("derive") => derive as Func,
+        String::from("underive") => underive as Func,
+        String::from("isa?") => isa as Func,
+        String::from("parents") => parents as Func,
+        String::from("ancestors") => ancestors as Func,
+        String::from("descendants") => descendants as Func,
         // Issue 10
     };
+    pub static ref LIBRARIES: Hamt<String, Library> = hashmap! {
+        String::from("clojure.core.async") => Library {
+            natives: hashmap! {
+                String::from("chan") => chan as Func,
+                String::from("buffer") => buffer as Func,
+                String::from("dropping-buffer") => dropping_buffer as Func,
+                String::from("sliding-buffer") => sliding_buffer as Func,
+                String::from("close!") => close as Func,
+                String::from(">!!") => put_blocking as Func,
+                String::from("<!!") => take_blocking as Func,
+                String::from(">!") => put_parking as Func,
+                String::from("<!") => take_parking as Func,
+                String::from("put!") => put_async as Func,
+                String::from("take!") => take_async as Func,
+                String::from("alts!!") => alts_blocking as Func,
+                String::from("alts!") => alts_parking as Func,
+                String::from("timeout") => timeout as Func,
+                String::from("go-call") => go_call as Func,
+                String::from("thread-call") => thread_call as Func,
+                String::from("pipeline") => pipeline as Func,
+                String::from("mult") => mult as Func,
+                String::from("tap") => tap_mult as Func,
+                String::from("untap") => untap as Func,
+                String::from("untap-all") => untap_all as Func,
+                String::from("pub") => publication as Func,
+                String::from("sub") => subscribe as Func,
+                String::from("unsub") => unsubscribe as Func,
+                String::from("unsub-all") => unsubscribe_all as Func,
+            },
+            source: include_str!("async.clj"),
+        },
+        String::from("clojure.string") => Library {
+            natives: hashmap! {
+                String::from("join") => join as Func,
+                String::from("split") => split as Func,
+                String::from("split-lines") => split_lines as Func,
+                String::from("trim") => trim as Func,
+                String::from("triml") => triml as Func,
+                String::from("trimr") => trimr as Func,
+                String::from("upper-case") => upper_case as Func,
+                String::from("lower-case") => lower_case as Func,
+                String::from("capitalize") => capitalize as Func,
+                String::from("replace") => replace as Func,
+                String::from("replace-first") => replace_first as Func,
+                String::from("starts-with?") => starts_with as Func,
+                String::from("ends-with?") => ends_with as Func,
+                String::from("includes?") => includes as Func,
+                String::from("index-of") => index_of as Func,
+                String::from("blank?") => is_blank as Func,
+                String::from("reverse") => reverse as Func,
+                String::from("escape") => escape as Func,
+            },
+            source: include_str!("string.clj"),
+        },
+    };
     pub static ref LOCAL: Mutex<Hamt<String, Func>> = Mutex::new(Hamt::new());
-    pub static ref DATA: Mutex<Hamt<String, T>> = Mutex::new(Hamt::new());
+    pub static ref SOURCE_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(default_source_paths());
+    pub static ref TAPS: Mutex<Vec<Sender<String>>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    pub static SCOPE: RefCell<Hamt<String, T>> = RefCell::new(Hamt::new());
+    pub static BINDINGS: RefCell<Hamt<String, T>> = RefCell::new(Hamt::new());
+    pub static CURRENT_NS: RefCell<String> = RefCell::new(String::from("user"));
+    pub static OUTPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+    pub static INTERRUPT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+    pub static DEPTH: Cell<usize> = const { Cell::new(0) };
+    pub static ENV: Cell<&'static Env> = Cell::new(Env::root());
 }
 
+const USAGE: &str = "Usage: clojurs [-cp roots] [--socket-repl host:port] [--prepl host:port]
+               [-e expr]... [-m ns | file | -] [args...]
+       clojurs [-cp roots] nrepl [--host host] [--port port]
+
+Socket servers run alongside the rest of the command, or on their own when
+nothing else is given.";
+
 fn main() {
-    loop {
-        print!("Crs > ");
-        std::io::stdout().flush().unwrap();
-        let expr = slurp_expr();
-        match read(&expr) {
-            Ok(resp) => println!("{}", resp),
-            Err(err) => println!("{:?}", err),
+    let mut args = std::env::args().skip(1);
+    let mut evaluated = false;
+    let mut servers = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "--source-path" => set_source_paths(&expect_value(&arg, args.next())),
+            "--socket-repl" | "--prepl" => {
+                let protocol = match arg.as_str() {
+                    "--socket-repl" => Protocol::Repl,
+                    _ => Protocol::Prepl,
+                };
+                let addr = expect_value(&arg, args.next());
+                servers.push(start_socket_server(&addr, protocol));
+            }
+            "-e" | "--eval" => {
+                let expr = expect_value(&arg, args.next());
+                bootstrap();
+                match run(eval_source(&expr)) {
+                    T::Nil => (),
+                    value => println!("{}", run(value.print())),
+                }
+                evaluated = true;
+            }
+            "-m" | "--main" => {
+                let ns = expect_value(&arg, args.next());
+                let args = args.collect::<Vec<String>>();
+                bootstrap();
+                set_command_line_args(&args);
+                run(run_main(&ns, &args));
+                return;
+            }
+            "nrepl" => {
+                serve_nrepl(args);
+                return;
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-" => {
+                let mut source = String::new();
+                if let Err(err) = std::io::stdin().read_to_string(&mut source) {
+                    exit_with(&format!("Could not read stdin: {}", err));
+                }
+                bootstrap();
+                set_command_line_args(&args.collect::<Vec<String>>());
+                run(eval_source(&source));
+                return;
+            }
+            option if option.starts_with('-') => {
+                exit_with(&format!("Unknown option {}\n{}", option, USAGE))
+            }
+            path => {
+                bootstrap();
+                set_command_line_args(&args.collect::<Vec<String>>());
+                run(eval_file(Path::new(path)));
+                return;
+            }
+        }
+    }
+
+    if !evaluated {
+        if servers.is_empty() {
+            repl();
+        }
+        for server in servers {
+            let _ = server.join();
+        }
+    }
+}
+
+/// Serves `protocol` clients on `addr` from a background thread.
+fn start_socket_server(addr: &str, protocol: Protocol) -> std::thread::JoinHandle<()> {
+    let server = run_io(SocketServer::bind(addr, protocol));
+    let port = run_io(server.port());
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    println!(
+        "{} server started on port {} on host {}",
+        protocol.name(),
+        port,
+        host
+    );
+    std::thread::spawn(move || run_io(server.run()))
+}
+
+/// Listens for nREPL clients until the process is stopped.
+fn serve_nrepl(mut args: impl Iterator<Item = String>) {
+    let mut host = String::from("127.0.0.1");
+    let mut port = 0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => host = expect_value(&arg, args.next()),
+            "--port" => {
+                port = expect_value(&arg, args.next())
+                    .parse()
+                    .unwrap_or_else(|err| exit_with(&format!("Invalid port: {}", err)))
+            }
+            option => exit_with(&format!("Unknown nrepl option {}\n{}", option, USAGE)),
         }
     }
+
+    let server = run_io(Server::bind((host.as_str(), port)));
+    let port = run_io(server.port());
+    if let Err(err) = std::env::current_dir().and_then(|dir| server.write_port_file(&dir)) {
+        eprintln!("Could not write {}: {}", nrepl::PORT_FILE, err);
+    }
+    println!(
+        "nREPL server started on port {} on host {} - nrepl://{}:{}",
+        port, host, host, port
+    );
+    run_io(server.run());
+}
+
+fn run_io<V>(result: std::io::Result<V>) -> V {
+    result.unwrap_or_else(|err| exit_with(&err.to_string()))
+}
+
+fn expect_value(option: &str, value: Option<String>) -> String {
+    value.unwrap_or_else(|| exit_with(&format!("{} expects a value\n{}", option, USAGE)))
 }
 
-fn slurp_expr() -> String {
+/// Uncaught errors end the process with their trace on stderr.
+fn run<V>(result: Result<V, Error>) -> V {
+    result.unwrap_or_else(|err| exit_with(&err.trace()))
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1)
+}
+
+const PROMPT: &str = "Crs > ";
+const CONTINUATION_PROMPT: &str = "  #_=> ";
+const HISTORY_FILE: &str = ".clojurs_history";
+
+/// Completes the symbol or keyword before the cursor.
+struct ReplHelper;
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .rfind(|c: char| c.is_whitespace() || "()[]{}\"'`~@,".contains(c))
+            .map_or(0, |i| i + 1);
+        Ok((start, completions(&line[start..pos])))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Line editing when stdin is a terminal, plain reads when it is piped.
+enum Input {
+    Editor(Box<Editor<ReplHelper>>),
+    Piped,
+}
+
+impl Input {
+    fn read_line(&mut self, prompt: &str) -> Result<String, ReadlineError> {
+        match self {
+            Input::Editor(editor) => editor.readline(prompt).map(|line| line + "\n"),
+            Input::Piped => {
+                print!("{}", prompt);
+                std::io::stdout().flush()?;
+                let mut line = String::new();
+                match std::io::stdin().read_line(&mut line)? {
+                    0 => Err(ReadlineError::Eof),
+                    _ => Ok(line),
+                }
+            }
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+fn repl() {
+    let mut input = if std::io::stdin().is_terminal() {
+        let mut editor = Editor::<ReplHelper>::new();
+        editor.set_helper(Some(ReplHelper));
+        if let Some(history) = history_path() {
+            // A missing history file just means a first session.
+            let _ = editor.load_history(&history);
+        }
+        Input::Editor(Box::new(editor))
+    } else {
+        Input::Piped
+    };
+
+    while let Some(source) = slurp_expr(&mut input) {
+        if let Input::Editor(editor) = &mut input {
+            editor.add_history_entry(source.trim_end());
+        }
+        eval_print(&source);
+    }
+    println!();
+
+    if let (Input::Editor(editor), Some(history)) = (&mut input, history_path()) {
+        if let Err(err) = editor.save_history(&history) {
+            eprintln!("Could not save history to {}: {}", history.display(), err);
+        }
+    }
+}
+
+/// Reads lines until every delimiter is closed, `None` once stdin is closed.
+/// Ctrl-C discards the form being entered.
+fn slurp_expr(input: &mut Input) -> Option<String> {
     let mut expr = String::new();
+    loop {
+        let prompt = if expr.trim().is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
 
-    std::io::stdin()
-        .read_line(&mut expr)
-        .expect("Failed to read line");
+        match input.read_line(prompt) {
+            Ok(line) => {
+                expr.push_str(&line);
+                if is_balanced(&expr) {
+                    return Some(expr);
+                }
+            }
+            Err(ReadlineError::Interrupted) => expr.clear(),
+            Err(_) => return None,
+        }
+    }
+}
 
-    expr
+/// Prints the value of every form in `source`, stopping at the first error.
+fn eval_print(source: &str) {
+    bootstrap();
+    let result: Result<(), Error> = read_forms(source).try_for_each(|form| {
+        let (form, text) = form?;
+        record_source(&form, &text);
+        let value = expand_all(form)?.eval()?;
+        println!("{}", value.print()?);
+        record_result(&value);
+        Ok(())
+    });
+    if let Err(err) = result {
+        println!("{}", err.trace());
+        record_error(&err);
+    }
 }
 
-fn read(list: &str) -> Result<String, Error> {
-    let clean = String::from(list.maybe_replace("#{", "@").trim_start());
-    let mut tokens = tokenize(&clean);
-    let parsed = parse(tokens.next(), &mut tokens)?;
-    parsed.print()
+/// Loads the macros and functions defined in `core.clj` the first time a form
+/// is read in the current environment.
+pub(crate) fn bootstrap() {
+    current_env().bootstrap.call_once(|| {
+        let previous = CURRENT_NS.with(|ns| ns.replace(String::from(CORE)));
+        for form in read_forms(include_str!("core.clj")) {
+            let (form, text) = form.expect("core.clj should parse");
+            record_source(&form, &text);
+            expand_all(form)
+                .and_then(T::eval)
+                .expect("core.clj should load");
+        }
+        CURRENT_NS.with(|ns| ns.replace(previous));
+    });
 }
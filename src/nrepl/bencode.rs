@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    io::{self, BufRead, ErrorKind, Read},
+};
+
+/// A value of the bencode encoding nREPL messages travel in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bencode {
+    Int(i64),
+    Str(String),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<String, Bencode>),
+}
+
+impl Bencode {
+    pub fn str(s: impl Into<String>) -> Self {
+        Bencode::Str(s.into())
+    }
+
+    pub fn list<S: Into<String>>(items: impl IntoIterator<Item = S>) -> Self {
+        Bencode::List(items.into_iter().map(Bencode::str).collect())
+    }
+
+    pub fn dict<'a>(entries: impl IntoIterator<Item = (&'a str, Bencode)>) -> Self {
+        Bencode::Dict(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Bencode::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The string stored under `key` when `self` is a dictionary.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self {
+            Bencode::Dict(dict) => dict.get(key)?.as_str(),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(i) => out.extend(format!("i{}e", i).bytes()),
+            Bencode::Str(s) => {
+                out.extend(format!("{}:", s.len()).bytes());
+                out.extend(s.bytes());
+            }
+            Bencode::List(items) => {
+                out.push(b'l');
+                items.iter().for_each(|item| item.encode_into(out));
+                out.push(b'e');
+            }
+            // `BTreeMap` keeps the keys sorted, as bencode requires.
+            Bencode::Dict(dict) => {
+                out.push(b'd');
+                for (key, value) in dict {
+                    Bencode::str(key.as_str()).encode_into(out);
+                    value.encode_into(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+
+    /// Reads the next value, or `None` when `reader` ends between values.
+    pub fn decode(reader: &mut impl BufRead) -> io::Result<Option<Bencode>> {
+        match peek(reader)? {
+            None => Ok(None),
+            Some(_) => decode_value(reader).map(Some),
+        }
+    }
+}
+
+/// The longest string a message may hold. The length comes from the client, so
+/// it is checked before anything is read for it.
+const MAX_STRING_LEN: usize = 64 * 1024 * 1024;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.into())
+}
+
+fn peek(reader: &mut impl BufRead) -> io::Result<Option<u8>> {
+    Ok(reader.fill_buf()?.first().copied())
+}
+
+/// Reads the digits up to `end`, consuming it.
+fn read_number(reader: &mut impl BufRead, end: u8) -> io::Result<i64> {
+    let mut digits = Vec::new();
+    reader.read_until(end, &mut digits)?;
+    if digits.pop() != Some(end) {
+        return Err(invalid("Unterminated bencode number"));
+    }
+    String::from_utf8_lossy(&digits)
+        .parse()
+        .map_err(|_| invalid("Invalid bencode number"))
+}
+
+fn decode_value(reader: &mut impl BufRead) -> io::Result<Bencode> {
+    match peek(reader)?.ok_or_else(|| invalid("Unexpected end of bencode input"))? {
+        b'i' => {
+            reader.consume(1);
+            Ok(Bencode::Int(read_number(reader, b'e')?))
+        }
+        b'l' => {
+            reader.consume(1);
+            let mut items = Vec::new();
+            while peek(reader)? != Some(b'e') {
+                items.push(decode_value(reader)?);
+            }
+            reader.consume(1);
+            Ok(Bencode::List(items))
+        }
+        b'd' => {
+            reader.consume(1);
+            let mut dict = BTreeMap::new();
+            while peek(reader)? != Some(b'e') {
+                let key = match decode_value(reader)? {
+                    Bencode::Str(key) => key,
+                    _ => return Err(invalid("Bencode dictionary keys must be strings")),
+                };
+                dict.insert(key, decode_value(reader)?);
+            }
+            reader.consume(1);
+            Ok(Bencode::Dict(dict))
+        }
+        b'0'..=b'9' => {
+            let len = usize::try_from(read_number(reader, b':')?)
+                .map_err(|_| invalid("Invalid bencode string length"))?;
+            if len > MAX_STRING_LEN {
+                return Err(invalid(format!(
+                    "Bencode string of {} bytes is too long",
+                    len
+                )));
+            }
+            // Grows with the bytes that actually arrive rather than the length claimed.
+            let mut bytes = Vec::new();
+            reader.by_ref().take(len as u64).read_to_end(&mut bytes)?;
+            if bytes.len() < len {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Unterminated bencode string",
+                ));
+            }
+            Ok(Bencode::Str(String::from_utf8_lossy(&bytes).into_owned()))
+        }
+        byte => {
+            reader.consume(1);
+            Err(invalid(format!(
+                "Unexpected bencode byte {:?}",
+                byte as char
+            )))
+        }
+    }
+}
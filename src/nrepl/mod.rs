@@ -0,0 +1,584 @@
+use std::{
+    collections::{
+        hash_map::{DefaultHasher, RandomState},
+        BTreeMap, HashMap,
+    },
+    fs,
+    hash::{BuildHasher, Hash, Hasher},
+    io::{self, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{channel, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use im::HashMap as Hamt;
+
+use crate::{
+    bootstrap,
+    definitions::DefinitionTypes as T,
+    env::{current_env, with_env},
+    error::{Error, ErrorKind},
+    funtions::{
+        capture_output,
+        macros::expand_all,
+        namespaces::{completions, current_ns, in_ns_named, ns_exists, with_ns},
+        repl::{push_result, record_source, var_doc},
+        set_interrupt_flag, with_scope, THREAD_STACK_SIZE,
+    },
+    parser::read_forms,
+};
+
+use self::bencode::Bencode;
+
+pub mod bencode;
+
+/// Name of the file editors read the port from.
+pub const PORT_FILE: &str = ".nrepl-port";
+
+const OPS: [&str; 8] = [
+    "clone",
+    "close",
+    "describe",
+    "eval",
+    "load-file",
+    "interrupt",
+    "completions",
+    "lookup",
+];
+
+/// Writes responses to one client, from its connection and from its sessions' threads.
+#[derive(Clone)]
+struct Transport(Arc<Mutex<TcpStream>>);
+
+impl Transport {
+    /// Sends `response` to the request `msg`, tagged with its `id` and `session`.
+    fn send(&self, msg: &Bencode, session: Option<&str>, response: Bencode) {
+        let mut response = match response {
+            Bencode::Dict(dict) => dict,
+            _ => BTreeMap::new(),
+        };
+        if let Some(id) = msg.get_str("id") {
+            response.insert(String::from("id"), Bencode::str(id));
+        }
+        if let Some(session) = session {
+            response.insert(String::from("session"), Bencode::str(session));
+        }
+        if let Ok(mut stream) = self.0.lock() {
+            // A client that went away has nobody left to tell.
+            let _ = stream.write_all(&Bencode::Dict(response).encode());
+        }
+    }
+}
+
+fn status(statuses: &[&str]) -> Bencode {
+    Bencode::dict(vec![("status", Bencode::list(statuses.iter().copied()))])
+}
+
+/// Code to evaluate for `eval`, or the file contents for `load-file`, which only
+/// reports the value of the last form.
+struct Request {
+    code: String,
+    last_only: bool,
+    msg: Bencode,
+    transport: Transport,
+}
+
+/// An evaluation context with its own namespace and `*1`, `*2`, `*3` and `*e`,
+/// evaluating on its own thread.
+struct Session {
+    requests: Sender<Request>,
+    interrupt: Arc<AtomicBool>,
+    ns: Arc<Mutex<String>>,
+    /// The `id` of the evaluation in progress.
+    running: Arc<Mutex<Option<String>>>,
+}
+
+type Sessions = Arc<Mutex<HashMap<String, Session>>>;
+
+fn new_session_id() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let high = RandomState::new().hash_one(COUNTER.fetch_add(1, Ordering::SeqCst));
+    let mut hasher = DefaultHasher::new();
+    high.hash(&mut hasher);
+    let low = hasher.finish();
+    let hex = format!("{:016x}{:016x}", high, low);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+struct Worker {
+    id: String,
+    ns: Arc<Mutex<String>>,
+    running: Arc<Mutex<Option<String>>>,
+    interrupt: Arc<AtomicBool>,
+    /// `*1`, `*2`, `*3` and `*e` for this session.
+    history: Hamt<String, T>,
+}
+
+impl Worker {
+    fn run(mut self, requests: std::sync::mpsc::Receiver<Request>) {
+        bootstrap();
+        set_interrupt_flag(Some(self.interrupt.clone()));
+        for request in requests {
+            if let Ok(mut running) = self.running.lock() {
+                *running = request.msg.get_str("id").map(str::to_owned);
+            }
+            self.interrupt.store(false, Ordering::SeqCst);
+            self.eval(&request);
+            if let Ok(mut running) = self.running.lock() {
+                *running = None;
+            }
+        }
+    }
+
+    fn eval(&mut self, request: &Request) {
+        let id = self.id.clone();
+        let send = |response| request.transport.send(&request.msg, Some(&id), response);
+        let ns = match request.msg.get_str("ns") {
+            Some(ns) if ns_exists(ns) => ns.to_owned(),
+            _ => self.ns.lock().map(|ns| ns.clone()).unwrap_or_default(),
+        };
+        // Failing to switch namespace is impossible here since `ns` exists or was the session's.
+        let _ = in_ns_named(&ns);
+
+        // `load-file` holds each value back until the next form proves it wasn't the last.
+        let mut last = None;
+        for form in read_forms(&request.code) {
+            let (form, text) = match form {
+                Ok(form) => form,
+                Err(err) => {
+                    last = None;
+                    self.report(&send, err);
+                    break;
+                }
+            };
+            record_source(&form, &text);
+            let (result, out) = capture_output(|| {
+                with_scope(self.history.clone(), || {
+                    expand_all(form)?.eval().and_then(|value| {
+                        let printed = value.print()?;
+                        Ok((value, printed))
+                    })
+                })
+            });
+            if !out.is_empty() {
+                send(Bencode::dict(vec![("out", Bencode::str(out))]));
+            }
+            match result {
+                Ok((value, printed)) => {
+                    let response = Bencode::dict(vec![
+                        ("value", Bencode::str(printed)),
+                        ("ns", Bencode::str(current_ns())),
+                    ]);
+                    if request.last_only {
+                        last = Some(response);
+                    } else {
+                        send(response);
+                    }
+                    self.history = push_result(&self.history, value);
+                }
+                Err(err) => {
+                    last = None;
+                    self.report(&send, err);
+                    break;
+                }
+            }
+        }
+        if let Some(response) = last {
+            send(response);
+        }
+
+        if let Ok(mut ns) = self.ns.lock() {
+            *ns = current_ns();
+        }
+        send(status(&["done"]));
+    }
+
+    fn report(&mut self, send: &impl Fn(Bencode), err: Error) {
+        send(Bencode::dict(vec![(
+            "err",
+            Bencode::str(format!("{}\n", err.trace())),
+        )]));
+        let class = err.kind.class_name();
+        let root = root_cause(&err).kind.class_name();
+        if err.kind == ErrorKind::Interrupted {
+            send(status(&["interrupted"]));
+        } else {
+            send(Bencode::dict(vec![
+                ("ex", Bencode::str(class)),
+                ("root-ex", Bencode::str(root)),
+                ("status", Bencode::list(vec!["eval-error"])),
+            ]));
+        }
+        self.history = self
+            .history
+            .update(String::from("*e"), T::Throwable(Box::new(err)));
+    }
+}
+
+fn root_cause(err: &Error) -> &Error {
+    match &err.cause {
+        Some(cause) => root_cause(cause),
+        None => err,
+    }
+}
+
+fn spawn_session(sessions: &Sessions, ns: String) -> io::Result<String> {
+    let id = new_session_id();
+    let (requests, receiver) = channel();
+    let worker = Worker {
+        id: id.clone(),
+        ns: Arc::new(Mutex::new(ns)),
+        running: Arc::new(Mutex::new(None)),
+        interrupt: Arc::new(AtomicBool::new(false)),
+        history: Hamt::new(),
+    };
+    let session = Session {
+        requests,
+        interrupt: worker.interrupt.clone(),
+        ns: worker.ns.clone(),
+        running: worker.running.clone(),
+    };
+    let env = current_env();
+    thread::Builder::new()
+        .name(format!("nrepl-session-{}", id))
+        .stack_size(THREAD_STACK_SIZE)
+        .spawn(move || with_env(env, || worker.run(receiver)))?;
+    if let Ok(mut sessions) = sessions.lock() {
+        sessions.insert(id.clone(), session);
+    }
+    Ok(id)
+}
+
+/// A TCP server speaking the bencode nREPL protocol.
+pub struct Server {
+    listener: TcpListener,
+    sessions: Sessions,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Writes `.nrepl-port` into `dir` so editors can find the server.
+    pub fn write_port_file(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = dir.join(PORT_FILE);
+        fs::write(&path, self.port()?.to_string())?;
+        Ok(path)
+    }
+
+    /// Serves every client on its own thread until the listener fails, in the
+    /// environment of the thread running the server.
+    pub fn run(self) -> io::Result<()> {
+        let env = current_env();
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let sessions = self.sessions.clone();
+            thread::spawn(move || {
+                with_env(env, || {
+                    if let Err(err) = Connection::new(stream, sessions).and_then(Connection::serve)
+                    {
+                        eprintln!("nREPL connection closed: {}", err);
+                    }
+                })
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    transport: Transport,
+    sessions: Sessions,
+    /// The session requests without a `session` use, so each client stays isolated.
+    default_session: Option<String>,
+}
+
+// The client's own session lives as long as its connection, so its thread ends
+// when the client disconnects. Cloned sessions stay until they are closed.
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let (Some(id), Ok(mut sessions)) = (&self.default_session, self.sessions.lock()) {
+            sessions.remove(id);
+        }
+    }
+}
+
+impl Connection {
+    fn new(stream: TcpStream, sessions: Sessions) -> io::Result<Connection> {
+        Ok(Connection {
+            transport: Transport(Arc::new(Mutex::new(stream.try_clone()?))),
+            reader: BufReader::new(stream),
+            sessions,
+            default_session: None,
+        })
+    }
+
+    fn serve(mut self) -> io::Result<()> {
+        while let Some(msg) = Bencode::decode(&mut self.reader)? {
+            self.handle(msg)?;
+        }
+        Ok(())
+    }
+
+    fn reply(&self, msg: &Bencode, session: Option<&str>, response: Bencode) {
+        self.transport.send(msg, session, response)
+    }
+
+    /// The session `msg` names, or this client's own one.
+    fn session(&mut self, msg: &Bencode) -> io::Result<Option<String>> {
+        match msg.get_str("session") {
+            Some(id) => {
+                let known = self
+                    .sessions
+                    .lock()
+                    .map(|sessions| sessions.contains_key(id))
+                    .unwrap_or(false);
+                Ok(Some(id.to_owned()).filter(|_| known))
+            }
+            None => match &self.default_session {
+                Some(id) => Ok(Some(id.clone())),
+                None => {
+                    let id = spawn_session(&self.sessions, String::from("user"))?;
+                    self.default_session = Some(id.clone());
+                    Ok(Some(id))
+                }
+            },
+        }
+    }
+
+    fn session_ns(&self, session: Option<&str>) -> String {
+        session
+            .and_then(|id| {
+                let sessions = self.sessions.lock().ok()?;
+                let ns = sessions.get(id)?.ns.lock().ok()?.clone();
+                Some(ns)
+            })
+            .unwrap_or_else(|| String::from("user"))
+    }
+
+    fn handle(&mut self, msg: Bencode) -> io::Result<()> {
+        let op = msg.get_str("op").unwrap_or_default().to_owned();
+        match op.as_str() {
+            "clone" => {
+                let ns = self.session_ns(msg.get_str("session"));
+                let id = spawn_session(&self.sessions, ns)?;
+                self.reply(
+                    &msg,
+                    Some(&id),
+                    Bencode::dict(vec![
+                        ("new-session", Bencode::str(id.as_str())),
+                        ("status", Bencode::list(vec!["done"])),
+                    ]),
+                );
+            }
+            "close" => {
+                let id = msg.get_str("session").unwrap_or_default();
+                // Dropping the session closes its channel, which ends its thread.
+                let closed = self
+                    .sessions
+                    .lock()
+                    .map(|mut sessions| sessions.remove(id).is_some())
+                    .unwrap_or(false);
+                let statuses = if closed {
+                    vec!["session-closed", "done"]
+                } else {
+                    vec!["error", "unknown-session", "done"]
+                };
+                self.reply(&msg, Some(id), status(&statuses));
+            }
+            "describe" => {
+                let session = msg.get_str("session").or(self.default_session.as_deref());
+                let ns = self.session_ns(session);
+                self.reply(&msg, msg.get_str("session"), self.describe(&ns))
+            }
+            "eval" | "load-file" => {
+                let code = match op.as_str() {
+                    "eval" => msg.get_str("code"),
+                    _ => msg.get_str("file"),
+                };
+                let code = match code {
+                    Some(code) => code.to_owned(),
+                    None => {
+                        let missing = if op == "eval" { "no-code" } else { "no-file" };
+                        self.reply(&msg, None, status(&["error", missing, "done"]));
+                        return Ok(());
+                    }
+                };
+                let session = match self.session(&msg)? {
+                    Some(session) => session,
+                    None => {
+                        self.reply(
+                            &msg,
+                            msg.get_str("session"),
+                            status(&["error", "unknown-session", "done"]),
+                        );
+                        return Ok(());
+                    }
+                };
+                let request = Request {
+                    code,
+                    last_only: op == "load-file",
+                    msg,
+                    transport: self.transport.clone(),
+                };
+                if let Ok(sessions) = self.sessions.lock() {
+                    if let Some(session) = sessions.get(&session) {
+                        // The worker only stops once its session is closed.
+                        let _ = session.requests.send(request);
+                    }
+                }
+            }
+            "interrupt" => {
+                let id = msg.get_str("session").unwrap_or_default();
+                let statuses = match self.sessions.lock().ok().and_then(|sessions| {
+                    let session = sessions.get(id)?;
+                    let running = session.running.lock().ok()?.clone();
+                    Some((session.interrupt.clone(), running))
+                }) {
+                    None => vec!["error", "unknown-session", "done"],
+                    Some((_, None)) => vec!["session-idle", "done"],
+                    Some((interrupt, Some(running))) => {
+                        if msg
+                            .get_str("interrupt-id")
+                            .is_none_or(|target| target == running)
+                        {
+                            interrupt.store(true, Ordering::SeqCst);
+                            vec!["done"]
+                        } else {
+                            vec!["error", "interrupt-id-mismatch", "done"]
+                        }
+                    }
+                };
+                self.reply(&msg, Some(id), status(&statuses));
+            }
+            "completions" | "complete" => {
+                let prefix = msg
+                    .get_str("prefix")
+                    .or_else(|| msg.get_str("symbol"))
+                    .unwrap_or_default();
+                let ns = match msg.get_str("ns") {
+                    Some(ns) if ns_exists(ns) => ns.to_owned(),
+                    _ => self.session_ns(msg.get_str("session")),
+                };
+                let candidates = with_ns(&ns, || completions(prefix))
+                    .into_iter()
+                    .map(|candidate| {
+                        let kind = if candidate.starts_with(':') {
+                            "keyword"
+                        } else if ns_exists(&candidate) {
+                            "namespace"
+                        } else {
+                            "var"
+                        };
+                        Bencode::dict(vec![
+                            ("candidate", Bencode::str(candidate)),
+                            ("type", Bencode::str(kind)),
+                        ])
+                    })
+                    .collect();
+                self.reply(
+                    &msg,
+                    msg.get_str("session"),
+                    Bencode::dict(vec![
+                        ("completions", Bencode::List(candidates)),
+                        ("status", Bencode::list(vec!["done"])),
+                    ]),
+                );
+            }
+            "lookup" | "info" => {
+                let symbol = msg
+                    .get_str("sym")
+                    .or_else(|| msg.get_str("symbol"))
+                    .unwrap_or_default();
+                let ns = match msg.get_str("ns") {
+                    Some(ns) if ns_exists(ns) => ns.to_owned(),
+                    _ => self.session_ns(msg.get_str("session")),
+                };
+                let response = match with_ns(&ns, || var_doc(symbol)) {
+                    Some(doc) => {
+                        let (ns, name) = doc.name.split_once('/').unwrap_or(("", &doc.name));
+                        let mut info = vec![("ns", Bencode::str(ns)), ("name", Bencode::str(name))];
+                        if let Some(arglists) = &doc.arglists {
+                            info.push(("arglists-str", Bencode::str(arglists.as_str())));
+                        }
+                        if let Some(text) = &doc.doc {
+                            info.push(("doc", Bencode::str(text.as_str())));
+                        }
+                        if doc.is_macro {
+                            info.push(("macro", Bencode::str("true")));
+                        }
+                        Bencode::dict(vec![
+                            ("info", Bencode::dict(info)),
+                            ("status", Bencode::list(vec!["done"])),
+                        ])
+                    }
+                    None => Bencode::dict(vec![
+                        ("info", Bencode::dict(vec![])),
+                        ("status", Bencode::list(vec!["done", "no-info"])),
+                    ]),
+                };
+                self.reply(&msg, msg.get_str("session"), response);
+            }
+            _ => self.reply(
+                &msg,
+                msg.get_str("session"),
+                Bencode::dict(vec![
+                    ("op", Bencode::str(op.as_str())),
+                    ("status", Bencode::list(vec!["error", "unknown-op", "done"])),
+                ]),
+            ),
+        }
+        Ok(())
+    }
+
+    fn describe(&self, ns: &str) -> Bencode {
+        let version = |version: &str| {
+            let mut parts = version.split('.').map(Bencode::str);
+            Bencode::dict(vec![
+                ("major", parts.next().unwrap_or_else(|| Bencode::str("0"))),
+                ("minor", parts.next().unwrap_or_else(|| Bencode::str("0"))),
+                (
+                    "incremental",
+                    parts.next().unwrap_or_else(|| Bencode::str("0")),
+                ),
+                ("version-string", Bencode::str(version)),
+            ])
+        };
+        Bencode::dict(vec![
+            (
+                "ops",
+                Bencode::dict(OPS.iter().map(|op| (*op, Bencode::dict(vec![])))),
+            ),
+            (
+                "versions",
+                Bencode::dict(vec![
+                    ("clojurs", version(env!("CARGO_PKG_VERSION"))),
+                    ("nrepl", version("1.0.0")),
+                ]),
+            ),
+            ("aux", Bencode::dict(vec![("current-ns", Bencode::str(ns))])),
+            ("status", Bencode::list(vec!["done"])),
+        ])
+    }
+}
@@ -6,9 +6,14 @@ use std::{
 use num_bigint::BigInt;
 use ordered_float::OrderedFloat;
 
-use crate::{definitions::DefinitionTypes as T, error::Error};
+use crate::{
+    definitions::{DefinitionTypes as T, Meta},
+    env::current_env,
+    error::{Error, Span},
+    funtions::{namespaces::auto_resolve_keyword, strings::Pattern},
+};
 
-pub(crate) fn tokenize(exp: &str) -> std::iter::Enumerate<std::str::Chars> {
+pub(crate) fn tokenize(exp: &str) -> std::iter::Enumerate<std::str::Chars<'_>> {
     exp.chars().enumerate()
 }
 
@@ -19,12 +24,182 @@ pub(crate) fn parse(
     Ok(match c {
         Some((_, '[')) => read_vec(chars)?,
         Some((_, '(')) => read_list(chars)?,
-        Some((_, '@')) => read_set(chars)?,
+        Some((_, '#')) if chars.clone().next().map(|c| c.1) == Some('{') => {
+            chars.next();
+            read_set(chars)?
+        }
+        Some((_, '#')) if chars.clone().next().map(|c| c.1) == Some('\'') => {
+            chars.next();
+            read_wrapped("var", chars)?
+        }
+        Some((i, '#')) if chars.clone().next().map(|c| c.1) == Some('"') => {
+            chars.next();
+            read_regex(i, chars)?
+        }
         Some((_, '{')) => read_map(chars)?,
+        Some((_, '^')) => read_meta(chars)?,
+        Some((_, '\'')) => read_wrapped("quote", chars)?,
+        Some((_, '`')) => read_wrapped("syntax-quote", chars)?,
+        Some((_, '@')) => read_wrapped("deref", chars)?,
+        Some((_, '~')) if chars.clone().next().map(|c| c.1) == Some('@') => {
+            chars.next();
+            read_wrapped("unquote-splicing", chars)?
+        }
+        Some((_, '~')) => read_wrapped("unquote", chars)?,
         edn => parse_edn(edn, chars)?,
     })
 }
 
+/// Reads the forms in `exp` one at a time, pairing every form with the text it
+/// was read from. A form is only read once the one before has been evaluated,
+/// so `::k` and `::alias/k` resolve in the namespace that form left current.
+pub(crate) fn read_forms(exp: &str) -> Forms<'_> {
+    Forms {
+        text: exp.chars().collect(),
+        chars: tokenize(exp),
+    }
+}
+
+pub(crate) struct Forms<'a> {
+    text: Vec<char>,
+    chars: std::iter::Enumerate<std::str::Chars<'a>>,
+}
+
+impl Iterator for Forms<'_> {
+    type Item = Result<(T, String), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(c) = self.chars.next() {
+            if c.1 == ';' {
+                skip_comment(&mut self.chars);
+            } else if !c.1.is_whitespace() && c.1 != ',' {
+                let form = match parse(Some(c), &mut self.chars) {
+                    Ok(form) => with_position(form, &self.text[..c.0]),
+                    Err(err) => {
+                        // Where the next form would start is unknown after an error.
+                        self.chars = tokenize("");
+                        return Some(Err(err));
+                    }
+                };
+                let end = self
+                    .chars
+                    .clone()
+                    .next()
+                    .map_or(self.text.len(), |next| next.0);
+                return Some(Ok((form, self.text[c.0..end].iter().collect())));
+            }
+        }
+        None
+    }
+}
+
+/// Gives a top level list the `:line` and `:column` it starts at, after `before`.
+fn with_position(form: T, before: &[char]) -> T {
+    let (meta, items) = match form {
+        T::List(items, meta) => (meta, items),
+        form => return form,
+    };
+    let line = before.iter().filter(|c| **c == '\n').count() + 1;
+    let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+    let mut position = HashMap::from([
+        (T::Keyword(String::from(":line")), T::Int(line.into())),
+        (T::Keyword(String::from(":column")), T::Int(column.into())),
+    ]);
+    if let T::HashMap(existing, _) = meta.get() {
+        position.extend(existing);
+    }
+    T::List(items, Meta::new(T::hash_map(position)))
+}
+
+/// Whether every delimiter and string opened in `exp` is closed, so the REPL
+/// knows when to stop asking for continuation lines.
+pub fn is_balanced(exp: &str) -> bool {
+    let mut depth = 0i64;
+    let mut chars = exp.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '\\' => {
+                chars.next();
+            }
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '"' => loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => {
+                        chars.next();
+                    }
+                    Some(_) => (),
+                    None => return false,
+                }
+            },
+            _ => (),
+        }
+    }
+    depth <= 0
+}
+
+// `;` comments run to the end of the line.
+fn skip_comment(chars: &mut std::iter::Enumerate<std::str::Chars>) {
+    for (_, c) in chars.by_ref() {
+        if c == '\n' {
+            break;
+        }
+    }
+}
+
+fn unparsable(message: String, i: usize) -> Error {
+    Error::reason(message).with_span(Span {
+        start: i,
+        end: i + 1,
+    })
+}
+
+// Reader macros such as `'form` expand to `(quote form)`.
+fn read_wrapped(
+    symbol: &str,
+    chars: &mut std::iter::Enumerate<std::str::Chars>,
+) -> Result<T, Error> {
+    let form = parse(chars.next(), chars)?;
+    Ok(T::list(vec![T::symbol(symbol.to_owned()), form]))
+}
+
+// `^:dynamic x` attaches `{:dynamic true}` to `x`, `^Tag x` attaches
+// `{:tag Tag}` and `^{...} x` the map itself, over any metadata `x` has.
+fn read_meta(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Error> {
+    let i = chars.clone().next().map_or(0, |c| c.0);
+    let meta = match parse(chars.next(), chars)? {
+        key @ T::Keyword(_) => HashMap::from([(key, T::Bool(true))]),
+        tag @ (T::Symbol(..) | T::String(_)) => {
+            HashMap::from([(T::Keyword(String::from(":tag")), tag)])
+        }
+        T::HashMap(map, _) => map,
+        _ => {
+            return Err(unparsable(
+                String::from("Metadata must be Symbol, Keyword, String or Map"),
+                i,
+            ))
+        }
+    };
+    let form = parse(chars.find(|c| !c.1.is_whitespace() && c.1 != ','), chars)?;
+    let meta = match form.meta() {
+        T::HashMap(mut existing, _) => {
+            existing.extend(meta);
+            existing
+        }
+        _ => meta,
+    };
+    form.with_meta(Meta::new(T::hash_map(meta))).map_err(|_| {
+        unparsable(
+            String::from("Metadata can only be applied to symbols, collections and functions"),
+            i,
+        )
+    })
+}
+
 pub(crate) fn parse_edn(
     c: Option<(usize, char)>,
     chars: &mut std::iter::Enumerate<std::str::Chars>,
@@ -37,7 +212,7 @@ pub(crate) fn parse_edn(
         Some((_, b)) if b == 't' || b == 'f' || b == 'n' => Ok(read_bool_or_nil(b, chars)?),
         Some((_, n)) if n.is_numeric() => Ok(read_number(n, chars)?),
         Some((_, a)) => Ok(read_symbol(a, chars)?),
-        None => Err(Error::Reason("Expression could not be parsed".to_string())),
+        None => Err(Error::reason("Expression could not be parsed".to_string())),
     }
 }
 
@@ -49,19 +224,25 @@ fn read_key_or_nsmap(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Resul
 
     Ok(match key_chars.find(|c| c.1 == '{') {
         Some(_) => {
-            return Err(Error::Reason(String::from(
+            return Err(Error::reason(String::from(
                 "Namespace maps not yet supported",
             )))
         } //read_namespaced_map(chars)?,
-        None => read_key(chars, c_len),
+        None => read_key(chars, c_len)?,
     })
 }
 
-fn read_key(chars: &mut std::iter::Enumerate<std::str::Chars>, c_len: usize) -> T {
-    let mut key = String::from(":");
+fn read_key(chars: &mut std::iter::Enumerate<std::str::Chars>, c_len: usize) -> Result<T, Error> {
     let key_chars = chars.take(c_len).map(|c| c.1).collect::<String>();
-    key.push_str(&key_chars);
-    T::Keyword(key)
+    let keyword = match key_chars.strip_prefix(':') {
+        Some(name) => auto_resolve_keyword(name)?,
+        None => format!(":{}", key_chars),
+    };
+    // Remembered for completion at the REPL.
+    if let Ok(mut keywords) = current_env().keywords.write() {
+        keywords.insert(keyword.clone());
+    }
+    Ok(T::Keyword(keyword))
 }
 
 fn read_str(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Error> {
@@ -77,7 +258,7 @@ fn read_str(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
                     '\\' => s.push('\\'),
                     '\"' => s.push('\"'),
                     _ => {
-                        return Err(Err(Error::Reason(format!(
+                        return Err(Err(Error::reason(format!(
                             "Invalid escape sequence \\{}",
                             c
                         ))))
@@ -100,52 +281,65 @@ fn read_str(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
     match result {
         // An Ok means we actually finished parsing *without* seeing the end of the string, so that's
         // an error.
-        Ok(_) => Err(Error::Reason("Unterminated string".to_string())),
+        Ok(_) => Err(Error::reason("Unterminated string".to_string())),
         Err(Err(e)) => Err(e),
         Err(Ok(string)) => Ok(T::String(string)),
     }
 }
 
+/// `#"..."` is a regex. Its escapes are kept for the regex to interpret, so a
+/// `\"` doesn't end it.
+fn read_regex(i: usize, chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Error> {
+    let mut source = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '"')) => break,
+            Some((_, '\\')) => {
+                source.push('\\');
+                source.extend(chars.next().map(|c| c.1));
+            }
+            Some((_, c)) => source.push(c),
+            None => return Err(unparsable(String::from("Unterminated regex"), i)),
+        }
+    }
+    Pattern::new(&source)
+        .map(T::Regex)
+        .map_err(|e| unparsable(format!("Invalid regex #\"{}\": {}", source, e), i))
+}
+
 fn read_symbol(a: char, chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Error> {
     let c_len = chars
         .clone()
         .enumerate()
         .take_while(|&(i, c)| {
-            i <= 200 && !c.1.is_whitespace() && c.1 != ')' && c.1 != '}' && c.1 != ']'
+            i <= 200 && !c.1.is_whitespace() && !matches!(c.1, ')' | '}' | ']' | ',' | ';')
         })
         .count();
-    let i = chars
-        .clone()
-        .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
-        .0;
+    // An atom may end the input, leaving no next char to take the index from.
+    let i = chars.clone().next().map_or(0, |c| c.0);
 
     if a.is_whitespace() {
-        return Err(Error::Reason(format!(
-            "\"{}\" could not be parsed at char count {}",
-            a, i
-        )));
+        return Err(unparsable(
+            format!("\"{}\" could not be parsed at char count {}", a, i),
+            i,
+        ));
     }
 
     let mut symbol = String::from(a);
     let symbol_chars = chars.take(c_len).map(|c| c.1).collect::<String>();
     symbol.push_str(&symbol_chars);
-    Ok(T::Symbol(symbol))
+    Ok(T::symbol(symbol))
 }
 
 fn read_number(n: char, chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Error> {
-    let i = chars
-        .clone()
-        .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
-        .0;
+    let i = chars.clone().next().map_or(0, |c| c.0);
 
     let c_len = chars
         .clone()
         .take_while(|c| c.1.is_numeric() || c.1 == '.' || c.1 == '/')
         .count();
     if n == '-' && c_len == 0 {
-        return Ok(T::Symbol("-".to_string()));
+        return read_symbol(n, chars);
     }
 
     let mut number = String::new();
@@ -165,35 +359,29 @@ fn read_number(n: char, chars: &mut std::iter::Enumerate<std::str::Chars>) -> Re
         n if n.parse::<BigInt>().is_ok() => Ok(T::Int(n.parse::<BigInt>().unwrap())),
         n if n.parse::<f64>().is_ok() => Ok(T::Double(n.parse()?)),
 
-        _ => Err(Error::Reason(format!(
-            "{} could not be parsed at char count {}",
-            number, i
-        ))),
+        _ => Err(unparsable(
+            format!("{} could not be parsed at char count {}", number, i),
+            i,
+        )),
     }
 }
 
 fn read_char(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Error> {
-    let i = chars
-        .clone()
-        .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
-        .0;
+    let i = chars.clone().next().map_or(0, |c| c.0);
     let c = chars.next();
-    c.ok_or(format!("{:?} could not be parsed at char count {}", c, i))
-        .map(|c| c.1)
-        .map(T::Char)
-        .map_err(Error::Reason)
+    c.map(|c| T::Char(c.1)).ok_or_else(|| {
+        unparsable(
+            format!("{:?} could not be parsed at char count {}", c, i),
+            i,
+        )
+    })
 }
 
 fn read_bool_or_nil(
     c: char,
     chars: &mut std::iter::Enumerate<std::str::Chars>,
 ) -> Result<T, Error> {
-    let i = chars
-        .clone()
-        .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
-        .0;
+    let i = chars.clone().next().map_or(0, |c| c.0);
     match c {
         't' if {
             let val = chars.clone().take(4).map(|c| c.1).collect::<String>();
@@ -243,10 +431,10 @@ fn read_bool_or_nil(
             string.push_str(&n);
             match &string[..] {
                 "nil" => Ok(T::Nil),
-                _ => Err(Error::Reason(format!(
-                    "{} could not be parsed at char count {}",
-                    string, i
-                ))),
+                _ => Err(unparsable(
+                    format!("{} could not be parsed at char count {}", string, i),
+                    i,
+                )),
             }
         }
         _ => read_symbol(c, chars),
@@ -257,21 +445,22 @@ fn read_vec(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
     let i = chars
         .clone()
         .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
+        .ok_or_else(|| Error::reason("Could not identify symbol index".to_string()))?
         .0;
     let mut res: Vec<T> = vec![];
     loop {
         match chars.next() {
-            Some((_, ']')) => return Ok(T::Vector(res)),
+            Some((_, ']')) => return Ok(T::vector(res)),
+            Some((_, ';')) => skip_comment(chars),
             Some(c) if !c.1.is_whitespace() && c.1 != ',' => {
                 res.push(parse(Some(c), chars)?);
             }
             Some(c) if c.1.is_whitespace() || c.1 == ',' => (),
             err => {
-                return Err(Error::Reason(format!(
-                    "{:?} could not be parsed at char count {}",
-                    err, i
-                )))
+                return Err(unparsable(
+                    format!("{:?} could not be parsed at char count {}", err, i),
+                    i,
+                ))
             }
         }
     }
@@ -281,21 +470,22 @@ fn read_list(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Err
     let i = chars
         .clone()
         .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
+        .ok_or_else(|| Error::reason("Could not identify symbol index".to_string()))?
         .0;
     let mut res: Vec<T> = vec![];
     loop {
         match chars.next() {
-            Some((_, ')')) => return Ok(T::List(res)),
+            Some((_, ')')) => return Ok(T::list(res)),
+            Some((_, ';')) => skip_comment(chars),
             Some(c) if !c.1.is_whitespace() && c.1 != ',' => {
                 res.push(parse(Some(c), chars)?);
             }
             Some(c) if c.1.is_whitespace() || c.1 == ',' => (),
             err => {
-                return Err(Error::Reason(format!(
-                    "{:?} could not be parsed at char count {}",
-                    err, i
-                )))
+                return Err(unparsable(
+                    format!("{:?} could not be parsed at char count {}", err, i),
+                    i,
+                ))
             }
         }
     }
@@ -305,21 +495,22 @@ fn read_set(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
     let i = chars
         .clone()
         .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
+        .ok_or_else(|| Error::reason("Could not identify symbol index".to_string()))?
         .0;
     let mut res: HashSet<T> = HashSet::new();
     loop {
         match chars.next() {
-            Some((_, '}')) => return Ok(T::HashSet(res)),
+            Some((_, '}')) => return Ok(T::hash_set(res)),
+            Some((_, ';')) => skip_comment(chars),
             Some(c) if !c.1.is_whitespace() && c.1 != ',' => {
                 res.insert(parse(Some(c), chars)?);
             }
             Some(c) if c.1.is_whitespace() || c.1 == ',' => (),
             err => {
-                return Err(Error::Reason(format!(
-                    "{:?} could not be parsed at char count {}",
-                    err, i
-                )))
+                return Err(unparsable(
+                    format!("{:?} could not be parsed at char count {}", err, i),
+                    i,
+                ))
             }
         }
     }
@@ -329,7 +520,7 @@ fn read_set(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
 //     let i = chars
 //         .clone()
 //         .next()
-//         .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
+//         .ok_or_else(|| Error::reason("Could not identify symbol index".to_string()))?
 //         .0;
 //     use std::collections::BTreeMap;
 //     let mut res: BTreeMap<String, T> = BTreeMap::new();
@@ -352,7 +543,7 @@ fn read_set(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
 //             }
 //             Some(c) if c.1.is_whitespace() || c.1 == ',' => (),
 //             err => {
-//                 return Err(Error::Reason(format!(
+//                 return Err(Error::reason(format!(
 //                     "{:?} could not be parsed at char count {}",
 //                     err, i
 //                 )))
@@ -371,14 +562,15 @@ fn read_map(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
     let i = chars
         .clone()
         .next()
-        .ok_or_else(|| Error::Reason("Could not identify symbol index".to_string()))?
+        .ok_or_else(|| Error::reason("Could not identify symbol index".to_string()))?
         .0;
     let mut res: HashMap<T, T> = HashMap::new();
     let mut key: Option<T> = None;
     let mut val: Option<T> = None;
     loop {
         match chars.next() {
-            Some((_, '}')) => return Ok(T::HashMap(res)),
+            Some((_, '}')) => return Ok(T::hash_map(res)),
+            Some((_, ';')) => skip_comment(chars),
             Some(c) if !c.1.is_whitespace() && c.1 != ',' => {
                 if key.is_some() {
                     val = Some(parse(Some(c), chars)?);
@@ -388,10 +580,10 @@ fn read_map(chars: &mut std::iter::Enumerate<std::str::Chars>) -> Result<T, Erro
             }
             Some(c) if c.1.is_whitespace() || c.1 == ',' => (),
             err => {
-                return Err(Error::Reason(format!(
-                    "{:?} could not be parsed at char count {}",
-                    err, i
-                )))
+                return Err(unparsable(
+                    format!("{:?} could not be parsed at char count {}", err, i),
+                    i,
+                ))
             }
         }
 
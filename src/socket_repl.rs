@@ -0,0 +1,289 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
+
+use im::HashMap as Hamt;
+
+use crate::{
+    bootstrap,
+    definitions::DefinitionTypes as T,
+    env::{current_env, with_env},
+    error::Error,
+    funtions::{
+        capture_output,
+        macros::expand_all,
+        namespaces::current_ns,
+        repl::{add_tap, push_result, record_source},
+        with_scope, THREAD_STACK_SIZE,
+    },
+    parser::{is_balanced, read_forms},
+};
+
+/// Typed by a client to end its session.
+const QUIT: &str = ":repl/quit";
+
+/// How a server talks to its clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// Prompts and printed values, like the terminal REPL.
+    Repl,
+    /// One EDN map per line, tagged `:ret`, `:out`, `:err` or `:tap`.
+    Prepl,
+}
+
+impl Protocol {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Repl => "Socket REPL",
+            Protocol::Prepl => "prepl",
+        }
+    }
+}
+
+/// A TCP server reading forms line by line, every client evaluating on its own
+/// thread against the interpreter shared by the process.
+pub struct SocketServer {
+    listener: TcpListener,
+    protocol: Protocol,
+}
+
+impl SocketServer {
+    pub fn bind(addr: impl ToSocketAddrs, protocol: Protocol) -> io::Result<SocketServer> {
+        Ok(SocketServer {
+            listener: TcpListener::bind(addr)?,
+            protocol,
+        })
+    }
+
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Serves every client on its own thread until the listener fails, in the
+    /// environment of the thread running the server.
+    pub fn run(self) -> io::Result<()> {
+        let env = current_env();
+        for (i, stream) in self.listener.incoming().enumerate() {
+            let client = Client::new(stream?, self.protocol)?;
+            let name = self.protocol.name();
+            thread::Builder::new()
+                .name(format!("{} client {}", name, i))
+                .stack_size(THREAD_STACK_SIZE)
+                .spawn(move || {
+                    with_env(env, || {
+                        if let Err(err) = client.serve() {
+                            eprintln!("{} connection closed: {}", name, err);
+                        }
+                    })
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes to one client, from its thread and from the thread forwarding its taps.
+#[derive(Clone)]
+struct Writer(Arc<Mutex<TcpStream>>);
+
+impl Writer {
+    fn write(&self, text: &str) -> io::Result<()> {
+        match self.0.lock() {
+            Ok(mut stream) => stream.write_all(text.as_bytes()),
+            Err(_) => Err(io::Error::other("Client stream poisoned")),
+        }
+    }
+
+    fn shutdown(&self) {
+        if let Ok(stream) = self.0.lock() {
+            // The client may have hung up first.
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+fn edn_string(s: &str) -> String {
+    let mut escaped = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// A prepl message: `entries` hold already printed EDN values.
+fn edn_message(tag: &str, entries: &[(&str, String)]) -> String {
+    let mut message = format!("{{:tag :{}", tag);
+    for (key, value) in entries {
+        message.push_str(&format!(", :{} {}", key, value));
+    }
+    message.push_str("}\n");
+    message
+}
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: Writer,
+    protocol: Protocol,
+    /// `*1`, `*2`, `*3` and `*e` for this client.
+    history: Hamt<String, T>,
+}
+
+impl Client {
+    fn new(stream: TcpStream, protocol: Protocol) -> io::Result<Client> {
+        Ok(Client {
+            writer: Writer(Arc::new(Mutex::new(stream.try_clone()?))),
+            reader: BufReader::new(stream),
+            protocol,
+            history: Hamt::new(),
+        })
+    }
+
+    fn serve(mut self) -> io::Result<()> {
+        bootstrap();
+        if self.protocol == Protocol::Prepl {
+            self.forward_taps();
+        }
+        let result = self.read_eval_print();
+        self.writer.shutdown();
+        result
+    }
+
+    /// Reads lines until every delimiter is closed, then evaluates them, until the
+    /// client quits or hangs up.
+    fn read_eval_print(&mut self) -> io::Result<()> {
+        self.prompt()?;
+        let mut source = String::new();
+        loop {
+            if self.reader.read_line(&mut source)? == 0 {
+                return Ok(());
+            }
+            if !is_balanced(&source) {
+                continue;
+            }
+            if !self.eval(&std::mem::take(&mut source))? {
+                return Ok(());
+            }
+            self.prompt()?;
+        }
+    }
+
+    fn prompt(&self) -> io::Result<()> {
+        match self.protocol {
+            Protocol::Repl => self.writer.write(&format!("{}=> ", current_ns())),
+            Protocol::Prepl => Ok(()),
+        }
+    }
+
+    /// Sends every value given to `tap>` while the client is connected.
+    fn forward_taps(&self) {
+        let taps = add_tap();
+        let writer = self.writer.clone();
+        thread::spawn(move || {
+            for value in taps {
+                if writer
+                    .write(&edn_message("tap", &[("val", edn_string(&value))]))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Evaluates the forms in `source`, stopping at the first error. Returns
+    /// false once the client asks to quit.
+    fn eval(&mut self, source: &str) -> io::Result<bool> {
+        for form in read_forms(source) {
+            let (form, text) = match form {
+                Ok(form) => form,
+                Err(err) => {
+                    self.report(source.trim(), err, 0)?;
+                    break;
+                }
+            };
+            if text.trim() == QUIT {
+                return Ok(false);
+            }
+            record_source(&form, &text);
+            let start = Instant::now();
+            let (result, out) = capture_output(|| {
+                with_scope(self.history.clone(), || {
+                    expand_all(form)?.eval().and_then(|value| {
+                        let printed = value.print()?;
+                        Ok((value, printed))
+                    })
+                })
+            });
+            let ms = start.elapsed().as_millis();
+            if !out.is_empty() {
+                self.out(&out)?;
+            }
+            match result {
+                Ok((value, printed)) => {
+                    self.history = push_result(&self.history, value);
+                    self.ret(text.trim(), &printed, ms, false)?;
+                }
+                Err(err) => {
+                    self.report(text.trim(), err, ms)?;
+                    break;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn out(&self, out: &str) -> io::Result<()> {
+        match self.protocol {
+            Protocol::Repl => self.writer.write(out),
+            Protocol::Prepl => self
+                .writer
+                .write(&edn_message("out", &[("val", edn_string(out))])),
+        }
+    }
+
+    fn ret(&self, form: &str, printed: &str, ms: u128, exception: bool) -> io::Result<()> {
+        match self.protocol {
+            Protocol::Repl => self.writer.write(&format!("{}\n", printed)),
+            Protocol::Prepl => {
+                let mut entries = vec![
+                    ("val", edn_string(printed)),
+                    ("ns", edn_string(&current_ns())),
+                    ("ms", ms.to_string()),
+                    ("form", edn_string(form)),
+                ];
+                if exception {
+                    entries.push(("exception", String::from("true")));
+                }
+                self.writer.write(&edn_message("ret", &entries))
+            }
+        }
+    }
+
+    /// Prints the trace of `err`, which prepl clients also get as an exceptional `:ret`.
+    fn report(&mut self, form: &str, err: Error, ms: u128) -> io::Result<()> {
+        let trace = format!("{}\n", err.trace());
+        let err = T::Throwable(Box::new(err));
+        match self.protocol {
+            Protocol::Repl => self.writer.write(&trace)?,
+            Protocol::Prepl => {
+                self.writer
+                    .write(&edn_message("err", &[("val", edn_string(&trace))]))?;
+                let printed = err.print().unwrap_or_default();
+                self.ret(form, &printed, ms, true)?;
+            }
+        }
+        self.history = self.history.update(String::from("*e"), err);
+        Ok(())
+    }
+}
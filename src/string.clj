@@ -0,0 +1 @@
+(ns clojure.string)
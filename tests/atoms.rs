@@ -0,0 +1,95 @@
+use std::thread;
+
+use ClojuRS::{error::Error, read};
+
+#[test]
+fn deref_swap_and_reset() {
+    assert_eq!(read("(def a42 (atom 1))").unwrap(), "#'user/a42");
+    assert_eq!(read("(swap! a42 + 10)").unwrap(), "11");
+    assert_eq!(read("@a42").unwrap(), "11");
+    assert_eq!(read("(deref a42)").unwrap(), "11");
+    assert_eq!(read("(swap-vals! a42 inc)").unwrap(), "[11 12 ]");
+    assert_eq!(read("(reset! a42 :x)").unwrap(), ":x");
+    assert_eq!(read("(reset-vals! a42 0)").unwrap(), "[:x 0 ]");
+    assert_eq!(read("(swap! a42 (fn [x y z] (+ x y z)) 1 2)").unwrap(), "3");
+    assert_eq!(read("(do a42)").unwrap(), "#atom[3]");
+    assert_eq!(
+        read("(deref 1)").err(),
+        Some(Error::cant_eval(String::from(
+            "`deref` expects a reference but received 1"
+        )))
+    );
+}
+
+#[test]
+fn compare_and_set() {
+    assert_eq!(read("(def c42 (atom [1]))").unwrap(), "#'user/c42");
+    assert_eq!(read("(compare-and-set! c42 [2] [3])").unwrap(), "false");
+    assert_eq!(read("(compare-and-set! c42 [1] [3])").unwrap(), "true");
+    assert_eq!(read("@c42").unwrap(), "[3 ]");
+}
+
+#[test]
+fn atoms_are_identities() {
+    assert_eq!(read("(let [a (atom 1)] (= a a))").unwrap(), "true");
+    assert_eq!(read("(= (atom 1) (atom 1))").unwrap(), "false");
+}
+
+#[test]
+fn validators() {
+    assert_eq!(
+        read("(def v42 (atom 1 :validator pos?))").unwrap(),
+        "#'user/v42"
+    );
+    assert_eq!(
+        read("(reset! v42 -1)").err(),
+        Some(Error::illegal_state("Invalid reference state"))
+    );
+    assert_eq!(
+        read("(try (swap! v42 - 5) (catch IllegalStateException e (ex-message e)))").unwrap(),
+        "\"Invalid reference state\""
+    );
+    assert_eq!(read("@v42").unwrap(), "1");
+    assert_eq!(
+        read("(atom 0 :validator pos?)").err(),
+        Some(Error::illegal_state("Invalid reference state"))
+    );
+    assert_eq!(read("(set-validator! v42 nil)").unwrap(), "nil");
+    assert_eq!(read("(reset! v42 -1)").unwrap(), "-1");
+    assert_eq!(read("(get-validator v42)").unwrap(), "nil");
+}
+
+#[test]
+fn watches() {
+    read("(def w42 (atom 0))").unwrap();
+    read("(def log42 (atom []))").unwrap();
+    read("(add-watch w42 :log (fn [k r old new] (swap! log42 conj [k old new])))").unwrap();
+    read("(swap! w42 inc)").unwrap();
+    read("(reset! w42 5)").unwrap();
+    read("(compare-and-set! w42 5 6)").unwrap();
+    assert_eq!(
+        read("@log42").unwrap(),
+        "[[:log 0 1 ] [:log 1 5 ] [:log 5 6 ] ]"
+    );
+    read("(remove-watch w42 :log)").unwrap();
+    read("(swap! w42 inc)").unwrap();
+    assert_eq!(read("(count @log42)").unwrap(), "3");
+}
+
+#[test]
+fn concurrent_swaps() {
+    read("(def counter42 (atom 0))").unwrap();
+    let threads = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..50 {
+                    read("(swap! counter42 inc)").unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(read("@counter42").unwrap(), "200");
+}
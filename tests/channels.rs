@@ -0,0 +1,198 @@
+use ClojuRS::read;
+
+fn require_async() {
+    read(
+        "(require '[clojure.core.async :as a \
+          :refer [go go-loop chan <! >! <!! >!! close! alts! alts!! timeout thread]])",
+    )
+    .unwrap();
+}
+
+#[test]
+fn channels_and_buffers() {
+    require_async();
+    read("(def c45 (chan))").unwrap();
+    read("(go (>! c45 1) (>! c45 2) (close! c45))").unwrap();
+    assert_eq!(
+        read("[(<!! c45) (<!! c45) (<!! c45)]").unwrap(),
+        "[1 2 nil ]"
+    );
+    assert_eq!(read("(>!! c45 3)").unwrap(), "false");
+    assert_eq!(read("(do c45)").unwrap(), "#chan[closed]");
+
+    read("(def fixed45 (chan 2))").unwrap();
+    assert_eq!(
+        read("[(>!! fixed45 :a) (>!! fixed45 :b)]").unwrap(),
+        "[true true ]"
+    );
+    assert_eq!(read("[(<!! fixed45) (<!! fixed45)]").unwrap(), "[:a :b ]");
+
+    read("(def dropping45 (chan (a/dropping-buffer 1)))").unwrap();
+    read("(do (>!! dropping45 1) (>!! dropping45 2))").unwrap();
+    assert_eq!(read("(<!! dropping45)").unwrap(), "1");
+
+    read("(def sliding45 (chan (a/sliding-buffer 1)))").unwrap();
+    read("(do (>!! sliding45 1) (>!! sliding45 2))").unwrap();
+    assert_eq!(read("(<!! sliding45)").unwrap(), "2");
+
+    assert_eq!(read("(a/sliding-buffer 3)").unwrap(), "#buffer[sliding 3]");
+    assert_eq!(
+        read("(try (>!! (chan 1) nil) (catch IllegalArgumentException e (ex-message e)))").unwrap(),
+        "\"Can't put nil on channel\""
+    );
+}
+
+#[test]
+fn go_blocks_and_threads() {
+    require_async();
+    assert_eq!(read("(<!! (go (+ 1 2)))").unwrap(), "3");
+    assert_eq!(read("(<!! (thread (* 6 7)))").unwrap(), "42");
+    assert_eq!(read("(<!! (go nil))").unwrap(), "nil");
+    assert_eq!(
+        read("(let [x 10] (<!! (go (let [y (<! (go x))] (* y 2)))))").unwrap(),
+        "20"
+    );
+    assert_eq!(
+        read("(<!! (go-loop [i 0 acc []] (if (= i 3) acc (recur (inc i) (conj acc i)))))").unwrap(),
+        "[0 1 2 ]"
+    );
+    assert_eq!(
+        read("(try (<! (chan)) (catch IllegalStateException e (ex-message e)))").unwrap(),
+        "\"`<!` used not in (go ...) block\""
+    );
+}
+
+#[test]
+fn parked_go_blocks_free_their_threads() {
+    require_async();
+    read("(def parked45 (chan))").unwrap();
+    read("(loop [i 0] (when (not= i 1000) (go (>! parked45 i)) (recur (inc i))))").unwrap();
+    assert_eq!(
+        read("(loop [i 0 sum 0] (if (= i 1000) sum (recur (inc i) (+ sum (<!! parked45)))))")
+            .unwrap(),
+        "499500"
+    );
+}
+
+#[test]
+fn alts_and_timeouts() {
+    require_async();
+    read("(def ready45 (chan 1))").unwrap();
+    read("(>!! ready45 :ready)").unwrap();
+    assert_eq!(
+        read("(let [[v port] (alts!! [(chan) ready45])] [v (= port ready45)])").unwrap(),
+        "[:ready true ]"
+    );
+    assert_eq!(
+        read("(alts!! [(chan)] :default 0)").unwrap(),
+        "[0 :default ]"
+    );
+    assert_eq!(
+        read("(first (alts!! [(chan) (timeout 20)]))").unwrap(),
+        "nil"
+    );
+    assert_eq!(read("(<!! (timeout 10))").unwrap(), "nil");
+
+    read("(def out45 (chan 1))").unwrap();
+    assert_eq!(
+        read("(first (alts!! [[out45 :put] (chan)] :priority true))").unwrap(),
+        "true"
+    );
+    assert_eq!(read("(<!! out45)").unwrap(), ":put");
+    assert_eq!(
+        read("(<!! (go (first (alts! [(timeout 10) (chan)]))))").unwrap(),
+        "nil"
+    );
+}
+
+#[test]
+fn put_and_take_callbacks() {
+    require_async();
+    read("(def callbacks45 (chan))").unwrap();
+    read("(def taken45 (promise))").unwrap();
+    read("(def put45 (promise))").unwrap();
+    assert_eq!(
+        read("(a/take! callbacks45 (fn [v] (deliver taken45 v)))").unwrap(),
+        "nil"
+    );
+    assert_eq!(
+        read("(a/put! callbacks45 :v (fn [ok] (deliver put45 ok)))").unwrap(),
+        "true"
+    );
+    assert_eq!(read("(deref taken45 1000 :timeout)").unwrap(), ":v");
+    assert_eq!(read("(deref put45 1000 :timeout)").unwrap(), "true");
+}
+
+#[test]
+fn pipelines() {
+    require_async();
+    read("(def in45 (chan 10))").unwrap();
+    read("(def piped45 (chan 10))").unwrap();
+    read("(a/pipeline 4 piped45 (comp (map inc) (filter odd?)) in45)").unwrap();
+    read("(loop [i 0] (when (not= i 8) (>!! in45 i) (recur (inc i))))").unwrap();
+    read("(close! in45)").unwrap();
+    assert_eq!(
+        read("(loop [acc []] (let [v (<!! piped45)] (if (nil? v) acc (recur (conj acc v)))))")
+            .unwrap(),
+        "[1 3 5 7 ]"
+    );
+
+    read("(def failing45 (chan 10))").unwrap();
+    read("(def handled45 (chan 10))").unwrap();
+    read(
+        "(a/pipeline 2 handled45 (map (fn [x] (if (= x 2) (throw \"two\") x))) failing45 true \
+          (fn [e] (ex-message e)))",
+    )
+    .unwrap();
+    read("(do (>!! failing45 1) (>!! failing45 2) (>!! failing45 3) (close! failing45))").unwrap();
+    assert_eq!(
+        read("[(<!! handled45) (<!! handled45) (<!! handled45) (<!! handled45)]").unwrap(),
+        "[1 \"two\" 3 nil ]"
+    );
+
+    read("(def pairs26 (chan 10))").unwrap();
+    read("(def paired26 (chan 10))").unwrap();
+    read("(a/pipeline 3 paired26 (comp (dedupe) (partition-all 2)) pairs26)").unwrap();
+    read("(do (>!! pairs26 1) (>!! pairs26 1) (>!! pairs26 2) (>!! pairs26 3) (close! pairs26))")
+        .unwrap();
+    assert_eq!(
+        read("[(<!! paired26) (<!! paired26) (<!! paired26)]").unwrap(),
+        "[[1 2 ] [3 ] nil ]"
+    );
+
+    read("(def taking26 (chan 10))").unwrap();
+    read("(def taken26 (chan 10))").unwrap();
+    read("(a/pipeline 2 taken26 (take 2) taking26)").unwrap();
+    read("(do (>!! taking26 :a) (>!! taking26 :b) (>!! taking26 :c))").unwrap();
+    assert_eq!(
+        read("[(<!! taken26) (<!! taken26) (<!! taken26)]").unwrap(),
+        "[:a :b nil ]"
+    );
+}
+
+#[test]
+fn mult_and_pub() {
+    require_async();
+    read("(def source45 (chan))").unwrap();
+    read("(def mult45 (a/mult source45))").unwrap();
+    read("(def tap45 (a/tap mult45 (chan 1)))").unwrap();
+    read("(def other45 (a/tap mult45 (chan 1)))").unwrap();
+    read("(>!! source45 5)").unwrap();
+    assert_eq!(read("[(<!! tap45) (<!! other45)]").unwrap(), "[5 5 ]");
+    read("(a/untap mult45 other45)").unwrap();
+    read("(>!! source45 6)").unwrap();
+    assert_eq!(read("(<!! tap45)").unwrap(), "6");
+    assert_eq!(
+        read("(alts!! [other45] :default :none)").unwrap(),
+        "[:none :default ]"
+    );
+    read("(close! source45)").unwrap();
+    assert_eq!(read("(<!! tap45)").unwrap(), "nil");
+
+    read("(def topics45 (chan))").unwrap();
+    read("(def pub45 (a/pub topics45 :topic))").unwrap();
+    read("(def sub45 (a/sub pub45 :a (chan 1)))").unwrap();
+    read("(>!! topics45 {:topic :b :v 1})").unwrap();
+    read("(>!! topics45 {:topic :a :v 2})").unwrap();
+    assert_eq!(read("(:v (<!! sub45))").unwrap(), "2");
+}
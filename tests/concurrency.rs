@@ -0,0 +1,121 @@
+use std::thread;
+
+use ClojuRS::{
+    env::{with_env, Env},
+    read,
+};
+
+#[test]
+fn futures() {
+    assert_eq!(read("(def f44 (future (+ 1 2)))").unwrap(), "#'user/f44");
+    assert_eq!(read("@f44").unwrap(), "3");
+    assert_eq!(read("(realized? f44)").unwrap(), "true");
+    assert_eq!(read("(future-done? f44)").unwrap(), "true");
+    assert_eq!(read("(do f44)").unwrap(), "#future[3]");
+    assert_eq!(read("(let [x 5] @(future (* x 2)))").unwrap(), "10");
+    assert_eq!(read("@(future-call (fn [] :called))").unwrap(), ":called");
+    assert_eq!(
+        read("(try @(future (throw \"inner\")) (catch Exception e (ex-message e)))").unwrap(),
+        "\"inner\""
+    );
+    assert_eq!(
+        read("(do (ns conc44.fut) (def y 3) (defn g [] y) [@(future (g)) @(future (str *ns*))])")
+            .unwrap(),
+        "[3 \"conc44.fut\" ]"
+    );
+}
+
+#[test]
+fn promises() {
+    assert_eq!(read("(def p44 (promise))").unwrap(), "#'user/p44");
+    assert_eq!(read("(realized? p44)").unwrap(), "false");
+    assert_eq!(read("(deref p44 10 :timeout)").unwrap(), ":timeout");
+    assert_eq!(read("(do (future (deliver p44 42)) @p44)").unwrap(), "42");
+    assert_eq!(read("(deliver p44 43)").unwrap(), "nil");
+    assert_eq!(read("(deref p44 10 :timeout)").unwrap(), "42");
+    assert_eq!(read("(do p44)").unwrap(), "#promise[42]");
+}
+
+#[test]
+fn agents() {
+    assert_eq!(read("(def a44 (agent []))").unwrap(), "#'user/a44");
+    read("(loop [i 0] (when (not= i 20) (send a44 conj i) (recur (inc i))))").unwrap();
+    read("(send-off a44 conj :off)").unwrap();
+    assert_eq!(read("(await a44)").unwrap(), "nil");
+    assert_eq!(
+        read("@a44").unwrap(),
+        "[0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 :off ]"
+    );
+    assert_eq!(read("(await-for 1000 a44)").unwrap(), "true");
+    assert_eq!(read("(agent-error a44)").unwrap(), "nil");
+
+    read("(def failing44 (agent 1))").unwrap();
+    read("(send failing44 (fn [_] (throw \"bad\")))").unwrap();
+    read("(await failing44)").unwrap();
+    assert_eq!(
+        read("(ex-message (agent-error failing44))").unwrap(),
+        "\"bad\""
+    );
+    assert_eq!(read("@failing44").unwrap(), "1");
+    assert_eq!(
+        read("(try (send failing44 inc) (catch Exception e (ex-message e)))").unwrap(),
+        "\"Agent is failed, needs restart: bad\""
+    );
+}
+
+#[test]
+fn agents_from_many_threads() {
+    read("(def counter44 (agent 0))").unwrap();
+    let threads = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..50 {
+                    read("(send counter44 inc)").unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    read("(await counter44)").unwrap();
+    assert_eq!(read("@counter44").unwrap(), "200");
+}
+
+#[test]
+fn pmap_and_pcalls() {
+    assert_eq!(read("(pmap inc [1 2 3 4 5])").unwrap(), "(2 3 4 5 6 )");
+    assert_eq!(read("(pmap + [1 2 3] [10 20])").unwrap(), "(11 22 )");
+    assert_eq!(read("(pmap inc [])").unwrap(), "()");
+    assert_eq!(
+        read("(pcalls (fn [] 1) (fn [] (+ 1 1)))").unwrap(),
+        "(1 2 )"
+    );
+    assert_eq!(
+        read("(try (pmap (fn [x] (throw \"p\")) [1]) (catch Exception e (ex-message e)))").unwrap(),
+        "\"p\""
+    );
+}
+
+#[test]
+fn threads_run_in_the_environment_they_start_from() {
+    let env = Env::new();
+    let read_in = |form: &str| with_env(env, || read(form)).unwrap();
+    assert_eq!(read_in("(def where44 :own)"), "#'user/where44");
+    read("(def where44 :root)").unwrap();
+    assert_eq!(read_in("(when true where44)"), ":own");
+    assert_eq!(read("(when true where44)").unwrap(), ":root");
+
+    assert_eq!(read_in("@(future where44)"), ":own");
+    assert_eq!(read_in("(pmap (fn [_] where44) [1 2])"), "(:own :own )");
+    assert_eq!(
+        read_in("(let [a (agent nil)] (send a (fn [_] where44)) (await a) @a)"),
+        ":own"
+    );
+    read_in("(require '[clojure.core.async :as a])");
+    assert_eq!(read_in("(a/<!! (a/go where44))"), ":own");
+    assert_eq!(
+        read_in("(a/<!! (a/go (a/<! (a/timeout 10)) where44))"),
+        ":own"
+    );
+}
@@ -0,0 +1,91 @@
+use ClojuRS::{funtions::capture_output, read};
+
+#[test]
+fn let_bindings() {
+    assert_eq!(read("(let [x 1 y (+ x 1)] (* x y))").unwrap(), "2");
+    assert_eq!(read("(let [x 1] (let [x (inc x)] x))").unwrap(), "2");
+}
+
+#[test]
+fn sequential() {
+    assert_eq!(
+        read("(let [[a b & more] [1 2 3 4]] [a b more])").unwrap(),
+        "[1 2 (3 4 ) ]"
+    );
+    assert_eq!(read("(let [[a b c] [1 2]] [a b c])").unwrap(), "[1 2 nil ]");
+    assert_eq!(
+        read("(let [[a [b c] :as all] [1 [2 3]]] [c all])").unwrap(),
+        "[3 [1 [2 3 ] ] ]"
+    );
+    assert_eq!(read("(let [[a & more] [1]] more)").unwrap(), "nil");
+}
+
+#[test]
+fn associative() {
+    assert_eq!(
+        read("(let [{:keys [x y] :or {y 0}} {:x 1}] [x y])").unwrap(),
+        "[1 0 ]"
+    );
+    assert_eq!(
+        read("(let [{:keys [x] :as m} {:x 1}] [x (:x m)])").unwrap(),
+        "[1 1 ]"
+    );
+    assert_eq!(
+        read("(let [{:strs [name]} {\"name\" \"ana\"}] name)").unwrap(),
+        "\"ana\""
+    );
+    assert_eq!(read("(let [{:syms [a]} {(quote a) 1}] a)").unwrap(), "1");
+    assert_eq!(
+        read("(let [{:user/keys [id]} {:user/id 7}] id)").unwrap(),
+        "7"
+    );
+    assert_eq!(
+        read("(let [{:keys [user/id]} {:user/id 8}] id)").unwrap(),
+        "8"
+    );
+    assert_eq!(
+        read("(let [{a :a [b] :b} {:a 1 :b [2]}] [a b])").unwrap(),
+        "[1 2 ]"
+    );
+    assert_eq!(read("(let [[_ & {:keys [k]}] [0 :k 3]] k)").unwrap(), "3");
+}
+
+#[test]
+fn fn_params() {
+    assert_eq!(
+        read("((fn [[a b] {:keys [c]}] (+ a b c)) [1 2] {:c 3})").unwrap(),
+        "6"
+    );
+    assert_eq!(read("((fn [x & [y]] [x y]) 1 2)").unwrap(), "[1 2 ]");
+}
+
+#[test]
+fn arglists_keep_patterns() {
+    read("(defn pair-28 ([[a b]] a) ([x & {:keys [c]}] c))").unwrap();
+    assert_eq!(
+        read("(get (meta #'pair-28) :arglists)").unwrap(),
+        "([[a b ] ] [x & {:keys [c ] } ] )"
+    );
+    let (_, doc) = capture_output(|| read("(doc pair-28)").unwrap());
+    assert!(doc.contains("\n([[a b ]] [x & {:keys [c ] }])\n"));
+}
+
+#[test]
+fn loops() {
+    assert_eq!(
+        read("(loop [i 0 acc []] (if (< i 3) (recur (inc i) (conj acc i)) acc))").unwrap(),
+        "[0 1 2 ]"
+    );
+    assert_eq!(
+        read("(loop [[x & xs] [1 2 3] sum 0] (if x (recur xs (+ sum x)) sum))").unwrap(),
+        "6"
+    );
+    assert_eq!(
+        read("((fn [n acc] (if (= n 0) acc (recur (dec n) (* acc n)))) 5 1)").unwrap(),
+        "120"
+    );
+    assert_eq!(
+        read("((fn [x & xs] (if xs (recur (+ x (nth xs 0)) (nthnext xs 1)) x)) 1 2 3)").unwrap(),
+        "6"
+    );
+}
@@ -0,0 +1,74 @@
+use std::error::Error as _;
+
+use ClojuRS::{
+    error::{Error, ErrorKind},
+    read,
+};
+
+#[test]
+fn kinds() {
+    assert_eq!(read("(inc 1 2)").unwrap_err().kind, ErrorKind::Arity(1));
+    assert_eq!(
+        read("(inc unbound-33)").unwrap_err().kind,
+        ErrorKind::UnknownSymbol
+    );
+    assert_eq!(read("(/ 1 0)").unwrap_err().kind, ErrorKind::Arithmetic);
+    assert_eq!(read("(throw \"x\")").unwrap_err().kind, ErrorKind::Thrown);
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        read("(inc unbound-33)").unwrap_err().to_string(),
+        "UnknownSymbolException: Unable to resolve symbol: unbound-33"
+    );
+    assert_eq!(
+        read("(throw (ex-info \"boom\" {:a 1}))")
+            .unwrap_err()
+            .to_string(),
+        "ExceptionInfo: boom {:a 1 }"
+    );
+}
+
+#[test]
+fn ex_data_payload() {
+    let err = read("(throw (ex-info \"boom\" {:a 1}))").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ExInfo);
+    assert_eq!(err.data.unwrap().print().unwrap(), "{:a 1 }");
+}
+
+#[test]
+fn source_chain() {
+    let err =
+        read("(try (/ 1 0) (catch Exception e (throw (ex-info \"outer\" {} e))))").unwrap_err();
+    let source = err.source().unwrap().to_string();
+    assert_eq!(source, "ArithmeticException: Divide by zero");
+    assert!(err
+        .trace()
+        .contains("\nCaused by: ArithmeticException: Divide by zero"));
+}
+
+#[test]
+fn stack() {
+    read("(defn inner-33 [x] (/ x 0))").unwrap();
+    read("(defn outer-33 [x] (inner-33 (inc x)))").unwrap();
+    let err = read("(outer-33 1)").unwrap_err();
+    let names = err
+        .stack
+        .iter()
+        .map(|frame| frame.name.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(names, vec!["/", "inner-33", "outer-33"]);
+    assert_eq!(err.stack[1].call_site, "(inner-33 (inc x ) )");
+    assert!(err
+        .trace()
+        .starts_with("ArithmeticException: Divide by zero\n  at / (/ x 0 )"));
+}
+
+#[test]
+fn span() {
+    let err = read("[1 2 ").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Reason);
+    assert!(err.span.is_some());
+    assert_eq!(Error::reason("x"), Error::reason("x"));
+}
@@ -0,0 +1,90 @@
+use ClojuRS::{error::Error, read};
+
+#[test]
+fn try_catch_finally() {
+    assert_eq!(read("(try 1 2)").unwrap(), "2");
+    assert_eq!(
+        read("(try (throw \"boom\") (catch Exception e (ex-message e)))").unwrap(),
+        "\"boom\""
+    );
+    assert_eq!(
+        read("(try 1 (finally (throw \"cleanup\")))").err(),
+        Some(Error::thrown(String::from("cleanup")))
+    );
+    assert_eq!(
+        read("(try (throw \"uncaught\") (catch ArityException e 1))").err(),
+        Some(Error::thrown(String::from("uncaught")))
+    );
+    assert_eq!(
+        read("(let [x 2] (try (throw \"x\") (catch :default e (* x 2))))").unwrap(),
+        "4"
+    );
+}
+
+#[test]
+fn catch_by_category() {
+    assert_eq!(
+        read("(try (/ 1 0) (catch ArithmeticException e (ex-message e)))").unwrap(),
+        "\"Divide by zero\""
+    );
+    assert_eq!(
+        read("(try (inc 1 2) (catch ArithmeticException e :math) (catch clojure.lang.ArityException e :arity))")
+            .unwrap(),
+        ":arity"
+    );
+    assert_eq!(
+        read("(try unbound-32 (catch UnknownSymbolException e (ex-message e)))").unwrap(),
+        "\"Unable to resolve symbol: unbound-32\""
+    );
+    assert_eq!(
+        read("(try (throw (ex-info \"boom\" {})) (catch RuntimeException e :runtime) (catch ExceptionInfo e :info))")
+            .unwrap(),
+        ":info"
+    );
+}
+
+#[test]
+fn ex_info() {
+    assert_eq!(
+        read("(try (throw (ex-info \"boom\" {:a 1})) (catch ExceptionInfo e (ex-data e)))")
+            .unwrap(),
+        "{:a 1 }"
+    );
+    assert_eq!(
+        read("(ex-data (ex-info \"boom\" {:a 1}))").unwrap(),
+        "{:a 1 }"
+    );
+    assert_eq!(read("(ex-data 1)").unwrap(), "nil");
+    assert_eq!(
+        read("(ex-message (ex-info \"boom\" {}))").unwrap(),
+        "\"boom\""
+    );
+    assert_eq!(read("(ex-cause (ex-info \"boom\" {}))").unwrap(), "nil");
+    assert!(read("(ex-info \"boom\" 1)").is_err());
+}
+
+#[test]
+fn chained_cause() {
+    assert_eq!(
+        read("(try (try (/ 1 0) (catch Exception e (throw (ex-info \"outer\" {:step 1} e)))) (catch Exception e [(ex-message e) (ex-message (ex-cause e))]))")
+            .unwrap(),
+        "[\"outer\" \"Divide by zero\" ]"
+    );
+}
+
+#[test]
+fn deep_recursion_is_catchable() {
+    read("(defn count40 [n] (if (= n 0) 0 (inc (count40 (dec n)))))").unwrap();
+    // Futures run on threads with the interpreter's full stack.
+    assert_eq!(
+        read("@(future (try (count40 100000) (catch StackOverflowError e (ex-message e))))")
+            .unwrap(),
+        "\"Calls nested too deeply\""
+    );
+    assert_eq!(
+        read("@(future (try (count40 100000) (catch RuntimeException e :runtime) (catch Throwable e :throwable)))")
+            .unwrap(),
+        ":throwable"
+    );
+    assert_eq!(read("@(future (count40 100))").unwrap(), "100");
+}
@@ -0,0 +1,71 @@
+use ClojuRS::{error::Error, read};
+
+#[test]
+fn keywords_as_functions() {
+    assert_eq!(read("(:a {:a 1 :b 2})").unwrap(), "1");
+    assert_eq!(read("(:c {:a 1 :b 2})").unwrap(), "nil");
+    assert_eq!(read("(:c {:a 1} 42)").unwrap(), "42");
+    assert_eq!(read("(:a #{:a :b})").unwrap(), ":a");
+    assert_eq!(read("(map :a [{:a 1} {:a 2}])").unwrap(), "(1 2 )");
+}
+
+#[test]
+fn collections_as_functions() {
+    assert_eq!(read("({:a 1} :a)").unwrap(), "1");
+    assert_eq!(read("({:a 1} :b :not-found)").unwrap(), ":not-found");
+    assert_eq!(read("(#{1 2} 1)").unwrap(), "1");
+    assert_eq!(read("(#{1 2} 3)").unwrap(), "nil");
+    assert_eq!(read("([:a :b :c] 1)").unwrap(), ":b");
+    assert_eq!(read("([:a :b :c] 5 :none)").unwrap(), ":none");
+    assert_eq!(
+        read("([:a :b :c] 5)").err(),
+        Some(Error::cant_eval(Some(String::from(
+            "Index 5 out of bounds"
+        ))))
+    );
+    assert_eq!(read("(get {:a 1} :a)").unwrap(), "1");
+    assert_eq!(read("(get [1 2] 4 :none)").unwrap(), ":none");
+}
+
+#[test]
+fn evaluated_heads() {
+    assert_eq!(read("((fn [x] x) 1)").unwrap(), "1");
+    assert_eq!(read("((fn [x y] (+ x y)) 1 2)").unwrap(), "3");
+    assert_eq!(read("((fn [x & xs] xs) 1 2 3)").unwrap(), "(2 3 )");
+    assert_eq!(read("((fn [x & xs] xs) 1)").unwrap(), "nil");
+    assert_eq!(read("((fn ([] 0) ([x] x)))").unwrap(), "0");
+    assert_eq!(read("(((fn [x] (fn [y] (+ x y))) 1) 2)").unwrap(), "3");
+    assert_eq!(read("((comp inc inc) 1)").unwrap(), "3");
+    assert_eq!(
+        read("(transduce (map (fn [x] (* x x))) + [1 2 3])").unwrap(),
+        "14"
+    );
+    assert_eq!(
+        read("((fn [x] x))").err(),
+        Some(Error::arity(
+            1,
+            String::from("Wrong number of args (0) passed to: fn")
+        ))
+    );
+    assert_eq!(
+        read("(1 2)").err(),
+        Some(Error::cant_eval(Some(String::from("1"))))
+    );
+}
+
+#[test]
+fn recur_only_in_tail_position() {
+    let tail = Some(Error::cant_eval(Some(String::from(
+        "Can only recur from tail position",
+    ))));
+    assert_eq!(read("(loop [i 0] (do (recur 1) 5))").err(), tail);
+    assert_eq!(read("(loop [i 0] (inc (recur 1)))").err(), tail);
+    assert_eq!(read("(loop [i 0] (let [j (recur 1)] j))").err(), tail);
+    assert_eq!(read("((fn [x] (if (recur 1) x 2)) 0)").err(), tail);
+    assert_eq!(read("(loop [i 0] [(recur 1)])").err(), tail);
+    assert_eq!(
+        read("(loop [i 0] (when (< i 3) (do (let [j (inc i)] (if (= j 3) j (recur j))))))")
+            .unwrap(),
+        "3"
+    );
+}
@@ -1,8 +1,30 @@
 use ClojuRS::{error::Error, read};
 
+mod atoms;
+mod channels;
+mod concurrency;
+mod destructuring;
+mod errors;
+mod exceptions;
+mod invoke;
+mod loading;
 mod logic;
+mod macros;
 mod math;
+mod metadata;
+mod multimethods;
+mod namespaces;
+mod nrepl;
+mod protocols;
+mod refs;
+mod repl;
+mod runner;
+mod socket_repl;
 mod std;
+mod strings;
+mod threading;
+mod transducers;
+mod vars;
 
 #[test]
 fn inner_1() {
@@ -16,18 +38,18 @@ fn inner_1() {
 fn thrown() {
     assert_eq!(
         read("(throw 1 2 3)").err(),
-        Some(Error::ArityException(
+        Some(Error::arity(
             1,
             String::from("`throw` has arity of 1 but received 3")
         ))
     );
     assert_eq!(
         read("(throw 1)").err(),
-        Some(Error::CantEval(Some(String::from("[Int(1)]"))))
+        Some(Error::cant_eval(Some(String::from("[Int(1)]"))))
     );
 
     assert_eq!(
         read("(throw \"this is an error message\")").err(),
-        Some(Error::Thrown(String::from("this is an error message")))
+        Some(Error::thrown(String::from("this is an error message")))
     );
 }
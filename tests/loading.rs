@@ -0,0 +1,110 @@
+use std::{fs, path::PathBuf};
+
+use ClojuRS::{funtions::loading::add_source_path, read};
+
+/// Writes `files` under a fresh source root and adds it to the source path.
+fn source_root(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("clojurs-{}-{}", name, std::process::id()));
+    for (path, source) in files {
+        let file = root.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, source).unwrap();
+    }
+    add_source_path(&root);
+    root
+}
+
+#[test]
+fn require_from_source_roots() {
+    source_root(
+        "require",
+        &[
+            (
+                "my/app35/core.clj",
+                "(ns my.app35.core (:require [my.app35.util :as u])) ; entry point\n(defn run [] (u/twice 21))",
+            ),
+            ("my/app35/util.cljrs", "(ns my.app35.util)\n(defn twice [n] (* 2 n))"),
+        ],
+    );
+    assert_eq!(
+        read("(do (require '[my.app35.core :as app]) (app/run))").unwrap(),
+        "42"
+    );
+    assert_eq!(read("(str *ns*)").unwrap(), "\"user\"");
+    assert!(read("(require 'my.app35.missing)")
+        .unwrap_err()
+        .message
+        .contains("my/app35/missing.clj"));
+}
+
+#[test]
+fn require_loads_once_unless_reloaded() {
+    source_root(
+        "once",
+        &[(
+            "once35/stamp.clj",
+            "(ns once35.stamp)\n(def stamp (gensym))",
+        )],
+    );
+    let first = read("(do (require 'once35.stamp) (str once35.stamp/stamp))").unwrap();
+    assert_eq!(
+        read("(do (require 'once35.stamp) (str once35.stamp/stamp))").unwrap(),
+        first
+    );
+    assert_ne!(
+        read("(do (require 'once35.stamp :reload) (str once35.stamp/stamp))").unwrap(),
+        first
+    );
+}
+
+#[test]
+fn keywords_resolve_as_the_file_is_read() {
+    source_root(
+        "keywords",
+        &[
+            (
+                "kw35/keys.clj",
+                "(ns kw35.keys)\n(require '[kw35.other :as other])\n(def own ::k)\n(def aliased ::other/k)",
+            ),
+            ("kw35/other.clj", "(ns kw35.other)"),
+        ],
+    );
+    assert_eq!(
+        read("(do (require 'kw35.keys) [kw35.keys/own kw35.keys/aliased])").unwrap(),
+        "[:kw35.keys/k :kw35.other/k ]"
+    );
+}
+
+#[test]
+fn cyclic_require() {
+    source_root(
+        "cycle",
+        &[
+            ("cycle35/a.clj", "(ns cycle35.a (:require cycle35.b))"),
+            ("cycle35/b.clj", "(ns cycle35.b (:require cycle35.a))"),
+        ],
+    );
+    let err = read("(require 'cycle35.a)").unwrap_err();
+    assert!(err
+        .trace()
+        .contains("Cyclic load dependency: cycle35.a -> cycle35.b -> cycle35.a"));
+    assert_eq!(read("(str *ns*)").unwrap(), "\"user\"");
+}
+
+#[test]
+fn load_file_and_load() {
+    let root = source_root(
+        "load",
+        &[
+            ("scripts35/sum.clj", "(def sum-35 (+ 1 2))\n(* sum-35 10)"),
+            ("scripts35/more.cljrs", "(def more-35 7)"),
+        ],
+    );
+    let path = root.join("scripts35/sum.clj");
+    assert_eq!(
+        read(&format!("(load-file {:?})", path.to_str().unwrap())).unwrap(),
+        "30"
+    );
+    assert_eq!(read("(do (load \"scripts35/more\") more-35)").unwrap(), "7");
+    assert!(read("(load-file \"/no/such/file35.clj\")").is_err());
+}
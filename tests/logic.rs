@@ -60,3 +60,31 @@ fn is_nil() {
     assert_eq!(read("(nil? (= nil true) (= nil 1))").unwrap(), "false");
     assert_eq!(read("(nil? true)").unwrap(), "false");
 }
+
+#[test]
+fn and_or() {
+    assert_eq!(read("(and)").unwrap(), "true");
+    assert_eq!(read("(and 1 2)").unwrap(), "2");
+    assert_eq!(
+        read("(and 1 false (throw \"unreached\"))").unwrap(),
+        "false"
+    );
+    assert_eq!(read("(and 1 nil 3)").unwrap(), "nil");
+    assert_eq!(read("(or)").unwrap(), "nil");
+    assert_eq!(read("(or nil false)").unwrap(), "false");
+    assert_eq!(read("(or nil :a (throw \"unreached\"))").unwrap(), ":a");
+    assert_eq!(read("(or (> 1 2) (and (= 1 1) (< 1 2)))").unwrap(), "true");
+}
+
+#[test]
+fn not() {
+    assert_eq!(read("(not nil)").unwrap(), "true");
+    assert_eq!(read("(not 0)").unwrap(), "false");
+    assert_eq!(read("(not= 1 2)").unwrap(), "true");
+    assert_eq!(read("(not= 1 (- 2 1))").unwrap(), "false");
+    assert_eq!(read("(boolean nil)").unwrap(), "false");
+    assert_eq!(read("(boolean \"\")").unwrap(), "true");
+    assert_eq!(read("(some? false)").unwrap(), "true");
+    assert_eq!(read("(some? nil)").unwrap(), "false");
+    assert_eq!(read("(any? nil)").unwrap(), "true");
+}
@@ -0,0 +1,109 @@
+use ClojuRS::read;
+
+#[test]
+fn defmacro() {
+    assert_eq!(
+        read("(defmacro unless-29 [test then] `(if ~test nil ~then))").unwrap(),
+        "#'user/unless-29"
+    );
+    assert_eq!(read("(unless-29 false 3)").unwrap(), "3");
+    assert_eq!(
+        read("(unless-29 true (throw \"not evaluated\"))").unwrap(),
+        "nil"
+    );
+    assert_eq!(
+        read("(defmacro form-29 [x] (str &form))").unwrap(),
+        "#'user/form-29"
+    );
+    assert_eq!(read("(form-29 1)").unwrap(), "\"(form-29 1 )\"");
+}
+
+#[test]
+fn macroexpand() {
+    assert_eq!(
+        read("(defmacro twice-29 [x] `(do ~x ~x))").unwrap(),
+        "#'user/twice-29"
+    );
+    assert_eq!(
+        read("(macroexpand-1 '(twice-29 (println! 1)))").unwrap(),
+        "(do (println! 1 ) (println! 1 ) )"
+    );
+    assert_eq!(
+        read("(macroexpand '(when a b))").unwrap(),
+        "(if a (do b ) )"
+    );
+    assert_eq!(read("(macroexpand '(f a))").unwrap(), "(f a )");
+    assert_eq!(
+        read("(macroexpand-all '(when (when a b) c))").unwrap(),
+        "(if (if a (do b ) ) (do c ) )"
+    );
+}
+
+#[test]
+fn gensyms() {
+    assert!(read("(gensym)").unwrap().starts_with("G__"));
+    assert!(read("(gensym \"tmp\")").unwrap().starts_with("tmp"));
+    let expanded = read("`(let [x# 1] x#)").unwrap();
+    let generated = expanded
+        .split(|c: char| c.is_whitespace() || c == '[')
+        .find(|s| s.starts_with("x__"))
+        .unwrap()
+        .to_owned();
+    assert!(generated.ends_with("__auto__"));
+    assert_eq!(expanded.matches(&generated).count(), 2);
+}
+
+#[test]
+fn core_macros() {
+    assert_eq!(read("(when true 1 2)").unwrap(), "2");
+    assert_eq!(read("(when-not true 1)").unwrap(), "nil");
+    assert_eq!(read("(if-not false 1 2)").unwrap(), "1");
+    assert_eq!(read("(cond false 1 (= 1 2) 2 :else 3)").unwrap(), "3");
+    assert_eq!(read("(cond false 1)").unwrap(), "nil");
+    assert_eq!(read("(and 1 2 nil 3)").unwrap(), "nil");
+    assert_eq!(read("(and 1 2)").unwrap(), "2");
+    assert_eq!(read("(or nil false 7)").unwrap(), "7");
+    assert!(read("(or false (throw \"reached\") 1)").is_err());
+    assert_eq!(read("(or 1 (throw \"unreached\"))").unwrap(), "1");
+    assert_eq!(read("(-> 3 inc (* 2))").unwrap(), "8");
+}
+
+#[test]
+fn defn() {
+    assert_eq!(
+        read("(defn square-29 \"Squares x.\" [x] (* x x))").unwrap(),
+        "#'user/square-29"
+    );
+    assert_eq!(read("(square-29 4)").unwrap(), "16");
+    assert_eq!(
+        read("(defn fact-29 ([n] (fact-29 n 1)) ([n acc] (if (= n 0) acc (recur (dec n) (* n acc)))))")
+            .unwrap(),
+        "#'user/fact-29"
+    );
+    assert_eq!(read("(fact-29 5)").unwrap(), "120");
+}
+
+#[test]
+fn locals_are_env_and_shadow_macros() {
+    read("(defmacro env-count-29 [] (count &env))").unwrap();
+    assert_eq!(read("(env-count-29)").unwrap(), "0");
+    assert_eq!(read("(let [a 1 b 2] (env-count-29))").unwrap(), "2");
+    assert_eq!(
+        read("(let [a 1] (loop [c 3] ((fn f [d & more] (env-count-29)) c)))").unwrap(),
+        "5"
+    );
+    assert_eq!(
+        read("(try (throw \"x\") (catch Exception e (env-count-29)))").unwrap(),
+        "1"
+    );
+
+    assert_eq!(
+        read("(let [when (fn [a b] b)] (when false 2))").unwrap(),
+        "2"
+    );
+    assert_eq!(
+        read("((fn [when] (when false 2)) (fn [a b] b))").unwrap(),
+        "2"
+    );
+    assert_eq!(read("(let [x 1] (when false 2))").unwrap(), "nil");
+}
@@ -0,0 +1,103 @@
+use ClojuRS::read;
+
+#[test]
+fn collection_and_symbol_metadata() {
+    assert_eq!(read("(meta (with-meta [1 2] {:a 1}))").unwrap(), "{:a 1 }");
+    assert_eq!(read("(meta [1 2])").unwrap(), "nil");
+    assert_eq!(read("(= (with-meta [1 2] {:a 1}) [1 2])").unwrap(), "true");
+    assert_eq!(
+        read("(meta (conj (with-meta [1] {:a 1}) 2))").unwrap(),
+        "{:a 1 }"
+    );
+    assert_eq!(read("(meta (with-meta {:k 1} {:a 1}))").unwrap(), "{:a 1 }");
+    assert_eq!(read("(meta (with-meta #{1} {:a 1}))").unwrap(), "{:a 1 }");
+    assert_eq!(read("(meta (with-meta 'sym {:a 1}))").unwrap(), "{:a 1 }");
+    assert_eq!(
+        read("(get (meta (vary-meta (with-meta [] {:a 1}) conj {:b 2})) :b)").unwrap(),
+        "2"
+    );
+    assert_eq!(
+        read("(meta (with-meta (fn [x] x) {:f true}))").unwrap(),
+        "{:f true }"
+    );
+    assert_eq!(read("((with-meta (fn [x] x) {:f true}) 5)").unwrap(), "5");
+    assert!(read("(with-meta 1 {})").is_err());
+    assert!(read("(with-meta [] 1)").is_err());
+}
+
+#[test]
+fn reader_metadata() {
+    assert_eq!(read("(meta ^:foo [1])").unwrap(), "{:foo true }");
+    assert_eq!(read("(meta ^{:x 1} {:k 2})").unwrap(), "{:x 1 }");
+    assert_eq!(read("(meta '^:private sym)").unwrap(), "{:private true }");
+    assert_eq!(read("(meta '^String sym)").unwrap(), "{:tag String }");
+    assert_eq!(
+        read("(get (meta '^:a ^:b sym) :a)").unwrap(),
+        read("(get (meta '^:a ^:b sym) :b)").unwrap()
+    );
+    assert!(read("^:foo 1").is_err());
+}
+
+#[test]
+fn var_metadata() {
+    assert_eq!(read("(def ^:private p47 1)").unwrap(), "#'user/p47");
+    assert_eq!(read("(get (meta #'p47) :private)").unwrap(), "true");
+    assert_eq!(read("(get (meta #'p47) :name)").unwrap(), "p47");
+    assert_eq!(read("(get (meta #'p47) :ns)").unwrap(), "user");
+    assert_eq!(
+        read("(get (meta #'p47) :file)").unwrap(),
+        "\"NO_SOURCE_PATH\""
+    );
+
+    assert_eq!(
+        read("(defn add47 \"Adds\" [x y] (+ x y))").unwrap(),
+        "#'user/add47"
+    );
+    assert_eq!(read("(get (meta #'add47) :doc)").unwrap(), "\"Adds\"");
+    assert_eq!(read("(get (meta #'add47) :arglists)").unwrap(), "([x y ] )");
+    assert_eq!(
+        read("(defn multi47 ([x] x) ([x & more] more))").unwrap(),
+        "#'user/multi47"
+    );
+    assert_eq!(
+        read("(get (meta #'multi47) :arglists)").unwrap(),
+        "([x ] [x & more ] )"
+    );
+
+    assert_eq!(
+        read("(get (alter-meta! #'p47 conj {:q 3}) :q)").unwrap(),
+        "3"
+    );
+    assert_eq!(read("(get (meta #'p47) :q)").unwrap(), "3");
+    assert_eq!(
+        read("(reset-meta! #'p47 {:r 4})").unwrap(),
+        read("(meta #'p47)").unwrap()
+    );
+    assert_eq!(read("(get (meta #'p47) :private)").unwrap(), "nil");
+    assert_eq!(read("(get (meta #'p47) :name)").unwrap(), "p47");
+    assert!(read("(reset-meta! #'p47 1)").is_err());
+}
+
+#[test]
+fn dynamic_vars_use_metadata() {
+    assert_eq!(read("(def ^:dynamic *d47* 1)").unwrap(), "#'user/*d47*");
+    assert_eq!(read("(get (meta #'*d47*) :dynamic)").unwrap(), "true");
+    assert_eq!(read("(binding [*d47* 2] *d47*)").unwrap(), "2");
+    assert_eq!(read("(def s47 1)").unwrap(), "#'user/s47");
+    assert!(read("(binding [s47 2] s47)").is_err());
+    assert!(read("(alter-meta! #'s47 conj {:dynamic true})").is_ok());
+    assert_eq!(read("(binding [s47 2] s47)").unwrap(), "2");
+}
+
+#[test]
+fn atom_metadata() {
+    assert_eq!(
+        read("(def a47 (atom 1 :meta {:m 1}))").unwrap(),
+        "#'user/a47"
+    );
+    assert_eq!(read("(meta a47)").unwrap(), "{:m 1 }");
+    assert_eq!(read("(get (alter-meta! a47 conj {:n 2}) :n)").unwrap(), "2");
+    assert_eq!(read("(reset-meta! a47 {})").unwrap(), "{}");
+    assert_eq!(read("(meta (atom 1))").unwrap(), "nil");
+    assert!(read("(atom 1 :meta 2)").is_err());
+}
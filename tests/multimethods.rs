@@ -0,0 +1,117 @@
+use ClojuRS::read;
+
+#[test]
+fn dispatch_and_default() {
+    assert_eq!(
+        read("(defmulti area49 \"Area of a shape\" :shape)").unwrap(),
+        "#'user/area49"
+    );
+    assert_eq!(
+        read("(defmethod area49 :rect [r] (* (:w r) (:h r)))").unwrap(),
+        "#function[area49]"
+    );
+    assert!(read("(defmethod area49 :circle [c] (* 3 (:r c) (:r c)))").is_ok());
+    assert_eq!(read("(area49 {:shape :rect :w 2 :h 3})").unwrap(), "6");
+    assert_eq!(read("(area49 {:shape :circle :r 2})").unwrap(), "12");
+    assert_eq!(
+        read("(try (area49 {:shape :tri}) (catch IllegalArgumentException e (ex-message e)))")
+            .unwrap(),
+        "\"No method in multimethod 'area49' for dispatch value: :tri\""
+    );
+    assert!(read("(defmethod area49 :default [s] :unknown)").is_ok());
+    assert_eq!(read("(area49 {:shape :tri})").unwrap(), ":unknown");
+    assert_eq!(
+        read("(get (meta #'area49) :doc)").unwrap(),
+        "\"Area of a shape\""
+    );
+
+    assert_eq!(read("(count (methods area49))").unwrap(), "3");
+    assert!(read("(remove-method area49 :circle)").is_ok());
+    assert_eq!(read("(area49 {:shape :circle :r 2})").unwrap(), ":unknown");
+    assert_eq!(read("(defmulti area49 :kind)").unwrap(), "nil");
+    assert_eq!(read("(area49 {:shape :rect :w 1 :h 1})").unwrap(), "1");
+}
+
+#[test]
+fn dispatch_functions_and_options() {
+    assert!(read("(defmulti pair49 (fn [a b] [a b]) :default :none)").is_ok());
+    assert!(read("(defmethod pair49 [:x :y] [a b] :xy)").is_ok());
+    assert!(read("(defmethod pair49 :none ([a b] :other))").is_ok());
+    assert_eq!(read("(pair49 :x :y)").unwrap(), ":xy");
+    assert_eq!(read("(pair49 1 2)").unwrap(), ":other");
+}
+
+#[test]
+fn global_hierarchy() {
+    assert_eq!(read("(derive :user/square49 :user/rect49)").unwrap(), "nil");
+    assert_eq!(read("(isa? :user/square49 :user/rect49)").unwrap(), "true");
+    assert_eq!(read("(isa? :user/rect49 :user/square49)").unwrap(), "false");
+    assert_eq!(read("(isa? 1 1)").unwrap(), "true");
+    assert_eq!(
+        read("(parents :user/square49)").unwrap(),
+        "#{:user/rect49 }"
+    );
+    assert_eq!(read("(derive :user/rect49 :user/shape49)").unwrap(), "nil");
+    assert_eq!(read("(count (ancestors :user/square49))").unwrap(), "2");
+    assert_eq!(read("(count (descendants :user/shape49))").unwrap(), "2");
+    assert_eq!(read("(parents :user/shape49)").unwrap(), "nil");
+    assert!(read("(derive :user/shape49 :user/square49)").is_err());
+    assert!(read("(derive :square49 :rect49)").is_err());
+
+    assert!(read("(defmulti kind49 identity49)").is_err());
+    assert!(read("(defmulti kind49 (fn [x] x))").is_ok());
+    assert!(read("(defmethod kind49 :user/rect49 [_] :rect)").is_ok());
+    assert_eq!(read("(kind49 :user/square49)").unwrap(), ":rect");
+
+    assert_eq!(
+        read("(derive :user/square49 :user/equal49)").unwrap(),
+        "nil"
+    );
+    assert!(read("(defmethod kind49 :user/equal49 [_] :equal)").is_ok());
+    assert!(read("(kind49 :user/square49)").is_err());
+    assert!(read("(prefer-method kind49 :user/equal49 :user/rect49)").is_ok());
+    assert_eq!(read("(kind49 :user/square49)").unwrap(), ":equal");
+    assert!(read("(prefer-method kind49 :user/rect49 :user/equal49)").is_err());
+
+    assert_eq!(
+        read("(underive :user/square49 :user/rect49)").unwrap(),
+        "nil"
+    );
+    assert_eq!(
+        read("(isa? :user/square49 :user/shape49)").unwrap(),
+        "false"
+    );
+}
+
+#[test]
+fn ad_hoc_hierarchies() {
+    assert_eq!(
+        read("(def h49 (-> (make-hierarchy) (derive :dog :animal) (derive :cat :animal)))")
+            .unwrap(),
+        "#'user/h49"
+    );
+    assert_eq!(read("(isa? h49 :dog :animal)").unwrap(), "true");
+    assert_eq!(read("(isa? :dog :animal)").unwrap(), "false");
+    assert_eq!(
+        read("(isa? h49 [:dog :cat] [:animal :animal])").unwrap(),
+        "true"
+    );
+    assert_eq!(
+        read("(isa? h49 [:dog :cat] [:animal :dog])").unwrap(),
+        "false"
+    );
+    assert_eq!(
+        read("(= (descendants h49 :animal) #{:dog :cat})").unwrap(),
+        "true"
+    );
+    assert_eq!(
+        read("(parents (underive h49 :dog :animal) :dog)").unwrap(),
+        "nil"
+    );
+
+    assert!(read("(defmulti speak49 (fn [x] x) :hierarchy #'h49 :default :none)").is_ok());
+    assert!(read("(defmethod speak49 :animal [_] :generic)").is_ok());
+    assert!(read("(defmethod speak49 :none [_] :silence)").is_ok());
+    assert_eq!(read("(speak49 :dog)").unwrap(), ":generic");
+    assert_eq!(read("(speak49 :rock)").unwrap(), ":silence");
+}
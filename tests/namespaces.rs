@@ -0,0 +1,98 @@
+use ClojuRS::{funtions::namespaces::completions, read};
+
+#[test]
+fn ns_and_in_ns() {
+    assert_eq!(read("(str *ns*)").unwrap(), "\"user\"");
+    assert_eq!(
+        read("(do (ns app.ns34) (def x 1) (let [n (str *ns*)] (in-ns 'user) n))").unwrap(),
+        "\"app.ns34\""
+    );
+    assert_eq!(read("(def x 2)").unwrap(), "#'user/x");
+    assert_eq!(read("(vector x app.ns34/x)").unwrap(), "[2 1 ]");
+    assert_eq!(
+        read("(do (in-ns 'app.ns34) (def y (inc x)) (in-ns 'user) app.ns34/y)").unwrap(),
+        "2"
+    );
+}
+
+#[test]
+fn require_as_and_refer() {
+    read("(do (ns lib.ns34) (defn greet [n] (* n 10)) (def answer 42) (in-ns 'user))").unwrap();
+    assert_eq!(
+        read("(do (require '[lib.ns34 :as l :refer [answer]]) [(l/greet 1) answer])").unwrap(),
+        "[10 42 ]"
+    );
+    assert_eq!(
+        read("(do (ns app2.ns34 (:require [lib.ns34 :refer :all])) (let [r (greet 2)] (in-ns 'user) r))")
+            .unwrap(),
+        "20"
+    );
+    assert!(read("(require 'missing.ns34)").is_err());
+}
+
+#[test]
+fn refer_and_alias() {
+    read("(do (ns util.ns34) (def a 1) (def b 2) (in-ns 'user))").unwrap();
+    assert_eq!(
+        read("(do (ns refer.ns34) (refer 'util.ns34 :exclude '[b]) (alias 'u 'util.ns34) (let [r [a u/b]] (in-ns 'user) r))")
+            .unwrap(),
+        "[1 2 ]"
+    );
+    assert!(read("(do (in-ns 'refer.ns34) b)").is_err());
+    read("(in-ns 'user)").unwrap();
+}
+
+#[test]
+fn publics_and_resolve() {
+    read("(do (ns pub.ns34) (def one 1) (in-ns 'user))").unwrap();
+    assert_eq!(
+        read("(ns-publics 'pub.ns34)").unwrap(),
+        "{one #'pub.ns34/one }"
+    );
+    assert_eq!(
+        read("(ns-resolve 'pub.ns34 'one)").unwrap(),
+        "#'pub.ns34/one"
+    );
+    assert_eq!(read("(resolve 'inc)").unwrap(), "#'clojure.core/inc");
+    assert_eq!(read("(resolve 'when)").unwrap(), "#'clojure.core/when");
+    assert_eq!(read("(resolve 'nothing-34)").unwrap(), "nil");
+}
+
+#[test]
+fn core_builtins() {
+    assert_eq!(read("(clojure.core/inc 1)").unwrap(), "2");
+    assert_eq!(read("(clojure.core/when true 1)").unwrap(), "1");
+    assert_eq!(
+        read("(do (def inc-34 (fn [x] (+ x 10))) (inc-34 1))").unwrap(),
+        "11"
+    );
+}
+
+#[test]
+fn auto_resolved_keywords() {
+    assert_eq!(read("::k").unwrap(), ":user/k");
+    read("(do (ns kw.ns34) (in-ns 'user) (alias 'kw 'kw.ns34))").unwrap();
+    assert_eq!(read("::kw/k").unwrap(), ":kw.ns34/k");
+    assert!(read("::nope/k").is_err());
+}
+
+#[test]
+fn syntax_quote_qualifies() {
+    assert_eq!(
+        read("`(if x (inc y))").unwrap(),
+        "(if user/x (clojure.core/inc user/y ) )"
+    );
+}
+
+#[test]
+fn complete_names() {
+    read("(do (ns compl.ns38) (def widget-38 1) (in-ns 'user))").unwrap();
+    read("(do (def gadget-38 2) (alias 'c38 'compl.ns38) :seen-kw-38)").unwrap();
+    assert_eq!(completions("gadget-3"), vec!["gadget-38"]);
+    assert!(completions("macroexp").contains(&String::from("macroexpand-1")));
+    assert_eq!(completions("c38/wid"), vec!["c38/widget-38"]);
+    assert_eq!(completions("compl.ns38/"), vec!["compl.ns38/widget-38"]);
+    assert!(completions("compl.").contains(&String::from("compl.ns38")));
+    assert_eq!(completions(":seen-kw"), vec![":seen-kw-38"]);
+    assert!(completions("widget-3").is_empty());
+}
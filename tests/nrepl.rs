@@ -0,0 +1,374 @@
+use std::{
+    io::{BufReader, Write},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+use ClojuRS::nrepl::{bencode::Bencode, Server};
+
+struct Client {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Client {
+    fn connect() -> Client {
+        let server = Server::bind(("127.0.0.1", 0)).unwrap();
+        let port = server.port().unwrap();
+        thread::spawn(move || server.run());
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        Client {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            stream,
+        }
+    }
+
+    /// Another client of the same server.
+    fn reconnect(&self) -> Client {
+        let stream = TcpStream::connect(self.stream.peer_addr().unwrap()).unwrap();
+        Client {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            stream,
+        }
+    }
+
+    fn send(&mut self, msg: Vec<(&str, &str)>) {
+        let msg = Bencode::dict(msg.into_iter().map(|(k, v)| (k, Bencode::str(v))));
+        self.stream.write_all(&msg.encode()).unwrap();
+    }
+
+    /// Every response to `id` up to and including the one with status `done`.
+    fn responses(&mut self, id: &str) -> Vec<Bencode> {
+        let mut responses = Vec::new();
+        loop {
+            let response = Bencode::decode(&mut self.reader).unwrap().unwrap();
+            if response.get_str("id") != Some(id) {
+                continue;
+            }
+            let done = matches!(&response, Bencode::Dict(dict)
+                if matches!(dict.get("status"), Some(Bencode::List(s)) if s.contains(&Bencode::str("done"))));
+            responses.push(response);
+            if done {
+                return responses;
+            }
+        }
+    }
+
+    fn request(&mut self, msg: Vec<(&str, &str)>) -> Vec<Bencode> {
+        let id = msg.iter().find(|(k, _)| *k == "id").unwrap().1.to_owned();
+        self.send(msg);
+        self.responses(&id)
+    }
+
+    fn values(&mut self, msg: Vec<(&str, &str)>) -> Vec<String> {
+        self.request(msg)
+            .iter()
+            .filter_map(|r| r.get_str("value").map(str::to_owned))
+            .collect()
+    }
+}
+
+fn get<'a>(response: &'a Bencode, key: &str) -> Option<&'a Bencode> {
+    match response {
+        Bencode::Dict(dict) => dict.get(key),
+        _ => None,
+    }
+}
+
+#[test]
+fn bencode_round_trip() {
+    let value = Bencode::dict(vec![
+        ("op", Bencode::str("eval")),
+        ("n", Bencode::Int(-42)),
+        ("list", Bencode::list(vec!["a", "λ"])),
+    ]);
+    let encoded = value.encode();
+    assert_eq!(
+        String::from_utf8_lossy(&encoded),
+        "d4:listl1:a2:λe1:ni-42e2:op4:evale"
+    );
+    assert_eq!(Bencode::decode(&mut &encoded[..]).unwrap(), Some(value));
+    assert_eq!(Bencode::decode(&mut &b""[..]).unwrap(), None);
+    assert!(Bencode::decode(&mut &b"d3:key"[..]).is_err());
+    assert!(Bencode::decode(&mut &b"99999999999:"[..]).is_err());
+    assert!(Bencode::decode(&mut &b"10:short"[..]).is_err());
+}
+
+#[test]
+fn describe_and_unknown_op() {
+    let mut client = Client::connect();
+    let describe = client.request(vec![("op", "describe"), ("id", "1")]);
+    let ops = get(&describe[0], "ops").unwrap();
+    for op in [
+        "clone",
+        "describe",
+        "eval",
+        "load-file",
+        "interrupt",
+        "completions",
+        "lookup",
+    ] {
+        assert!(get(ops, op).is_some(), "{} is not described", op);
+    }
+    let unknown = client.request(vec![("op", "frobnicate"), ("id", "2")]);
+    assert_eq!(
+        get(&unknown[0], "status"),
+        Some(&Bencode::list(vec!["error", "unknown-op", "done"]))
+    );
+}
+
+#[test]
+fn eval_values_output_and_errors() {
+    let mut client = Client::connect();
+    assert_eq!(
+        client.values(vec![
+            ("op", "eval"),
+            ("code", "(+ 1 2) (* 2 5)"),
+            ("id", "1")
+        ]),
+        vec!["3", "10"]
+    );
+    assert_eq!(
+        client.values(vec![("op", "eval"), ("code", "(+ *1 *2)"), ("id", "2")]),
+        vec!["13"]
+    );
+
+    let printed = client.request(vec![("op", "eval"), ("code", "(println! 40)"), ("id", "3")]);
+    assert_eq!(printed[0].get_str("out"), Some("40\n"));
+    assert_eq!(printed[1].get_str("value"), Some("nil"));
+
+    let failed = client.request(vec![("op", "eval"), ("code", "(/ 1 0)"), ("id", "4")]);
+    assert!(failed[0]
+        .get_str("err")
+        .unwrap()
+        .starts_with("ArithmeticException: Divide by zero"));
+    assert_eq!(failed[1].get_str("ex"), Some("ArithmeticException"));
+    assert_eq!(
+        client.values(vec![
+            ("op", "eval"),
+            ("code", "(ex-message *e)"),
+            ("id", "5")
+        ]),
+        vec!["\"Divide by zero\""]
+    );
+    assert_eq!(
+        client.values(vec![
+            ("op", "eval"),
+            ("code", "(ns kw.nrepl35) ::k"),
+            ("id", "6")
+        ]),
+        vec!["nil", ":kw.nrepl35/k"]
+    );
+}
+
+#[test]
+fn sessions_are_isolated() {
+    let mut client = Client::connect();
+    let clone = |client: &mut Client, id: &str| {
+        client.request(vec![("op", "clone"), ("id", id)])[0]
+            .get_str("new-session")
+            .unwrap()
+            .to_owned()
+    };
+    let first = clone(&mut client, "1");
+    let second = clone(&mut client, "2");
+    assert_ne!(first, second);
+
+    client.values(vec![
+        ("op", "eval"),
+        ("code", "(ns nrepl.one40) 1"),
+        ("session", &first),
+        ("id", "3"),
+    ]);
+    let in_first = client.request(vec![
+        ("op", "eval"),
+        ("code", "(str *ns*)"),
+        ("session", &first),
+        ("id", "4"),
+    ]);
+    assert_eq!(in_first[0].get_str("ns"), Some("nrepl.one40"));
+    assert_eq!(
+        client.values(vec![
+            ("op", "eval"),
+            ("code", "[(str *ns*) *1]"),
+            ("session", &second),
+            ("id", "5"),
+        ]),
+        vec!["[\"user\" nil ]"]
+    );
+
+    let unknown = client.request(vec![
+        ("op", "eval"),
+        ("code", "1"),
+        ("session", "nope"),
+        ("id", "6"),
+    ]);
+    assert_eq!(
+        get(&unknown[0], "status"),
+        Some(&Bencode::list(vec!["error", "unknown-session", "done"]))
+    );
+}
+
+#[test]
+fn default_session_ends_with_its_connection() {
+    let mut client = Client::connect();
+    let eval = client.request(vec![
+        ("op", "eval"),
+        ("code", "(ns nrepl.two40)"),
+        ("id", "1"),
+    ]);
+    let session = eval[0].get_str("session").unwrap().to_owned();
+    let describe = client.request(vec![("op", "describe"), ("session", &session), ("id", "2")]);
+    assert_eq!(
+        get(&describe[0], "aux"),
+        Some(&Bencode::dict(vec![(
+            "current-ns",
+            Bencode::str("nrepl.two40")
+        )]))
+    );
+
+    let mut other = client.reconnect();
+    drop(client);
+    let unknown = Bencode::list(vec!["error", "unknown-session", "done"]);
+    let mut status = None;
+    for _ in 0..50 {
+        let response = other.request(vec![
+            ("op", "eval"),
+            ("code", "1"),
+            ("session", &session),
+            ("id", "3"),
+        ]);
+        status = get(&response[0], "status").cloned();
+        if status.as_ref() == Some(&unknown) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(status, Some(unknown));
+}
+
+#[test]
+fn load_file_completions_and_lookup() {
+    let mut client = Client::connect();
+    assert_eq!(
+        client.values(vec![
+            ("op", "load-file"),
+            (
+                "file",
+                "(ns loaded.ns40)\n(defn shout-40 \"Loud.\" [s] s)\n(shout-40 7)"
+            ),
+            ("file-path", "loaded/ns40.clj"),
+            ("id", "1"),
+        ]),
+        vec!["7"]
+    );
+
+    let completions = client.request(vec![
+        ("op", "completions"),
+        ("prefix", "shout-4"),
+        ("ns", "loaded.ns40"),
+        ("id", "2"),
+    ]);
+    assert_eq!(
+        get(&completions[0], "completions"),
+        Some(&Bencode::List(vec![Bencode::dict(vec![
+            ("candidate", Bencode::str("shout-40")),
+            ("type", Bencode::str("var")),
+        ])]))
+    );
+
+    let lookup = client.request(vec![
+        ("op", "lookup"),
+        ("sym", "shout-40"),
+        ("ns", "loaded.ns40"),
+        ("id", "3"),
+    ]);
+    let info = get(&lookup[0], "info").unwrap();
+    assert_eq!(info.get_str("ns"), Some("loaded.ns40"));
+    assert_eq!(info.get_str("arglists-str"), Some("([s])"));
+    assert_eq!(info.get_str("doc"), Some("Loud."));
+}
+
+#[test]
+fn interrupt() {
+    let mut client = Client::connect();
+    let session = client.request(vec![("op", "clone"), ("id", "1")])[0]
+        .get_str("new-session")
+        .unwrap()
+        .to_owned();
+    let idle = client.request(vec![
+        ("op", "interrupt"),
+        ("session", &session),
+        ("id", "2"),
+    ]);
+    assert_eq!(
+        get(&idle[0], "status"),
+        Some(&Bencode::list(vec!["session-idle", "done"]))
+    );
+
+    client.send(vec![
+        ("op", "eval"),
+        ("code", "(loop [i 0] (recur (inc i)))"),
+        ("session", &session),
+        ("id", "3"),
+    ]);
+    thread::sleep(Duration::from_millis(200));
+    client.send(vec![
+        ("op", "interrupt"),
+        ("session", &session),
+        ("interrupt-id", "3"),
+        ("id", "4"),
+    ]);
+    let interrupted = client.responses("3");
+    assert!(interrupted
+        .iter()
+        .any(|r| get(r, "status") == Some(&Bencode::list(vec!["interrupted"]))));
+    assert_eq!(
+        client.values(vec![
+            ("op", "eval"),
+            ("code", "(inc 1)"),
+            ("session", &session),
+            ("id", "5")
+        ]),
+        vec!["2"]
+    );
+}
+
+#[test]
+fn deep_recursion_is_an_eval_error() {
+    let mut client = Client::connect();
+    let failed = client.request(vec![
+        ("op", "eval"),
+        ("code", "(defn deep40 [x] (deep40 x)) (deep40 1)"),
+        ("id", "1"),
+    ]);
+    assert!(failed
+        .iter()
+        .any(|r| r.get_str("ex") == Some("StackOverflowError")));
+    assert!(failed
+        .iter()
+        .any(|r| get(r, "status") == Some(&Bencode::list(vec!["eval-error"]))));
+    assert_eq!(
+        client.values(vec![("op", "eval"), ("code", "(inc 1)"), ("id", "2")]),
+        vec!["2"]
+    );
+    assert_eq!(
+        client
+            .reconnect()
+            .values(vec![("op", "eval"), ("code", "(inc 2)"), ("id", "3")]),
+        vec!["3"]
+    );
+}
+
+#[test]
+fn port_file() {
+    let server = Server::bind(("127.0.0.1", 0)).unwrap();
+    let dir = std::env::temp_dir().join(format!("clojurs-nrepl40-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = server.write_port_file(&dir).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap(),
+        server.port().unwrap().to_string()
+    );
+}
@@ -0,0 +1,133 @@
+use ClojuRS::read;
+
+#[test]
+fn protocols_and_records() {
+    assert_eq!(
+        read("(defprotocol Shape48 \"Shapes\" (area48 [this] \"The area\") (scale48 [this k]))")
+            .unwrap(),
+        "Shape48"
+    );
+    assert_eq!(
+        read("(defrecord Rect48 [w h] Shape48 (area48 [this] (* w h)) (scale48 [this k] (->Rect48 (* w k) (* h k))))")
+            .unwrap(),
+        "user.Rect48"
+    );
+    assert_eq!(read("(area48 (->Rect48 2 3))").unwrap(), "6");
+    assert_eq!(
+        read("(scale48 (->Rect48 2 3) 2)").unwrap(),
+        "#user.Rect48{:w 4 :h 6 }"
+    );
+    assert_eq!(read("(get (meta #'area48) :doc)").unwrap(), "\"The area\"");
+    assert_eq!(
+        read("(get (meta #'scale48) :arglists)").unwrap(),
+        "([this k ] )"
+    );
+    assert_eq!(
+        read("(try (area48 \"s\") (catch IllegalArgumentException e (ex-message e)))").unwrap(),
+        "\"No implementation of method: :area48 of protocol: #'user/Shape48 found for class: java.lang.String\""
+    );
+}
+
+#[test]
+fn records_are_maps() {
+    assert_eq!(read("(defrecord Point48 [x y])").unwrap(), "user.Point48");
+    assert_eq!(read("(:x (->Point48 1 2))").unwrap(), "1");
+    assert_eq!(read("(get (->Point48 1 2) :y)").unwrap(), "2");
+    assert_eq!(read("(count (->Point48 1 2))").unwrap(), "2");
+    assert_eq!(read("(= (->Point48 1 2) (->Point48 1 2))").unwrap(), "true");
+    assert_eq!(read("(= (->Point48 1 2) {:x 1 :y 2})").unwrap(), "false");
+    assert_eq!(
+        read("(conj (->Point48 1 2) [:x 5] {:z 3})").unwrap(),
+        "#user.Point48{:x 5 :y 2 :z 3 }"
+    );
+    assert_eq!(
+        read("(map->Point48 {:y 2})").unwrap(),
+        "#user.Point48{:x nil :y 2 }"
+    );
+    assert_eq!(
+        read("(new Point48 3 4)").unwrap(),
+        "#user.Point48{:x 3 :y 4 }"
+    );
+    assert!(read("(->Point48 1)").is_err());
+    assert_eq!(read("(type (->Point48 1 2))").unwrap(), "user.Point48");
+    assert_eq!(read("(= (type (->Point48 1 2)) Point48)").unwrap(), "true");
+    assert_eq!(read("(record? (->Point48 1 2))").unwrap(), "true");
+    assert_eq!(read("(record? {:x 1})").unwrap(), "false");
+
+    read("(def zw48 (conj (->Point48 1 2) [:z 3] [:w 4]))").unwrap();
+    read("(def wz48 (conj (->Point48 1 2) [:w 4] [:z 3]))").unwrap();
+    assert_eq!(read("(= zw48 wz48)").unwrap(), "true");
+    assert_eq!(read("(count (conj #{zw48} wz48))").unwrap(), "1");
+    assert_eq!(read("(get {zw48 :found} wz48)").unwrap(), ":found");
+}
+
+#[test]
+fn extending_builtin_types() {
+    assert_eq!(
+        read("(defprotocol Describe48 (describe48 [x]))").unwrap(),
+        "Describe48"
+    );
+    assert_eq!(
+        read(
+            "(extend-protocol Describe48
+               String (describe48 [s] :string)
+               clojure.lang.PersistentVector (describe48 [v] (count v))
+               nil (describe48 [_] :nothing)
+               Number (describe48 [n] :number))"
+        )
+        .unwrap(),
+        "nil"
+    );
+    assert_eq!(read("(describe48 \"a\")").unwrap(), ":string");
+    assert_eq!(read("(describe48 [1 2 3])").unwrap(), "3");
+    assert_eq!(read("(describe48 nil)").unwrap(), ":nothing");
+    assert_eq!(read("(describe48 1.5)").unwrap(), ":number");
+    assert!(read("(describe48 :k)").is_err());
+    assert_eq!(read("(satisfies? Describe48 :k)").unwrap(), "false");
+
+    assert_eq!(
+        read("(extend-type clojure.lang.Keyword Describe48 (describe48 [k] :keyword))").unwrap(),
+        "nil"
+    );
+    assert_eq!(read("(describe48 :k)").unwrap(), ":keyword");
+    assert_eq!(read("(satisfies? Describe48 :k)").unwrap(), "true");
+    assert_eq!(
+        read("(extend-type Object Describe48 (describe48 [_] :object))").unwrap(),
+        "nil"
+    );
+    assert_eq!(read("(describe48 #{})").unwrap(), ":object");
+    assert_eq!(read("(describe48 \"a\")").unwrap(), ":string");
+}
+
+#[test]
+fn types_and_reify() {
+    assert_eq!(
+        read("(defprotocol Counter48 (total48 [c]) (add48 [c n]))").unwrap(),
+        "Counter48"
+    );
+    assert_eq!(
+        read("(deftype Tally48 [n] Counter48 (total48 [this] n) (add48 [this k] (->Tally48 (+ n k))))")
+            .unwrap(),
+        "user.Tally48"
+    );
+    assert_eq!(read("(total48 (add48 (->Tally48 1) 2))").unwrap(), "3");
+    assert_eq!(read("(:n (->Tally48 1))").unwrap(), "nil");
+    assert_eq!(read("(let [t (->Tally48 1)] (= t t))").unwrap(), "true");
+    assert_eq!(read("(= (->Tally48 1) (->Tally48 1))").unwrap(), "false");
+    assert_eq!(
+        read("(satisfies? Counter48 (->Tally48 1))").unwrap(),
+        "true"
+    );
+
+    assert_eq!(
+        read("(def r48 (let [base 10] (reify Counter48 (total48 [_] base) (add48 [_ k] (+ base k)))))")
+            .unwrap(),
+        "#'user/r48"
+    );
+    assert_eq!(read("(total48 r48)").unwrap(), "10");
+    assert_eq!(read("(add48 r48 5)").unwrap(), "15");
+    assert_eq!(read("(satisfies? Counter48 r48)").unwrap(), "true");
+    assert_eq!(read("(satisfies? Counter48 1)").unwrap(), "false");
+    assert!(read("(reify Counter48 (missing48 [_] 1))").is_err());
+    assert!(read("(extend-type String NotAProtocol48 (f [_] 1))").is_err());
+}
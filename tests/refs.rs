@@ -0,0 +1,110 @@
+use std::thread;
+
+use ClojuRS::{error::Error, read};
+
+#[test]
+fn alter_ref_set_and_commute() {
+    assert_eq!(read("(def r43 (ref 1))").unwrap(), "#'user/r43");
+    assert_eq!(read("(dosync (alter r43 + 10))").unwrap(), "11");
+    assert_eq!(read("@r43").unwrap(), "11");
+    assert_eq!(read("(do r43)").unwrap(), "#ref[11]");
+    assert_eq!(
+        read("(dosync (ref-set r43 0) (commute r43 inc) @r43)").unwrap(),
+        "1"
+    );
+    assert_eq!(
+        read("(dosync (commute r43 inc) (commute r43 + 10))").unwrap(),
+        "12"
+    );
+    assert_eq!(
+        read("(dosync (dosync (alter r43 inc)) (ensure r43))").unwrap(),
+        "13"
+    );
+    assert_eq!(read("(deref r43)").unwrap(), "13");
+}
+
+#[test]
+fn changes_need_a_transaction() {
+    assert_eq!(read("(def n43 (ref 0))").unwrap(), "#'user/n43");
+    for form in [
+        "(alter n43 inc)",
+        "(commute n43 inc)",
+        "(ref-set n43 1)",
+        "(ensure n43)",
+    ] {
+        assert_eq!(
+            read(form).err(),
+            Some(Error::illegal_state("No transaction running"))
+        );
+    }
+    assert_eq!(
+        read("(dosync (commute n43 inc) (alter n43 inc))").err(),
+        Some(Error::illegal_state("Can't set after commute"))
+    );
+    assert_eq!(read("@n43").unwrap(), "0");
+}
+
+#[test]
+fn errors_roll_back() {
+    assert_eq!(read("(def e43 (ref [:a]))").unwrap(), "#'user/e43");
+    assert_eq!(
+        read("(try (dosync (alter e43 conj :b) (throw \"boom\")) (catch Exception e @e43))")
+            .unwrap(),
+        "[:a ]"
+    );
+}
+
+fn run_threads(count: usize, f: fn(usize)) {
+    let threads = (0..count)
+        .map(|i| thread::spawn(move || f(i)))
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn contending_counters() {
+    read("(def altered43 (ref 0))").unwrap();
+    read("(def commuted43 (ref 0))").unwrap();
+    run_threads(4, |_| {
+        for _ in 0..50 {
+            read("(dosync (alter altered43 inc) (commute commuted43 inc))").unwrap();
+        }
+    });
+    assert_eq!(read("@altered43").unwrap(), "200");
+    assert_eq!(read("@commuted43").unwrap(), "200");
+}
+
+#[test]
+fn transfers_keep_the_total() {
+    read("(def accounts43 [(ref 100) (ref 100) (ref 100) (ref 100) (ref 100)])").unwrap();
+    read(
+        "(defn transfer43 [from to amount]
+           (dosync
+             (alter (nth accounts43 from) - amount)
+             (alter (nth accounts43 to) + amount)))",
+    )
+    .unwrap();
+    read(
+        "(defn total43 []
+           (dosync
+             (+ @(nth accounts43 0) @(nth accounts43 1) @(nth accounts43 2)
+                @(nth accounts43 3) @(nth accounts43 4))))",
+    )
+    .unwrap();
+
+    run_threads(6, |i| {
+        for k in 0..40 {
+            if i % 3 == 0 {
+                // Readers see every transfer entirely or not at all.
+                assert_eq!(read("(total43)").unwrap(), "500");
+            } else {
+                let from = (i + k) % 5;
+                let to = (i + 2 * k + 1) % 5;
+                read(&format!("(transfer43 {} {} {})", from, to, k % 7)).unwrap();
+            }
+        }
+    });
+    assert_eq!(read("(total43)").unwrap(), "500");
+}
@@ -0,0 +1,128 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use ClojuRS::{
+    funtions::{capture_output, docs::documented_natives},
+    read, LIBRARIES, STD,
+};
+
+/// What the REPL prints for `input`.
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ClojuRS"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    String::from_utf8_lossy(&child.wait_with_output().unwrap().stdout).into_owned()
+}
+
+#[test]
+fn result_history() {
+    let out = repl("(+ 1 2)\n(* 2 5)\n[*1 *2]\n(inc *3)\n(/ 1 0)\n(ex-message *e)\n");
+    assert_eq!(
+        out.lines()
+            .map(|line| line.trim_start_matches("Crs > "))
+            .collect::<Vec<&str>>(),
+        vec![
+            "3",
+            "10",
+            "[10 3 ]",
+            "4",
+            "ArithmeticException: Divide by zero",
+            "  at / (/ 1 0 )",
+            "\"Divide by zero\"",
+            "",
+        ]
+    );
+}
+
+#[test]
+fn doc_and_source() {
+    let out = repl(
+        "(defn area-39\n  \"Area of a square.\"\n  [side] (* side side))\n(doc area-39)\n(source area-39)\n(doc when)\n",
+    );
+    assert!(out.contains("user/area-39\n([side])\n  Area of a square.\n"));
+    assert!(out.contains("(defn area-39\n  \"Area of a square.\"\n  [side] (* side side))\n"));
+    assert!(out.contains("clojure.core/when\n([test & body])\nMacro\n"));
+    let (_, when) = capture_output(|| read("(source when)").unwrap());
+    assert_eq!(
+        when,
+        "(defmacro when\n  \"Evaluates body in an implicit do when test is truthy.\"\n  \
+         [test & body]\n  `(if ~test (do ~@body)))\n"
+    );
+}
+
+#[test]
+fn builtin_docs() {
+    let doc = |name: &str| capture_output(|| read(&format!("(doc {})", name)).unwrap()).1;
+    assert_eq!(
+        doc("swap!"),
+        "-------------------------\nclojure.core/swap!\n([atom f & args])\n  \
+         Sets the atom to (apply f value args), retrying on conflict. Returns the new value.\n"
+    );
+    for name in STD.keys() {
+        let lines = doc(name).lines().count();
+        assert_eq!(lines, 4, "`{}` should have arglists and a docstring", name);
+    }
+    for (ns, library) in LIBRARIES.iter() {
+        read(&format!("(require '{})", ns)).unwrap();
+        for name in library.natives.keys() {
+            let lines = doc(&format!("{}/{}", ns, name)).lines().count();
+            assert_eq!(
+                lines, 4,
+                "`{}/{}` should have arglists and a docstring",
+                ns, name
+            );
+        }
+    }
+    for name in documented_natives("clojure.core") {
+        assert!(
+            STD.contains_key(name),
+            "`{}` is documented but not a builtin",
+            name
+        );
+    }
+    for (ns, library) in LIBRARIES.iter() {
+        for name in documented_natives(ns) {
+            assert!(
+                library.natives.contains_key(name),
+                "`{}/{}` is documented but not a builtin",
+                ns,
+                name
+            );
+        }
+    }
+    read("(require '[clojure.string :as str39])").unwrap();
+    assert!(doc("str39/join").contains("clojure.string/join\n([coll] [separator coll])\n"));
+    let (_, found) = capture_output(|| read("(find-doc \"conjoined\")").unwrap());
+    assert!(found.contains("clojure.core/into\n"));
+}
+
+#[test]
+fn find_doc_and_dir() {
+    let out = repl(
+        "(ns tools39)\n(def b 1)\n(def a \"Alpha value.\" 2)\n(in-ns 'user)\n(dir tools39)\n(find-doc \"Alpha\")\n",
+    );
+    assert!(out.contains("a\nb\n"));
+    assert!(out.contains("tools39/a\n  Alpha value.\n"));
+}
+
+#[test]
+fn apropos() {
+    read("(defn frobnicate-39 [x] x)").unwrap();
+    assert_eq!(
+        read("(apropos \"frobnicate-3\")").unwrap(),
+        "(user/frobnicate-39 )"
+    );
+    assert!(read("(apropos \"macroexpand\")")
+        .unwrap()
+        .contains("clojure.core/macroexpand-1"));
+}
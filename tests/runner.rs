@@ -0,0 +1,149 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Command, Output, Stdio},
+};
+
+use ClojuRS::is_balanced;
+
+fn clojurs(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ClojuRS"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn eval_option() {
+    let output = clojurs(&["-e", "(+ 1 2)", "-e", "(def x 1)", "-e", "nil"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "3\n#'user/x\n");
+
+    let output = clojurs(&["-e", "(def y 2) y", "-e", "42"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "2\n42\n");
+}
+
+#[test]
+fn script_file_with_args() {
+    let path = std::env::temp_dir().join(format!("clojurs-script36-{}.clj", std::process::id()));
+    fs::write(
+        &path,
+        "(println! (count *command-line-args*))\n(println! (first *command-line-args*))",
+    )
+    .unwrap();
+    let output = clojurs(&[path.to_str().unwrap(), "a", "b"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "2\n\"a\"\n");
+}
+
+#[test]
+fn stdin_script() {
+    let output = clojurs(&["-"], "(println! (* 6 7))");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "42\n");
+}
+
+#[test]
+fn main_namespace() {
+    let root = std::env::temp_dir().join(format!("clojurs-main36-{}", std::process::id()));
+    fs::create_dir_all(root.join("tool36")).unwrap();
+    fs::write(
+        root.join("tool36/cli.clj"),
+        "(ns tool36.cli)\n(defn -main [& args] (println! (count args)))",
+    )
+    .unwrap();
+    let output = clojurs(
+        &[
+            "-cp",
+            root.to_str().unwrap(),
+            "-m",
+            "tool36.cli",
+            "x",
+            "y",
+            "z",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn uncaught_errors_exit_non_zero() {
+    let output = clojurs(&["-e", "(throw \"boom\")"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("ExceptionInfo: boom"));
+
+    let output = clojurs(&["-e", "(+ 1", "-e", "(println! 1)"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn repl_exits_at_eof() {
+    let output = clojurs(&[], "(+ 1 1)\n");
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("2"));
+}
+
+#[test]
+fn balanced_input() {
+    assert!(is_balanced("(+ 1 2)"));
+    assert!(is_balanced("[1 {:a #{2}}] (inc 1)"));
+    assert!(!is_balanced("(defn f [x]\n"));
+    assert!(!is_balanced("(str \"a)"));
+    assert!(is_balanced("(str \"(\" \\( \"\\\"\") ; (unclosed"));
+}
+
+#[test]
+fn repl_reads_continuation_lines() {
+    let output = clojurs(&[], "(+ 1\n   2)\n(def y37 5) (inc y37)\n");
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Crs >   #_=> 3\nCrs > #'user/y37\n6\nCrs > \n"
+    );
+
+    let output = clojurs(&[], "(+ 1\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Crs >   #_=> \n");
+}
+
+#[test]
+fn socket_repl_option() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ClojuRS"))
+        .args(["--socket-repl", "127.0.0.1:0"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut banner = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut banner)
+        .unwrap();
+    let port = banner.split_whitespace().nth(6).unwrap();
+    assert!(banner.starts_with("Socket REPL server started on port"));
+
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+    stream.write_all(b"(* 6 7)\n:repl/quit\n").unwrap();
+    let mut output = String::new();
+    stream.read_to_string(&mut output).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(output, "user=> 42\nuser=> ");
+}
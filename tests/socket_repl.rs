@@ -0,0 +1,109 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    thread,
+};
+
+use ClojuRS::socket_repl::{Protocol, SocketServer};
+
+fn connect(protocol: Protocol) -> TcpStream {
+    let server = SocketServer::bind(("127.0.0.1", 0), protocol).unwrap();
+    let port = server.port().unwrap();
+    thread::spawn(move || server.run());
+    TcpStream::connect(("127.0.0.1", port)).unwrap()
+}
+
+/// Everything the server sends back for `input` until the client quits.
+fn session(stream: &mut TcpStream, input: &str) -> String {
+    stream.write_all(input.as_bytes()).unwrap();
+    stream.write_all(b":repl/quit\n").unwrap();
+    let mut output = String::new();
+    stream.read_to_string(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn socket_repl_session() {
+    let mut stream = connect(Protocol::Repl);
+    let output = session(
+        &mut stream,
+        "(def x41 41)\n(inc x41)\n(println! \"hi\")\n(+ 1\n2) *1\n(/ 1 0)\n(ns sock41.session)\n",
+    );
+    assert_eq!(
+        output,
+        "user=> #'user/x41\n\
+         user=> 42\n\
+         user=> \"hi\"\nnil\n\
+         user=> 3\n3\n\
+         user=> ArithmeticException: Divide by zero\n  at / (/ 1 0 )\n\
+         user=> nil\n\
+         sock41.session=> "
+    );
+}
+
+#[test]
+fn clients_share_vars_but_not_namespaces() {
+    let mut first = connect(Protocol::Repl);
+    let output = session(&mut first, "(ns sock41.shared)\n(def answer 42)\n");
+    assert!(output.ends_with("sock41.shared=> "));
+
+    let mut second = connect(Protocol::Repl);
+    let output = session(&mut second, "sock41.shared/answer\n(str *ns*)\n");
+    assert_eq!(output, "user=> 42\nuser=> \"user\"\nuser=> ");
+}
+
+#[test]
+fn prepl_messages() {
+    let mut stream = connect(Protocol::Prepl);
+    let output = session(
+        &mut stream,
+        "(println! \"a\\\"b\")\n(throw \"boom\")\n(+ 1\n2)\n",
+    );
+    let lines = output
+        .lines()
+        .filter(|line| !line.starts_with("{:tag :tap"))
+        .map(|line| line.replace(|c: char| c.is_ascii_digit(), "0"))
+        .collect::<Vec<String>>();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{:tag :out, :val "\"a\"b\"\n"}"#,
+            r#"{:tag :ret, :val "nil", :ns "user", :ms 0, :form "(println! \"a\\\"b\")"}"#,
+            r#"{:tag :err, :val "ExceptionInfo: boom\n  at throw (throw \"boom\" )\n"}"#,
+            r##"{:tag :ret, :val "#error[ExceptionInfo \"boom\"]", :ns "user", :ms 0, :form "(throw \"boom\")", :exception true}"##,
+            r#"{:tag :ret, :val "0", :ns "user", :ms 0, :form "(+ 0\n0)"}"#,
+        ]
+    );
+}
+
+#[test]
+fn prepl_taps() {
+    let stream = connect(Protocol::Prepl);
+    let mut writer = stream.try_clone().unwrap();
+    writer.write_all(b"(tap> [:sock41 1])\n").unwrap();
+    let tap = BufReader::new(stream)
+        .lines()
+        .map(Result::unwrap)
+        .find(|line| line.starts_with("{:tag :tap") && line.contains(":sock41"));
+    assert_eq!(tap.as_deref(), Some(r#"{:tag :tap, :val "[:sock41 1 ]"}"#));
+}
+
+#[test]
+fn prepl_deep_recursion() {
+    let mut stream = connect(Protocol::Prepl);
+    let mut other = TcpStream::connect(stream.peer_addr().unwrap()).unwrap();
+    let output = session(
+        &mut stream,
+        "(defn deep41 [x] (deep41 x))\n(deep41 1)\n(inc 41)\n",
+    );
+    let lines = output
+        .lines()
+        .filter(|line| !line.starts_with("{:tag :tap"))
+        .collect::<Vec<&str>>();
+    assert!(
+        lines[1].starts_with(r#"{:tag :err, :val "StackOverflowError: Calls nested too deeply"#)
+    );
+    assert!(lines[2].contains(":exception true"));
+    assert!(lines[3].starts_with(r#"{:tag :ret, :val "42""#));
+    assert!(session(&mut other, "(inc 42)\n").contains(r#":val "43""#));
+}
@@ -16,6 +16,6 @@ fn keyword() {
     );
     assert_eq!(
         read("(keyword [:hello \"word\" ])").err(),
-        Some(Error::Thrown("Can't keywordize a collection".to_string()))
+        Some(Error::thrown("Can't keywordize a collection".to_string()))
     );
 }
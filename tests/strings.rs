@@ -0,0 +1,133 @@
+use ClojuRS::read;
+
+fn require_string() {
+    read("(require '[clojure.string :as s50])").unwrap();
+}
+
+#[test]
+fn join_split_and_lines() {
+    require_string();
+    assert_eq!(read("(s50/join [1 \"a\" \\b nil])").unwrap(), "\"1ab\"");
+    assert_eq!(read("(s50/join \", \" [1 2 3])").unwrap(), "\"1, 2, 3\"");
+    assert_eq!(
+        read("(s50/split \"a1b22c\" #\"\\d+\")").unwrap(),
+        "[\"a\" \"b\" \"c\" ]"
+    );
+    assert_eq!(
+        read("(s50/split \"a,b,,\" #\",\")").unwrap(),
+        "[\"a\" \"b\" ]"
+    );
+    assert_eq!(
+        read("(s50/split \"a,b,c\" #\",\" 2)").unwrap(),
+        "[\"a\" \"b,c\" ]"
+    );
+    assert_eq!(
+        read("(s50/split \"abc\" #\"\")").unwrap(),
+        "[\"a\" \"b\" \"c\" ]"
+    );
+    assert_eq!(read("(s50/split \"\" #\",\")").unwrap(), "[\"\" ]");
+    assert_eq!(
+        read("(clojure.string/split-lines \"one\\ntwo\\r\\nthree\\n\")").unwrap(),
+        "[\"one\" \"two\" \"three\" ]"
+    );
+}
+
+#[test]
+fn case_trim_and_predicates() {
+    require_string();
+    assert_eq!(read("(s50/trim \"  hi \\n\")").unwrap(), "\"hi\"");
+    assert_eq!(read("(s50/triml \"  hi \")").unwrap(), "\"hi \"");
+    assert_eq!(read("(s50/trimr \"  hi \")").unwrap(), "\"  hi\"");
+    assert_eq!(read("(s50/upper-case \"MiXed\")").unwrap(), "\"MIXED\"");
+    assert_eq!(read("(s50/lower-case \"MiXed\")").unwrap(), "\"mixed\"");
+    assert_eq!(read("(s50/capitalize \"hELLO\")").unwrap(), "\"Hello\"");
+    assert_eq!(read("(s50/capitalize \"\")").unwrap(), "\"\"");
+    assert_eq!(
+        read("(s50/starts-with? \"clojure\" \"clo\")").unwrap(),
+        "true"
+    );
+    assert_eq!(
+        read("(s50/ends-with? \"clojure\" \"clo\")").unwrap(),
+        "false"
+    );
+    assert_eq!(read("(s50/includes? \"clojure\" \"oju\")").unwrap(), "true");
+    assert_eq!(read("(s50/index-of \"héllo\" \\l)").unwrap(), "2");
+    assert_eq!(read("(s50/index-of \"héllo\" \"l\" 3)").unwrap(), "3");
+    assert_eq!(read("(s50/index-of \"héllo\" \"z\")").unwrap(), "nil");
+    assert_eq!(
+        read("[(s50/blank? nil) (s50/blank? \" \\t\") (s50/blank? \" a\")]").unwrap(),
+        "[true true false ]"
+    );
+    assert_eq!(read("(s50/reverse \"abc\")").unwrap(), "\"cba\"");
+    assert_eq!(
+        read("(s50/escape \"<a&b>\" {\\< \"&lt;\" \\> \"&gt;\" \\& \"&amp;\"})").unwrap(),
+        "\"&lt;a&amp;b&gt;\""
+    );
+}
+
+#[test]
+fn replace_with_strings_regexes_and_functions() {
+    require_string();
+    assert_eq!(
+        read("(s50/replace \"a.b.c\" \".\" \"-\")").unwrap(),
+        "\"a-b-c\""
+    );
+    assert_eq!(read("(s50/replace \"aXbX\" \\X \\y)").unwrap(), "\"ayby\"");
+    assert_eq!(
+        read("(s50/replace \"John Smith\" #\"(\\w+) (\\w+)\" \"$2, $1\")").unwrap(),
+        "\"Smith, John\""
+    );
+    assert_eq!(
+        read("(s50/replace \"a1b2\" #\"\\d\" (fn [d] (str (* 2 (count d)))))").unwrap(),
+        "\"a2b2\""
+    );
+    assert_eq!(
+        read("(s50/replace \"k=v\" #\"(\\w)=(\\w)\" (fn [[_ k v]] (s50/join [v \"=\" k])))")
+            .unwrap(),
+        "\"v=k\""
+    );
+    assert_eq!(
+        read("(s50/replace-first \"aaa\" #\"a\" \"b\")").unwrap(),
+        "\"baa\""
+    );
+    assert_eq!(
+        read("(s50/replace-first \"a.a\" \".\" \"!\")").unwrap(),
+        "\"a!a\""
+    );
+    assert!(read("(s50/replace \"abc\" \"b\" 1)").is_err());
+}
+
+#[test]
+fn regex_literals_and_core_string_fns() {
+    assert_eq!(read("#\"a\\d+\\\"\"").unwrap(), "#\"a\\d+\\\"\"");
+    assert_eq!(read("(= #\"a+\" #\"a+\")").unwrap(), "true");
+    assert_eq!(read("(type #\"a+\")").unwrap(), "java.util.regex.Pattern");
+    assert!(read("#\"(\"").is_err());
+    assert!(read("#\"abc").unwrap_err().span.is_some());
+    assert_eq!(
+        read("(- #\"a\" 1)").unwrap_err().message,
+        "Can't subtract #\"a\""
+    );
+    assert_eq!(
+        read("(/ #\"a\" 2)").unwrap_err().message,
+        "Can't divide #\"a\""
+    );
+
+    assert_eq!(read("(subs \"héllo\" 1)").unwrap(), "\"éllo\"");
+    assert_eq!(read("(subs \"héllo\" 1 3)").unwrap(), "\"él\"");
+    assert!(read("(subs \"abc\" 2 5)").is_err());
+    assert!(read("(subs \"abc\" 2 1)").is_err());
+    assert_eq!(read("(count \"héllo\")").unwrap(), "5");
+    assert_eq!(read("(seq \"ab\")").unwrap(), "(\\a \\b )");
+    assert_eq!(read("(char 97)").unwrap(), "\\a");
+    assert_eq!(read("(int \\a)").unwrap(), "97");
+    assert_eq!(read("(int 2.7)").unwrap(), "2");
+    assert_eq!(
+        read("[(char? \\a) (char? \"a\")]").unwrap(),
+        "[true false ]"
+    );
+    assert_eq!(
+        read("(clojure.string/join (map char [104 105]))").unwrap(),
+        "\"hi\""
+    );
+}
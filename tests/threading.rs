@@ -0,0 +1,70 @@
+use ClojuRS::read;
+
+#[test]
+fn thread_first() {
+    assert_eq!(read("(-> 3 inc (* 2) (- 1))").unwrap(), "7");
+    assert_eq!(read("(-> {:a {:b 1}} :a :b)").unwrap(), "1");
+    assert_eq!(
+        read("(macroexpand '(-> a b (c d)))").unwrap(),
+        "(c (b a ) d )"
+    );
+}
+
+#[test]
+fn thread_last() {
+    assert_eq!(
+        read("(->> [1 2 3] (map inc) (filter odd?))").unwrap(),
+        "(3 )"
+    );
+    assert_eq!(
+        read("(macroexpand '(->> a (b c) d))").unwrap(),
+        "(d (b c a ) )"
+    );
+}
+
+#[test]
+fn thread_as() {
+    assert_eq!(read("(as-> 1 x (+ x 1) [x (* x 10)])").unwrap(), "[2 20 ]");
+    assert_eq!(
+        read("(macroexpand '(as-> 1 x (inc x)))").unwrap(),
+        "(clojure.core/let [x 1 x (inc x ) ] x )"
+    );
+}
+
+#[test]
+fn thread_some() {
+    assert_eq!(read("(some-> {:a {:b 1}} :a :b inc)").unwrap(), "2");
+    assert_eq!(read("(some-> {:a nil} :a :b inc)").unwrap(), "nil");
+    assert_eq!(read("(some->> [1 2] (map inc))").unwrap(), "(2 3 )");
+    assert_eq!(read("(some->> nil (map inc))").unwrap(), "nil");
+}
+
+#[test]
+fn thread_cond() {
+    assert_eq!(
+        read("(cond-> 1 true inc false (* 10) (= 1 1) (* 2))").unwrap(),
+        "4"
+    );
+    assert_eq!(
+        read("(cond->> [1 2] true (map inc) false (map dec))").unwrap(),
+        "(2 3 )"
+    );
+}
+
+#[test]
+fn member_chain_and_doto() {
+    assert_eq!(read("(.. 3 (+ 1) inc)").unwrap(), "5");
+    assert_eq!(
+        read("(macroexpand '(.. a (b c) d))").unwrap(),
+        "(d (b a c ) )"
+    );
+    assert_eq!(
+        read("(macroexpand '(.. a (b c) d))").unwrap(),
+        read("(macroexpand '(-> a (b c) d))").unwrap()
+    );
+    assert_eq!(
+        read("(.. {:a {:b 2}} :a :b)").unwrap(),
+        read("(-> {:a {:b 2}} :a :b)").unwrap()
+    );
+    assert_eq!(read("(doto [1] (conj 2) count)").unwrap(), "[1 ]");
+}
@@ -0,0 +1,72 @@
+use ClojuRS::read;
+
+#[test]
+fn transduce() {
+    assert_eq!(
+        read("(transduce (comp (map inc) (filter even?)) + [1 2 3 4 5])").unwrap(),
+        "12"
+    );
+    assert_eq!(read("(transduce (map inc) + 10 [1 2 3])").unwrap(), "19");
+    assert_eq!(
+        read("(transduce (take 2) conj [] [1 2 3 4])").unwrap(),
+        "[1 2 ]"
+    );
+}
+
+#[test]
+fn into() {
+    assert_eq!(
+        read("(into [] (comp (take 3) (map inc)) [1 2 3 4 5])").unwrap(),
+        "[2 3 4 ]"
+    );
+    assert_eq!(read("(into [0] [1 2])").unwrap(), "[0 1 2 ]");
+    assert_eq!(read("(into () [1 2 3])").unwrap(), "(3 2 1 )");
+    assert_eq!(read("(conj () 1)").unwrap(), "(1 )");
+    assert_eq!(
+        read("(= (into #{} (remove odd?) [1 2 3 4 4]) #{2 4})").unwrap(),
+        "true"
+    );
+    assert_eq!(
+        read("(= (into {} (map conj [[:a] [:b]] [1 2])) {:a 1 :b 2})").unwrap(),
+        "true"
+    );
+}
+
+#[test]
+fn sequence() {
+    assert_eq!(
+        read("(sequence (partition-all 2) [1 2 3 4 5])").unwrap(),
+        "([1 2 ] [3 4 ] [5 ] )"
+    );
+    assert_eq!(
+        read("(sequence (comp (take 3) (partition-all 2)) [1 2 3 4 5])").unwrap(),
+        "([1 2 ] [3 ] )"
+    );
+    assert_eq!(
+        read("(sequence (comp (drop 1) (dedupe)) [1 1 2 2 2 3 1])").unwrap(),
+        "(1 2 3 1 )"
+    );
+    assert_eq!(
+        read("(sequence (comp (partition-all 2) cat (keep even?)) [1 2 3])").unwrap(),
+        "(false true false )"
+    );
+    assert_eq!(
+        read("(sequence (mapcat keyword) [\"a\" \"b\"])").unwrap(),
+        "(:a :b )"
+    );
+}
+
+#[test]
+fn eduction() {
+    assert_eq!(
+        read("(eduction (filter odd?) (map inc) [1 2 3 4 5])").unwrap(),
+        "(2 4 6 )"
+    );
+}
+
+#[test]
+fn collection_arities() {
+    assert_eq!(read("(map inc [1 2 3])").unwrap(), "(2 3 4 )");
+    assert_eq!(read("(map + [1 2 3] [10 20])").unwrap(), "(11 22 )");
+    assert_eq!(read("(take 2 (map inc [1 2 3]))").unwrap(), "(2 3 )");
+}
@@ -0,0 +1,121 @@
+use ClojuRS::{error::Error, read};
+
+#[test]
+fn binding_dynamic_vars() {
+    assert_eq!(read("(def ^:dynamic *x46* 1)").unwrap(), "#'user/*x46*");
+    assert_eq!(read("(defn get-x46 [] *x46*)").unwrap(), "#'user/get-x46");
+    assert_eq!(read("(binding [*x46* 10] (get-x46))").unwrap(), "10");
+    assert_eq!(read("(get-x46)").unwrap(), "1");
+    assert_eq!(
+        read("(binding [*x46* 2] [(binding [*x46* 3] (get-x46)) (get-x46)])").unwrap(),
+        "[3 2 ]"
+    );
+    assert_eq!(
+        read("(binding [*x46* 2 *x46* (inc *x46*)] *x46*)").unwrap(),
+        "2"
+    );
+    assert_eq!(read("(with-bindings {#'*x46* 7} (get-x46))").unwrap(), "7");
+
+    assert_eq!(
+        read("(def ^{:dynamic true} *y46* :root)").unwrap(),
+        "#'user/*y46*"
+    );
+    assert_eq!(read("(binding [*y46* :bound] *y46*)").unwrap(), ":bound");
+
+    assert_eq!(read("(def z46 1)").unwrap(), "#'user/z46");
+    assert_eq!(
+        read("(binding [z46 2] z46)").err(),
+        Some(Error::illegal_state(
+            "Can't dynamically bind non-dynamic var: user/z46"
+        ))
+    );
+}
+
+#[test]
+fn set_within_binding() {
+    assert_eq!(read("(def ^:dynamic *s46* 0)").unwrap(), "#'user/*s46*");
+    assert_eq!(
+        read("(binding [*s46* 1] (set! *s46* 5) *s46*)").unwrap(),
+        "5"
+    );
+    assert_eq!(
+        read("(binding [*s46* 1] (binding [*s46* 2] (set! *s46* 3)) *s46*)").unwrap(),
+        "1"
+    );
+    assert_eq!(read("(do *s46*)").unwrap(), "0");
+    assert_eq!(
+        read("(set! *s46* 2)").err(),
+        Some(Error::illegal_state(
+            "Can't change/establish root binding of: *s46* with set"
+        ))
+    );
+}
+
+#[test]
+fn bindings_are_conveyed() {
+    assert_eq!(read("(def ^:dynamic *c46* :root)").unwrap(), "#'user/*c46*");
+    assert_eq!(
+        read("(binding [*c46* :future] @(future *c46*))").unwrap(),
+        ":future"
+    );
+    assert_eq!(
+        read("(binding [*c46* :call] @(future-call (fn [] *c46*)))").unwrap(),
+        ":call"
+    );
+    assert_eq!(read("(def a46 (agent nil))").unwrap(), "#'user/a46");
+    read("(binding [*c46* :agent] (send a46 (fn [_] *c46*)))").unwrap();
+    read("(await a46)").unwrap();
+    assert_eq!(read("@a46").unwrap(), ":agent");
+    assert_eq!(
+        read("(binding [*c46* :other] @(future (binding [*c46* :inner] *c46*)))").unwrap(),
+        ":inner"
+    );
+    assert_eq!(
+        read("(binding [*c46* 2] (pmap (fn [_] *c46*) [1 2 3]))").unwrap(),
+        "(2 2 2 )"
+    );
+    assert_eq!(
+        read("(binding [*c46* :pcalls] (pcalls (fn [] *c46*)))").unwrap(),
+        "(:pcalls )"
+    );
+    assert_eq!(read("(do *c46*)").unwrap(), ":root");
+}
+
+#[test]
+fn printing_vars() {
+    assert_eq!(
+        read("(binding [*print-length* 2] (str [1 2 3] '(4 5 6) {:a 1}))").unwrap(),
+        "\"[1 2 ... ](4 5 ... ){:a 1 }\""
+    );
+    assert_eq!(
+        read("(binding [*print-length* 0] (str #{1}))").unwrap(),
+        "\"#{... }\""
+    );
+    assert_eq!(read("(str [1 2 3])").unwrap(), "\"[1 2 3 ]\"");
+    assert_eq!(read("(def out46 (atom nil))").unwrap(), "#'user/out46");
+    assert_eq!(
+        read("(binding [*out* out46] (println! 1) (println! :a) @out46)").unwrap(),
+        "\"1\n:a\n\""
+    );
+    assert_eq!(read("(do *out*)").unwrap(), "nil");
+    assert_eq!(
+        read("(get (meta #'*print-length*) :dynamic)").unwrap(),
+        "true"
+    );
+}
+
+#[test]
+fn vars_and_roots() {
+    assert_eq!(read("(def r46 10)").unwrap(), "#'user/r46");
+    assert_eq!(read("(var r46)").unwrap(), "#'user/r46");
+    assert_eq!(read("(do #'r46)").unwrap(), "#'user/r46");
+    assert_eq!(read("(do #'inc)").unwrap(), "#'clojure.core/inc");
+    assert_eq!(read("(alter-var-root #'r46 + 1 2)").unwrap(), "13");
+    assert_eq!(read("(do r46)").unwrap(), "13");
+    assert_eq!(
+        read("(var missing46)").err(),
+        Some(Error::illegal_state(
+            "Unable to resolve var: missing46 in this context"
+        ))
+    );
+}